@@ -0,0 +1,29 @@
+//! Signal parsing throughput over a synthetic `!ticker@arr` frame.
+//!
+//! Run:  cargo bench --bench parse [-- FRAMES [TICKERS]]
+
+use crypto_scanner_agent::bench::{measure_parsing, synthetic_ticker_frame, DEFAULT_TICKERS};
+
+fn arg(n: usize, default: usize) -> usize {
+    // `cargo bench` passes `--bench` along; skip flags.
+    std::env::args()
+        .skip(1)
+        .filter(|a| !a.starts_with('-'))
+        .nth(n)
+        .and_then(|a| a.parse().ok())
+        .unwrap_or(default)
+}
+
+fn main() {
+    let frames = arg(0, 200);
+    let tickers = arg(1, DEFAULT_TICKERS);
+    let frame = synthetic_ticker_frame(tickers);
+
+    println!("{tickers} tickers/frame ({} KiB)", frame.len() / 1024);
+    for (name, borrowed) in [("value", false), ("borrowed", true)] {
+        // Warm up allocator and caches before timing.
+        measure_parsing(&frame, frames.div_ceil(10), borrowed);
+        let result = measure_parsing(&frame, frames, borrowed);
+        println!("{name:>9}: {result}");
+    }
+}
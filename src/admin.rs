@@ -0,0 +1,232 @@
+//! Operator endpoints, enabled by setting `ADMIN_TOKEN` and guarded by
+//! `Authorization: Bearer <ADMIN_TOKEN>`.
+
+use std::{fmt, sync::Arc};
+
+use serde::{Deserialize, Serialize};
+use shuttle_axum::axum::{
+    body::Bytes,
+    http::{header, HeaderMap, StatusCode},
+    Extension, Json,
+};
+
+use crate::{
+    events::{write_snapshot, SignalSnapshot},
+    shutdown::ShutdownReason,
+    stream::SignalConfig,
+    SharedState,
+};
+
+/// The configured admin token; `None` disables the admin API.
+#[derive(Clone, Default)]
+pub struct AdminToken(pub Option<Arc<str>>);
+
+/// Never prints the token itself.
+impl fmt::Debug for AdminToken {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let state = if self.0.is_some() { "set" } else { "unset" };
+        write!(f, "AdminToken({state})")
+    }
+}
+
+impl AdminToken {
+    /// `ADMIN_TOKEN`, ignoring an empty value.
+    pub fn from_env() -> Self {
+        Self(
+            std::env::var("ADMIN_TOKEN")
+                .ok()
+                .filter(|t| !t.trim().is_empty())
+                .map(Into::into),
+        )
+    }
+
+    fn authorize(&self, headers: &HeaderMap) -> Result<(), (StatusCode, String)> {
+        let Some(expected) = &self.0 else {
+            return Err((StatusCode::NOT_FOUND, "admin API disabled".to_owned()));
+        };
+        let given = headers
+            .get(header::AUTHORIZATION)
+            .and_then(|v| v.to_str().ok())
+            .and_then(|v| v.strip_prefix("Bearer "));
+        match given {
+            Some(token) if constant_time_eq(token.as_bytes(), expected.as_bytes()) => Ok(()),
+            _ => Err((StatusCode::UNAUTHORIZED, "invalid admin token".to_owned())),
+        }
+    }
+}
+
+/// Compare without bailing out at the first differing byte, so response
+/// timing doesn't reveal how much of a guessed token was right.
+fn constant_time_eq(a: &[u8], b: &[u8]) -> bool {
+    a.len() == b.len() && a.iter().zip(b).fold(0, |acc, (x, y)| acc | (x ^ y)) == 0
+}
+
+/// Body of `POST /admin/thresholds`; omitted fields keep their value.
+#[derive(Debug, Default, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct ThresholdUpdate {
+    pub min_pct_gain: Option<f64>,
+    pub min_quote_vol: Option<f64>,
+    pub min_last_price: Option<f64>,
+}
+
+impl ThresholdUpdate {
+    /// Apply every field to `config`, or none of them if any is invalid.
+    pub fn apply(&self, config: &mut SignalConfig) -> Result<(), String> {
+        let fields = [
+            ("min_pct_gain", self.min_pct_gain),
+            ("min_quote_vol", self.min_quote_vol),
+            ("min_last_price", self.min_last_price),
+        ];
+        if fields.iter().all(|(_, v)| v.is_none()) {
+            return Err("no threshold given".to_owned());
+        }
+        for (name, value) in fields {
+            match value {
+                Some(v) if !v.is_finite() => return Err(format!("{name} must be a number")),
+                Some(v) if v < 0.0 && name != "min_pct_gain" => {
+                    return Err(format!("{name} must not be negative, got {v}"))
+                }
+                _ => {}
+            }
+        }
+
+        config.min_pct_gain = self.min_pct_gain.unwrap_or(config.min_pct_gain);
+        config.min_quote_vol = self.min_quote_vol.unwrap_or(config.min_quote_vol);
+        config.min_last_price = self.min_last_price.unwrap_or(config.min_last_price);
+        Ok(())
+    }
+}
+
+/// Thresholds currently in force, as returned by the endpoint.
+#[derive(Debug, Serialize)]
+pub struct Thresholds {
+    pub min_pct_gain: f64,
+    pub min_quote_vol: f64,
+    pub min_last_price: f64,
+}
+
+impl From<&SignalConfig> for Thresholds {
+    fn from(config: &SignalConfig) -> Self {
+        Self {
+            min_pct_gain: config.min_pct_gain,
+            min_quote_vol: config.min_quote_vol,
+            min_last_price: config.min_last_price,
+        }
+    }
+}
+
+/// `POST /admin/thresholds` — change the signal thresholds without a
+/// restart. Answers with the thresholds now in force.
+pub async fn thresholds_handler(
+    headers: HeaderMap,
+    Extension(token): Extension<AdminToken>,
+    Extension(state): Extension<SharedState>,
+    body: Bytes,
+) -> Result<Json<Thresholds>, (StatusCode, String)> {
+    // Authenticate before even looking at the body.
+    token.authorize(&headers)?;
+    let update: ThresholdUpdate = serde_json::from_slice(&body)
+        .map_err(|e| (StatusCode::BAD_REQUEST, format!("invalid body: {e}")))?;
+
+    let config = state.lock().await.config.clone();
+    let mut config = config.write().unwrap();
+    update
+        .apply(&mut config)
+        .map_err(|e| (StatusCode::BAD_REQUEST, e))?;
+    tracing::info!(?update, "Signal thresholds updated");
+    Ok(Json(Thresholds::from(&*config)))
+}
+
+/// `POST /admin/snapshot` — write the buffered signals to a timestamped
+/// JSON file and answer with its path.
+pub async fn snapshot_handler(
+    headers: HeaderMap,
+    Extension(token): Extension<AdminToken>,
+    Extension(state): Extension<SharedState>,
+) -> Result<Json<SignalSnapshot>, (StatusCode, String)> {
+    token.authorize(&headers)?;
+    let (buffer, dir) = {
+        let state = state.lock().await;
+        (state.buffer.clone(), state.snapshot_dir.clone())
+    };
+    let snapshot = write_snapshot(&buffer, &dir)
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, format!("{e:#}")))?;
+    tracing::info!(path = %snapshot.path.display(), signals = snapshot.signals, "Snapshot written");
+    Ok(Json(snapshot))
+}
+
+/// `POST /admin/shutdown` — stop the server gracefully, as `SIGTERM` would
+/// but exiting with code 0.
+pub async fn shutdown_handler(
+    headers: HeaderMap,
+    Extension(token): Extension<AdminToken>,
+    Extension(state): Extension<SharedState>,
+) -> Result<StatusCode, (StatusCode, String)> {
+    token.authorize(&headers)?;
+    state.lock().await.shutdown.trigger(ShutdownReason::Admin);
+    Ok(StatusCode::ACCEPTED)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::stream::extract_signals_from_text;
+
+    const TICKERS: &str = r#"[
+        { "s": "BTCUSDT", "P": "3.5", "q": "600000", "c": "30000" },
+        { "s": "ETHUSDT", "P": "6.0", "q": "2000000", "c": "2000" }
+    ]"#;
+
+    fn symbols(config: &SignalConfig) -> Vec<String> {
+        extract_signals_from_text(TICKERS, config)
+            .unwrap()
+            .into_iter()
+            .map(|s| s.symbol)
+            .collect()
+    }
+
+    #[test]
+    fn lowered_thresholds_let_more_tickers_through() {
+        let mut config = SignalConfig::default();
+        assert_eq!(symbols(&config), ["ETHUSDT"]);
+
+        let update: ThresholdUpdate =
+            serde_json::from_str(r#"{"min_pct_gain":3,"min_quote_vol":500000}"#).unwrap();
+        update.apply(&mut config).unwrap();
+        assert_eq!(symbols(&config), ["BTCUSDT", "ETHUSDT"]);
+    }
+
+    #[test]
+    fn invalid_updates_change_nothing() {
+        let mut config = SignalConfig::default();
+        let bad = ThresholdUpdate {
+            min_pct_gain: Some(1.0),
+            min_quote_vol: Some(-5.0),
+            ..Default::default()
+        };
+        assert!(bad.apply(&mut config).is_err());
+        assert!(ThresholdUpdate::default().apply(&mut config).is_err());
+        assert_eq!(config, SignalConfig::default());
+
+        assert!(serde_json::from_str::<ThresholdUpdate>(r#"{"min_gain":1}"#).is_err());
+    }
+
+    #[test]
+    fn token_must_match_exactly() {
+        let token = AdminToken(Some("s3cret".into()));
+        let mut headers = HeaderMap::new();
+        assert_eq!(
+            token.authorize(&headers).unwrap_err().0,
+            StatusCode::UNAUTHORIZED
+        );
+
+        headers.insert(header::AUTHORIZATION, "Bearer s3cre".parse().unwrap());
+        assert!(token.authorize(&headers).is_err());
+        headers.insert(header::AUTHORIZATION, "Bearer s3cret".parse().unwrap());
+        assert!(token.authorize(&headers).is_ok());
+
+        let disabled = AdminToken(None).authorize(&headers).unwrap_err();
+        assert_eq!(disabled.0, StatusCode::NOT_FOUND);
+    }
+}
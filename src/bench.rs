@@ -0,0 +1,118 @@
+//! Parsing throughput of the feed's hot path, measured by
+//! `cargo bench --bench parse`.
+
+use std::{fmt, hint::black_box, time::Duration, time::Instant};
+
+use crate::stream::{extract_signals_from_text, SignalConfig};
+
+/// Tickers in a frame, about what Binance's `!ticker@arr` carries.
+pub const DEFAULT_TICKERS: usize = 2000;
+
+/// A synthetic `!ticker@arr` frame of `tickers` entries; every fourth one
+/// passes the default thresholds.
+pub fn synthetic_ticker_frame(tickers: usize) -> String {
+    let entries: Vec<_> = (0..tickers)
+        .map(|i| {
+            let (pct, vol) = if i % 4 == 0 {
+                ("12.5", "2500000")
+            } else {
+                ("1.2", "40000")
+            };
+            serde_json::json!({
+                "e": "24hrTicker",
+                "E": 1_700_000_000_000u64 + i as u64,
+                "s": format!("SYM{i}USDT"),
+                "P": pct,
+                "q": vol,
+                "c": format!("{}.{:04}", i % 100, i),
+            })
+        })
+        .collect();
+    serde_json::Value::Array(entries).to_string()
+}
+
+/// Outcome of [`measure_parsing`].
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct ParseThroughput {
+    pub frames: usize,
+    pub tickers: usize,
+    pub signals: usize,
+    pub elapsed: Duration,
+}
+
+impl ParseThroughput {
+    /// Signals produced per second of wall-clock time; 0 before any time
+    /// has been recorded.
+    pub fn signals_per_sec(&self) -> f64 {
+        self.per_sec(self.signals)
+    }
+
+    /// Tickers parsed per second, whether or not they became signals.
+    pub fn tickers_per_sec(&self) -> f64 {
+        self.per_sec(self.tickers)
+    }
+
+    fn per_sec(&self, n: usize) -> f64 {
+        let secs = self.elapsed.as_secs_f64();
+        if secs > 0.0 {
+            n as f64 / secs
+        } else {
+            0.0
+        }
+    }
+}
+
+impl fmt::Display for ParseThroughput {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{} frames, {} signals in {:.2?} ({:.0} signals/s, {:.0} tickers/s)",
+            self.frames,
+            self.signals,
+            self.elapsed,
+            self.signals_per_sec(),
+            self.tickers_per_sec()
+        )
+    }
+}
+
+/// Parse `frame` `frames` times with the default thresholds, into borrowed
+/// tickers or a `serde_json::Value` tree.
+pub fn measure_parsing(frame: &str, frames: usize, borrowed: bool) -> ParseThroughput {
+    let config = SignalConfig {
+        borrowed_parsing: borrowed,
+        ..SignalConfig::default()
+    };
+    let per_frame = serde_json::from_str::<serde_json::Value>(frame)
+        .ok()
+        .and_then(|v| v.as_array().map(Vec::len))
+        .unwrap_or(0);
+    let mut signals = 0;
+    let started = Instant::now();
+    for _ in 0..frames {
+        let parsed = extract_signals_from_text(black_box(frame), &config);
+        signals += black_box(parsed).map_or(0, |s| s.len());
+    }
+    ParseThroughput {
+        frames,
+        tickers: per_frame * frames,
+        signals,
+        elapsed: started.elapsed(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn benchmark_runs_and_finds_signals() {
+        let frame = synthetic_ticker_frame(40);
+        for borrowed in [false, true] {
+            let result = measure_parsing(&frame, 3, borrowed);
+            assert_eq!(result.tickers, 120);
+            assert_eq!(result.signals, 30);
+            assert!(result.signals_per_sec() > 0.0);
+        }
+    }
+}
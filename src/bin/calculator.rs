@@ -131,4 +131,3 @@ impl Tool for Subtract {
         Ok(result)
     }
 }
-
@@ -9,6 +9,8 @@ fn main() {
 
 #[cfg(not(feature = "nautilus"))]
 fn main() {
-    println!("Nautilus Trader feature not enabled.\n\
-             Rebuild with `--features nautilus` to run this example.");
+    println!(
+        "Nautilus Trader feature not enabled.\n\
+             Rebuild with `--features nautilus` to run this example."
+    );
 }
@@ -0,0 +1,296 @@
+//! `raydium_cli compare-price`: one token's price on Raydium next to
+//! Binance's spot price, and how far apart they are.
+
+use std::fmt;
+
+use anyhow::{bail, Context, Result};
+use reqwest::{Client, StatusCode, Url};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+
+use crypto_scanner_agent::price::{PriceProvider, RaydiumPrices};
+
+use crate::mints::fetch_mints;
+
+/// Binance's spot REST API.
+pub const DEFAULT_BINANCE_API: &str = "https://api.binance.com";
+const BINANCE_PRICE_PATH: &str = "/api/v3/ticker/price";
+/// Binance's error code for a pair it doesn't list.
+const INVALID_SYMBOL: i64 = -1121;
+/// Binance is quoted in USDT, taken as one dollar.
+const BINANCE_QUOTE: &str = "USDT";
+
+/// Where a token trades.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Venue {
+    Raydium,
+    Binance,
+}
+
+impl fmt::Display for Venue {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            Venue::Raydium => "Raydium",
+            Venue::Binance => "Binance",
+        })
+    }
+}
+
+/// A token's price on both venues, either of which may not list it.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct Comparison {
+    pub symbol: String,
+    /// Mint the symbol resolved to on Raydium.
+    pub mint: Option<String>,
+    pub raydium: Option<f64>,
+    /// Binance pair quoted, e.g. `SOLUSDT`.
+    pub pair: String,
+    pub binance: Option<f64>,
+    /// How much dearer the dearer venue is, in percent of the cheaper
+    /// price; `None` unless both venues quote the token.
+    pub spread_pct: Option<f64>,
+    /// `None` when only one venue quotes it or both agree.
+    pub cheaper: Option<Venue>,
+}
+
+impl Comparison {
+    fn new(symbol: &str, mint: Option<String>, raydium: Option<f64>, binance: Option<f64>) -> Self {
+        let (spread_pct, cheaper) = match (raydium, binance) {
+            (Some(r), Some(b)) if r > 0.0 && b > 0.0 => {
+                let cheaper = match r.total_cmp(&b) {
+                    std::cmp::Ordering::Less => Some(Venue::Raydium),
+                    std::cmp::Ordering::Greater => Some(Venue::Binance),
+                    std::cmp::Ordering::Equal => None,
+                };
+                (Some((r.max(b) - r.min(b)) / r.min(b) * 100.0), cheaper)
+            }
+            _ => (None, None),
+        };
+        Self {
+            symbol: symbol.to_owned(),
+            mint,
+            raydium,
+            pair: binance_pair(symbol),
+            binance,
+            spread_pct,
+            cheaper,
+        }
+    }
+}
+
+impl fmt::Display for Comparison {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let price =
+            |p: Option<f64>| p.map_or_else(|| "not listed".to_owned(), |p| format!("${p:.6}"));
+        writeln!(f, "{}", self.symbol)?;
+        write!(f, "  Raydium : {}", price(self.raydium))?;
+        match &self.mint {
+            Some(mint) => writeln!(f, " ({mint})")?,
+            None => writeln!(f)?,
+        }
+        writeln!(f, "  Binance : {} ({})", price(self.binance), self.pair)?;
+        match (self.spread_pct, self.cheaper) {
+            (Some(spread), Some(venue)) => {
+                writeln!(f, "  Spread  : {spread:.2}%, cheaper on {venue}")
+            }
+            (Some(_), None) => writeln!(f, "  Spread  : 0.00%, same price on both"),
+            (None, _) => {
+                let only = if self.raydium.is_some() {
+                    Venue::Raydium
+                } else {
+                    Venue::Binance
+                };
+                writeln!(f, "  Spread  : n/a, only quoted on {only}")
+            }
+        }
+    }
+}
+
+/// The Binance pair `symbol` is compared on.
+fn binance_pair(symbol: &str) -> String {
+    format!("{}{BINANCE_QUOTE}", symbol.trim().to_ascii_uppercase())
+}
+
+/// `symbol`'s mint and price on Raydium, `None` if Raydium's mint list
+/// doesn't have it or has no price for it. A symbol shared by several
+/// unofficial mints is an error, as for `price`.
+async fn raydium_price(
+    client: &Client,
+    base: &str,
+    symbol: &str,
+) -> Result<Option<(String, Option<f64>)>> {
+    let list = fetch_mints(client, base).await?;
+    if list.resolve_symbol(symbol).is_none() {
+        return Ok(None);
+    }
+    let mint = list.resolve_token(symbol)?;
+    let prices = RaydiumPrices::new(client.clone(), base)
+        .prices(&[mint.as_str()])
+        .await
+        .context("fetching the Raydium price")?;
+    let price = prices.get(&mint).copied();
+    Ok(Some((mint, price)))
+}
+
+#[derive(Deserialize)]
+struct TickerPrice {
+    price: String,
+}
+
+/// Binance's last price of `pair`, `None` if Binance doesn't list it.
+async fn binance_price(client: &Client, base: &str, pair: &str) -> Result<Option<f64>> {
+    let url = Url::parse_with_params(
+        &format!("{}{BINANCE_PRICE_PATH}", base.trim_end_matches('/')),
+        &[("symbol", pair)],
+    )?;
+    let res = client.get(url).send().await?;
+    if res.status() == StatusCode::BAD_REQUEST {
+        // `{"code":-1121,"msg":"Invalid symbol."}`
+        let body: Value = res.json().await.unwrap_or_default();
+        if body["code"].as_i64() == Some(INVALID_SYMBOL) {
+            return Ok(None);
+        }
+        bail!(
+            "Binance error: {}",
+            body["msg"].as_str().unwrap_or("HTTP 400")
+        );
+    }
+    let ticker: TickerPrice = res.error_for_status()?.json().await?;
+    let price = ticker
+        .price
+        .parse()
+        .with_context(|| format!("invalid Binance price `{}`", ticker.price))?;
+    Ok(Some(price))
+}
+
+/// `symbol` on Raydium (`raydium_base`) and Binance (`binance_base`); an
+/// error if neither lists it.
+pub async fn compare_price(
+    client: &Client,
+    raydium_base: &str,
+    binance_base: &str,
+    symbol: &str,
+) -> Result<Comparison> {
+    let pair = binance_pair(symbol);
+    let (raydium, binance) = tokio::join!(
+        raydium_price(client, raydium_base, symbol),
+        binance_price(client, binance_base, &pair)
+    );
+    let (mint, raydium) = match raydium? {
+        Some((mint, price)) => (Some(mint), price),
+        None => (None, None),
+    };
+    let binance = binance?;
+    if raydium.is_none() && binance.is_none() {
+        match mint {
+            Some(mint) => {
+                bail!("Raydium has no price for {symbol} ({mint}) and Binance doesn't list {pair}")
+            }
+            None => bail!("{symbol} is listed on neither Raydium nor Binance (as {pair})"),
+        }
+    }
+    Ok(Comparison::new(symbol, mint, raydium, binance))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use shuttle_axum::axum::{
+        extract::Query, http::StatusCode as HttpStatus, response::IntoResponse, routing::get, Json,
+        Router,
+    };
+    use std::collections::HashMap;
+
+    /// Raydium's mint list and prices plus Binance's ticker on one server:
+    /// SOL trades on both, WIF only on Raydium, BTC only on Binance.
+    async fn mock_venues() -> String {
+        let mint_list = || async {
+            let item = |address: &str, symbol: &str| {
+                serde_json::json!({
+                    "address": address, "symbol": symbol, "name": symbol, "decimals": 6
+                })
+            };
+            Json(serde_json::json!({
+                "success": true,
+                "data": {
+                    "mintList": [item("SolMint", "SOL"), item("WifMint", "WIF")],
+                    "whiteList": ["SolMint", "WifMint"],
+                }
+            }))
+        };
+        let mint_price = || async {
+            Json(serde_json::json!({
+                "success": true,
+                "data": { "SolMint": 150.0, "WifMint": 2.5 }
+            }))
+        };
+        let ticker = |q: Query<HashMap<String, String>>| async move {
+            let price = match q["symbol"].as_str() {
+                "SOLUSDT" => "153.00000000",
+                "BTCUSDT" => "65000.00000000",
+                _ => {
+                    let invalid = serde_json::json!({ "code": -1121, "msg": "Invalid symbol." });
+                    return (HttpStatus::BAD_REQUEST, Json(invalid)).into_response();
+                }
+            };
+            Json(serde_json::json!({ "symbol": q["symbol"], "price": price })).into_response()
+        };
+        let app = Router::new()
+            .route("/mint/list", get(mint_list))
+            .route("/mint/price", get(mint_price))
+            .route(BINANCE_PRICE_PATH, get(ticker));
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let base = format!("http://{}", listener.local_addr().unwrap());
+        tokio::spawn(async move { shuttle_axum::axum::serve(listener, app).await });
+        base
+    }
+
+    #[tokio::test]
+    async fn spread_and_cheaper_venue_from_both_sources() {
+        let base = mock_venues().await;
+        let client = Client::new();
+        let compare = |symbol| compare_price(&client, &base, &base, symbol);
+
+        let sol = compare("sol").await.unwrap();
+        assert_eq!(sol.mint.as_deref(), Some("SolMint"));
+        assert_eq!((sol.raydium, sol.binance), (Some(150.0), Some(153.0)));
+        assert_eq!(sol.pair, "SOLUSDT");
+        assert!((sol.spread_pct.unwrap() - 2.0).abs() < 1e-9);
+        assert_eq!(sol.cheaper, Some(Venue::Raydium));
+        assert!(sol
+            .to_string()
+            .ends_with("  Spread  : 2.00%, cheaper on Raydium\n"));
+
+        let wif = compare("WIF").await.unwrap();
+        assert_eq!((wif.raydium, wif.binance), (Some(2.5), None));
+        assert_eq!((wif.spread_pct, wif.cheaper), (None, None));
+        assert!(wif
+            .to_string()
+            .contains("  Binance : not listed (WIFUSDT)\n"));
+        assert!(wif.to_string().ends_with("only quoted on Raydium\n"));
+
+        let btc = compare("BTC").await.unwrap();
+        assert_eq!(
+            (btc.mint.as_deref(), btc.raydium, btc.binance),
+            (None, None, Some(65000.0))
+        );
+        assert!(btc.to_string().ends_with("only quoted on Binance\n"));
+
+        let err = compare("NOPE").await.unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "NOPE is listed on neither Raydium nor Binance (as NOPEUSDT)"
+        );
+    }
+
+    #[test]
+    fn spread_is_relative_to_the_cheaper_price() {
+        let dearer_raydium = Comparison::new("X", None, Some(110.0), Some(100.0));
+        assert!((dearer_raydium.spread_pct.unwrap() - 10.0).abs() < 1e-9);
+        assert_eq!(dearer_raydium.cheaper, Some(Venue::Binance));
+
+        let same = Comparison::new("X", None, Some(1.0), Some(1.0));
+        assert_eq!((same.spread_pct, same.cheaper), (Some(0.0), None));
+    }
+}
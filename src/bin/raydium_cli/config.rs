@@ -0,0 +1,274 @@
+//! Layered settings for `raydium_cli`.
+//!
+//! Every setting is resolved independently, highest precedence first:
+//!
+//! 1. command-line flag (`--rpc=`, `--risk-pct=`, `--format=`, …)
+//! 2. config file: `--config=PATH`, else
+//!    `$XDG_CONFIG_HOME/crypto-scanner/config.toml` (or `~/.config/…`)
+//! 3. environment variable (`OWNER`, `SOLANA_RPC_URL`, …)
+//! 4. built-in default
+//!
+//! So a value in the file beats the environment, and a flag beats both.
+//!
+//! ```toml
+//! owner = "YOUR_SOLANA_ADDRESS"
+//! rpc = "https://api.mainnet-beta.solana.com"
+//! raydium_base = "https://api-v3.raydium.io"
+//! risk_pct = 1.5
+//! format = "json"
+//! ```
+
+use std::{
+    path::{Path, PathBuf},
+    str::FromStr,
+};
+
+use anyhow::{anyhow, Context, Result};
+use crypto_scanner_agent::price::DEFAULT_RAYDIUM_BASE;
+use serde::Deserialize;
+
+pub const DEFAULT_RPC: &str = "https://api.mainnet-beta.solana.com";
+
+/// Default risk per trade used by `balances --suggest-size`.
+pub const DEFAULT_RISK_PCT: f64 = 2.0;
+
+/// How command results are printed.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum OutputFormat {
+    #[default]
+    Text,
+    Json,
+}
+
+impl FromStr for OutputFormat {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s.trim().to_ascii_lowercase().as_str() {
+            "text" => Ok(OutputFormat::Text),
+            "json" => Ok(OutputFormat::Json),
+            other => Err(anyhow!("unknown format `{other}` (expected text|json)")),
+        }
+    }
+}
+
+/// One source of settings. Unset fields fall through to the next layer.
+#[derive(Debug, Clone, Default, PartialEq, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Layer {
+    pub owner: Option<String>,
+    pub rpc: Option<String>,
+    pub raydium_base: Option<String>,
+    pub risk_pct: Option<f64>,
+    pub format: Option<OutputFormat>,
+}
+
+impl Layer {
+    /// Settings from `OWNER`, `SOLANA_RPC_URL`, `RAYDIUM_API_BASE`,
+    /// `RISK_PCT` and `OUTPUT_FORMAT`.
+    pub fn from_env() -> Result<Self> {
+        Self::from_vars(|key| std::env::var(key).ok())
+    }
+
+    fn from_vars(get: impl Fn(&str) -> Option<String>) -> Result<Self> {
+        let get = |key| get(key).filter(|v| !v.trim().is_empty());
+        Ok(Self {
+            owner: get("OWNER"),
+            rpc: get("SOLANA_RPC_URL"),
+            raydium_base: get("RAYDIUM_API_BASE"),
+            risk_pct: get("RISK_PCT")
+                .map(|v| v.trim().parse().context("invalid RISK_PCT"))
+                .transpose()?,
+            format: get("OUTPUT_FORMAT").map(|v| v.parse()).transpose()?,
+        })
+    }
+
+    /// Read a config file. An explicit `path` must exist; the default
+    /// location is optional.
+    pub fn load(path: Option<&Path>) -> Result<Self> {
+        let (path, required) = match path {
+            Some(p) => (p.to_owned(), true),
+            None => match default_config_path() {
+                Some(p) => (p, false),
+                None => return Ok(Self::default()),
+            },
+        };
+        match std::fs::read_to_string(&path) {
+            Ok(text) => Self::parse(&text).with_context(|| format!("in {}", path.display())),
+            Err(e) if !required && e.kind() == std::io::ErrorKind::NotFound => Ok(Self::default()),
+            Err(e) => Err(e).with_context(|| format!("reading {}", path.display())),
+        }
+    }
+
+    fn parse(text: &str) -> Result<Self> {
+        Ok(toml::from_str(text)?)
+    }
+
+    /// Fill every field unset here from `lower`.
+    fn or(self, lower: Layer) -> Layer {
+        Layer {
+            owner: self.owner.or(lower.owner),
+            rpc: self.rpc.or(lower.rpc),
+            raydium_base: self.raydium_base.or(lower.raydium_base),
+            risk_pct: self.risk_pct.or(lower.risk_pct),
+            format: self.format.or(lower.format),
+        }
+    }
+}
+
+/// `$XDG_CONFIG_HOME/crypto-scanner/config.toml`, falling back to
+/// `$HOME/.config`.
+pub fn default_config_path() -> Option<PathBuf> {
+    let base = std::env::var_os("XDG_CONFIG_HOME")
+        .map(PathBuf::from)
+        .or_else(|| std::env::var_os("HOME").map(|h| PathBuf::from(h).join(".config")))?;
+    Some(base.join("crypto-scanner").join("config.toml"))
+}
+
+/// Reject risk percentages outside `(0, 100]`.
+pub fn validate_risk_pct(pct: f64) -> Result<f64> {
+    if pct > 0.0 && pct <= 100.0 {
+        Ok(pct)
+    } else {
+        Err(anyhow!("risk percentage must be in (0, 100], got {pct}"))
+    }
+}
+
+/// Fully resolved settings.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Settings {
+    owner: Option<String>,
+    pub rpc: String,
+    pub raydium_base: String,
+    pub risk_pct: f64,
+    pub format: OutputFormat,
+}
+
+impl Settings {
+    /// Merge the layers (see the module docs for the precedence) and fill
+    /// in built-in defaults.
+    pub fn resolve(flags: Layer, file: Layer, env: Layer) -> Result<Self> {
+        let merged = flags.or(file).or(env);
+        Ok(Self {
+            owner: merged.owner,
+            rpc: merged.rpc.unwrap_or_else(|| DEFAULT_RPC.to_owned()),
+            raydium_base: merged
+                .raydium_base
+                .map(|b| b.trim_end_matches('/').to_owned())
+                .unwrap_or_else(|| DEFAULT_RAYDIUM_BASE.to_owned()),
+            risk_pct: validate_risk_pct(merged.risk_pct.unwrap_or(DEFAULT_RISK_PCT))?,
+            format: merged.format.unwrap_or_default(),
+        })
+    }
+
+    /// The wallet to inspect; there is no built-in default.
+    pub fn owner(&self) -> Result<&str> {
+        self.owner.as_deref().ok_or_else(|| {
+            anyhow!(
+                "balances requires owner \
+                 (pass it as arg, set it in the config file or set OWNER env-var)"
+            )
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn layer(rpc: &str, risk_pct: f64) -> Layer {
+        Layer {
+            rpc: Some(rpc.to_owned()),
+            risk_pct: Some(risk_pct),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn flags_beat_file_beat_env_beat_defaults() {
+        let flags = Layer {
+            rpc: Some("flag-rpc".into()),
+            ..Default::default()
+        };
+        let file = Layer {
+            owner: Some("file-owner".into()),
+            ..layer("file-rpc", 3.0)
+        };
+        let env = Layer {
+            owner: Some("env-owner".into()),
+            format: Some(OutputFormat::Json),
+            ..layer("env-rpc", 4.0)
+        };
+
+        let s = Settings::resolve(flags, file, env).unwrap();
+        assert_eq!(s.rpc, "flag-rpc");
+        assert_eq!(s.owner().unwrap(), "file-owner");
+        assert_eq!(s.risk_pct, 3.0);
+        assert_eq!(s.format, OutputFormat::Json);
+        assert_eq!(s.raydium_base, DEFAULT_RAYDIUM_BASE);
+    }
+
+    #[test]
+    fn defaults_apply_when_nothing_is_set() {
+        let s = Settings::resolve(Layer::default(), Layer::default(), Layer::default()).unwrap();
+        assert_eq!(s.rpc, DEFAULT_RPC);
+        assert_eq!(s.risk_pct, DEFAULT_RISK_PCT);
+        assert_eq!(s.format, OutputFormat::Text);
+        assert!(s.owner().is_err());
+    }
+
+    #[test]
+    fn resolved_risk_pct_is_validated() {
+        let err = Settings::resolve(Layer::default(), layer("rpc", 150.0), Layer::default());
+        assert!(err.is_err());
+    }
+
+    #[test]
+    fn env_layer_ignores_empty_values() {
+        let env = Layer::from_vars(|key| match key {
+            "OWNER" => Some(" ".into()),
+            "RISK_PCT" => Some("1.5".into()),
+            "OUTPUT_FORMAT" => Some("JSON".into()),
+            _ => None,
+        })
+        .unwrap();
+        assert_eq!(env.owner, None);
+        assert_eq!(env.risk_pct, Some(1.5));
+        assert_eq!(env.format, Some(OutputFormat::Json));
+    }
+
+    #[test]
+    fn file_layer_parses_toml() {
+        let file = Layer::parse(
+            r#"
+            owner = "Owner111"
+            raydium_base = "https://example.test/"
+            format = "json"
+            "#,
+        )
+        .unwrap();
+        assert_eq!(file.owner.as_deref(), Some("Owner111"));
+        assert_eq!(file.format, Some(OutputFormat::Json));
+
+        let s = Settings::resolve(Layer::default(), file, Layer::default()).unwrap();
+        assert_eq!(s.raydium_base, "https://example.test");
+
+        assert!(Layer::parse("colour = \"red\"").is_err());
+    }
+
+    #[test]
+    fn explicit_config_path_must_exist() {
+        assert!(Layer::load(Some(Path::new("/nonexistent/config.toml"))).is_err());
+    }
+
+    #[test]
+    fn risk_pct_must_be_in_range() {
+        assert!(validate_risk_pct(0.0).is_err());
+        assert!(validate_risk_pct(-1.0).is_err());
+        assert!(validate_risk_pct(100.1).is_err());
+        assert!(validate_risk_pct(f64::NAN).is_err());
+        assert_eq!(validate_risk_pct(100.0).unwrap(), 100.0);
+        assert_eq!(validate_risk_pct(2.0).unwrap(), 2.0);
+    }
+}
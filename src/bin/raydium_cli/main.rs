@@ -0,0 +1,1730 @@
+mod compare;
+mod config;
+mod mints;
+mod record;
+mod selftest;
+
+use anyhow::{anyhow, bail, Context, Result};
+use clap::{CommandFactory, Parser, Subcommand, ValueEnum};
+use clap_complete::Shell;
+use futures::StreamExt;
+use reqwest::{Client, Url};
+use rust_decimal::{prelude::ToPrimitive, Decimal, RoundingStrategy};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::{collections::BTreeMap, io::Write, path::PathBuf, str::FromStr, time::Duration};
+use tokio_tungstenite::tungstenite;
+
+use config::{Layer, OutputFormat, Settings};
+use crypto_scanner_agent::{
+    http::{build_client, validate_proxy_url, ClientOptions},
+    logging::{init_tracing, VerbosityArgs},
+    numbers::{format_volume, NumberFormat},
+    output::OutputArgs,
+    preflight::ConfigReport,
+    price::provider_from_env,
+    raydium::{locate_pool_array, Envelope},
+    solana::{bench_rpcs, fetch_balances_ui, Commitment, ReadOptions, SolanaRpc, TokenBalance},
+    tls::connect_ws,
+    Signal, Tier,
+};
+use mints::{
+    fetch_mint_pages, fetch_mints, is_mint_address, MintFilter, MintList, Resolved,
+    DEFAULT_MAX_PAGES,
+};
+use record::{record, RecordArgs};
+
+// Paths below the Raydium API base (see `price::DEFAULT_RAYDIUM_BASE`).
+const INFO_PATH: &str = "/main/info";
+const MINT_LIST_PATH: &str = "/mint/list";
+const POOL_BY_ID_PATH: &str = "/pools/info/ids";
+const POOLS_PATH: &str =
+    "/pools/info/list?poolType=all&poolSortField=default&sortType=desc&pageSize=10&page=1";
+
+/// Raydium and Solana helper commands.
+#[derive(Parser, Debug)]
+#[command(name = "raydium_cli", version, about)]
+struct Cli {
+    /// Config file [default: ~/.config/crypto-scanner/config.toml]
+    #[arg(long, global = true, value_name = "PATH")]
+    config: Option<PathBuf>,
+
+    /// Output format (text or json)
+    #[arg(long, global = true)]
+    format: Option<OutputFormat>,
+
+    /// How volumes and TVL are printed in text output
+    #[arg(long, global = true, value_enum, default_value_t = NumberFormat::Si)]
+    number_format: NumberFormat,
+
+    /// Route Raydium and RPC requests through this proxy (http, https,
+    /// socks5 or socks5h) [default: HTTPS_PROXY / HTTP_PROXY / ALL_PROXY]
+    #[arg(long, global = true, value_name = "URL", value_parser = proxy_url)]
+    proxy: Option<String>,
+
+    /// Accept invalid TLS certificates, e.g. a self-hosted RPC's
+    /// self-signed one [default: ALLOW_INVALID_CERTS]
+    #[arg(long, global = true)]
+    insecure: bool,
+
+    #[command(flatten)]
+    output: OutputArgs,
+
+    #[command(flatten)]
+    verbosity: VerbosityArgs,
+
+    #[command(subcommand)]
+    command: Command,
+}
+
+#[derive(Subcommand, Debug)]
+enum Command {
+    /// List the top Raydium pools
+    ListPools {
+        /// Only pools charging at most this many basis points
+        #[arg(long, value_name = "N")]
+        max_fee_bps: Option<u32>,
+        /// Order of the listing instead of Raydium's
+        #[arg(long, value_enum)]
+        sort: Option<PoolSort>,
+    },
+    /// Show the SOL and SPL-token balances of a wallet
+    Balances(BalancesArgs),
+    /// Show Raydium's TVL and 24 h volume
+    Info,
+    /// Show USD prices for one or more mints [source: PRICE_PROVIDER]
+    Price {
+        /// Mint address or symbol (e.g. SOL), or several separated by commas
+        tokens: String,
+    },
+    /// Compare a token's Raydium price with its Binance USDT price
+    ComparePrice {
+        /// Symbol as listed on Raydium, e.g. SOL
+        symbol: String,
+        /// Binance REST API base URL
+        #[arg(long, value_name = "URL", default_value = compare::DEFAULT_BINANCE_API)]
+        binance_url: String,
+    },
+    /// List the mints known to Raydium
+    Mints {
+        /// Only the mints trading under this ticker, official ones first
+        symbol: Option<String>,
+        /// Follow pagination and fetch the whole list
+        #[arg(long)]
+        all: bool,
+        /// Stop after this many pages with --all
+        #[arg(long, value_name = "N", requires = "all", default_value_t = DEFAULT_MAX_PAGES)]
+        max_pages: u32,
+        #[command(flatten)]
+        filter: MintFilter,
+    },
+    /// Rank Solana RPC endpoints by latency
+    RpcBench {
+        /// Endpoints to probe, separated by commas
+        #[arg(long, value_delimiter = ',', required = true)]
+        rpcs: Vec<String>,
+        /// Per-endpoint timeout in seconds
+        #[arg(long, default_value_t = 5, value_name = "SECS")]
+        timeout: u64,
+    },
+    /// Follow a running scanner's signals in the terminal
+    Stream {
+        /// The scanner's WebSocket endpoint
+        #[arg(long, default_value = DEFAULT_STREAM_URL)]
+        url: String,
+    },
+    /// Write every raw frame of an upstream WebSocket to a file, one per
+    /// line, for replaying later
+    Record(RecordArgs),
+    /// Estimate the price impact of a trade against a pool
+    Impact {
+        pool_id: String,
+        /// Amount to spend: quote units when buying, base units when selling
+        #[arg(long, value_parser = positive_amount)]
+        amount: f64,
+        #[arg(long, value_enum, default_value_t = Side::Buy)]
+        side: Side,
+    },
+    /// Resolve the scanner's feed and signal settings from the environment
+    /// and preview how many signals they let through on sample frames
+    ValidateConfig,
+    /// Check the bundled golden fixtures still parse as recorded, to spot
+    /// changes in the exchange and Raydium response formats
+    Selftest,
+    /// Print a shell completion script to stdout
+    #[command(hide = true)]
+    Completions { shell: Shell },
+}
+
+#[derive(clap::Args, Debug)]
+struct BalancesArgs {
+    /// Wallet address; falls back to the config file, then $OWNER
+    owner: Option<String>,
+    /// Solana RPC endpoint
+    #[arg(long)]
+    rpc: Option<String>,
+    /// Also print portfolio value and risk per trade
+    #[arg(long)]
+    suggest_size: bool,
+    /// Risk per trade in percent, used with --suggest-size
+    #[arg(long, value_name = "PCT")]
+    risk_pct: Option<f64>,
+    /// processed, confirmed or finalized
+    #[arg(long)]
+    commitment: Option<Commitment>,
+    /// Fail instead of reading state older than this slot
+    #[arg(long, value_name = "SLOT")]
+    min_context_slot: Option<u64>,
+    /// Show each token as its symbol and shortened mint, e.g.
+    /// `USDC (EPjF...)`; fetches Raydium's mint list once more
+    #[arg(long)]
+    resolve_symbols: bool,
+    /// Hide tokens worth less than this many dollars (SOL is always
+    /// shown); fetches prices as --suggest-size does
+    #[arg(long, value_name = "USD", value_parser = non_negative_amount)]
+    min_usd: Option<f64>,
+    /// With --min-usd, also hide tokens without a known price
+    #[arg(long, requires = "min_usd")]
+    hide_unpriced: bool,
+    /// Print these columns, in this order, as a tab-separated table (or
+    /// JSON objects with just these keys), e.g. `mint,ui_amount,usd`
+    /// [default: one `MINT: AMOUNT` line per balance, with its dollar value
+    /// when prices are fetched]
+    #[arg(long, value_enum, value_delimiter = ',', value_name = "COLUMNS")]
+    columns: Option<Vec<BalanceColumn>>,
+}
+
+/// `balances --columns`.
+#[derive(Debug, Clone, Copy, PartialEq, ValueEnum)]
+enum BalanceColumn {
+    /// Mint address, or SOL.
+    Mint,
+    /// Ticker from Raydium's mint list; fetches it.
+    Symbol,
+    /// Amount in base units.
+    Amount,
+    /// Amount scaled by the decimals.
+    #[value(name = "ui_amount")]
+    UiAmount,
+    Decimals,
+    /// Dollar value; fetches prices.
+    Usd,
+}
+
+impl BalanceColumn {
+    fn name(self) -> &'static str {
+        match self {
+            BalanceColumn::Mint => "mint",
+            BalanceColumn::Symbol => "symbol",
+            BalanceColumn::Amount => "amount",
+            BalanceColumn::UiAmount => "ui_amount",
+            BalanceColumn::Decimals => "decimals",
+            BalanceColumn::Usd => "usd",
+        }
+    }
+
+    /// This column of `balance`, worth `usd`; `null` when unknown.
+    fn value(self, balance: &TokenBalance, usd: Option<f64>, mints: Option<&MintList>) -> Value {
+        match self {
+            BalanceColumn::Mint => Value::from(balance.mint.as_str()),
+            BalanceColumn::Symbol if balance.mint == "SOL" => Value::from("SOL"),
+            BalanceColumn::Symbol => mints
+                .and_then(|list| list.symbol(&balance.mint))
+                .map_or(Value::Null, Value::from),
+            BalanceColumn::Amount => Value::from(balance.amount),
+            BalanceColumn::UiAmount => Value::from(balance.ui_amount()),
+            BalanceColumn::Decimals => Value::from(balance.decimals),
+            BalanceColumn::Usd => usd.map_or(Value::Null, Value::from),
+        }
+    }
+
+    /// [`value`](Self::value) as a table cell: dollars to the cent and
+    /// `-` for unknown.
+    fn cell(self, balance: &TokenBalance, usd: Option<f64>, mints: Option<&MintList>) -> String {
+        match (self, self.value(balance, usd, mints)) {
+            (_, Value::Null) => "-".to_owned(),
+            (BalanceColumn::Usd, _) => format!("{:.2}", usd.unwrap_or_default()),
+            (_, Value::String(s)) => s,
+            (_, v) => v.to_string(),
+        }
+    }
+}
+
+/// `balances` as `--columns` selects: a header line and one tab-separated
+/// line per balance.
+fn write_balance_table(
+    columns: &[BalanceColumn],
+    rows: &[(&TokenBalance, Option<f64>)],
+    mints: Option<&MintList>,
+    out: &mut impl Write,
+) -> Result<()> {
+    let header: Vec<&str> = columns.iter().map(|c| c.name()).collect();
+    writeln!(out, "{}", header.join("\t"))?;
+    for &(balance, usd) in rows {
+        let cells: Vec<String> = columns
+            .iter()
+            .map(|c| c.cell(balance, usd, mints))
+            .collect();
+        writeln!(out, "{}", cells.join("\t"))?;
+    }
+    Ok(())
+}
+
+/// One balance's `--columns` as a JSON object, keys in column order.
+struct BalanceObject(Vec<(&'static str, Value)>);
+
+impl Serialize for BalanceObject {
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.collect_map(self.0.iter().map(|(name, value)| (name, value)))
+    }
+}
+
+/// `balances --format=json` with `--columns`: one object per balance with
+/// the selected keys.
+fn balance_objects(
+    columns: &[BalanceColumn],
+    rows: &[(&TokenBalance, Option<f64>)],
+    mints: Option<&MintList>,
+) -> Vec<BalanceObject> {
+    rows.iter()
+        .map(|&(balance, usd)| {
+            BalanceObject(
+                columns
+                    .iter()
+                    .map(|c| (c.name(), c.value(balance, usd, mints)))
+                    .collect(),
+            )
+        })
+        .collect()
+}
+
+impl Cli {
+    /// The settings given on the command line, as the top config layer.
+    fn flags(&self) -> Layer {
+        let mut flags = Layer {
+            format: self.format,
+            ..Default::default()
+        };
+        if let Command::Balances(args) = &self.command {
+            // Make injects an *empty* owner when $OWNER is unset; treat it
+            // as missing so the other layers can supply one.
+            flags.owner = args.owner.clone().filter(|o| !o.trim().is_empty());
+            flags.rpc = args.rpc.clone();
+            flags.risk_pct = args.risk_pct;
+        }
+        flags
+    }
+}
+
+impl BalancesArgs {
+    fn read_options(&self) -> ReadOptions {
+        ReadOptions {
+            commitment: self.commitment,
+            min_context_slot: self.min_context_slot,
+        }
+    }
+
+    /// Whether balances are listed with their USD value, for
+    /// `--suggest-size`, `--min-usd` or a `usd` column.
+    fn needs_prices(&self) -> bool {
+        self.suggest_size || self.min_usd.is_some() || self.has_column(BalanceColumn::Usd)
+    }
+
+    fn has_column(&self, column: BalanceColumn) -> bool {
+        self.columns.as_ref().is_some_and(|c| c.contains(&column))
+    }
+
+    /// Whether `balance`, worth `usd` when its price is known, is listed
+    /// under `--min-usd` and `--hide-unpriced`.
+    fn shows(&self, balance: &TokenBalance, usd: Option<f64>) -> bool {
+        if balance.mint == "SOL" {
+            return true;
+        }
+        match (self.min_usd, usd) {
+            (None, _) => true,
+            (Some(min), Some(usd)) => usd >= min,
+            (Some(_), None) => !self.hide_unpriced,
+        }
+    }
+
+    /// Raydium's mint list with `--resolve-symbols` or a `symbol` column,
+    /// to label balances by.
+    async fn mint_list(&self, client: &Client, base: &str) -> Result<Option<MintList>> {
+        if !self.resolve_symbols && !self.has_column(BalanceColumn::Symbol) {
+            return Ok(None);
+        }
+        let list = fetch_mints(client, base)
+            .await
+            .context("fetching the mint list for symbols")?;
+        Ok(Some(list))
+    }
+
+    /// Print `rows` as `--columns` selects, if it was given.
+    fn print_columns(
+        &self,
+        rows: &[(&TokenBalance, Option<f64>)],
+        mints: Option<&MintList>,
+        json: bool,
+    ) -> Result<bool> {
+        let Some(columns) = &self.columns else {
+            return Ok(false);
+        };
+        if json {
+            print_json(&balance_objects(columns, rows, mints))?;
+        } else {
+            write_balance_table(columns, rows, mints, &mut std::io::stdout().lock())?;
+        }
+        Ok(true)
+    }
+
+    /// Priced balances as text on `out`: the `--columns` table, else one
+    /// line per balance, then the `--min-usd` notice for the `hidden` ones
+    /// and the `--suggest-size` summary. Under `--columns` those last two go
+    /// to `err`, keeping the table alone on `out` for scripts.
+    fn write_priced<'w>(
+        &self,
+        sized: &SuggestedSize<&[(&TokenBalance, Option<f64>)]>,
+        hidden: usize,
+        mints: Option<&MintList>,
+        mut out: &'w mut dyn Write,
+        err: &'w mut dyn Write,
+    ) -> Result<()> {
+        let tabular = match &self.columns {
+            Some(columns) => {
+                write_balance_table(columns, sized.balances, mints, &mut out)?;
+                true
+            }
+            None => {
+                for &(b, usd) in sized.balances {
+                    let mint = mint_label(mints, &b.mint);
+                    match usd {
+                        Some(usd) => writeln!(out, "{mint}: {}  (${usd:.2})", b.amount)?,
+                        None => writeln!(out, "{mint}: {}  (price unavailable)", b.amount)?,
+                    }
+                }
+                false
+            }
+        };
+        let notes = if tabular { err } else { out };
+        if let (Some(min_usd), 1..) = (self.min_usd, hidden) {
+            writeln!(notes, "({hidden} hidden by --min-usd={min_usd})")?;
+        }
+        if self.suggest_size {
+            writeln!(notes, "Portfolio value : ${:.2}", sized.portfolio_usd)?;
+            writeln!(
+                notes,
+                "Risk per trade  : ${:.2} ({}%)",
+                sized.suggested_size_usd, sized.risk_pct
+            )?;
+        }
+        Ok(())
+    }
+}
+
+/// How a balance's mint is printed: as is, or labelled from `mints`.
+fn mint_label(mints: Option<&MintList>, mint: &str) -> String {
+    match mints {
+        Some(list) => list.label(mint),
+        None => mint.to_owned(),
+    }
+}
+
+fn proxy_url(s: &str) -> Result<String> {
+    validate_proxy_url(s)?;
+    Ok(s.trim().to_owned())
+}
+
+fn positive_amount(s: &str) -> Result<f64, String> {
+    match s.parse::<f64>() {
+        Ok(n) if n > 0.0 => Ok(n),
+        Ok(n) => Err(format!("must be greater than 0, got {n}")),
+        Err(e) => Err(e.to_string()),
+    }
+}
+
+fn non_negative_amount(s: &str) -> Result<f64, String> {
+    match s.parse::<f64>() {
+        Ok(n) if n >= 0.0 => Ok(n),
+        Ok(n) => Err(format!("must be 0 or more, got {n}")),
+        Err(e) => Err(e.to_string()),
+    }
+}
+
+/// `list-pools --sort` orders.
+#[derive(Debug, Clone, Copy, PartialEq, ValueEnum)]
+enum PoolSort {
+    /// Lowest fee first.
+    Fee,
+}
+
+/// `pools` under `--max-fee-bps` and in `--sort` order; pools that tie keep
+/// Raydium's order.
+fn select_pools(
+    mut pools: Vec<Pool>,
+    max_fee_bps: Option<u32>,
+    sort: Option<PoolSort>,
+) -> Vec<Pool> {
+    if let Some(max) = max_fee_bps {
+        pools.retain(|p| p.fee_bps <= max);
+    }
+    if sort == Some(PoolSort::Fee) {
+        pools.sort_by_key(|p| p.fee_bps);
+    }
+    pools
+}
+
+/// Direction of a trade against a pool's base (token0) / quote (token1).
+#[derive(Debug, Clone, Copy, PartialEq, ValueEnum)]
+enum Side {
+    /// Spend quote to receive base.
+    Buy,
+    /// Spend base to receive quote.
+    Sell,
+}
+
+/// Print `value` as pretty JSON, for `--format=json`.
+fn print_json(value: &impl Serialize) -> Result<()> {
+    println!("{}", serde_json::to_string_pretty(value)?);
+    Ok(())
+}
+
+/// Tell the user on stderr that a command succeeded but has no `what` to
+/// show, so an empty listing isn't mistaken for a failure; `count` is how
+/// many there are. Whether the notice was written.
+fn report_empty(count: usize, what: &str, err: &mut impl Write) -> Result<bool> {
+    if count > 0 {
+        return Ok(false);
+    }
+    writeln!(err, "no {what} found")?;
+    Ok(true)
+}
+
+/// SPL tokens in `balances`, which always list native SOL.
+fn token_count(balances: &[TokenBalance]) -> usize {
+    balances.iter().filter(|b| b.mint != "SOL").count()
+}
+
+/// Dollar amount to put at risk per trade for a given portfolio value.
+fn position_size_usd(portfolio_usd: f64, risk_pct: f64) -> f64 {
+    portfolio_usd * risk_pct / 100.0
+}
+
+/// `balances --suggest-size --format=json`: the listed balances with the
+/// portfolio value and the position size suggested from it.
+#[derive(Debug, Serialize)]
+struct SuggestedSize<B> {
+    balances: B,
+    /// Includes tokens hidden by `--min-usd`.
+    portfolio_usd: f64,
+    risk_pct: f64,
+    /// Dollars to put at risk per trade.
+    suggested_size_usd: f64,
+}
+
+impl<B> SuggestedSize<B> {
+    fn new(balances: B, portfolio_usd: f64, risk_pct: f64) -> Self {
+        Self {
+            balances,
+            portfolio_usd,
+            risk_pct,
+            suggested_size_usd: position_size_usd(portfolio_usd, risk_pct),
+        }
+    }
+}
+
+/* ------------------------------------------------------------- impact */
+
+/// Result of a swap against a constant-product pool.
+#[derive(Debug, PartialEq)]
+struct SwapEstimate {
+    amount_out: f64,
+    /// How much worse the execution price is than the spot price, fees
+    /// excluded, in percent.
+    price_impact_pct: f64,
+}
+
+/// Estimate swapping `amount_in` into an `x · y = k` pool holding
+/// `reserve_in` / `reserve_out`, after a `fee_bps` input fee. `None` when the
+/// inputs don't describe a usable pool or trade.
+fn constant_product_swap(
+    reserve_in: f64,
+    reserve_out: f64,
+    amount_in: f64,
+    fee_bps: u32,
+) -> Option<SwapEstimate> {
+    if reserve_in <= 0.0 || reserve_out <= 0.0 || amount_in <= 0.0 || fee_bps >= 10_000 {
+        return None;
+    }
+    let effective_in = amount_in * (1.0 - fee_bps as f64 / 10_000.0);
+    let amount_out = reserve_out * effective_in / (reserve_in + effective_in);
+    // Spot gives `effective_in * reserve_out / reserve_in`; the shortfall
+    // relative to it simplifies to the trade's share of the new reserve.
+    let price_impact_pct = effective_in / (reserve_in + effective_in) * 100.0;
+    Some(SwapEstimate {
+        amount_out,
+        price_impact_pct,
+    })
+}
+
+/* ------------------------------------------------------------- stream */
+
+/// Where `stream` connects when no `--url` is given.
+const DEFAULT_STREAM_URL: &str = "ws://127.0.0.1:8000/websocket";
+
+/// Reconnect delays after the server goes away; the last one repeats.
+const STREAM_RETRY_SECS: [u64; 4] = [1, 2, 5, 10];
+
+/// Walks [`STREAM_RETRY_SECS`], starting over after a connection that
+/// delivered something.
+#[derive(Debug, Default)]
+struct Reconnect {
+    attempt: usize,
+}
+
+impl Reconnect {
+    /// How long to wait before the next attempt.
+    fn delay(&mut self, connected: bool) -> Duration {
+        if connected {
+            self.attempt = 0;
+        }
+        let secs = STREAM_RETRY_SECS[self.attempt.min(STREAM_RETRY_SECS.len() - 1)];
+        self.attempt += 1;
+        Duration::from_secs(secs)
+    }
+}
+
+/// One row of the live table, coloured by tier.
+fn format_signal_row(sig: &Signal, numbers: NumberFormat) -> String {
+    let colour = match sig.tier {
+        Tier::Watch => "\x1b[36m",
+        Tier::Strong => "\x1b[33m",
+        Tier::Extreme => "\x1b[1;31m",
+    };
+    format!(
+        "{colour}{:<14} {:>+8.2}% {:>14} {:>14} {:<8} {}\x1b[0m",
+        sig.symbol,
+        sig.pct_gain_24h,
+        format_volume(sig.quote_vol_usdt, numbers),
+        sig.last_price,
+        format!("{:?}", sig.tier).to_lowercase(),
+        sig.ts.format("%H:%M:%S"),
+    )
+}
+
+/// Redraw the whole table in place, biggest gainers first.
+fn render_board(board: &BTreeMap<String, Signal>, url: &str, numbers: NumberFormat) {
+    let mut rows: Vec<&Signal> = board.values().collect();
+    rows.sort_by(|a, b| b.pct_gain_24h.total_cmp(&a.pct_gain_24h));
+
+    let mut out = String::from("\x1b[2J\x1b[H");
+    out.push_str(&format!("Signals from {url}\n\n"));
+    out.push_str(&format!(
+        "{:<14} {:>9} {:>14} {:>14} {:<8} TIME\n",
+        "SYMBOL", "GAIN", "VOL", "PRICE", "TIER"
+    ));
+    for sig in rows {
+        out.push_str(&format_signal_row(sig, numbers));
+        out.push('\n');
+    }
+    print!("{out}");
+    let _ = std::io::stdout().flush();
+}
+
+/// Read signals from one connection until the server closes it. Frames that
+/// are not a [`Signal`] are skipped.
+async fn consume_signals(
+    url: &str,
+    accept_invalid_certs: bool,
+    mut on_signal: impl FnMut(Signal),
+) -> Result<()> {
+    let (mut ws, _) = connect_ws(url, accept_invalid_certs).await?;
+    while let Some(frame) = ws.next().await {
+        if let tungstenite::Message::Text(txt) = frame? {
+            match serde_json::from_str::<Signal>(&txt) {
+                Ok(sig) => on_signal(sig),
+                Err(e) => tracing::debug!("skipping non-signal frame: {e}"),
+            }
+        }
+    }
+    Ok(())
+}
+
+/// Follow the server's feed forever, reconnecting whenever it restarts.
+async fn stream_signals(url: &str, accept_invalid_certs: bool, numbers: NumberFormat) {
+    let mut board = BTreeMap::new();
+    let mut reconnect = Reconnect::default();
+    loop {
+        let mut connected = false;
+        let result = consume_signals(url, accept_invalid_certs, |sig| {
+            connected = true;
+            board.insert(sig.symbol.clone(), sig);
+            render_board(&board, url, numbers);
+        })
+        .await;
+
+        let delay = reconnect.delay(connected);
+        match result {
+            Ok(()) => eprintln!("connection closed, reconnecting in {delay:?}"),
+            Err(e) => eprintln!("{e}, reconnecting in {delay:?}"),
+        }
+        tokio::time::sleep(delay).await;
+    }
+}
+
+#[derive(Deserialize, Serialize)]
+struct MainInfoData {
+    tvl: f64,
+    #[serde(alias = "volume24")]
+    volume_24: f64,
+}
+
+async fn fetch_main_info(client: &Client, base: &str) -> Result<MainInfoData> {
+    let url = format!("{base}{INFO_PATH}");
+    let outer: Envelope<MainInfoData> = client.get(url).send().await?.json().await?;
+    outer.into_data(INFO_PATH)
+}
+
+#[derive(Deserialize, Clone)]
+struct TokenSide {
+    #[serde(alias = "mint", alias = "address")]
+    mint: Option<String>,
+}
+
+#[derive(Deserialize, Clone)]
+struct RawPool {
+    id: Option<String>,
+    #[serde(alias = "base_mint", alias = "baseMint")]
+    base_mint: Option<String>,
+    #[serde(alias = "quote_mint", alias = "quoteMint")]
+    quote_mint: Option<String>,
+    base: Option<TokenSide>,
+    quote: Option<TokenSide>,
+    #[serde(alias = "mintA")]
+    mint_a: Option<TokenSide>,
+    #[serde(alias = "mintB")]
+    mint_b: Option<TokenSide>,
+    #[serde(alias = "fee_bps", alias = "feeBps")]
+    fee_bps: Option<u32>,
+    #[serde(alias = "feeRate")]
+    fee_rate: Option<f64>,
+    #[serde(alias = "mintAmountA")]
+    mint_amount_a: Option<f64>,
+    #[serde(alias = "mintAmountB")]
+    mint_amount_b: Option<f64>,
+    #[serde(alias = "volume24h")]
+    volume_24h: Option<f64>,
+}
+
+#[derive(Debug, Serialize)]
+struct Pool {
+    id: String,
+    token0: String,
+    token1: String,
+    fee_bps: u32,
+    /// `(token0, token1)` amounts held by the pool, in UI units, when the API
+    /// reports them.
+    reserves: Option<(f64, f64)>,
+    /// 24-hour volume in USD, when the API reports it.
+    volume_24h: Option<f64>,
+}
+
+/// A fee rate such as `0.0025` in whole basis points, half a bp rounding
+/// up. The rate goes through its shortest decimal form, which is what the
+/// API sent, so the result is exact instead of depending on how
+/// `rate * 10000` happens to round in binary floating point. `None` for
+/// negative or non-finite rates.
+fn fee_rate_to_bps(rate: f64) -> Option<u32> {
+    let rate = Decimal::from_str(&rate.to_string()).ok()?;
+    (rate * Decimal::from(10_000))
+        .round_dp_with_strategy(0, RoundingStrategy::MidpointAwayFromZero)
+        .to_u32()
+}
+
+fn raw_to_pool(raw: &RawPool) -> Option<Pool> {
+    let id = raw.id.as_ref()?;
+
+    let mint0 = raw
+        .base_mint
+        .clone()
+        .or_else(|| raw.base.as_ref().and_then(|t| t.mint.clone()))
+        .or_else(|| raw.mint_a.as_ref().and_then(|t| t.mint.clone()))?;
+    let mint1 = raw
+        .quote_mint
+        .clone()
+        .or_else(|| raw.quote.as_ref().and_then(|t| t.mint.clone()))
+        .or_else(|| raw.mint_b.as_ref().and_then(|t| t.mint.clone()))?;
+    let fee_bps = raw
+        .fee_bps
+        .or_else(|| raw.fee_rate.and_then(fee_rate_to_bps))?;
+
+    Some(Pool {
+        id: id.to_owned(),
+        token0: mint0,
+        token1: mint1,
+        fee_bps,
+        reserves: raw.mint_amount_a.zip(raw.mint_amount_b),
+        volume_24h: raw.volume_24h,
+    })
+}
+
+async fn fetch_pools(client: &Client, base: &str) -> Result<Vec<Pool>> {
+    fetch_pools_from(client, Url::parse(&format!("{base}{POOLS_PATH}"))?).await
+}
+
+/// Look up a single pool by id.
+async fn fetch_pool(client: &Client, base: &str, id: &str) -> Result<Pool> {
+    let url = Url::parse_with_params(&format!("{base}{POOL_BY_ID_PATH}"), &[("ids", id)])?;
+    fetch_pools_from(client, url)
+        .await?
+        .into_iter()
+        .find(|p| p.id == id)
+        .ok_or_else(|| anyhow!("pool {id} not found"))
+}
+
+async fn fetch_pools_from(client: &Client, url: Url) -> Result<Vec<Pool>> {
+    let body: Value = client.get(url).send().await?.json().await?;
+    pools_from_body(&body)
+}
+
+/// The usable pools of a Raydium response; rows missing an id, a mint or
+/// a fee are skipped.
+fn pools_from_body(body: &Value) -> Result<Vec<Pool>> {
+    let pools = locate_pool_array(body).context("Raydium API")?;
+    if pools.is_empty() {
+        return Err(anyhow!("Raydium API: no pool list found in response"));
+    }
+
+    let mut out = Vec::with_capacity(pools.len());
+    for item in pools {
+        if let Ok(raw) = serde_json::from_value::<RawPool>(item) {
+            if let Some(p) = raw_to_pool(&raw) {
+                out.push(p);
+            }
+        }
+    }
+    Ok(out)
+}
+
+/// Print the scanner's resolved configuration and its effect on the sample
+/// frames; an invalid setting is the error the server would stop with.
+fn validate_config() -> Result<()> {
+    let report = ConfigReport::from_env()?;
+    print!("{report}");
+    if report.signals() == 0 {
+        eprintln!(
+            "warning: no sample ticker passes; check the thresholds, \
+             SIGNAL_FILTER_EXPR and TICKER_FIELDS"
+        );
+    }
+    Ok(())
+}
+
+/// Write the completion script for `shell` to `out`.
+fn write_completions(shell: Shell, out: &mut impl Write) {
+    clap_complete::generate(shell, &mut Cli::command(), "raydium_cli", out);
+}
+
+#[tokio::main]
+async fn main() -> Result<()> {
+    let cli = Cli::parse();
+    // Needs neither settings nor network, so skip loading them.
+    if let Command::Completions { shell } = cli.command {
+        write_completions(shell, &mut std::io::stdout());
+        return Ok(());
+    }
+    if let Command::Selftest = cli.command {
+        return selftest::run(&mut std::io::stdout());
+    }
+    if let Command::ValidateConfig = cli.command {
+        return validate_config();
+    }
+    init_tracing(cli.verbosity.verbosity());
+    let settings = Settings::resolve(
+        cli.flags(),
+        Layer::load(cli.config.as_deref())?,
+        Layer::from_env()?,
+    )?;
+    let base = settings.raydium_base.as_str();
+    let json = settings.format == OutputFormat::Json;
+    let numbers = cli.number_format;
+    let opts = ClientOptions::from_env()?
+        .with_proxy_override(cli.proxy.as_deref())
+        .with_insecure_override(cli.insecure);
+    let http = build_client(&opts)?;
+
+    match cli.command {
+        Command::ListPools { max_fee_bps, sort } => {
+            let pools = select_pools(fetch_pools(&http, base).await?, max_fee_bps, sort);
+            report_empty(pools.len(), "pools", &mut std::io::stderr())?;
+            if json {
+                return print_json(&pools);
+            }
+            for p in pools {
+                let volume = p
+                    .volume_24h
+                    .map(|v| format!(", vol ${}", format_volume(v, numbers)))
+                    .unwrap_or_default();
+                println!(
+                    "{:<20} {}→{} (fee {} bps{volume})",
+                    p.id, p.token0, p.token1, p.fee_bps
+                );
+            }
+        }
+        Command::Balances(args) if !args.needs_prices() => {
+            // Share the client so balance queries honour the proxy too.
+            let rpc = SolanaRpc::with_client(http.clone(), &settings.rpc)
+                .with_read_options(args.read_options());
+            let balances = fetch_balances_ui(settings.owner()?, &rpc).await?;
+            let tokens = token_count(&balances);
+            report_empty(tokens, "SPL-token balances", &mut std::io::stderr())?;
+            if json && args.columns.is_none() {
+                return print_json(&balances);
+            }
+            let mints = args.mint_list(&http, base).await?;
+            let rows: Vec<_> = balances.iter().map(|b| (b, None)).collect();
+            if args.print_columns(&rows, mints.as_ref(), json)? {
+                return Ok(());
+            }
+            for b in balances {
+                println!("{}: {}", mint_label(mints.as_ref(), &b.mint), b.amount);
+            }
+        }
+        Command::Balances(args) => {
+            let rpc = SolanaRpc::with_client(http.clone(), &settings.rpc)
+                .with_read_options(args.read_options());
+            let risk_pct = settings.risk_pct;
+            let balances = fetch_balances_ui(settings.owner()?, &rpc).await?;
+            let ids: Vec<&str> = balances.iter().map(|b| b.price_mint()).collect();
+            let provider = provider_from_env(http.clone(), base).map_err(anyhow::Error::msg)?;
+            let prices = provider.prices(&ids).await?;
+            let tokens = token_count(&balances);
+            report_empty(tokens, "SPL-token balances", &mut std::io::stderr())?;
+
+            // Hidden tokens still count towards the portfolio value.
+            let mut portfolio_usd = 0.0;
+            let mut shown = Vec::new();
+            for b in &balances {
+                let usd = prices.get(b.price_mint()).map(|p| b.ui_amount() * p);
+                portfolio_usd += usd.unwrap_or(0.0);
+                if args.shows(b, usd) {
+                    shown.push((b, usd));
+                }
+            }
+            if json && args.suggest_size {
+                return match &args.columns {
+                    Some(columns) => {
+                        let mints = args.mint_list(&http, base).await?;
+                        let objects = balance_objects(columns, &shown, mints.as_ref());
+                        print_json(&SuggestedSize::new(objects, portfolio_usd, risk_pct))
+                    }
+                    None => {
+                        let shown: Vec<&TokenBalance> = shown.iter().map(|&(b, _)| b).collect();
+                        print_json(&SuggestedSize::new(shown, portfolio_usd, risk_pct))
+                    }
+                };
+            }
+            if json && args.columns.is_none() {
+                let shown: Vec<&TokenBalance> = shown.iter().map(|&(b, _)| b).collect();
+                return print_json(&shown);
+            }
+
+            let mints = args.mint_list(&http, base).await?;
+            if json {
+                args.print_columns(&shown, mints.as_ref(), json)?;
+                return Ok(());
+            }
+            let hidden = balances.len() - shown.len();
+            let sized = SuggestedSize::new(shown.as_slice(), portfolio_usd, risk_pct);
+            args.write_priced(
+                &sized,
+                hidden,
+                mints.as_ref(),
+                &mut std::io::stdout().lock(),
+                &mut std::io::stderr(),
+            )?;
+        }
+        Command::Info => {
+            let i = fetch_main_info(&http, base).await?;
+            if json {
+                return print_json(&i);
+            }
+            println!(
+                "Raydium TVL  : ${}\nRaydium 24 h : ${}",
+                format_volume(i.tvl, numbers),
+                format_volume(i.volume_24, numbers)
+            );
+        }
+        Command::Price { tokens } => {
+            let tokens: Vec<&str> = tokens.split(',').map(str::trim).collect();
+            let provider = provider_from_env(http.clone(), base).map_err(anyhow::Error::msg)?;
+            // Only download the mint list when there are symbols to resolve
+            // and the provider can't take them as they are.
+            let list = if provider.accepts_symbols() || tokens.iter().all(|t| is_mint_address(t)) {
+                MintList::default()
+            } else {
+                fetch_mints(&http, base).await?
+            };
+            let mut resolved = Vec::new();
+            for token in tokens {
+                if provider.accepts_symbols() {
+                    resolved.push((token, token.to_owned()));
+                    continue;
+                }
+                match list.resolve_token(token) {
+                    Ok(mint) => resolved.push((token, mint)),
+                    Err(e) => eprintln!("{e}"),
+                }
+            }
+            if resolved.is_empty() {
+                bail!("no token could be resolved to a mint");
+            }
+
+            let ids: Vec<&str> = resolved.iter().map(|(_, mint)| mint.as_str()).collect();
+            let prices = provider.prices(&ids).await?;
+            report_empty(prices.len(), "prices", &mut std::io::stderr())?;
+            if json {
+                return print_json(&prices);
+            }
+            for (token, mint) in &resolved {
+                let label = if token == mint {
+                    mint.clone()
+                } else {
+                    format!("{token} ({mint})")
+                };
+                match prices.get(mint) {
+                    Some(p) => println!("{label}  ${:.6}", p),
+                    None => println!("{label}  (price unavailable)"),
+                }
+            }
+        }
+        Command::ComparePrice {
+            symbol,
+            binance_url,
+        } => {
+            let cmp = compare::compare_price(&http, base, &binance_url, &symbol).await?;
+            if json {
+                return print_json(&cmp);
+            }
+            print!("{cmp}");
+        }
+        Command::Mints {
+            symbol,
+            all,
+            max_pages,
+            filter,
+        } => {
+            let list = if all {
+                fetch_mint_pages(&http, base, max_pages).await?
+            } else {
+                fetch_mints(&http, base).await?
+            };
+            let toks = match &symbol {
+                Some(symbol) => list
+                    .resolve_symbol(symbol)
+                    .map(Resolved::candidates)
+                    .unwrap_or_default(),
+                None => list.mints.iter().collect(),
+            };
+            let toks = filter.apply(&list, toks);
+            report_empty(toks.len(), "mints", &mut std::io::stderr())?;
+            if json {
+                return print_json(&toks);
+            }
+            for t in toks {
+                println!(
+                    "{:<44} {:<10} {:<3} {}",
+                    t.mint, t.symbol, t.decimals, t.name
+                );
+            }
+        }
+        Command::RpcBench { rpcs, timeout } => {
+            let timeout = Duration::from_secs(timeout);
+            println!("{:<4} {:<50} {:>10}  STATUS", "#", "RPC", "LATENCY");
+            for (rank, probe) in bench_rpcs(&http, &rpcs, timeout).await.iter().enumerate() {
+                let latency = probe
+                    .latency
+                    .map(|l| format!("{} ms", l.as_millis()))
+                    .unwrap_or_else(|| "-".into());
+                let status = probe.error.as_deref().unwrap_or("ok");
+                println!(
+                    "{:<4} {:<50} {:>10}  {status}",
+                    rank + 1,
+                    probe.url,
+                    latency
+                );
+            }
+        }
+        Command::Stream { url } => stream_signals(&url, opts.accept_invalid_certs, numbers).await,
+        Command::Record(args) => {
+            record(&args, &cli.output.resolve(), opts.accept_invalid_certs).await?
+        }
+        Command::Impact {
+            pool_id,
+            amount,
+            side,
+        } => {
+            let pool = fetch_pool(&http, base, &pool_id).await?;
+            let Some((base_reserve, quote_reserve)) = pool.reserves else {
+                return Err(anyhow!(
+                    "pool {pool_id} has no reserve data; cannot estimate impact"
+                ));
+            };
+            let (reserve_in, reserve_out, mint_in, mint_out) = match side {
+                Side::Buy => (quote_reserve, base_reserve, &pool.token1, &pool.token0),
+                Side::Sell => (base_reserve, quote_reserve, &pool.token0, &pool.token1),
+            };
+            let est = constant_product_swap(reserve_in, reserve_out, amount, pool.fee_bps)
+                .ok_or_else(|| anyhow!("pool {pool_id} has empty reserves"))?;
+
+            println!("Pool          : {} (fee {} bps)", pool.id, pool.fee_bps);
+            println!(
+                "Reserves      : {base_reserve} {} / {quote_reserve} {}",
+                pool.token0, pool.token1
+            );
+            println!("Input         : {amount} {mint_in}");
+            println!("Expected out  : {:.6} {mint_out}", est.amount_out);
+            println!("Price impact  : {:.4}%", est.price_impact_pct);
+        }
+        Command::Completions { .. } | Command::Selftest | Command::ValidateConfig => {
+            unreachable!("handled before loading settings")
+        }
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use shuttle_axum::axum::{
+        extract::{ws::Message, WebSocketUpgrade},
+        routing::get,
+        Router,
+    };
+
+    /// A fake scanner that sends `frames` to each client, then closes.
+    async fn mock_scanner(frames: &'static [&'static str]) -> String {
+        let app = Router::new().route(
+            "/websocket",
+            get(move |ws: WebSocketUpgrade| async move {
+                ws.on_upgrade(move |mut socket| async move {
+                    for frame in frames {
+                        let _ = socket.send(Message::Text((*frame).to_owned())).await;
+                    }
+                    let _ = socket.send(Message::Close(None)).await;
+                })
+            }),
+        );
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        tokio::spawn(async move { shuttle_axum::axum::serve(listener, app).await });
+        format!("ws://{addr}/websocket")
+    }
+
+    #[tokio::test]
+    async fn stream_decodes_and_formats_signals() {
+        let url = mock_scanner(&[
+            r#"{"symbol":"BONKUSDT","pct_gain_24h":12.5,"quote_vol_usdt":3500000.0,"last_price":0.00002,"tier":"strong","ts":"2024-05-01T12:34:56Z"}"#,
+            r#"{"type":"heartbeat"}"#,
+            r#"{"symbol":"WIFUSDT","pct_gain_24h":25.0,"quote_vol_usdt":1200000.0,"last_price":2.5,"tier":"extreme","ts":"2024-05-01T12:35:00Z"}"#,
+        ])
+        .await;
+
+        let mut signals = Vec::new();
+        consume_signals(&url, false, |sig| signals.push(sig))
+            .await
+            .unwrap();
+
+        assert_eq!(signals.len(), 2);
+        assert_eq!(signals[0].symbol, "BONKUSDT");
+        assert_eq!(signals[1].tier, Tier::Extreme);
+
+        let row = format_signal_row(&signals[0], NumberFormat::Si);
+        assert!(row.starts_with("\x1b[33mBONKUSDT"), "{row:?}");
+        assert!(row.contains("+12.50%"), "{row:?}");
+        assert!(row.contains("3.5M"), "{row:?}");
+        assert!(row.contains("strong"), "{row:?}");
+        assert!(row.contains("12:34:56"), "{row:?}");
+        assert!(row.ends_with("\x1b[0m"), "{row:?}");
+    }
+
+    #[tokio::test]
+    async fn record_writes_raw_frames_and_rotates() {
+        const FRAMES: &[&str] = &[
+            r#"[{"s":"BTCUSDT","P":"1.0"}]"#,
+            "{\n  \"pretty\": true\n}",
+            r#"[{"s":"ETHUSDT","P":"9.0"}]"#,
+        ];
+        let url = mock_scanner(FRAMES).await;
+        let dir = std::env::temp_dir().join(format!("record-{}", std::process::id()));
+
+        // A relative --out lands under --output-dir, which is created.
+        let output_dir = format!("--output-dir={}", dir.display());
+        let cli = parse(&["record", "--out=corpus/frames.jsonl", &output_dir]).unwrap();
+        let Command::Record(args) = &cli.command else {
+            panic!("expected record");
+        };
+        let out = args.out_path(&cli.output.resolve()).unwrap();
+        assert_eq!(out, dir.join("corpus/frames.jsonl"));
+
+        let mut writer = record::FrameWriter::create(out, None).unwrap();
+        record::record_connection(&url, false, &mut writer)
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(writer.frames, 3);
+        let written = std::fs::read_to_string(dir.join("corpus/frames.jsonl")).unwrap();
+        let lines: Vec<_> = written.lines().collect();
+        assert_eq!(lines[0], FRAMES[0]);
+        assert_eq!(lines[1], "{   \"pretty\": true }");
+        assert_eq!(lines[2], FRAMES[2]);
+
+        // Room for two of the short frames per file.
+        let limit = 2 * (FRAMES[0].len() as u64 + 1);
+        let mut writer =
+            record::FrameWriter::create(dir.join("rotated.jsonl"), Some(limit)).unwrap();
+        for frame in [FRAMES[0]; 5] {
+            writer.write(frame).unwrap();
+        }
+        assert_eq!(writer.files(), 3);
+        assert_eq!(writer.path(), dir.join("rotated.2.jsonl"));
+        let lines = |name: &str| {
+            std::fs::read_to_string(dir.join(name))
+                .unwrap()
+                .lines()
+                .count()
+        };
+        assert_eq!(
+            (
+                lines("rotated.jsonl"),
+                lines("rotated.1.jsonl"),
+                lines("rotated.2.jsonl")
+            ),
+            (2, 2, 1)
+        );
+        std::fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn parses_record() {
+        let cli = parse(&["record", "--duration=60", "--rotate-mb=10"]).unwrap();
+        let Command::Record(args) = cli.command else {
+            panic!("expected record");
+        };
+        assert_eq!(args.out, PathBuf::from("frames.jsonl"));
+        assert_eq!((args.duration, args.rotate_mb), (Some(60), Some(10)));
+        assert!(parse(&["record", "--rotate-mb=0"]).is_err());
+    }
+
+    fn parse(args: &[&str]) -> Result<Cli, clap::Error> {
+        Cli::try_parse_from(std::iter::once("raydium_cli").chain(args.iter().copied()))
+    }
+
+    #[test]
+    fn cli_definition_is_consistent() {
+        use clap::CommandFactory;
+        Cli::command().debug_assert();
+    }
+
+    #[test]
+    fn parses_simple_commands() {
+        assert!(matches!(
+            parse(&["list-pools"]).unwrap().command,
+            Command::ListPools {
+                max_fee_bps: None,
+                sort: None
+            }
+        ));
+        assert!(matches!(
+            parse(&["list-pools", "--max-fee-bps=25", "--sort=fee"])
+                .unwrap()
+                .command,
+            Command::ListPools {
+                max_fee_bps: Some(25),
+                sort: Some(PoolSort::Fee)
+            }
+        ));
+        assert!(parse(&["list-pools", "--sort=tvl"]).is_err());
+        assert!(matches!(parse(&["info"]).unwrap().command, Command::Info));
+        assert!(matches!(
+            parse(&["mints"]).unwrap().command,
+            Command::Mints {
+                symbol: None,
+                all: false,
+                ..
+            }
+        ));
+        let Command::Mints { symbol, .. } = parse(&["mints", "USDC"]).unwrap().command else {
+            panic!("expected mints");
+        };
+        assert_eq!(symbol.as_deref(), Some("USDC"));
+        let Command::Mints { all, max_pages, .. } =
+            parse(&["mints", "--all", "--max-pages=5"]).unwrap().command
+        else {
+            panic!("expected mints");
+        };
+        assert!(all);
+        assert_eq!(max_pages, 5);
+        assert!(parse(&["mints", "--max-pages=5"]).is_err());
+        let Command::Mints { filter, .. } = parse(&[
+            "mints",
+            "--symbol-contains=usd",
+            "--min-decimals=6",
+            "--max-decimals=9",
+            "--verified-only",
+            "--limit=3",
+        ])
+        .unwrap()
+        .command
+        else {
+            panic!("expected mints");
+        };
+        assert_eq!(
+            filter,
+            MintFilter {
+                symbol_contains: Some("usd".into()),
+                min_decimals: Some(6),
+                max_decimals: Some(9),
+                verified_only: true,
+                limit: Some(3),
+            }
+        );
+        assert!(parse(&[]).is_err());
+        assert!(parse(&["unknown"]).is_err());
+    }
+
+    #[test]
+    fn parses_balances() {
+        let cli = parse(&[
+            "balances",
+            "Owner111",
+            "--rpc=https://rpc.test",
+            "--suggest-size",
+            "--risk-pct=1.5",
+            "--commitment=finalized",
+            "--min-context-slot=42",
+        ])
+        .unwrap();
+        let Command::Balances(args) = &cli.command else {
+            panic!("expected balances, got {:?}", cli.command);
+        };
+        assert!(args.suggest_size);
+        assert_eq!(
+            args.read_options(),
+            ReadOptions {
+                commitment: Some(Commitment::Finalized),
+                min_context_slot: Some(42),
+            }
+        );
+
+        let flags = cli.flags();
+        assert_eq!(flags.owner.as_deref(), Some("Owner111"));
+        assert_eq!(flags.rpc.as_deref(), Some("https://rpc.test"));
+        assert_eq!(flags.risk_pct, Some(1.5));
+
+        assert!(parse(&["balances", "--commitment=max"]).is_err());
+    }
+
+    #[test]
+    fn empty_results_get_a_notice_instead_of_an_error() {
+        let mut err = Vec::new();
+        assert!(report_empty(0, "pools", &mut err).unwrap());
+        assert_eq!(String::from_utf8(err).unwrap(), "no pools found\n");
+
+        let mut err = Vec::new();
+        assert!(!report_empty(3, "pools", &mut err).unwrap());
+        assert!(err.is_empty());
+
+        let sol_only = [TokenBalance {
+            mint: "SOL".to_owned(),
+            amount: 42,
+            decimals: 9,
+        }];
+        assert_eq!(token_count(&sol_only), 0);
+    }
+
+    #[test]
+    fn min_usd_hides_dust_but_keeps_sol() {
+        let balance = |mint: &str| TokenBalance {
+            mint: mint.to_owned(),
+            amount: 1,
+            decimals: 0,
+        };
+        let args = |extra: &[&str]| {
+            let cli = parse(&[&["balances", "Owner111"], extra].concat()).unwrap();
+            let Command::Balances(args) = cli.command else {
+                panic!("expected balances, got {:?}", cli.command);
+            };
+            args
+        };
+
+        let all = args(&[]);
+        assert!(!all.needs_prices());
+        assert!(all.shows(&balance("MintA"), Some(0.01)));
+
+        let min = args(&["--min-usd=5"]);
+        assert!(min.needs_prices());
+        assert!(min.shows(&balance("MintA"), Some(5.0)));
+        assert!(!min.shows(&balance("MintA"), Some(4.99)));
+        assert!(min.shows(&balance("SOL"), Some(0.01)));
+        // Unpriced tokens stay, marked "price unavailable".
+        assert!(min.shows(&balance("MintA"), None));
+
+        let strict = args(&["--min-usd=5", "--hide-unpriced"]);
+        assert!(!strict.shows(&balance("MintA"), None));
+        assert!(strict.shows(&balance("MintA"), Some(10.0)));
+        assert!(strict.shows(&balance("SOL"), None));
+
+        // Zero keeps every priced token and, with --hide-unpriced, drops the
+        // rest.
+        let priced = args(&["--min-usd=0", "--hide-unpriced"]);
+        assert!(priced.shows(&balance("MintA"), Some(0.0)));
+        assert!(!priced.shows(&balance("MintA"), None));
+
+        assert!(parse(&["balances", "--hide-unpriced"]).is_err());
+        assert!(parse(&["balances", "--min-usd=-1"]).is_err());
+    }
+
+    #[test]
+    fn balance_columns_are_validated_and_printed_in_order() {
+        assert!(parse(&["balances", "--columns=mint,price"]).is_err());
+        assert!(parse(&["balances", "--columns=ui-amount"]).is_err());
+        let cli = parse(&["balances", "--columns=usd,mint,ui_amount"]).unwrap();
+        let Command::Balances(args) = cli.command else {
+            panic!("expected balances, got {:?}", cli.command);
+        };
+        let columns = args.columns.clone().unwrap();
+        assert_eq!(
+            columns,
+            [
+                BalanceColumn::Usd,
+                BalanceColumn::Mint,
+                BalanceColumn::UiAmount
+            ]
+        );
+        assert!(args.needs_prices());
+
+        let sol = TokenBalance {
+            mint: "SOL".to_owned(),
+            amount: 1_500_000_000,
+            decimals: 9,
+        };
+        let usdc = TokenBalance {
+            mint: "EPjFWdd5AufqSSqeM2qN1xzybapC8G4wEGGkZwyTDt1v".to_owned(),
+            amount: 2_500_000,
+            decimals: 6,
+        };
+        let rows = [(&sol, Some(225.0)), (&usdc, None)];
+        let mut out = Vec::new();
+        write_balance_table(&columns, &rows, None, &mut out).unwrap();
+        assert_eq!(
+            String::from_utf8(out).unwrap(),
+            "usd\tmint\tui_amount\n\
+             225.00\tSOL\t1.5\n\
+             -\tEPjFWdd5AufqSSqeM2qN1xzybapC8G4wEGGkZwyTDt1v\t2.5\n"
+        );
+
+        let list = MintList::new(
+            vec![mints::MintItem {
+                mint: usdc.mint.clone(),
+                symbol: "USDC".to_owned(),
+                name: "USD Coin".to_owned(),
+                decimals: 6,
+            }],
+            [],
+        );
+        // Keys keep the column order rather than sorting.
+        let columns = [BalanceColumn::Symbol, BalanceColumn::Amount];
+        let objects = balance_objects(&columns, &rows, Some(&list));
+        assert_eq!(
+            serde_json::to_string(&objects).unwrap(),
+            r#"[{"symbol":"SOL","amount":1500000000},{"symbol":"USDC","amount":2500000}]"#
+        );
+    }
+
+    #[test]
+    fn balances_text_keeps_a_columns_table_alone_on_stdout() {
+        let sol = TokenBalance {
+            mint: "SOL".to_owned(),
+            amount: 2_000_000_000,
+            decimals: 9,
+        };
+        let usdc = TokenBalance {
+            mint: "UsdcMint".to_owned(),
+            amount: 50_000_000,
+            decimals: 6,
+        };
+        let rows = [(&sol, Some(300.0)), (&usdc, Some(50.0))];
+        let sized = SuggestedSize::new(&rows[..], 350.0, 2.0);
+        let write = |flags: &[&str]| {
+            let cli = parse(&[&["balances"], flags].concat()).unwrap();
+            let Command::Balances(args) = cli.command else {
+                panic!("expected balances, got {:?}", cli.command);
+            };
+            let (mut out, mut err) = (Vec::new(), Vec::new());
+            args.write_priced(&sized, 1, None, &mut out, &mut err)
+                .unwrap();
+            (
+                String::from_utf8(out).unwrap(),
+                String::from_utf8(err).unwrap(),
+            )
+        };
+        let summary = "Portfolio value : $350.00\nRisk per trade  : $7.00 (2%)\n";
+
+        let (out, err) = write(&["--columns=mint,usd", "--suggest-size", "--min-usd=1"]);
+        assert_eq!(out, "mint\tusd\nSOL\t300.00\nUsdcMint\t50.00\n");
+        assert_eq!(err, format!("(1 hidden by --min-usd=1)\n{summary}"));
+
+        // Without --columns, the default is one line per balance, with the
+        // summary after it on stdout.
+        let (out, err) = write(&["--suggest-size"]);
+        assert_eq!(
+            out,
+            format!("SOL: 2000000000  ($300.00)\nUsdcMint: 50000000  ($50.00)\n{summary}")
+        );
+        assert_eq!(err, "");
+    }
+
+    #[test]
+    fn empty_balances_owner_falls_through_to_other_layers() {
+        // `make raydium-balances` passes "" when $OWNER is unset.
+        let cli = parse(&["balances", ""]).unwrap();
+        assert_eq!(cli.flags().owner, None);
+    }
+
+    #[test]
+    fn parses_price() {
+        let cli = parse(&["price", "MintA,MintB"]).unwrap();
+        assert!(matches!(cli.command, Command::Price { tokens } if tokens == "MintA,MintB"));
+        assert!(parse(&["price"]).is_err());
+    }
+
+    #[test]
+    fn parses_compare_price() {
+        let cli = parse(&["compare-price", "SOL"]).unwrap();
+        assert!(matches!(
+            cli.command,
+            Command::ComparePrice { symbol, binance_url }
+                if symbol == "SOL" && binance_url == compare::DEFAULT_BINANCE_API
+        ));
+        let cli = parse(&[
+            "compare-price",
+            "SOL",
+            "--binance-url",
+            "http://127.0.0.1:1",
+        ])
+        .unwrap();
+        assert!(matches!(
+            cli.command,
+            Command::ComparePrice { binance_url, .. } if binance_url == "http://127.0.0.1:1"
+        ));
+        assert!(parse(&["compare-price"]).is_err());
+    }
+
+    #[test]
+    fn parses_rpc_bench() {
+        let cli = parse(&["rpc-bench", "--rpcs=http://a,http://b", "--timeout=2"]).unwrap();
+        let Command::RpcBench { rpcs, timeout } = cli.command else {
+            panic!("expected rpc-bench");
+        };
+        assert_eq!(rpcs, ["http://a", "http://b"]);
+        assert_eq!(timeout, 2);
+        assert!(parse(&["rpc-bench"]).is_err());
+    }
+
+    #[test]
+    fn parses_stream() {
+        let cli = parse(&["stream"]).unwrap();
+        assert!(matches!(cli.command, Command::Stream { url } if url == DEFAULT_STREAM_URL));
+        let cli = parse(&["stream", "--url=ws://host/websocket"]).unwrap();
+        assert!(matches!(cli.command, Command::Stream { url } if url == "ws://host/websocket"));
+    }
+
+    #[test]
+    fn parses_number_format() {
+        assert_eq!(parse(&["info"]).unwrap().number_format, NumberFormat::Si);
+        let cli = parse(&["stream", "--number-format=commas"]).unwrap();
+        assert_eq!(cli.number_format, NumberFormat::Commas);
+        assert!(parse(&["info", "--number-format=hex"]).is_err());
+    }
+
+    #[test]
+    fn parses_impact() {
+        let cli = parse(&["impact", "Pool1", "--amount=10", "--side=sell"]).unwrap();
+        let Command::Impact {
+            pool_id,
+            amount,
+            side,
+        } = cli.command
+        else {
+            panic!("expected impact");
+        };
+        assert_eq!(
+            (pool_id.as_str(), amount, side),
+            ("Pool1", 10.0, Side::Sell)
+        );
+
+        let cli = parse(&["impact", "Pool1", "--amount=10"]).unwrap();
+        assert!(matches!(
+            cli.command,
+            Command::Impact {
+                side: Side::Buy,
+                ..
+            }
+        ));
+        assert!(parse(&["impact", "Pool1", "--amount=0"]).is_err());
+        assert!(parse(&["impact", "Pool1"]).is_err());
+    }
+
+    #[test]
+    fn completions_generate_for_every_shell() {
+        for shell in [Shell::Bash, Shell::Zsh, Shell::Fish, Shell::PowerShell] {
+            let cli = parse(&["completions", &shell.to_string()]).unwrap();
+            assert!(matches!(cli.command, Command::Completions { shell: s } if s == shell));
+
+            let mut out = Vec::new();
+            write_completions(shell, &mut out);
+            let script = String::from_utf8(out).unwrap();
+            assert!(script.contains("raydium_cli"), "{shell}");
+            assert!(script.contains("list-pools"), "{shell}");
+        }
+        assert!(parse(&["completions", "tcsh"]).is_err());
+    }
+
+    #[test]
+    fn global_options_work_after_the_subcommand() {
+        let cli = parse(&["mints", "--format=json", "--config=/tmp/c.toml"]).unwrap();
+        assert_eq!(cli.flags().format, Some(OutputFormat::Json));
+        assert_eq!(
+            cli.config.as_deref(),
+            Some(std::path::Path::new("/tmp/c.toml"))
+        );
+    }
+
+    #[test]
+    fn verbosity_flags_are_global() {
+        use crypto_scanner_agent::logging::Verbosity;
+
+        let cli = parse(&["info", "-q"]).unwrap();
+        assert_eq!(cli.verbosity.verbosity(), Verbosity::Quiet);
+        let cli = parse(&["--verbose", "mints"]).unwrap();
+        assert_eq!(cli.verbosity.verbosity(), Verbosity::Verbose);
+        assert!(parse(&["info", "-q", "-v"]).is_err());
+    }
+
+    #[test]
+    fn proxy_flag_is_validated() {
+        let cli = parse(&["info", "--proxy", "socks5h://127.0.0.1:9050"]).unwrap();
+        assert_eq!(cli.proxy.as_deref(), Some("socks5h://127.0.0.1:9050"));
+        assert!(parse(&["--proxy", "ftp://proxy.local", "info"]).is_err());
+        assert!(!cli.insecure);
+        assert!(parse(&["info", "--insecure"]).unwrap().insecure);
+    }
+
+    #[test]
+    fn pools_are_filtered_and_sorted_by_fee() {
+        let pool = |id: &str, fee_bps| Pool {
+            id: id.to_owned(),
+            token0: "MintA".to_owned(),
+            token1: "MintB".to_owned(),
+            fee_bps,
+            reserves: None,
+            volume_24h: None,
+        };
+        let pools = || {
+            vec![
+                pool("p100", 100),
+                pool("p25", 25),
+                pool("p1", 1),
+                pool("p25b", 25),
+                pool("p30", 30),
+            ]
+        };
+        let ids = |pools: Vec<Pool>| pools.into_iter().map(|p| p.id).collect::<Vec<_>>();
+
+        assert_eq!(
+            ids(select_pools(pools(), None, None)),
+            ["p100", "p25", "p1", "p25b", "p30"]
+        );
+        assert_eq!(
+            ids(select_pools(pools(), Some(25), None)),
+            ["p25", "p1", "p25b"]
+        );
+        assert_eq!(
+            ids(select_pools(pools(), None, Some(PoolSort::Fee))),
+            ["p1", "p25", "p25b", "p30", "p100"]
+        );
+        assert_eq!(
+            ids(select_pools(pools(), Some(30), Some(PoolSort::Fee))),
+            ["p1", "p25", "p25b", "p30"]
+        );
+        assert!(select_pools(pools(), Some(0), None).is_empty());
+    }
+
+    #[test]
+    fn fee_rates_convert_to_exact_bps() {
+        for (rate, bps) in [
+            (0.0001, 1),
+            (0.0025, 25),
+            (0.003, 30),
+            (0.0005, 5),
+            (0.01, 100),
+            // Half a bp rounds up.
+            (0.00025, 3),
+            (0.0, 0),
+        ] {
+            assert_eq!(fee_rate_to_bps(rate), Some(bps), "{rate}");
+        }
+        assert_eq!(fee_rate_to_bps(-0.0025), None);
+        assert_eq!(fee_rate_to_bps(f64::NAN), None);
+    }
+
+    #[test]
+    fn constant_product_swap_without_fee() {
+        let est = constant_product_swap(1_000.0, 1_000.0, 100.0, 0).unwrap();
+        assert!((est.amount_out - 90.909_090_909).abs() < 1e-6);
+        assert!((est.price_impact_pct - 9.090_909_090).abs() < 1e-6);
+
+        // A tiny trade barely moves the price: out ≈ in · spot.
+        let est = constant_product_swap(5_000.0, 10.0, 0.5, 0).unwrap();
+        assert!((est.amount_out - 0.000_999_9).abs() < 1e-9);
+        assert!(est.price_impact_pct < 0.01);
+    }
+
+    #[test]
+    fn constant_product_swap_applies_fee_to_input() {
+        // 0.25 % fee: 99.75 effectively enters the pool.
+        let est = constant_product_swap(1_000.0, 1_000.0, 100.0, 25).unwrap();
+        assert!((est.amount_out - 90.702_432_371).abs() < 1e-6);
+        assert!((est.price_impact_pct - 9.070_243_237).abs() < 1e-6);
+    }
+
+    #[test]
+    fn constant_product_swap_rejects_unusable_input() {
+        assert_eq!(constant_product_swap(0.0, 1_000.0, 1.0, 25), None);
+        assert_eq!(constant_product_swap(1_000.0, 1_000.0, 0.0, 25), None);
+        assert_eq!(constant_product_swap(1_000.0, 1_000.0, 1.0, 10_000), None);
+    }
+
+    #[test]
+    fn position_size_is_risk_share_of_portfolio() {
+        assert!((position_size_usd(10_000.0, 2.0) - 200.0).abs() < f64::EPSILON);
+        assert!((position_size_usd(10_000.0, 100.0) - 10_000.0).abs() < f64::EPSILON);
+        assert!((position_size_usd(1_234.5, 0.5) - 6.1725).abs() < 1e-9);
+        assert_eq!(position_size_usd(0.0, 2.0), 0.0);
+    }
+
+    #[test]
+    fn suggested_size_serializes_with_the_balances() {
+        let sized = SuggestedSize::new(["SOL"], 10_000.0, 2.0);
+        assert_eq!(
+            serde_json::to_value(&sized).unwrap(),
+            serde_json::json!({
+                "balances": ["SOL"],
+                "portfolio_usd": 10_000.0,
+                "risk_pct": 2.0,
+                "suggested_size_usd": 200.0,
+            })
+        );
+    }
+}
@@ -0,0 +1,488 @@
+//! Raydium's mint list and symbol → mint resolution.
+//!
+//! Tickers are not unique on Solana: anyone can mint a token called `USDC`.
+//! [`MintList::resolve_symbol`] therefore returns every candidate, official
+//! entries (those on Raydium's whitelist) first, and only picks one on the
+//! caller's behalf when exactly one candidate is official.
+
+use std::collections::HashSet;
+
+use anyhow::{bail, Result};
+use reqwest::{Client, Url};
+use serde::{Deserialize, Serialize};
+
+use crypto_scanner_agent::raydium::Envelope;
+
+use crate::MINT_LIST_PATH;
+
+/// Page cap for `mints --all` unless `--max-pages` says otherwise.
+pub const DEFAULT_MAX_PAGES: u32 = 20;
+
+/// Characters of a mint address kept by [`MintList::label`].
+const SHORT_ADDRESS_LEN: usize = 4;
+
+const BASE58_ALPHABET: &str = "123456789ABCDEFGHJKLMNPQRSTUVWXYZabcdefghijkmnopqrstuvwxyz";
+
+/// Whether `s` looks like a mint address (32–44 base58 characters) rather
+/// than a ticker. No ticker is that long, so the two can't be confused.
+pub fn is_mint_address(s: &str) -> bool {
+    (32..=44).contains(&s.len()) && s.chars().all(|c| BASE58_ALPHABET.contains(c))
+}
+
+#[derive(Deserialize)]
+struct MintListData {
+    #[serde(rename = "mintList")]
+    mint_list: Vec<MintItem>,
+    /// Addresses Raydium vouches for.
+    #[serde(rename = "whiteList", default)]
+    white_list: Vec<String>,
+    /// Pagination hints, if the endpoint pages: whether another page
+    /// follows, or the total number of mints.
+    #[serde(default, alias = "hasNextPage", alias = "hasMore")]
+    has_next_page: Option<bool>,
+    #[serde(default)]
+    count: Option<usize>,
+}
+
+impl MintListData {
+    /// Whether a page follows this one, `fetched` mints in. Without any
+    /// hint the list is taken to be complete.
+    fn more_pages(&self, fetched: usize) -> bool {
+        if self.mint_list.is_empty() {
+            return false;
+        }
+        match (self.has_next_page, self.count) {
+            (Some(more), _) => more,
+            (None, Some(total)) => fetched < total,
+            (None, None) => false,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Deserialize, Serialize)]
+pub struct MintItem {
+    #[serde(rename = "address")]
+    pub mint: String,
+    pub symbol: String,
+    pub name: String,
+    pub decimals: u8,
+}
+
+/// The mint list as fetched once per run; resolve against it as often as
+/// needed without going back to the API.
+#[derive(Debug, Default)]
+pub struct MintList {
+    pub mints: Vec<MintItem>,
+    official: HashSet<String>,
+}
+
+/// Outcome of [`MintList::resolve_symbol`].
+#[derive(Debug, PartialEq)]
+pub enum Resolved<'a> {
+    /// One mint carries the symbol, or exactly one of several is official.
+    Unique(&'a MintItem),
+    /// Several candidates and nothing to choose between them; official
+    /// entries come first. Callers can narrow it with e.g. the decimals.
+    Ambiguous(Vec<&'a MintItem>),
+}
+
+impl<'a> Resolved<'a> {
+    /// Every candidate, the chosen one (or official ones) first.
+    pub fn candidates(self) -> Vec<&'a MintItem> {
+        match self {
+            Resolved::Unique(m) => vec![m],
+            Resolved::Ambiguous(candidates) => candidates,
+        }
+    }
+}
+
+impl MintList {
+    pub fn new(mints: Vec<MintItem>, official: impl IntoIterator<Item = String>) -> Self {
+        Self {
+            mints,
+            official: official.into_iter().collect(),
+        }
+    }
+
+    pub fn is_official(&self, mint: &str) -> bool {
+        self.official.contains(mint)
+    }
+
+    /// Mints whose symbol matches `symbol` (case-insensitively); `None` when
+    /// the symbol is unknown.
+    pub fn resolve_symbol(&self, symbol: &str) -> Option<Resolved<'_>> {
+        let symbol = symbol.trim();
+        let mut candidates: Vec<&MintItem> = self
+            .mints
+            .iter()
+            .filter(|m| m.symbol.eq_ignore_ascii_case(symbol))
+            .collect();
+        // Stable sort: official first, list order otherwise.
+        candidates.sort_by_key(|m| !self.is_official(&m.mint));
+
+        let official = candidates
+            .iter()
+            .take_while(|m| self.is_official(&m.mint))
+            .count();
+        match (candidates.len(), official) {
+            (0, _) => None,
+            (1, _) | (_, 1) => Some(Resolved::Unique(candidates[0])),
+            _ => Some(Resolved::Ambiguous(candidates)),
+        }
+    }
+
+    /// `mint` as `SYMBOL (ABCD...)` when the list knows it, else just its
+    /// shortened address. Anything that isn't a mint address, such as
+    /// `SOL` for native SOL, is kept as is.
+    pub fn label(&self, mint: &str) -> String {
+        if !is_mint_address(mint) {
+            return mint.to_owned();
+        }
+        let short = short_address(mint);
+        match self.symbol(mint) {
+            Some(symbol) => format!("{symbol} ({short})"),
+            None => short,
+        }
+    }
+
+    /// The symbol `mint` trades under, if it is on the list.
+    pub fn symbol(&self, mint: &str) -> Option<&str> {
+        self.mints
+            .iter()
+            .find(|m| m.mint == mint)
+            .map(|m| m.symbol.as_str())
+    }
+
+    /// Mint for a user-supplied token: addresses pass through unchanged,
+    /// tickers must resolve to a single mint.
+    pub fn resolve_token(&self, token: &str) -> Result<String> {
+        let token = token.trim();
+        if is_mint_address(token) {
+            return Ok(token.to_owned());
+        }
+        match self.resolve_symbol(token) {
+            Some(Resolved::Unique(m)) => Ok(m.mint.clone()),
+            Some(Resolved::Ambiguous(candidates)) => {
+                let listed: Vec<String> = candidates
+                    .iter()
+                    .map(|m| format!("{} ({} decimals)", m.mint, m.decimals))
+                    .collect();
+                bail!(
+                    "symbol `{token}` is ambiguous, pass one of these mints instead: {}",
+                    listed.join(", ")
+                )
+            }
+            None => bail!("unknown symbol `{token}`"),
+        }
+    }
+}
+
+/// The first characters of a mint address, enough to tell mints apart at
+/// a glance.
+fn short_address(mint: &str) -> String {
+    format!("{}...", &mint[..SHORT_ADDRESS_LEN.min(mint.len())])
+}
+
+/// Narrows the `mints` listing after it was fetched; with no flags every
+/// mint is kept.
+#[derive(clap::Args, Debug, Clone, Default, PartialEq)]
+pub struct MintFilter {
+    /// Only symbols containing this text, ignoring case
+    #[arg(long, value_name = "TEXT")]
+    pub symbol_contains: Option<String>,
+    #[arg(long, value_name = "N")]
+    pub min_decimals: Option<u8>,
+    #[arg(long, value_name = "N")]
+    pub max_decimals: Option<u8>,
+    /// Only mints on Raydium's official list
+    #[arg(long)]
+    pub verified_only: bool,
+    /// Show at most this many mints
+    #[arg(long, value_name = "N")]
+    pub limit: Option<usize>,
+}
+
+impl MintFilter {
+    /// The entries of `mints` that pass, in order; `list` tells which are
+    /// official.
+    pub fn apply<'a>(&self, list: &MintList, mints: Vec<&'a MintItem>) -> Vec<&'a MintItem> {
+        let needle = self.symbol_contains.as_deref().map(str::to_lowercase);
+        mints
+            .into_iter()
+            .filter(|m| {
+                needle
+                    .as_deref()
+                    .is_none_or(|n| m.symbol.to_lowercase().contains(n))
+            })
+            .filter(|m| self.min_decimals.is_none_or(|min| m.decimals >= min))
+            .filter(|m| self.max_decimals.is_none_or(|max| m.decimals <= max))
+            .filter(|m| !self.verified_only || list.is_official(&m.mint))
+            .take(self.limit.unwrap_or(usize::MAX))
+            .collect()
+    }
+}
+
+/// The first page of the mint list, which is all of it unless the endpoint
+/// paginates.
+pub async fn fetch_mints(client: &Client, base: &str) -> Result<MintList> {
+    fetch_mint_pages(client, base, 1).await
+}
+
+/// The mint list with every page merged, following `?page=N` while the
+/// response says more follow, up to `max_pages`.
+pub async fn fetch_mint_pages(client: &Client, base: &str, max_pages: u32) -> Result<MintList> {
+    let endpoint = format!("{base}{MINT_LIST_PATH}");
+    let (mut mints, mut official) = (Vec::new(), Vec::new());
+    for page in 1..=max_pages.max(1) {
+        let url = match page {
+            1 => Url::parse(&endpoint)?,
+            n => Url::parse_with_params(&endpoint, &[("page", n.to_string())])?,
+        };
+        let outer: Envelope<MintListData> = client.get(url).send().await?.json().await?;
+        let data = outer.into_data(MINT_LIST_PATH)?;
+        let more = data.more_pages(mints.len() + data.mint_list.len());
+        mints.extend(data.mint_list);
+        official.extend(data.white_list);
+        if !more {
+            return Ok(MintList::new(mints, official));
+        }
+    }
+    tracing::warn!(
+        mints = mints.len(),
+        "mint list has more than {max_pages} page(s); the rest was not fetched"
+    );
+    Ok(MintList::new(mints, official))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn sample() -> MintList {
+        let mint = |mint: &str, symbol: &str, decimals| MintItem {
+            mint: mint.to_owned(),
+            symbol: symbol.to_owned(),
+            name: format!("{symbol} token"),
+            decimals,
+        };
+        MintList::new(
+            vec![
+                mint("So11111111111111111111111111111111111111112", "SOL", 9),
+                mint("FakeUsdc1111111111111111111111111111111111", "USDC", 9),
+                mint("EPjFWdd5AufqSSqeM2qN1xzybapC8G4wEGGkZwyTDt1v", "USDC", 6),
+                mint("Meme111111111111111111111111111111111111111", "MEME", 6),
+                mint("Meme222222222222222222222222222222222222222", "MEME", 9),
+            ],
+            [
+                "So11111111111111111111111111111111111111112".to_owned(),
+                "EPjFWdd5AufqSSqeM2qN1xzybapC8G4wEGGkZwyTDt1v".to_owned(),
+            ],
+        )
+    }
+
+    #[test]
+    fn unique_symbol_resolves_case_insensitively() {
+        let list = sample();
+        let Some(Resolved::Unique(m)) = list.resolve_symbol("sol") else {
+            panic!("SOL should be unique");
+        };
+        assert_eq!(m.decimals, 9);
+    }
+
+    #[test]
+    fn official_entry_wins_a_shared_ticker() {
+        let list = sample();
+        let Some(Resolved::Unique(m)) = list.resolve_symbol("USDC") else {
+            panic!("the official USDC should be picked");
+        };
+        assert_eq!(m.mint, "EPjFWdd5AufqSSqeM2qN1xzybapC8G4wEGGkZwyTDt1v");
+    }
+
+    #[test]
+    fn ambiguous_symbol_returns_all_candidates() {
+        let list = sample();
+        let Some(Resolved::Ambiguous(candidates)) = list.resolve_symbol("MEME") else {
+            panic!("MEME should be ambiguous");
+        };
+        let decimals: Vec<u8> = candidates.iter().map(|m| m.decimals).collect();
+        assert_eq!(decimals, [6, 9]);
+    }
+
+    #[test]
+    fn unknown_symbol_resolves_to_none() {
+        assert_eq!(sample().resolve_symbol("NOPE"), None);
+    }
+
+    #[test]
+    fn balances_are_labelled_by_symbol() {
+        let list = sample();
+        let balances = [
+            "SOL",
+            "EPjFWdd5AufqSSqeM2qN1xzybapC8G4wEGGkZwyTDt1v",
+            "9n4nbM75f5Ui33ZbPYXn59EwSgE8CGsHtAeTH5YFeJ9E",
+        ];
+        let labels: Vec<String> = balances.iter().map(|mint| list.label(mint)).collect();
+        assert_eq!(labels, ["SOL", "USDC (EPjF...)", "9n4n..."]);
+    }
+
+    #[test]
+    fn tokens_mix_symbols_and_mints() {
+        let list = sample();
+        let usdc = "EPjFWdd5AufqSSqeM2qN1xzybapC8G4wEGGkZwyTDt1v";
+        let unlisted = "9n4nbM75f5Ui33ZbPYXn59EwSgE8CGsHtAeTH5YFeJ9E";
+
+        assert_eq!(list.resolve_token("usdc").unwrap(), usdc);
+        assert_eq!(list.resolve_token(usdc).unwrap(), usdc);
+        // Addresses are passed through even when not in the list.
+        assert_eq!(list.resolve_token(unlisted).unwrap(), unlisted);
+
+        let err = list.resolve_token("NOPE").unwrap_err().to_string();
+        assert!(err.contains("unknown symbol `NOPE`"), "{err}");
+        let err = list.resolve_token("MEME").unwrap_err().to_string();
+        assert!(err.contains("Meme111"), "{err}");
+    }
+
+    #[test]
+    fn addresses_are_told_apart_from_tickers() {
+        assert!(is_mint_address(
+            "So11111111111111111111111111111111111111112"
+        ));
+        assert!(!is_mint_address("SOL"));
+        // `0`, `O`, `I` and `l` are not base58.
+        assert!(!is_mint_address(
+            "O0111111111111111111111111111111111111112"
+        ));
+    }
+
+    fn filtered(filter: MintFilter) -> Vec<(String, u8)> {
+        let list = sample();
+        filter
+            .apply(&list, list.mints.iter().collect())
+            .into_iter()
+            .map(|m| (m.symbol.clone(), m.decimals))
+            .collect()
+    }
+
+    #[test]
+    fn no_filter_keeps_every_mint() {
+        assert_eq!(filtered(MintFilter::default()).len(), 5);
+    }
+
+    #[test]
+    fn symbol_contains_ignores_case() {
+        let filter = MintFilter {
+            symbol_contains: Some("usd".into()),
+            ..Default::default()
+        };
+        assert_eq!(filtered(filter), [("USDC".into(), 9), ("USDC".into(), 6)]);
+    }
+
+    #[test]
+    fn decimals_bounds_are_inclusive() {
+        let filter = MintFilter {
+            min_decimals: Some(9),
+            ..Default::default()
+        };
+        assert_eq!(
+            filtered(filter),
+            [("SOL".into(), 9), ("USDC".into(), 9), ("MEME".into(), 9)]
+        );
+        let filter = MintFilter {
+            max_decimals: Some(6),
+            ..Default::default()
+        };
+        assert_eq!(filtered(filter), [("USDC".into(), 6), ("MEME".into(), 6)]);
+    }
+
+    #[test]
+    fn verified_only_keeps_official_mints() {
+        let filter = MintFilter {
+            verified_only: true,
+            ..Default::default()
+        };
+        assert_eq!(filtered(filter), [("SOL".into(), 9), ("USDC".into(), 6)]);
+    }
+
+    #[test]
+    fn limit_applies_after_the_other_filters() {
+        let filter = MintFilter {
+            symbol_contains: Some("meme".into()),
+            limit: Some(1),
+            ..Default::default()
+        };
+        assert_eq!(filtered(filter), [("MEME".into(), 6)]);
+    }
+
+    #[tokio::test]
+    async fn all_pages_are_merged() {
+        use shuttle_axum::axum::{extract::Query, routing::get, Json, Router};
+        use std::collections::HashMap;
+
+        let page = |q: Query<HashMap<String, String>>| async move {
+            let page: usize = q.get("page").map_or(1, |p| p.parse().unwrap());
+            let item = |n: usize| {
+                serde_json::json!({
+                    "address": format!("Mint{n}"), "symbol": format!("T{n}"),
+                    "name": "t", "decimals": 6
+                })
+            };
+            Json(serde_json::json!({
+                "success": true,
+                "data": {
+                    "mintList": [item(page * 2 - 1), item(page * 2)],
+                    "whiteList": [format!("Mint{}", page * 2)],
+                    "hasNextPage": page < 3,
+                }
+            }))
+        };
+        let app = Router::new().route(MINT_LIST_PATH, get(page));
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let base = format!("http://{}", listener.local_addr().unwrap());
+        tokio::spawn(async move { shuttle_axum::axum::serve(listener, app).await });
+        let client = Client::new();
+
+        let list = fetch_mint_pages(&client, &base, DEFAULT_MAX_PAGES)
+            .await
+            .unwrap();
+        let mints: Vec<&str> = list.mints.iter().map(|m| m.mint.as_str()).collect();
+        assert_eq!(
+            mints,
+            ["Mint1", "Mint2", "Mint3", "Mint4", "Mint5", "Mint6"]
+        );
+        assert!(list.is_official("Mint4") && list.is_official("Mint6"));
+
+        // The cap and the single-page default stop early.
+        assert_eq!(
+            fetch_mint_pages(&client, &base, 2)
+                .await
+                .unwrap()
+                .mints
+                .len(),
+            4
+        );
+        assert_eq!(fetch_mints(&client, &base).await.unwrap().mints.len(), 2);
+    }
+
+    #[test]
+    fn page_hints_decide_whether_to_continue() {
+        let data = |json: &str| serde_json::from_str::<MintListData>(json).unwrap();
+        let one = r#"[{"address": "A", "symbol": "X", "name": "x", "decimals": 6}]"#;
+
+        assert!(!data(&format!(r#"{{"mintList": {one}}}"#)).more_pages(1));
+        assert!(data(&format!(r#"{{"mintList": {one}, "hasMore": true}}"#)).more_pages(1));
+        assert!(data(&format!(r#"{{"mintList": {one}, "count": 5}}"#)).more_pages(1));
+        assert!(!data(&format!(r#"{{"mintList": {one}, "count": 5}}"#)).more_pages(5));
+        assert!(!data(r#"{"mintList": [], "hasNextPage": true}"#).more_pages(0));
+    }
+
+    #[test]
+    fn whitelist_is_read_from_the_response() {
+        let data: MintListData = serde_json::from_str(
+            r#"{"mintList": [{"address": "A", "symbol": "X", "name": "x", "decimals": 6}],
+                "whiteList": ["A"]}"#,
+        )
+        .unwrap();
+        let list = MintList::new(data.mint_list, data.white_list);
+        assert!(list.is_official("A"));
+    }
+}
@@ -0,0 +1,195 @@
+//! `raydium_cli record`: every raw text frame of an upstream WebSocket,
+//! written to disk one per line to build a replay corpus.
+
+use std::{
+    fs::File,
+    io::Write,
+    path::{Path, PathBuf},
+    time::Duration,
+};
+
+use anyhow::{Context, Result};
+use futures::StreamExt;
+use tokio::time::Instant;
+use tokio_tungstenite::tungstenite;
+
+use crypto_scanner_agent::{output::OutputDir, tls::connect_ws};
+
+use crate::Reconnect;
+
+/// Binance's all-tickers stream, the scanner's busiest upstream.
+pub const DEFAULT_RECORD_URL: &str = "wss://stream.binance.com:9443/ws/!ticker@arr";
+
+#[derive(clap::Args, Debug)]
+pub struct RecordArgs {
+    /// Upstream WebSocket to record [default: BINANCE_WS_URL, else
+    /// Binance's all-tickers stream]
+    #[arg(long)]
+    pub url: Option<String>,
+    /// File to write, under `--output-dir` if relative; rotated files get
+    /// `.1`, `.2`, … before the extension
+    #[arg(long, value_name = "PATH", default_value = "frames.jsonl")]
+    pub out: PathBuf,
+    /// Stop after this many seconds [default: until Ctrl-C]
+    #[arg(long, value_name = "SECS")]
+    pub duration: Option<u64>,
+    /// Start a new file once the current one would exceed this many MB
+    #[arg(long, value_name = "MB", value_parser = clap::value_parser!(u64).range(1..))]
+    pub rotate_mb: Option<u64>,
+}
+
+impl RecordArgs {
+    pub fn url(&self) -> String {
+        self.url
+            .clone()
+            .or_else(|| std::env::var("BINANCE_WS_URL").ok())
+            .unwrap_or_else(|| DEFAULT_RECORD_URL.to_owned())
+    }
+
+    /// `--out` under `output`, creating the directories it needs.
+    pub fn out_path(&self, output: &OutputDir) -> Result<PathBuf> {
+        output.resolve_output_path(&self.out)
+    }
+}
+
+/// Appends frames to `base`, moving on to `base.1`, `base.2`, … whenever
+/// the current file would grow past `rotate_bytes`.
+pub struct FrameWriter {
+    base: PathBuf,
+    rotate_bytes: Option<u64>,
+    index: u32,
+    file: File,
+    written: u64,
+    pub frames: u64,
+}
+
+impl FrameWriter {
+    pub fn create(base: PathBuf, rotate_bytes: Option<u64>) -> Result<Self> {
+        let file = create(&base)?;
+        Ok(Self {
+            base,
+            rotate_bytes,
+            index: 0,
+            file,
+            written: 0,
+            frames: 0,
+        })
+    }
+
+    /// Write `frame` as one line. Line breaks inside it, which in JSON can
+    /// only be whitespace, become spaces so every line is one frame.
+    pub fn write(&mut self, frame: &str) -> Result<()> {
+        let mut line = frame.replace(['\r', '\n'], " ");
+        line.push('\n');
+        let len = line.len() as u64;
+        if let Some(limit) = self.rotate_bytes {
+            if self.written > 0 && self.written + len > limit {
+                self.index += 1;
+                self.file = create(&self.path())?;
+                self.written = 0;
+            }
+        }
+        self.file
+            .write_all(line.as_bytes())
+            .with_context(|| format!("writing {}", self.path().display()))?;
+        self.written += len;
+        self.frames += 1;
+        Ok(())
+    }
+
+    /// The file currently written to.
+    pub fn path(&self) -> PathBuf {
+        rotated_path(&self.base, self.index)
+    }
+
+    /// Files written so far, including the current one.
+    pub fn files(&self) -> u32 {
+        self.index + 1
+    }
+}
+
+fn create(path: &Path) -> Result<File> {
+    File::create(path).with_context(|| format!("creating {}", path.display()))
+}
+
+/// `frames.jsonl` → `frames.2.jsonl` for `index` 2; index 0 is `base`.
+fn rotated_path(base: &Path, index: u32) -> PathBuf {
+    if index == 0 {
+        return base.to_owned();
+    }
+    let stem = base.file_stem().unwrap_or_default().to_string_lossy();
+    let name = match base.extension() {
+        Some(ext) => format!("{stem}.{index}.{}", ext.to_string_lossy()),
+        None => format!("{stem}.{index}"),
+    };
+    base.with_file_name(name)
+}
+
+/// Write every text frame of one connection to `writer` until the server
+/// closes it. The outer error is a failed write, the inner one why the
+/// connection ended.
+pub async fn record_connection(
+    url: &str,
+    accept_invalid_certs: bool,
+    writer: &mut FrameWriter,
+) -> Result<tungstenite::Result<()>> {
+    let mut ws = match connect_ws(url, accept_invalid_certs).await {
+        Ok((ws, _)) => ws,
+        Err(e) => return Ok(Err(e)),
+    };
+    while let Some(frame) = ws.next().await {
+        match frame {
+            Ok(tungstenite::Message::Text(txt)) => writer.write(&txt)?,
+            Ok(_) => {}
+            Err(e) => return Ok(Err(e)),
+        }
+    }
+    Ok(Ok(()))
+}
+
+/// Record `args.url()` until the duration is up or Ctrl-C, reconnecting
+/// like `stream` does whenever the upstream drops.
+pub async fn record(
+    args: &RecordArgs,
+    output: &OutputDir,
+    accept_invalid_certs: bool,
+) -> Result<()> {
+    let url = args.url();
+    let out = args.out_path(output)?;
+    eprintln!("recording {url} to {}", out.display());
+    let mut writer = FrameWriter::create(out, args.rotate_mb.map(|mb| mb * 1024 * 1024))?;
+    let stop_at = args
+        .duration
+        .map(|secs| Instant::now() + Duration::from_secs(secs));
+
+    let run = async {
+        let mut reconnect = Reconnect::default();
+        loop {
+            let before = writer.frames;
+            let ended = record_connection(&url, accept_invalid_certs, &mut writer).await?;
+            let delay = reconnect.delay(writer.frames > before);
+            match ended {
+                Ok(()) => eprintln!("connection closed, reconnecting in {delay:?}"),
+                Err(e) => eprintln!("{e}, reconnecting in {delay:?}"),
+            }
+            tokio::time::sleep(delay).await;
+        }
+    };
+    let stopped: Result<()> = tokio::select! {
+        res = run => res,
+        _ = tokio::signal::ctrl_c() => Ok(()),
+        _ = async {
+            match stop_at {
+                Some(at) => tokio::time::sleep_until(at).await,
+                None => std::future::pending().await,
+            }
+        } => Ok(()),
+    };
+    eprintln!(
+        "recorded {} frames in {} file(s), last {}",
+        writer.frames,
+        writer.files(),
+        writer.path().display()
+    );
+    stopped
+}
@@ -0,0 +1,177 @@
+//! `raydium_cli selftest`: the parsers run over saved exchange and Raydium
+//! responses bundled into the binary, each compared with the golden output
+//! recorded next to it under `tests/fixtures`. A response format that has
+//! drifted upstream shows up as a failing fixture in the field instead of
+//! as quietly missing signals or pools.
+
+use std::io::Write;
+
+use anyhow::{anyhow, bail, Context, Result};
+use serde_json::Value;
+
+use crypto_scanner_agent::{extract_signals_from_text, raydium::parse_json, SignalConfig};
+
+use crate::pools_from_body;
+
+/// The parser a fixture goes through.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Parser {
+    /// `extract_signals_from_text` with the default thresholds.
+    Tickers,
+    /// `parse_json`, as `raydium_top_coins` reads `/pools/info/list`.
+    TopPools,
+    /// `raw_to_pool` over every row, as `list-pools` and `impact` do.
+    Pools,
+}
+
+/// A saved response and what [`Parser`] should make of it.
+struct Fixture {
+    name: &'static str,
+    parser: Parser,
+    input: &'static str,
+    golden: &'static str,
+}
+
+/// `tests/fixtures/<name>.json` and its `<name>.golden.json`.
+macro_rules! fixture {
+    ($name:literal, $parser:expr) => {
+        Fixture {
+            name: $name,
+            parser: $parser,
+            input: include_str!(concat!(
+                env!("CARGO_MANIFEST_DIR"),
+                "/tests/fixtures/",
+                $name,
+                ".json"
+            )),
+            golden: include_str!(concat!(
+                env!("CARGO_MANIFEST_DIR"),
+                "/tests/fixtures/",
+                $name,
+                ".golden.json"
+            )),
+        }
+    };
+}
+
+const FIXTURES: [Fixture; 4] = [
+    fixture!("binance_tickers", Parser::Tickers),
+    fixture!("raydium_pools", Parser::TopPools),
+    fixture!("raydium_pool_list", Parser::Pools),
+    fixture!("raydium_pool_ids", Parser::Pools),
+];
+
+impl Parser {
+    /// What the parser makes of `input`, as JSON to compare with a golden
+    /// file.
+    fn output(self, input: &str) -> Result<Value> {
+        let output = match self {
+            Parser::Tickers => {
+                let mut signals = extract_signals_from_text(input, &SignalConfig::default())
+                    .map_err(|e| anyhow!(e))?;
+                // The receive time differs on every run.
+                for sig in &mut signals {
+                    sig.received_at = None;
+                }
+                serde_json::to_value(signals)?
+            }
+            Parser::TopPools => serde_json::to_value(parse_json(input)?)?,
+            Parser::Pools => {
+                let body = serde_json::from_str(input).context("invalid JSON")?;
+                serde_json::to_value(pools_from_body(&body)?)?
+            }
+        };
+        Ok(output)
+    }
+}
+
+impl Fixture {
+    fn check(&self) -> Result<()> {
+        let golden: Value = serde_json::from_str(self.golden).context("invalid golden file")?;
+        let actual = self.parser.output(self.input)?;
+        if actual != golden {
+            bail!(
+                "output differs from the golden file\n  expected: {golden}\n  actual:   {actual}"
+            );
+        }
+        Ok(())
+    }
+}
+
+/// Check every bundled fixture, writing a line per fixture to `out`; an
+/// error once all have run if any failed.
+pub fn run(out: &mut impl Write) -> Result<()> {
+    check_all(&FIXTURES, out)
+}
+
+fn check_all(fixtures: &[Fixture], out: &mut impl Write) -> Result<()> {
+    let mut failed = 0;
+    for fixture in fixtures {
+        match fixture.check() {
+            Ok(()) => writeln!(out, "PASS  {}", fixture.name)?,
+            Err(e) => {
+                failed += 1;
+                writeln!(out, "FAIL  {}: {e:#}", fixture.name)?;
+            }
+        }
+    }
+    if failed > 0 {
+        bail!("{failed} of {} fixtures failed", fixtures.len());
+    }
+    writeln!(out, "all {} fixtures passed", fixtures.len())?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn bundled_fixtures_match_their_golden_output() {
+        let mut out = Vec::new();
+        let result = run(&mut out);
+        let out = String::from_utf8(out).unwrap();
+        assert!(result.is_ok(), "{out}");
+        for fixture in &FIXTURES {
+            assert!(out.contains(&format!("PASS  {}\n", fixture.name)), "{out}");
+        }
+    }
+
+    #[test]
+    fn a_mismatch_fails_the_run_after_checking_every_fixture() {
+        let drifted = Fixture {
+            golden: "[]",
+            ..fixture!("raydium_pools", Parser::TopPools)
+        };
+        let broken = Fixture {
+            name: "broken",
+            parser: Parser::Pools,
+            input: r#"{ "success": true, "result": [] }"#,
+            golden: "[]",
+        };
+        let mut out = Vec::new();
+        let err = check_all(
+            &[
+                drifted,
+                broken,
+                fixture!("binance_tickers", Parser::Tickers),
+            ],
+            &mut out,
+        )
+        .unwrap_err();
+        assert_eq!(err.to_string(), "2 of 3 fixtures failed");
+
+        let out = String::from_utf8(out).unwrap();
+        assert!(
+            out.starts_with(
+                "FAIL  raydium_pools: output differs from the golden file\n  expected: []\n"
+            ),
+            "{out}"
+        );
+        assert!(
+            out.contains("\nFAIL  broken: Raydium API: unrecognised payload shape"),
+            "{out}"
+        );
+        assert!(out.ends_with("\nPASS  binance_tickers\n"), "{out}");
+    }
+}
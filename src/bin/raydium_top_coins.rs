@@ -2,61 +2,138 @@
 //! print a table **and** save them to a local JSON file.
 //
 //! Build:  cargo run --bin raydium_top_coins --release
-//! Logs :  RUST_LOG=raydium_cli=debug cargo run …
+//! Logs :  cargo run --bin raydium_top_coins -- --verbose   (or --quiet)
+//!
+//! When Raydium changes its payload shape, save the body with
+//! `--dump-raw=PATH` and replay it offline with `--parse-only=PATH`.
+//!
+//! `--enrich` adds the USD price of each pool's tokens from
+//! `PRICE_PROVIDER`, looked up in batches on a small tokio runtime.
 
-use anyhow::{anyhow, bail, Context, Result};
+use anyhow::{Context, Result};
+use clap::Parser;
+use crypto_scanner_agent::{
+    http::{build_blocking_client, build_client, validate_proxy_url, ClientOptions},
+    logging::{init_tracing, VerbosityArgs},
+    numbers::{format_volume, NumberFormat},
+    output::{OutputArgs, OutputDir},
+    price::{provider_from_env, DEFAULT_RAYDIUM_BASE},
+    raydium::{parse_json, PoolMint, RaydiumPool},
+    util::parallel_map,
+};
 use reqwest::blocking::Client;
-use serde::{Deserialize, Serialize};
-use serde_json::Value;
-use std::{fs::File, io::Write, path::Path, time::Instant};
-use tracing::{debug, error, info, instrument};
-
-/* ─────────────────────────── Types ─────────────────────────── */
-
-/// Outer status wrapper used by every Raydium V3 call.
-#[derive(Debug, Deserialize)]
-struct ApiWrapper {
-    success: bool,
-    #[serde(default)]
-    msg: Option<String>,
-    data: Value, // shape varies → handle at runtime
-}
-
-/// Pool row – keep only the bits we care about.
-#[derive(Debug, Deserialize, Serialize)]
-#[serde(rename_all = "camelCase")]
-struct RaydiumPool {
-    /// Pair name, e.g. `"SOL/USDC"`.  
-    /// Some rows sadly omit it, so we supply an empty string instead of
-    /// aborting the whole deserialisation.
-    #[serde(default)]
-    name: String,
-
-    price: Option<f64>, // mid-price
-    volume24h: Option<f64>,
-}
+use serde::Serialize;
+use std::{
+    collections::{HashMap, HashSet},
+    fs::File,
+    io::Write,
+    path::{Path, PathBuf},
+    time::Instant,
+};
+use tracing::{debug, info, instrument, warn};
 
 /* ─────────────────────────── Constants ─────────────────────── */
 
-const ENDPOINT: &str = "https://api-v3.raydium.io/pools/info/list";
+const POOL_LIST_PATH: &str = "/pools/info/list";
 const LIMIT: usize = 50; // top-N in table / JSON
 const JSON_OUT: &str = "raydium_top_pools.json";
 
 /* ─────────────────────────── Main ──────────────────────────── */
 
+/// Print the highest-volume Raydium pools and save them as JSON.
+#[derive(Parser)]
+#[command(version, about)]
+struct Cli {
+    /// Route requests through this proxy (http, https, socks5 or socks5h)
+    #[arg(long, value_name = "URL", value_parser = proxy_url)]
+    proxy: Option<String>,
+
+    /// Accept invalid TLS certificates [default: ALLOW_INVALID_CERTS]
+    #[arg(long)]
+    insecure: bool,
+
+    /// Write the downloaded body to PATH before parsing it, e.g. to attach
+    /// to a bug report
+    #[arg(long, value_name = "PATH", conflicts_with = "parse_only")]
+    dump_raw: Option<PathBuf>,
+
+    /// Parse a body saved with --dump-raw instead of querying Raydium;
+    /// prints the table without touching the JSON output file
+    #[arg(long, value_name = "PATH")]
+    parse_only: Option<PathBuf>,
+
+    /// How the 24 h volume column is printed
+    #[arg(long, value_enum, default_value_t = NumberFormat::Plain)]
+    number_format: NumberFormat,
+
+    /// Add the USD price of each pool's two tokens [source: PRICE_PROVIDER]
+    #[arg(long, conflicts_with = "parse_only")]
+    enrich: bool,
+
+    /// Price requests in flight at once with --enrich
+    #[arg(long, value_name = "N", default_value_t = 4, requires = "enrich")]
+    enrich_concurrency: usize,
+
+    /// Mints per price request with --enrich
+    #[arg(long, value_name = "N", default_value_t = 50, requires = "enrich")]
+    enrich_batch_size: usize,
+
+    #[command(flatten)]
+    output: OutputArgs,
+
+    #[command(flatten)]
+    verbosity: VerbosityArgs,
+}
+
+fn proxy_url(s: &str) -> Result<String> {
+    validate_proxy_url(s)?;
+    Ok(s.trim().to_owned())
+}
+
 fn main() -> Result<()> {
-    tracing_subscriber::fmt::init();
+    let cli = Cli::parse();
+    init_tracing(cli.verbosity.verbosity());
     let t0 = Instant::now();
-    info!("Querying Raydium V3 pools…");
+    let output = cli.output.resolve();
 
-    let client = Client::builder()
-        .timeout(std::time::Duration::from_secs(15))
-        .build()
-        .context("building HTTP client")?;
+    if let Some(path) = &cli.parse_only {
+        let mut pools = parse_saved(path)?;
+        sort_and_trim(&mut pools);
+        print_table(&rows(&pools, None), cli.number_format);
+        return Ok(());
+    }
+
+    info!("Querying Raydium V3 pools…");
+    let opts = ClientOptions::from_env()?
+        .with_proxy_override(cli.proxy.as_deref())
+        .with_insecure_override(cli.insecure);
+    let client = build_blocking_client(&opts)?;
 
     let raw = fetch_raw(&client)?;
+    if let Some(path) = &cli.dump_raw {
+        let path = output.resolve_output_path(path)?;
+        std::fs::write(&path, &raw).with_context(|| format!("writing {}", path.display()))?;
+        info!(path = %path.display(), "raw body saved");
+    }
     let mut pools = parse_json(&raw)?;
+    sort_and_trim(&mut pools);
 
+    let prices = if cli.enrich {
+        let batches = mint_batches(&pools, cli.enrich_batch_size);
+        info!(batches = batches.len(), "Pricing pool tokens…");
+        Some(fetch_mint_prices(&opts, batches, cli.enrich_concurrency)?)
+    } else {
+        None
+    };
+    let rows = rows(&pools, prices.as_ref());
+    let out = save_json(&rows, &output)?;
+    print_table(&rows, cli.number_format);
+    info!("Done in {:.2?}  →  {}", t0.elapsed(), out.display());
+    Ok(())
+}
+
+/// Highest volume first, top [`LIMIT`] only.
+fn sort_and_trim(pools: &mut Vec<RaydiumPool>) {
     // sort & trim
     pools.sort_by(|a, b| {
         b.volume24h
@@ -64,11 +141,6 @@ fn main() -> Result<()> {
             .unwrap_or(std::cmp::Ordering::Equal)
     });
     pools.truncate(LIMIT);
-
-    save_json(&pools)?;
-    print_table(&pools);
-    info!("Done in {:.2?}  →  {}", t0.elapsed(), JSON_OUT);
-    Ok(())
 }
 
 /* ───────────────────────── HTTP ────────────────────────────── */
@@ -85,9 +157,8 @@ fn fetch_raw(client: &Client) -> Result<String> {
     ];
 
     let body = client
-        .get(ENDPOINT)
+        .get(format!("{DEFAULT_RAYDIUM_BASE}{POOL_LIST_PATH}"))
         .query(&qs)
-        .header("accept", "application/json")
         .send()
         .context("sending GET")?
         .error_for_status()
@@ -101,77 +172,246 @@ fn fetch_raw(client: &Client) -> Result<String> {
 
 /* ─────────────────────── JSON parsing ──────────────────────── */
 
-#[instrument(level = "debug", skip(raw))]
-fn parse_json(raw: &str) -> Result<Vec<RaydiumPool>> {
-    let wrapper: ApiWrapper =
-        serde_json::from_str(raw).map_err(|e| slice_err(raw, &e, "wrapper parse failed"))?;
+/// [`parse_json`] on a body saved with `--dump-raw`.
+fn parse_saved(path: &Path) -> Result<Vec<RaydiumPool>> {
+    let raw =
+        std::fs::read_to_string(path).with_context(|| format!("reading {}", path.display()))?;
+    parse_json(&raw).with_context(|| format!("parsing {}", path.display()))
+}
 
-    if !wrapper.success {
-        bail!(wrapper
-            .msg
-            .unwrap_or_else(|| "Raydium signalled failure".into()));
-    }
+/* ───────────────────── Price enrichment ────────────────────── */
 
-    // data = […] | { list:[…] } | { count:n , data:[…] }
-    let arr = if let Some(a) = wrapper.data.as_array() {
-        a.clone()
-    } else if wrapper.data.get("list").is_some() {
-        wrapper.data["list"]
-            .as_array()
-            .ok_or_else(|| anyhow!("‘list’ is not an array"))?
-            .clone()
-    } else if wrapper.data.get("data").is_some() {
-        wrapper.data["data"]
-            .as_array()
-            .ok_or_else(|| anyhow!("‘data’ is not an array"))?
-            .clone()
-    } else {
-        bail!("unrecognised payload shape: {}", wrapper.data);
-    };
+/// The distinct mints of `pools` in first-seen order, split into requests
+/// of at most `batch_size`.
+fn mint_batches(pools: &[RaydiumPool], batch_size: usize) -> Vec<Vec<String>> {
+    let mut seen = HashSet::new();
+    let mints: Vec<String> = pools
+        .iter()
+        .flat_map(|p| [&p.mint_a, &p.mint_b])
+        .flatten()
+        .map(|mint| mint.address.clone())
+        .filter(|address| seen.insert(address.clone()))
+        .collect();
+    mints
+        .chunks(batch_size.max(1))
+        .map(<[String]>::to_vec)
+        .collect()
+}
 
-    serde_json::from_value::<Vec<RaydiumPool>>(Value::Array(arr))
-        .map_err(|e| slice_err(raw, &e, "pool array parse failed"))
+/// The prices of every batch that was fetched; a failed batch is logged and
+/// leaves its mints unpriced instead of failing the run.
+fn merge_prices(results: Vec<Result<HashMap<String, f64>>>) -> HashMap<String, f64> {
+    let mut prices = HashMap::new();
+    for result in results {
+        match result {
+            Ok(batch) => prices.extend(batch),
+            Err(e) => warn!("price batch failed: {e:#}"),
+        }
+    }
+    prices
+}
+
+/// USD price of the mints in `batches`, one request per batch and at most
+/// `concurrency` at a time. The price providers are async, so they get a
+/// small runtime of their own.
+fn fetch_mint_prices(
+    opts: &ClientOptions,
+    batches: Vec<Vec<String>>,
+    concurrency: usize,
+) -> Result<HashMap<String, f64>> {
+    let runtime = tokio::runtime::Builder::new_current_thread()
+        .enable_all()
+        .build()
+        .context("starting the price runtime")?;
+    let provider =
+        provider_from_env(build_client(opts)?, DEFAULT_RAYDIUM_BASE).map_err(anyhow::Error::msg)?;
+    let results = runtime.block_on(parallel_map(batches, concurrency, false, |batch| {
+        let provider = &provider;
+        async move {
+            let ids: Vec<&str> = batch.iter().map(String::as_str).collect();
+            provider.prices(&ids).await
+        }
+    }));
+    Ok(merge_prices(results))
 }
 
 /* ──────────────────── JSON file output ─────────────────────── */
 
-fn save_json(pools: &[RaydiumPool]) -> Result<()> {
-    let path = Path::new(JSON_OUT);
-    let mut file = File::create(path).context("creating JSON output file")?;
+/// A pool as printed and saved, with its tokens' USD prices under
+/// `--enrich`.
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+struct PoolRow<'a> {
+    #[serde(flatten)]
+    pool: &'a RaydiumPool,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    mint_a_usd: Option<f64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    mint_b_usd: Option<f64>,
+}
+
+/// `pools` with their tokens priced from `prices`, if given.
+fn rows<'a>(pools: &'a [RaydiumPool], prices: Option<&HashMap<String, f64>>) -> Vec<PoolRow<'a>> {
+    let usd = |mint: Option<&PoolMint>| prices?.get(&mint?.address).copied();
+    pools
+        .iter()
+        .map(|pool| PoolRow {
+            pool,
+            mint_a_usd: usd(pool.mint_a.as_ref()),
+            mint_b_usd: usd(pool.mint_b.as_ref()),
+        })
+        .collect()
+}
+
+/// Write [`JSON_OUT`] under `output` and return where it went.
+fn save_json(pools: &[PoolRow], output: &OutputDir) -> Result<PathBuf> {
+    let path = output.resolve_output_path(JSON_OUT)?;
+    let mut file = File::create(&path).context("creating JSON output file")?;
     serde_json::to_writer_pretty(&mut file, pools).context("serialising pretty JSON")?;
     file.write_all(b"\n").ok(); // final newline – cosmetics
-    Ok(())
+    Ok(path)
 }
 
 /* ───────────────────────── Helpers ─────────────────────────── */
 
-fn slice_err(raw: &str, err: &impl std::fmt::Display, ctx: &str) -> anyhow::Error {
-    // Safe 200-byte snippet around the byte offset (serde_json ¹⁰² → .column()).
-    let pos = err
-        .to_string()
-        .split(" at line ")
-        .last()
-        .and_then(|s| s.split(" column ").nth(1)?.parse::<usize>().ok())
-        .unwrap_or(0);
-
-    let start = pos.saturating_sub(100);
-    let end = (pos + 100).min(raw.len());
-    error!(%err, snippet = &raw[start..end], ctx);
-    // `.context()` needs a `'static` str; own the string first.
-    anyhow!(err.to_string()).context(ctx.to_owned())
+fn print_table(rows: &[PoolRow], numbers: NumberFormat) {
+    let enriched = rows
+        .iter()
+        .any(|r| r.mint_a_usd.is_some() || r.mint_b_usd.is_some());
+    let usd = |price: Option<f64>| price.map_or_else(|| "-".into(), |p| format!("{p:.6}"));
+    if enriched {
+        println!(
+            "{:<22} | {:>13} | {:>13} | {:>13} | VOL 24H",
+            "POOL", "PRICE", "BASE USD", "QUOTE USD"
+        );
+        println!("{}", "-".repeat(92));
+    } else {
+        println!("{:<22} | {:>13} | VOL 24H", "POOL", "PRICE");
+        println!("{}", "-".repeat(60));
+    }
+    for row in rows {
+        let p = row.pool;
+        let volume = p
+            .volume24h
+            .map(|v| format_volume(v, numbers))
+            .unwrap_or_else(|| "-".into());
+        if enriched {
+            println!(
+                "{:<22} | {:>13.6} | {:>13} | {:>13} | {volume}",
+                p.name,
+                p.price.unwrap_or_default(),
+                usd(row.mint_a_usd),
+                usd(row.mint_b_usd),
+            );
+        } else {
+            println!(
+                "{:<22} | {:>13.6} | {volume}",
+                p.name,
+                p.price.unwrap_or_default()
+            );
+        }
+    }
 }
 
-fn print_table(pools: &[RaydiumPool]) {
-    println!("{:<22} | {:>13} | {}", "POOL", "PRICE", "VOL 24H");
-    println!("{}", "-".repeat(60));
-    for p in pools {
-        println!(
-            "{:<22} | {:>13.6} | {}",
-            p.name,
-            p.price.unwrap_or_default(),
-            p.volume24h
-                .map(|v| format!("{:.0}", v))
-                .unwrap_or_else(|| "-".into())
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn fixture(name: &str) -> PathBuf {
+        Path::new(env!("CARGO_MANIFEST_DIR"))
+            .join("tests/fixtures")
+            .join(name)
+    }
+
+    #[test]
+    fn saved_body_parses_offline() {
+        let mut pools = parse_saved(&fixture("raydium_pools.json")).unwrap();
+        assert_eq!(pools.len(), 2);
+        // Rows without a name still parse.
+        assert_eq!(pools[1].name, "");
+
+        sort_and_trim(&mut pools);
+        assert_eq!(pools[0].name, "SOL/USDC");
+        assert_eq!(pools[0].volume24h, Some(48210533.7));
+    }
+
+    #[test]
+    fn parse_only_errors_name_the_file() {
+        let err = parse_saved(&fixture("missing.json")).unwrap_err();
+        assert!(format!("{err:#}").contains("missing.json"), "{err:#}");
+    }
+
+    #[test]
+    fn enrichment_batches_distinct_mints_and_merges_what_was_priced() {
+        let mint = |address: &str| {
+            Some(PoolMint {
+                address: address.to_owned(),
+                symbol: String::new(),
+            })
+        };
+        let pool = |name: &str, a, b| RaydiumPool {
+            name: name.to_owned(),
+            price: None,
+            volume24h: None,
+            mint_a: a,
+            mint_b: b,
+        };
+        let pools = [
+            pool("SOL/USDC", mint("SOL"), mint("USDC")),
+            pool("WIF/SOL", mint("WIF"), mint("SOL")),
+            pool("BONK/USDC", mint("BONK"), mint("USDC")),
+            pool("", None, None),
+        ];
+
+        let batches = mint_batches(&pools, 3);
+        assert_eq!(batches, [vec!["SOL", "USDC", "WIF"], vec!["BONK"]]);
+        assert_eq!(mint_batches(&pools, 0).len(), 4);
+
+        let prices = merge_prices(vec![
+            Ok(HashMap::from([
+                ("SOL".to_owned(), 150.0),
+                ("USDC".to_owned(), 1.0),
+            ])),
+            Err(anyhow::anyhow!("HTTP 429")),
+        ]);
+        let priced = rows(&pools, Some(&prices));
+        assert_eq!(
+            (priced[0].mint_a_usd, priced[0].mint_b_usd),
+            (Some(150.0), Some(1.0))
         );
+        // WIF and BONK were in no successful batch.
+        assert_eq!(
+            (priced[1].mint_a_usd, priced[1].mint_b_usd),
+            (None, Some(150.0))
+        );
+        assert_eq!(
+            (priced[2].mint_a_usd, priced[2].mint_b_usd),
+            (None, Some(1.0))
+        );
+
+        let json = serde_json::to_value(&priced[0]).unwrap();
+        assert_eq!(json["mintAUsd"], 150.0);
+        assert_eq!(json["mintA"]["address"], "SOL");
+        // Unenriched rows save exactly as before.
+        let plain = serde_json::to_value(&rows(&pools[3..], None)[0]).unwrap();
+        assert_eq!(
+            plain,
+            serde_json::json!({ "name": "", "price": null, "volume24h": null })
+        );
+    }
+
+    #[test]
+    fn enrich_options_need_enrich() {
+        assert!(Cli::try_parse_from(["raydium_top_coins", "--enrich-concurrency=2"]).is_err());
+        let cli = Cli::try_parse_from(["raydium_top_coins", "--enrich", "--enrich-concurrency=2"])
+            .unwrap();
+        assert_eq!((cli.enrich_concurrency, cli.enrich_batch_size), (2, 50));
+        assert!(Cli::try_parse_from(["raydium_top_coins", "--enrich", "--parse-only=a"]).is_err());
+    }
+
+    #[test]
+    fn dump_raw_and_parse_only_are_exclusive() {
+        let args = ["raydium_top_coins", "--dump-raw=a", "--parse-only=b"];
+        assert!(Cli::try_parse_from(args).is_err());
     }
 }
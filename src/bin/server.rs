@@ -0,0 +1,46 @@
+//! Run the scanner without Shuttle, e.g. for local development.
+//!
+//! Binds the same router as the Shuttle service on `HOST:PORT`
+//! (default `0.0.0.0:8080`). Exits with a code reflecting why it stopped:
+//! 130 for `SIGINT`, 143 for `SIGTERM`, 1 for a feed failure and 0 for
+//! `POST /admin/shutdown`.
+//!
+//! Build:  cargo run --bin server --release
+
+use anyhow::{Context, Result};
+use crypto_scanner_agent::{
+    build_router, init_server_tracing, shutdown::on_signals, spawn_feed, RouterConfig,
+};
+use shuttle_axum::axum;
+use std::net::SocketAddr;
+
+#[tokio::main]
+async fn main() -> Result<()> {
+    init_server_tracing();
+
+    let host = std::env::var("HOST").unwrap_or_else(|_| "0.0.0.0".into());
+    let port = std::env::var("PORT").unwrap_or_else(|_| "8080".into());
+    let addr = format!("{host}:{port}");
+
+    let state = spawn_feed()?;
+    let shutdown = state.lock().await.shutdown.clone();
+    let router = build_router(state, &RouterConfig::from_env()?);
+    let listener = tokio::net::TcpListener::bind(&addr)
+        .await
+        .with_context(|| format!("binding {addr}"))?;
+    tracing::info!("Listening on http://{}", listener.local_addr()?);
+
+    // Connect info lets `/websocket` log the peer address of each client.
+    axum::serve(
+        listener,
+        router.into_make_service_with_connect_info::<SocketAddr>(),
+    )
+    .with_graceful_shutdown(on_signals(shutdown.clone()))
+    .await?;
+
+    let code = shutdown.exit_code();
+    if code != 0 {
+        std::process::exit(code);
+    }
+    Ok(())
+}
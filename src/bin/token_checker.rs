@@ -1,10 +1,15 @@
-use rig::providers::deepseek::{self, Client};
-use futures::{stream, StreamExt};
 use anyhow::Result;
-use std::env;
+use clap::Parser;
+use crypto_scanner_agent::{
+    logging::{init_tracing, VerbosityArgs},
+    util::{parallel_map_with_timeout, BatchSummary},
+};
+use rig::providers::deepseek::Client;
+use schemars::JsonSchema;
+use std::time::{Duration, Instant};
 
 /// Response structure describing token status.
-#[derive(serde::Deserialize, serde::Serialize)]
+#[derive(serde::Deserialize, serde::Serialize, JsonSchema)]
 struct TokenReview {
     /// Short comment about the token.
     comment: String,
@@ -24,30 +29,45 @@ async fn check_token(client: &Client, token: &str) -> Result<String> {
     Ok(review.comment)
 }
 
+/// Ask a DeepSeek agent whether token symbols look legitimate.
+#[derive(Parser)]
+#[command(version, about)]
+struct Cli {
+    #[command(flatten)]
+    verbosity: VerbosityArgs,
+    /// Give up on a token after this many seconds
+    #[arg(long, default_value_t = 60, value_name = "SECS")]
+    timeout: u64,
+    /// Token symbols to check, e.g. BTC ETH
+    #[arg(required = true, value_name = "SYMBOL")]
+    tokens: Vec<String>,
+}
+
 #[tokio::main]
 async fn main() -> Result<()> {
-    tracing_subscriber::fmt().with_target(false).init();
+    let cli = Cli::parse();
+    init_tracing(cli.verbosity.verbosity());
 
     let client = Client::from_env();
-    let tokens: Vec<String> = env::args().skip(1).collect();
-    if tokens.is_empty() {
-        eprintln!("usage: token_checker SYMBOL [SYMBOL...]" );
-        std::process::exit(1);
-    }
+    let tokens = cli.tokens;
 
-    let results = stream::iter(tokens.iter())
-        .map(|t| check_token(&client, t))
-        .buffer_unordered(8)
-        .collect::<Vec<_>>()
-        .await;
+    let started = Instant::now();
+    let timeout = Duration::from_secs(cli.timeout);
+    // Ordered, so each result is printed next to its own token.
+    let results =
+        parallel_map_with_timeout(&tokens, 8, true, timeout, |t| check_token(&client, t)).await;
 
+    let mut summary = BatchSummary::default();
     for (token, res) in tokens.iter().zip(results) {
+        let res = res.map_err(anyhow::Error::from).and_then(|r| r);
+        summary.record(&res);
         match res {
             Ok(comment) => println!("{token}: {comment}"),
             Err(e) => eprintln!("{token}: error - {e}"),
         }
     }
+    summary.elapsed = started.elapsed();
+    eprintln!("{summary}");
 
     Ok(())
 }
-
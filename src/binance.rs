@@ -0,0 +1,138 @@
+//! Binance's rate-limit signals. Every response reports the request weight
+//! used in the current minute in `X-MBX-USED-WEIGHT-1M`; going over the
+//! limit earns a `429` with `Retry-After`, and ignoring that a `418` IP ban.
+//! The feed backs off before the limit and waits out exactly what a `429`
+//! or `418` asks.
+
+use std::{
+    sync::{Arc, Mutex},
+    time::{Duration, SystemTime, UNIX_EPOCH},
+};
+
+use reqwest::header::{HeaderMap, RETRY_AFTER};
+
+/// Request weight Binance allows per IP and minute.
+pub const WEIGHT_LIMIT: u64 = 6000;
+
+/// Share of [`WEIGHT_LIMIT`] after which we wait for the next minute.
+const WEIGHT_HEADROOM: f64 = 0.9;
+
+/// Wait after a `429` that didn't say how long.
+const DEFAULT_RATE_LIMITED: Duration = Duration::from_secs(60);
+
+/// Wait after a `418` ban that didn't say how long.
+const DEFAULT_BANNED: Duration = Duration::from_secs(120);
+
+/// The last used weight Binance reported, shared by the Binance feed and
+/// `/stats`.
+#[derive(Debug, Clone, Default)]
+pub struct UsedWeight(Arc<Mutex<Option<u64>>>);
+
+impl UsedWeight {
+    /// The most recent `X-MBX-USED-WEIGHT` seen, `None` before any.
+    pub fn get(&self) -> Option<u64> {
+        *self.0.lock().unwrap()
+    }
+
+    /// Remember `limit`'s used weight, if it reported one.
+    pub fn record(&self, limit: &RateLimit) {
+        if let Some(weight) = limit.used_weight {
+            *self.0.lock().unwrap() = Some(weight);
+        }
+    }
+}
+
+/// What one Binance response says about the rate limits.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct RateLimit {
+    pub status: u16,
+    /// Weight used in the current minute.
+    pub used_weight: Option<u64>,
+    /// `Retry-After`, in seconds as Binance sends it.
+    pub retry_after: Option<Duration>,
+}
+
+impl RateLimit {
+    /// Read the rate-limit headers of a response.
+    pub fn from_headers(status: u16, headers: &HeaderMap) -> Self {
+        let number = |v: &reqwest::header::HeaderValue| v.to_str().ok()?.trim().parse().ok();
+        // `X-MBX-USED-WEIGHT-1M`, or the older bare `X-MBX-USED-WEIGHT`.
+        let used_weight = headers
+            .get("x-mbx-used-weight-1m")
+            .or_else(|| headers.get("x-mbx-used-weight"))
+            .and_then(number);
+        let retry_after = headers
+            .get(RETRY_AFTER)
+            .and_then(number)
+            .map(Duration::from_secs);
+        Self {
+            status,
+            used_weight,
+            retry_after,
+        }
+    }
+
+    /// How long to hold off before the next request at `now`, if at all: a
+    /// `429` or `418` waits exactly its `Retry-After`, and a weight close to
+    /// the limit waits for the minute to roll over.
+    pub fn backoff(&self, now: SystemTime) -> Option<Duration> {
+        match self.status {
+            429 => return Some(self.retry_after.unwrap_or(DEFAULT_RATE_LIMITED)),
+            418 => return Some(self.retry_after.unwrap_or(DEFAULT_BANNED)),
+            _ => {}
+        }
+        let weight = self.used_weight?;
+        if (weight as f64) < WEIGHT_LIMIT as f64 * WEIGHT_HEADROOM {
+            return None;
+        }
+        let into_minute = now
+            .duration_since(UNIX_EPOCH)
+            .unwrap_or_default()
+            .as_millis()
+            % 60_000;
+        Some(Duration::from_millis((60_000 - into_minute) as u64))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn headers(pairs: &[(&'static str, &'static str)]) -> HeaderMap {
+        pairs
+            .iter()
+            .map(|(k, v)| (k.parse().unwrap(), v.parse().unwrap()))
+            .collect()
+    }
+
+    #[test]
+    fn rate_limited_responses_wait_exactly_retry_after() {
+        let now = UNIX_EPOCH + Duration::from_secs(1_700_000_000);
+        let limited = RateLimit::from_headers(
+            429,
+            &headers(&[("retry-after", "17"), ("x-mbx-used-weight-1m", "6100")]),
+        );
+        assert_eq!(limited.used_weight, Some(6100));
+        assert_eq!(limited.backoff(now), Some(Duration::from_secs(17)));
+
+        let banned = RateLimit::from_headers(418, &headers(&[("Retry-After", "3600")]));
+        assert_eq!(banned.backoff(now), Some(Duration::from_secs(3600)));
+        let unspecified = RateLimit::from_headers(429, &HeaderMap::new());
+        assert_eq!(unspecified.backoff(now), Some(DEFAULT_RATE_LIMITED));
+    }
+
+    #[test]
+    fn heavy_weight_waits_for_the_next_minute() {
+        // 45 s into a minute.
+        let now = UNIX_EPOCH + Duration::from_secs(60 * 28_333_333 + 45);
+        let heavy = RateLimit::from_headers(200, &headers(&[("x-mbx-used-weight", "5500")]));
+        assert_eq!(heavy.backoff(now), Some(Duration::from_secs(15)));
+
+        let light = RateLimit::from_headers(200, &headers(&[("x-mbx-used-weight-1m", "40")]));
+        assert_eq!(light.backoff(now), None);
+        assert_eq!(
+            RateLimit::from_headers(200, &HeaderMap::new()).backoff(now),
+            None
+        );
+    }
+}
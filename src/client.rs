@@ -0,0 +1,174 @@
+//! A typed client for a running scanner's `/websocket`, the counterpart of
+//! the server for downstream tools written in Rust. [`ScannerClient`]
+//! decodes each frame into a [`Signal`] and reconnects whenever the server
+//! goes away, resuming where it left off.
+
+use std::time::Duration;
+
+use futures::{Stream, StreamExt};
+use tokio::net::TcpStream;
+use tokio_tungstenite::{tungstenite::Message, MaybeTlsStream, WebSocketStream};
+
+use crate::{events::Gap, tls::connect_ws, Heartbeat, Signal};
+
+/// Reconnect delays after the server goes away; the last one repeats.
+const RETRY_DELAYS: [Duration; 4] = [
+    Duration::from_secs(1),
+    Duration::from_secs(2),
+    Duration::from_secs(5),
+    Duration::from_secs(10),
+];
+
+type Socket = WebSocketStream<MaybeTlsStream<TcpStream>>;
+
+/// Follows a scanner's `/websocket`, e.g. `ws://127.0.0.1:8080/websocket`.
+///
+/// The server has no per-client filters, so every signal is yielded; narrow
+/// the stream with [`StreamExt::filter`]. The one subscription option it
+/// does have, `?since=SEQ`, is used to resume after a reconnect.
+#[derive(Debug, Clone)]
+pub struct ScannerClient {
+    url: String,
+    accept_invalid_certs: bool,
+    resume: bool,
+    retry_delays: Vec<Duration>,
+}
+
+impl ScannerClient {
+    pub fn new(url: impl Into<String>) -> Self {
+        Self {
+            url: url.into(),
+            accept_invalid_certs: false,
+            resume: true,
+            retry_delays: RETRY_DELAYS.to_vec(),
+        }
+    }
+
+    /// Accept a self-signed certificate from the server.
+    pub fn with_invalid_certs(mut self, accept: bool) -> Self {
+        self.accept_invalid_certs = accept;
+        self
+    }
+
+    /// Whether a reconnect asks for the signals missed while disconnected
+    /// (the default). Those no longer buffered by the server are logged as
+    /// a gap and skipped.
+    pub fn with_resume(mut self, resume: bool) -> Self {
+        self.resume = resume;
+        self
+    }
+
+    /// Wait these delays between reconnect attempts, the last one
+    /// repeating, instead of 1, 2, 5 and then 10 seconds. The count starts
+    /// over once a connection delivers a signal. Empty means no delay.
+    pub fn with_retry_delays(mut self, delays: Vec<Duration>) -> Self {
+        self.retry_delays = delays;
+        self
+    }
+
+    /// Every signal the server sends, decoded, across reconnects. The
+    /// stream never ends; drop it to disconnect. Heartbeats and any other
+    /// frames that aren't a signal are skipped.
+    pub fn signals(self) -> impl Stream<Item = Signal> + Send {
+        let session = Session {
+            client: self,
+            socket: None,
+            last_seq: None,
+            started: false,
+            retries: 0,
+        };
+        futures::stream::unfold(session, |mut session| async move {
+            let signal = session.next_signal().await;
+            Some((signal, session))
+        })
+    }
+
+    /// The URL to connect to, resuming after `last_seq` if there is one.
+    fn url(&self, last_seq: Option<u64>) -> String {
+        match last_seq {
+            Some(seq) if self.resume => {
+                let separator = if self.url.contains('?') { '&' } else { '?' };
+                format!("{}{separator}since={seq}", self.url)
+            }
+            _ => self.url.clone(),
+        }
+    }
+}
+
+/// One [`ScannerClient::signals`] stream's connection state.
+struct Session {
+    client: ScannerClient,
+    socket: Option<Socket>,
+    /// `seq` of the last signal yielded.
+    last_seq: Option<u64>,
+    /// Whether a connection was attempted yet; only the first goes out
+    /// without a delay.
+    started: bool,
+    /// Retry delays waited since the last signal.
+    retries: usize,
+}
+
+impl Session {
+    async fn next_signal(&mut self) -> Signal {
+        loop {
+            let socket = match &mut self.socket {
+                Some(socket) => socket,
+                None => {
+                    self.socket = Some(self.connect().await);
+                    continue;
+                }
+            };
+            let frame = match socket.next().await {
+                Some(Ok(frame)) => frame,
+                Some(Err(e)) => {
+                    tracing::warn!(url = %self.client.url, "Scanner connection failed: {e}");
+                    self.socket = None;
+                    continue;
+                }
+                None => {
+                    tracing::info!(url = %self.client.url, "Scanner closed the connection");
+                    self.socket = None;
+                    continue;
+                }
+            };
+            let Message::Text(txt) = frame else {
+                continue;
+            };
+            if let Ok(signal) = serde_json::from_str::<Signal>(&txt) {
+                self.last_seq = Some(signal.seq);
+                self.retries = 0;
+                return signal;
+            }
+            if let Ok(gap) = serde_json::from_str::<Gap>(&txt) {
+                tracing::warn!(missed = gap.missed, "Scanner could not replay signals");
+            } else if let Err(e) = serde_json::from_str::<Heartbeat>(&txt) {
+                tracing::debug!("Skipping non-signal frame: {e}");
+            }
+        }
+    }
+
+    /// Connect, waiting out the next retry delay before every attempt but
+    /// the very first.
+    async fn connect(&mut self) -> Socket {
+        loop {
+            if self.started {
+                tokio::time::sleep(self.delay()).await;
+                self.retries += 1;
+            }
+            self.started = true;
+            let url = self.client.url(self.last_seq);
+            match connect_ws(&url, self.client.accept_invalid_certs).await {
+                Ok((socket, _)) => return socket,
+                Err(e) => tracing::warn!(%url, "Scanner connect failed: {e}"),
+            }
+        }
+    }
+
+    fn delay(&self) -> Duration {
+        let delays = &self.client.retry_delays;
+        match delays.len() {
+            0 => Duration::ZERO,
+            n => delays[self.retries.min(n - 1)],
+        }
+    }
+}
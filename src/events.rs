@@ -0,0 +1,402 @@
+use std::{
+    collections::VecDeque,
+    convert::Infallible,
+    path::PathBuf,
+    sync::{Arc, Mutex},
+};
+
+use anyhow::Context;
+use chrono::{DateTime, Utc};
+use futures::{stream, Stream, StreamExt};
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use shuttle_axum::axum::{
+    extract::ws::Message,
+    http::{header, HeaderMap},
+    response::{
+        sse::{Event, KeepAlive, Sse},
+        IntoResponse,
+    },
+    Extension,
+};
+use tokio::sync::watch;
+
+use crate::{
+    output::{OutputArgs, OutputDir},
+    stream::Signal,
+    ws::{next_heartbeat, Heartbeat},
+    SharedState,
+};
+
+/// Number of recent signals kept for `Last-Event-ID` resumption, unless
+/// overridden by `SIGNAL_BUFFER_SIZE`.
+pub const DEFAULT_BUFFER_SIZE: usize = 100;
+
+/// Buffer capacity from `SIGNAL_BUFFER_SIZE`, or [`DEFAULT_BUFFER_SIZE`].
+pub fn buffer_size_from_env() -> Result<usize, String> {
+    let Ok(v) = std::env::var("SIGNAL_BUFFER_SIZE") else {
+        return Ok(DEFAULT_BUFFER_SIZE);
+    };
+    match v.trim().parse::<usize>() {
+        Ok(0) => Err("SIGNAL_BUFFER_SIZE must be at least 1".to_owned()),
+        Ok(n) => Ok(n),
+        Err(e) => Err(format!("invalid SIGNAL_BUFFER_SIZE `{v}`: {e}")),
+    }
+}
+
+/// A serialized signal together with its event id.
+#[derive(Debug, Clone, PartialEq)]
+pub struct BufferedSignal {
+    pub id: u64,
+    pub json: String,
+}
+
+/// Fixed-size ring buffer of the most recent signals. Ids increase by one
+/// per signal starting at 1, so `0` means "nothing seen yet"; they double as
+/// the signals' `seq`.
+#[derive(Debug)]
+pub struct SignalBuffer {
+    capacity: usize,
+    next_id: u64,
+    entries: VecDeque<BufferedSignal>,
+}
+
+/// What a client resuming after a given seq gets from the buffer.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Replay {
+    /// Signals it missed that were already evicted from the buffer.
+    pub missed: u64,
+    /// Signals it missed that are still buffered, oldest first.
+    pub signals: Vec<BufferedSignal>,
+    /// Id to continue live streaming from.
+    pub last_id: u64,
+}
+
+/// What a streaming client is sent.
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) enum Outgoing {
+    Signal(BufferedSignal),
+    /// A serialized [`Heartbeat`]. It isn't buffered, so it has no id.
+    Heartbeat(String),
+}
+
+/// Sent to a resuming `/websocket` client before the replay when some of
+/// what it missed is no longer buffered.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, JsonSchema)]
+#[serde(tag = "type", rename = "gap")]
+pub struct Gap {
+    /// Number of signals that can't be replayed.
+    pub missed: u64,
+}
+
+/// Buffer shared between the feed (writer) and the HTTP handlers (readers).
+pub type SharedBuffer = Arc<Mutex<SignalBuffer>>;
+
+impl SignalBuffer {
+    pub fn new(capacity: usize) -> Self {
+        Self {
+            capacity,
+            next_id: 1,
+            entries: VecDeque::with_capacity(capacity),
+        }
+    }
+
+    pub fn shared(capacity: usize) -> SharedBuffer {
+        Arc::new(Mutex::new(Self::new(capacity)))
+    }
+
+    /// Store `json`, evicting the oldest entry when full, and return its id.
+    pub fn push(&mut self, json: String) -> u64 {
+        let id = self.next_id;
+        self.next_id += 1;
+        if self.entries.len() == self.capacity {
+            self.entries.pop_front();
+        }
+        if self.capacity > 0 {
+            self.entries.push_back(BufferedSignal { id, json });
+        }
+        id
+    }
+
+    /// Stamp `sig` with the next sequence number and store it. Returns the
+    /// JSON to broadcast, which carries the same `seq`.
+    pub fn push_signal(&mut self, sig: &mut Signal) -> serde_json::Result<String> {
+        sig.seq = self.next_id;
+        let json = serde_json::to_string(sig)?;
+        self.push(json.clone());
+        Ok(json)
+    }
+
+    /// Number of signals currently retained.
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    pub fn capacity(&self) -> usize {
+        self.capacity
+    }
+
+    /// Id of the most recently pushed signal, `0` if none.
+    pub fn last_id(&self) -> u64 {
+        self.next_id - 1
+    }
+
+    /// Everything after `since`. A `since` ahead of [`last_id`](Self::last_id)
+    /// predates a server restart and is meaningless, so the client just
+    /// starts live.
+    pub fn replay(&self, since: u64) -> Replay {
+        let last_id = self.last_id();
+        if since >= last_id {
+            return Replay {
+                missed: 0,
+                signals: Vec::new(),
+                last_id,
+            };
+        }
+        let oldest = self.entries.front().map_or(last_id + 1, |e| e.id);
+        Replay {
+            missed: oldest.saturating_sub(since + 1),
+            signals: self.since(since),
+            last_id,
+        }
+    }
+
+    /// Every buffered signal newer than `last_id`, oldest first.
+    pub fn since(&self, last_id: u64) -> Vec<BufferedSignal> {
+        self.entries
+            .iter()
+            .filter(|e| e.id > last_id)
+            .cloned()
+            .collect()
+    }
+}
+
+/// Where snapshots are written: `SNAPSHOT_DIR`, else `OUTPUT_DIR`, else the
+/// working directory.
+pub fn snapshot_dir_from_env() -> OutputDir {
+    match std::env::var_os("SNAPSHOT_DIR").filter(|v| !v.is_empty()) {
+        Some(dir) => OutputDir(Some(dir.into())),
+        None => OutputArgs::default().resolve(),
+    }
+}
+
+/// Contents of a snapshot file.
+#[derive(Serialize)]
+struct SnapshotFile {
+    taken_at: DateTime<Utc>,
+    /// Buffered signals, oldest first.
+    signals: Vec<serde_json::Value>,
+}
+
+/// A snapshot that was written, as returned by `POST /admin/snapshot`.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct SignalSnapshot {
+    pub path: PathBuf,
+    pub signals: usize,
+}
+
+/// Write every buffered signal to `signals-<UTC timestamp>.json` under
+/// `dir`, to keep an interesting market moment after it scrolls out of the
+/// buffer.
+pub fn write_snapshot(buffer: &SharedBuffer, dir: &OutputDir) -> anyhow::Result<SignalSnapshot> {
+    let entries = buffer.lock().unwrap().since(0);
+    let signals = entries
+        .iter()
+        .map(|e| serde_json::from_str(&e.json))
+        .collect::<Result<Vec<_>, _>>()
+        .context("parsing buffered signals")?;
+    let taken_at = Utc::now();
+    let path = dir.resolve_output_path(format!(
+        "signals-{}.json",
+        taken_at.format("%Y%m%dT%H%M%S%.3fZ")
+    ))?;
+    let count = signals.len();
+    let file = SnapshotFile { taken_at, signals };
+    std::fs::write(&path, serde_json::to_vec_pretty(&file)?)
+        .with_context(|| format!("writing {}", path.display()))?;
+    Ok(SignalSnapshot {
+        path,
+        signals: count,
+    })
+}
+
+/// Write a snapshot every time the process receives `SIGUSR1`, e.g.
+/// `kill -USR1 <pid>`. Runs until the process exits.
+#[cfg(unix)]
+pub async fn snapshot_on_sigusr1(buffer: SharedBuffer, dir: OutputDir) -> anyhow::Result<()> {
+    use tokio::signal::unix::{signal, SignalKind};
+
+    let mut usr1 = signal(SignalKind::user_defined1()).context("listening for SIGUSR1")?;
+    while usr1.recv().await.is_some() {
+        match write_snapshot(&buffer, &dir) {
+            Ok(s) => {
+                tracing::info!(path = %s.path.display(), signals = s.signals, "Snapshot written")
+            }
+            Err(e) => tracing::warn!("Snapshot failed: {e:#}"),
+        }
+    }
+    Ok(())
+}
+
+/// `replay` followed by every signal pushed from then on, interleaved with
+/// the heartbeats broadcast on `heartbeats`. The watch channel only says
+/// "something changed"; the buffer tells us exactly which signals are new,
+/// so bursts are not coalesced away. The stream ends when the feed shuts
+/// down.
+pub(crate) fn replay_then_live(
+    replay: Replay,
+    rx: watch::Receiver<Option<Message>>,
+    heartbeats: watch::Receiver<Option<Heartbeat>>,
+    buffer: SharedBuffer,
+) -> impl Stream<Item = Outgoing> {
+    let live = stream::unfold(
+        (rx, heartbeats, buffer, replay.last_id),
+        |(mut rx, mut heartbeats, buffer, mut last_id)| async move {
+            let batch: Vec<_> = tokio::select! {
+                changed = rx.changed() => {
+                    changed.ok()?;
+                    let fresh = buffer.lock().unwrap().since(last_id);
+                    last_id = fresh.last().map_or(last_id, |e| e.id);
+                    fresh.into_iter().map(Outgoing::Signal).collect()
+                }
+                json = next_heartbeat(&mut heartbeats) => vec![Outgoing::Heartbeat(json)],
+            };
+            Some((stream::iter(batch), (rx, heartbeats, buffer, last_id)))
+        },
+    )
+    .flatten();
+    stream::iter(replay.signals)
+        .map(Outgoing::Signal)
+        .chain(live)
+}
+
+/// `GET /events` — the signal feed as Server-Sent Events.
+///
+/// Each signal is sent as one event whose id is its buffer id. A client
+/// reconnecting with `Last-Event-ID` first receives whatever it missed that
+/// is still in the buffer, then live events. Heartbeats are sent without an
+/// id, so they don't move the client's `Last-Event-ID`.
+pub async fn events_handler(
+    headers: HeaderMap,
+    Extension(state): Extension<SharedState>,
+) -> Sse<impl Stream<Item = Result<Event, Infallible>>> {
+    let (rx, heartbeats, buffer) = {
+        let state = state.lock().await;
+        (
+            state.rx.clone(),
+            state.heartbeats.clone(),
+            state.buffer.clone(),
+        )
+    };
+
+    let resume_from = headers
+        .get("last-event-id")
+        .and_then(|v| v.to_str().ok())
+        .and_then(|v| v.trim().parse::<u64>().ok());
+    let replay = {
+        let buffer = buffer.lock().unwrap();
+        buffer.replay(resume_from.unwrap_or(buffer.last_id()))
+    };
+
+    // Dropped by axum as soon as the client disconnects.
+    let events = replay_then_live(replay, rx, heartbeats, buffer).map(|out| {
+        Ok(match out {
+            Outgoing::Signal(e) => Event::default().id(e.id.to_string()).data(e.json),
+            Outgoing::Heartbeat(json) => Event::default().data(json),
+        })
+    });
+
+    Sse::new(events).keep_alive(KeepAlive::default())
+}
+
+/// `GET /signals.csv` — the buffered signals as CSV, oldest first, under a
+/// [`Signal::CSV_HEADER`] line.
+pub async fn signals_csv_handler(Extension(state): Extension<SharedState>) -> impl IntoResponse {
+    let entries = state.lock().await.buffer.lock().unwrap().since(0);
+    let mut csv = format!("{}\n", Signal::CSV_HEADER);
+    // Anything buffered that isn't a signal has no row to give.
+    for sig in entries
+        .iter()
+        .filter_map(|e| serde_json::from_str::<Signal>(&e.json).ok())
+    {
+        csv.push_str(&sig.to_csv_row());
+        csv.push('\n');
+    }
+    ([(header::CONTENT_TYPE, "text/csv; charset=utf-8")], csv)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn buffer_evicts_oldest_and_keeps_ids() {
+        let mut buffer = SignalBuffer::new(2);
+        assert_eq!(buffer.last_id(), 0);
+        for json in ["a", "b", "c"] {
+            buffer.push(json.to_owned());
+        }
+
+        assert_eq!(buffer.last_id(), 3);
+        assert_eq!(buffer.len(), buffer.capacity());
+        let ids: Vec<_> = buffer.since(0).iter().map(|e| e.id).collect();
+        assert_eq!(ids, [2, 3]);
+        assert_eq!(buffer.since(2)[0].json, "c");
+        assert!(buffer.since(3).is_empty());
+    }
+
+    #[test]
+    fn replay_reports_evicted_signals_as_missed() {
+        let mut buffer = SignalBuffer::new(3);
+        for i in 0..5 {
+            buffer.push(i.to_string());
+        }
+        // Ids 3..=5 are still buffered.
+        let ids = |r: &Replay| r.signals.iter().map(|e| e.id).collect::<Vec<_>>();
+
+        let r = buffer.replay(3);
+        assert_eq!((r.missed, ids(&r)), (0, vec![4, 5]));
+        let r = buffer.replay(1);
+        assert_eq!((r.missed, ids(&r)), (1, vec![3, 4, 5]));
+        let r = buffer.replay(0);
+        assert_eq!(r.missed, 2);
+        // Up to date, or from before a restart: nothing to replay.
+        for since in [5, 99] {
+            let r = buffer.replay(since);
+            assert_eq!((r.missed, r.signals.len(), r.last_id), (0, 0, 5));
+        }
+
+        let gap = serde_json::to_string(&Gap { missed: 2 }).unwrap();
+        assert_eq!(gap, r#"{"type":"gap","missed":2}"#);
+    }
+
+    #[test]
+    fn signals_get_unique_increasing_seq() {
+        let json = r#"[
+            { "s": "AUSDT", "P": "6", "q": "2000000", "c": "1" },
+            { "s": "BUSDT", "P": "7", "q": "2000000", "c": "1" },
+            { "s": "CUSDT", "P": "8", "q": "2000000", "c": "1" }
+        ]"#;
+        let mut buffer = SignalBuffer::new(2);
+        let mut seqs = Vec::new();
+        // Two batches, as if from two feeds sharing the buffer.
+        for _ in 0..2 {
+            let signals =
+                crate::stream::extract_signals_from_text(json, &Default::default()).unwrap();
+            for mut sig in signals {
+                let json = buffer.push_signal(&mut sig).unwrap();
+                let sent: Signal = serde_json::from_str(&json).unwrap();
+                assert_eq!(sent.seq, sig.seq);
+                seqs.push(sig.seq);
+            }
+        }
+
+        assert_eq!(seqs, [1, 2, 3, 4, 5, 6]);
+        assert_eq!(buffer.last_id(), 6);
+        assert_eq!(buffer.since(0)[0].id, 5);
+    }
+}
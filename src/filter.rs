@@ -0,0 +1,459 @@
+//! A tiny expression language for custom signal filters.
+//!
+//! ```text
+//! pct_gain_24h > 10 && quote_vol_usdt > 2000000 && symbol.ends_with("USDT")
+//! ```
+//!
+//! Supported: the ticker fields `symbol` (string) and `pct_gain_24h`,
+//! `quote_vol_usdt`, `last_price` (numbers); number and string literals;
+//! `== != < <= > >=`; `&& || !`; parentheses; and the string methods
+//! `ends_with`, `starts_with` and `contains`. Expressions are type-checked
+//! when parsed, so mistakes surface at startup rather than per ticker.
+
+use std::{fmt, str::FromStr};
+
+/// The ticker values an expression can refer to.
+#[derive(Debug, Clone, Copy)]
+pub struct TickerFields<'a> {
+    pub symbol: &'a str,
+    pub pct_gain_24h: f64,
+    pub quote_vol_usdt: f64,
+    pub last_price: f64,
+}
+
+/// A parsed, type-checked filter expression.
+#[derive(Debug, Clone, PartialEq)]
+pub struct FilterExpr {
+    source: String,
+    expr: Expr,
+}
+
+impl FilterExpr {
+    /// Evaluate the expression against a ticker.
+    pub fn matches(&self, t: &TickerFields) -> bool {
+        self.expr.eval_bool(t)
+    }
+}
+
+impl fmt::Display for FilterExpr {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.source)
+    }
+}
+
+impl FromStr for FilterExpr {
+    type Err = String;
+
+    fn from_str(source: &str) -> Result<Self, Self::Err> {
+        let tokens = tokenize(source)?;
+        let mut parser = Parser { tokens, pos: 0 };
+        let expr = parser.parse_or()?;
+        if let Some(tok) = parser.tokens.get(parser.pos) {
+            return Err(format!("unexpected `{tok}` after end of expression"));
+        }
+        if expr.ty()? != Ty::Bool {
+            return Err("expression must evaluate to true/false".into());
+        }
+        Ok(Self {
+            source: source.trim().to_owned(),
+            expr,
+        })
+    }
+}
+
+/* ───────────────────────────── Lexer ───────────────────────────── */
+
+#[derive(Debug, Clone, PartialEq)]
+enum Token {
+    Num(f64),
+    Str(String),
+    Ident(String),
+    Op(&'static str),
+}
+
+impl fmt::Display for Token {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Token::Num(n) => write!(f, "{n}"),
+            Token::Str(s) => write!(f, "\"{s}\""),
+            Token::Ident(i) => f.write_str(i),
+            Token::Op(op) => f.write_str(op),
+        }
+    }
+}
+
+const OPERATORS: [&str; 14] = [
+    "&&", "||", "==", "!=", "<=", ">=", "<", ">", "!", "(", ")", ".", ",", "=",
+];
+
+fn tokenize(src: &str) -> Result<Vec<Token>, String> {
+    let mut tokens = Vec::new();
+    let mut rest = src;
+
+    while let Some(c) = rest.chars().next() {
+        if c.is_whitespace() {
+            rest = &rest[c.len_utf8()..];
+        } else if c.is_ascii_digit() {
+            let end = rest
+                .find(|c: char| !(c.is_ascii_digit() || c == '.' || c == '_'))
+                .unwrap_or(rest.len());
+            let num = rest[..end].replace('_', "");
+            tokens.push(Token::Num(
+                num.parse()
+                    .map_err(|_| format!("invalid number `{}`", &rest[..end]))?,
+            ));
+            rest = &rest[end..];
+        } else if c == '"' {
+            let end = rest[1..]
+                .find('"')
+                .ok_or_else(|| "unterminated string literal".to_owned())?;
+            tokens.push(Token::Str(rest[1..=end].to_owned()));
+            rest = &rest[end + 2..];
+        } else if c.is_alphabetic() || c == '_' {
+            let end = rest
+                .find(|c: char| !(c.is_alphanumeric() || c == '_'))
+                .unwrap_or(rest.len());
+            tokens.push(Token::Ident(rest[..end].to_owned()));
+            rest = &rest[end..];
+        } else {
+            let op = OPERATORS
+                .iter()
+                .find(|op| rest.starts_with(**op))
+                .ok_or_else(|| format!("unexpected character `{c}`"))?;
+            if *op == "=" {
+                return Err("use `==` for comparison".into());
+            }
+            tokens.push(Token::Op(op));
+            rest = &rest[op.len()..];
+        }
+    }
+
+    Ok(tokens)
+}
+
+/* ───────────────────────────── Parser ──────────────────────────── */
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Field {
+    Symbol,
+    PctGain,
+    QuoteVol,
+    LastPrice,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Method {
+    EndsWith,
+    StartsWith,
+    Contains,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Cmp {
+    Eq,
+    Ne,
+    Lt,
+    Le,
+    Gt,
+    Ge,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum Expr {
+    Num(f64),
+    Str(String),
+    Field(Field),
+    Method(Field, Method, String),
+    Cmp(Box<Expr>, Cmp, Box<Expr>),
+    And(Box<Expr>, Box<Expr>),
+    Or(Box<Expr>, Box<Expr>),
+    Not(Box<Expr>),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Ty {
+    Num,
+    Str,
+    Bool,
+}
+
+struct Parser {
+    tokens: Vec<Token>,
+    pos: usize,
+}
+
+impl Parser {
+    fn peek_op(&self, op: &str) -> bool {
+        matches!(self.tokens.get(self.pos), Some(Token::Op(o)) if *o == op)
+    }
+
+    fn next(&mut self) -> Result<Token, String> {
+        let tok = self
+            .tokens
+            .get(self.pos)
+            .cloned()
+            .ok_or_else(|| "unexpected end of expression".to_owned())?;
+        self.pos += 1;
+        Ok(tok)
+    }
+
+    fn expect_op(&mut self, op: &str) -> Result<(), String> {
+        match self.tokens.get(self.pos) {
+            Some(Token::Op(o)) if *o == op => {
+                self.pos += 1;
+                Ok(())
+            }
+            Some(other) => Err(format!("expected `{op}`, found `{other}`")),
+            None => Err(format!("expected `{op}` before end of expression")),
+        }
+    }
+
+    fn parse_or(&mut self) -> Result<Expr, String> {
+        let mut lhs = self.parse_and()?;
+        while self.peek_op("||") {
+            self.pos += 1;
+            lhs = Expr::Or(Box::new(lhs), Box::new(self.parse_and()?));
+        }
+        Ok(lhs)
+    }
+
+    fn parse_and(&mut self) -> Result<Expr, String> {
+        let mut lhs = self.parse_unary()?;
+        while self.peek_op("&&") {
+            self.pos += 1;
+            lhs = Expr::And(Box::new(lhs), Box::new(self.parse_unary()?));
+        }
+        Ok(lhs)
+    }
+
+    fn parse_unary(&mut self) -> Result<Expr, String> {
+        if self.peek_op("!") {
+            self.pos += 1;
+            return Ok(Expr::Not(Box::new(self.parse_unary()?)));
+        }
+        self.parse_cmp()
+    }
+
+    fn parse_cmp(&mut self) -> Result<Expr, String> {
+        let lhs = self.parse_primary()?;
+        let cmp = match self.tokens.get(self.pos) {
+            Some(Token::Op("==")) => Cmp::Eq,
+            Some(Token::Op("!=")) => Cmp::Ne,
+            Some(Token::Op("<")) => Cmp::Lt,
+            Some(Token::Op("<=")) => Cmp::Le,
+            Some(Token::Op(">")) => Cmp::Gt,
+            Some(Token::Op(">=")) => Cmp::Ge,
+            _ => return Ok(lhs),
+        };
+        self.pos += 1;
+        let rhs = self.parse_primary()?;
+        Ok(Expr::Cmp(Box::new(lhs), cmp, Box::new(rhs)))
+    }
+
+    fn parse_primary(&mut self) -> Result<Expr, String> {
+        match self.next()? {
+            Token::Num(n) => Ok(Expr::Num(n)),
+            Token::Str(s) => Ok(Expr::Str(s)),
+            Token::Op("(") => {
+                let inner = self.parse_or()?;
+                self.expect_op(")")?;
+                Ok(inner)
+            }
+            Token::Ident(name) => {
+                let field = match name.as_str() {
+                    "symbol" => Field::Symbol,
+                    "pct_gain_24h" => Field::PctGain,
+                    "quote_vol_usdt" => Field::QuoteVol,
+                    "last_price" => Field::LastPrice,
+                    other => return Err(format!("unknown field `{other}`")),
+                };
+                if !self.peek_op(".") {
+                    return Ok(Expr::Field(field));
+                }
+                self.pos += 1;
+                let method = match self.next()? {
+                    Token::Ident(m) if m == "ends_with" => Method::EndsWith,
+                    Token::Ident(m) if m == "starts_with" => Method::StartsWith,
+                    Token::Ident(m) if m == "contains" => Method::Contains,
+                    other => return Err(format!("unknown method `{other}`")),
+                };
+                self.expect_op("(")?;
+                let arg = match self.next()? {
+                    Token::Str(s) => s,
+                    other => return Err(format!("expected a string argument, found `{other}`")),
+                };
+                self.expect_op(")")?;
+                Ok(Expr::Method(field, method, arg))
+            }
+            other => Err(format!("unexpected `{other}`")),
+        }
+    }
+}
+
+/* ─────────────────────── Type check & eval ─────────────────────── */
+
+impl Field {
+    fn ty(self) -> Ty {
+        match self {
+            Field::Symbol => Ty::Str,
+            _ => Ty::Num,
+        }
+    }
+
+    fn num(self, t: &TickerFields) -> f64 {
+        match self {
+            Field::PctGain => t.pct_gain_24h,
+            Field::QuoteVol => t.quote_vol_usdt,
+            Field::LastPrice => t.last_price,
+            Field::Symbol => f64::NAN,
+        }
+    }
+}
+
+impl Expr {
+    fn ty(&self) -> Result<Ty, String> {
+        match self {
+            Expr::Num(_) => Ok(Ty::Num),
+            Expr::Str(_) => Ok(Ty::Str),
+            Expr::Field(f) => Ok(f.ty()),
+            Expr::Method(f, _, _) => match f.ty() {
+                Ty::Str => Ok(Ty::Bool),
+                _ => Err("string methods can only be called on `symbol`".into()),
+            },
+            Expr::Cmp(lhs, cmp, rhs) => match (lhs.ty()?, rhs.ty()?) {
+                (Ty::Num, Ty::Num) => Ok(Ty::Bool),
+                (Ty::Str, Ty::Str) if matches!(cmp, Cmp::Eq | Cmp::Ne) => Ok(Ty::Bool),
+                (l, r) => Err(format!("cannot compare {l:?} with {r:?} using {cmp:?}")),
+            },
+            Expr::And(lhs, rhs) | Expr::Or(lhs, rhs) => {
+                if lhs.ty()? == Ty::Bool && rhs.ty()? == Ty::Bool {
+                    Ok(Ty::Bool)
+                } else {
+                    Err("`&&` and `||` need true/false operands".into())
+                }
+            }
+            Expr::Not(inner) => match inner.ty()? {
+                Ty::Bool => Ok(Ty::Bool),
+                _ => Err("`!` needs a true/false operand".into()),
+            },
+        }
+    }
+
+    fn eval_num(&self, t: &TickerFields) -> f64 {
+        match self {
+            Expr::Num(n) => *n,
+            Expr::Field(f) => f.num(t),
+            _ => f64::NAN,
+        }
+    }
+
+    fn eval_str<'a>(&'a self, t: &TickerFields<'a>) -> &'a str {
+        match self {
+            Expr::Str(s) => s,
+            Expr::Field(Field::Symbol) => t.symbol,
+            _ => "",
+        }
+    }
+
+    fn eval_bool(&self, t: &TickerFields) -> bool {
+        match self {
+            Expr::Method(_, method, arg) => match method {
+                Method::EndsWith => t.symbol.ends_with(arg.as_str()),
+                Method::StartsWith => t.symbol.starts_with(arg.as_str()),
+                Method::Contains => t.symbol.contains(arg.as_str()),
+            },
+            Expr::Cmp(lhs, cmp, rhs) if lhs.ty() == Ok(Ty::Str) => {
+                let equal = lhs.eval_str(t) == rhs.eval_str(t);
+                (*cmp == Cmp::Eq) == equal
+            }
+            Expr::Cmp(lhs, cmp, rhs) => {
+                let (l, r) = (lhs.eval_num(t), rhs.eval_num(t));
+                match cmp {
+                    Cmp::Eq => l == r,
+                    Cmp::Ne => l != r,
+                    Cmp::Lt => l < r,
+                    Cmp::Le => l <= r,
+                    Cmp::Gt => l > r,
+                    Cmp::Ge => l >= r,
+                }
+            }
+            Expr::And(lhs, rhs) => lhs.eval_bool(t) && rhs.eval_bool(t),
+            Expr::Or(lhs, rhs) => lhs.eval_bool(t) || rhs.eval_bool(t),
+            Expr::Not(inner) => !inner.eval_bool(t),
+            Expr::Num(_) | Expr::Str(_) | Expr::Field(_) => false,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const BTC: TickerFields = TickerFields {
+        symbol: "BTCUSDT",
+        pct_gain_24h: 12.0,
+        quote_vol_usdt: 3_000_000.0,
+        last_price: 30_000.0,
+    };
+    const ETH_BTC: TickerFields = TickerFields {
+        symbol: "ETHBTC",
+        pct_gain_24h: 6.0,
+        quote_vol_usdt: 1_500_000.0,
+        last_price: 0.05,
+    };
+
+    fn eval(expr: &str, t: &TickerFields) -> bool {
+        expr.parse::<FilterExpr>().unwrap().matches(t)
+    }
+
+    #[test]
+    fn evaluates_sample_expressions() {
+        let expr = r#"pct_gain_24h > 10 && quote_vol_usdt > 2000000 && symbol.ends_with("USDT")"#;
+        assert!(eval(expr, &BTC));
+        assert!(!eval(expr, &ETH_BTC));
+
+        assert!(eval("last_price < 1 || symbol == \"BTCUSDT\"", &BTC));
+        assert!(eval("last_price < 1 || symbol == \"BTCUSDT\"", &ETH_BTC));
+        assert!(eval("!(symbol.starts_with(\"ETH\"))", &BTC));
+        assert!(!eval("!symbol.starts_with(\"ETH\")", &ETH_BTC));
+        assert!(eval(
+            "symbol.contains(\"HB\") && pct_gain_24h >= 6",
+            &ETH_BTC
+        ));
+        assert!(eval(
+            "quote_vol_usdt >= 1_500_000 && symbol != \"X\"",
+            &ETH_BTC
+        ));
+    }
+
+    #[test]
+    fn and_binds_tighter_than_or() {
+        assert!(eval(
+            "pct_gain_24h > 100 && last_price > 0 || last_price > 1",
+            &BTC
+        ));
+        assert!(!eval(
+            "pct_gain_24h > 100 && (last_price > 0 || last_price > 1)",
+            &BTC
+        ));
+    }
+
+    #[test]
+    fn rejects_invalid_expressions() {
+        for (expr, msg) in [
+            ("volume > 3", "unknown field"),
+            ("pct_gain_24h > ", "unexpected end"),
+            ("symbol > 3", "cannot compare"),
+            ("pct_gain_24h", "true/false"),
+            ("symbol.ends_with(3)", "string argument"),
+            ("last_price.contains(\"1\")", "only be called on `symbol`"),
+            ("pct_gain_24h = 5", "use `==`"),
+            ("symbol == \"BTC", "unterminated"),
+            ("(pct_gain_24h > 1", "expected `)`"),
+            ("pct_gain_24h > 1 2", "unexpected `2`"),
+        ] {
+            let err = expr.parse::<FilterExpr>().unwrap_err();
+            assert!(err.contains(msg), "{expr}: {err}");
+        }
+    }
+}
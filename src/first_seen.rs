@@ -0,0 +1,150 @@
+//! When the scanner first observed each symbol. Scam tokens are usually
+//! brand new, so signals carry the symbol's age and can be held back until
+//! it is old enough. With `FIRST_SEEN_PATH` set the times are persisted, so
+//! a restart doesn't make every symbol new again.
+
+use std::{
+    collections::HashMap,
+    path::{Path, PathBuf},
+    sync::{Arc, Mutex},
+    time::Duration,
+};
+
+use chrono::Utc;
+use tokio_util::sync::CancellationToken;
+
+use crate::symbols::SymbolState;
+
+/// How often first-seen times are written to `FIRST_SEEN_PATH`.
+const FLUSH_INTERVAL: Duration = Duration::from_secs(30);
+
+/// Where first-seen times are persisted, from `FIRST_SEEN_PATH`.
+pub fn first_seen_path_from_env() -> Option<PathBuf> {
+    std::env::var("FIRST_SEEN_PATH")
+        .ok()
+        .filter(|v| !v.trim().is_empty())
+        .map(PathBuf::from)
+}
+
+/// First-seen Unix seconds by symbol, shared by the feeds. Bounded like the
+/// other per-symbol state: a forgotten symbol counts as new if it returns.
+#[derive(Debug, Clone, Default)]
+pub struct FirstSeen(Arc<Mutex<SymbolState<i64>>>);
+
+impl FirstSeen {
+    /// An empty store of at most `max` symbols.
+    pub fn new(max: usize) -> Self {
+        Self(Arc::new(Mutex::new(SymbolState::new(max))))
+    }
+
+    /// A store of at most `max` symbols holding `times`; past the limit the
+    /// most recently first-seen symbols are kept.
+    pub fn with_times(max: usize, times: HashMap<String, i64>) -> Self {
+        let mut times: Vec<_> = times.into_iter().collect();
+        times.sort_by_key(|&(_, at)| at);
+        let mut state = SymbolState::new(max);
+        for (symbol, at) in times {
+            state.insert(&symbol, at);
+        }
+        Self(Arc::new(Mutex::new(state)))
+    }
+
+    /// The store saved at `path` by a previous run. A missing file is an
+    /// empty store, as on the very first run.
+    pub fn load(path: &Path, max: usize) -> Result<Self, String> {
+        let times = match std::fs::read(path) {
+            Ok(bytes) => serde_json::from_slice(&bytes)
+                .map_err(|e| format!("invalid first-seen times in {}: {e}", path.display()))?,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => HashMap::new(),
+            Err(e) => return Err(format!("reading {}: {e}", path.display())),
+        };
+        Ok(Self::with_times(max, times))
+    }
+
+    /// When `symbol` was first observed, recording `now` if it never was.
+    pub fn observe(&self, symbol: &str, now: i64) -> i64 {
+        *self.0.lock().unwrap().get_or_insert_with(symbol, || now)
+    }
+
+    /// Every stored symbol's first-seen time.
+    pub fn times(&self) -> HashMap<String, i64> {
+        let state = self.0.lock().unwrap();
+        state
+            .iter()
+            .map(|(symbol, &at)| (symbol.to_owned(), at))
+            .collect()
+    }
+
+    /// Write to `path` through a temporary file, so a crash mid-write never
+    /// leaves a truncated file behind.
+    pub fn save(&self, path: &Path) -> std::io::Result<()> {
+        let tmp = path.with_extension("tmp");
+        std::fs::write(&tmp, serde_json::to_vec(&self.times())?)?;
+        std::fs::rename(tmp, path)
+    }
+}
+
+/// Seconds since `first_seen`, never negative.
+pub(crate) fn age_secs(first_seen: i64) -> u64 {
+    Utc::now()
+        .timestamp()
+        .saturating_sub(first_seen)
+        .try_into()
+        .unwrap_or(0)
+}
+
+/// Write `store` to `path` every [`FLUSH_INTERVAL`] whenever it changed,
+/// and once more when `shutdown` is cancelled.
+pub async fn flush_first_seen(store: FirstSeen, path: PathBuf, shutdown: CancellationToken) {
+    let mut saved = HashMap::new();
+    let mut interval = tokio::time::interval(FLUSH_INTERVAL);
+    loop {
+        let stopping = tokio::select! {
+            _ = interval.tick() => false,
+            _ = shutdown.cancelled() => true,
+        };
+        let times = store.times();
+        if times != saved {
+            match store.save(&path) {
+                Ok(()) => saved = times,
+                Err(e) => {
+                    tracing::warn!(path = %path.display(), error = %e, "Saving first-seen times failed")
+                }
+            }
+        }
+        if stopping {
+            return;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn times_survive_a_save_and_load() {
+        let dir = std::env::temp_dir().join(format!("first-seen-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("first-seen.json");
+        assert!(FirstSeen::load(&path, 10).unwrap().times().is_empty());
+
+        let store = FirstSeen::new(10);
+        assert_eq!(store.observe("BTCUSDT", 100), 100);
+        // Observing again keeps the first time.
+        assert_eq!(store.observe("BTCUSDT", 200), 100);
+        store.observe("WIFUSDT", 300);
+        store.save(&path).unwrap();
+
+        let restored = FirstSeen::load(&path, 10).unwrap();
+        assert_eq!(restored.times(), store.times());
+        assert_eq!(restored.observe("WIFUSDT", 400), 300);
+
+        // Past the limit the newest symbols are the ones kept.
+        assert_eq!(
+            FirstSeen::load(&path, 1).unwrap().times(),
+            HashMap::from([("WIFUSDT".to_owned(), 300)])
+        );
+        std::fs::remove_dir_all(dir).unwrap();
+    }
+}
@@ -0,0 +1,377 @@
+//! Shared HTTP client construction, so every binary talks to Raydium, the
+//! Solana RPC and webhooks with the same user-agent, timeouts, pooling and
+//! proxy.
+//!
+//! Every request identifies itself with [`USER_AGENT`] and asks for JSON
+//! with `Accept: application/json`; some CDNs in front of these APIs answer
+//! `403` to requests without either. `HTTP_USER_AGENT` and `HTTP_ACCEPT`
+//! replace them.
+//!
+//! A proxy comes from `--proxy=URL` where a binary offers it, else from
+//! `HTTPS_PROXY`, `HTTP_PROXY` or `ALL_PROXY` (upper- or lowercase), and
+//! applies to every request; `NO_PROXY` is honoured. `http://`, `https://`,
+//! `socks5://` and `socks5h://` (DNS resolved by the proxy, as Tor wants)
+//! are accepted.
+//!
+//! Certificates are always verified unless `ALLOW_INVALID_CERTS=1` or a
+//! binary's `--insecure` flag says otherwise; see [`crate::tls`].
+
+use std::time::Duration;
+
+use anyhow::{anyhow, bail, Context, Result};
+use reqwest::{
+    header::{HeaderMap, HeaderValue, ACCEPT},
+    NoProxy, Proxy, Url,
+};
+
+use crate::tls::{allow_invalid_certs_from_env, warn_insecure};
+
+/// `crypto-scanner-agent/<crate version>`; some APIs throttle requests
+/// that don't identify themselves.
+pub const USER_AGENT: &str = concat!(env!("CARGO_PKG_NAME"), "/", env!("CARGO_PKG_VERSION"));
+
+/// What every request accepts unless `HTTP_ACCEPT` says otherwise; all the
+/// APIs we call answer in JSON.
+pub const DEFAULT_ACCEPT: &str = "application/json";
+
+pub const DEFAULT_TIMEOUT: Duration = Duration::from_secs(15);
+pub const DEFAULT_CONNECT_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// How long an idle pooled connection is kept before it is closed.
+const POOL_IDLE_TIMEOUT: Duration = Duration::from_secs(90);
+const POOL_MAX_IDLE_PER_HOST: usize = 8;
+
+/// Proxy variables, most specific first; everything we call is HTTPS.
+const PROXY_VARS: [&str; 6] = [
+    "HTTPS_PROXY",
+    "https_proxy",
+    "HTTP_PROXY",
+    "http_proxy",
+    "ALL_PROXY",
+    "all_proxy",
+];
+
+const PROXY_SCHEMES: [&str; 4] = ["http", "https", "socks5", "socks5h"];
+
+/// Parse `url` as a proxy address, rejecting unsupported schemes and
+/// missing hosts up front rather than failing on the first request.
+pub fn validate_proxy_url(url: &str) -> Result<Url> {
+    let parsed = Url::parse(url.trim()).map_err(|e| anyhow!("invalid proxy URL `{url}`: {e}"))?;
+    if !PROXY_SCHEMES.contains(&parsed.scheme()) {
+        bail!(
+            "unsupported proxy scheme `{}` in `{url}` (expected one of {})",
+            parsed.scheme(),
+            PROXY_SCHEMES.join(", ")
+        );
+    }
+    if parsed.host_str().is_none_or(str::is_empty) {
+        bail!("proxy URL `{url}` has no host");
+    }
+    Ok(parsed)
+}
+
+/// The first non-empty proxy variable, validated.
+pub fn proxy_from_env() -> Result<Option<String>> {
+    proxy_from_vars(|key| std::env::var(key).ok())
+}
+
+fn proxy_from_vars(get: impl Fn(&str) -> Option<String>) -> Result<Option<String>> {
+    let Some((key, url)) = PROXY_VARS
+        .iter()
+        .find_map(|key| get(key).filter(|v| !v.trim().is_empty()).map(|v| (key, v)))
+    else {
+        return Ok(None);
+    };
+    validate_proxy_url(&url).with_context(|| format!("in {key}"))?;
+    Ok(Some(url.trim().to_owned()))
+}
+
+/// Knobs for [`build_client`]; the defaults suit the public APIs we call.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ClientOptions {
+    /// Total time allowed per request, including reading the body.
+    pub timeout: Duration,
+    pub connect_timeout: Duration,
+    /// Route every request through this proxy URL. When unset, reqwest
+    /// still picks up the proxy variables on its own.
+    pub proxy: Option<String>,
+    /// Skip certificate verification, for self-signed endpoints.
+    pub accept_invalid_certs: bool,
+    /// `User-Agent` sent with every request.
+    pub user_agent: String,
+    /// `Accept` sent with every request that doesn't set its own.
+    pub accept: String,
+}
+
+impl Default for ClientOptions {
+    fn default() -> Self {
+        Self {
+            timeout: DEFAULT_TIMEOUT,
+            connect_timeout: DEFAULT_CONNECT_TIMEOUT,
+            proxy: None,
+            accept_invalid_certs: false,
+            user_agent: USER_AGENT.to_owned(),
+            accept: DEFAULT_ACCEPT.to_owned(),
+        }
+    }
+}
+
+impl ClientOptions {
+    /// Defaults plus the proxy, `ALLOW_INVALID_CERTS`, `HTTP_USER_AGENT`
+    /// and `HTTP_ACCEPT` from the environment.
+    pub fn from_env() -> Result<Self> {
+        let header = |key| std::env::var(key).ok().filter(|v| !v.trim().is_empty());
+        let defaults = Self::default();
+        Ok(Self {
+            proxy: proxy_from_env()?,
+            accept_invalid_certs: allow_invalid_certs_from_env().map_err(anyhow::Error::msg)?,
+            user_agent: header("HTTP_USER_AGENT").unwrap_or(defaults.user_agent),
+            accept: header("HTTP_ACCEPT").unwrap_or(defaults.accept),
+            ..Self::default()
+        })
+    }
+
+    /// Use `proxy` if given, otherwise keep whatever is configured.
+    pub fn with_proxy_override(self, proxy: Option<&str>) -> Self {
+        match proxy {
+            Some(p) => self.with_proxy(p),
+            None => self,
+        }
+    }
+
+    /// Accept invalid certificates if `insecure`, otherwise keep whatever
+    /// is configured.
+    pub fn with_insecure_override(mut self, insecure: bool) -> Self {
+        self.accept_invalid_certs |= insecure;
+        self
+    }
+
+    pub fn with_timeout(mut self, timeout: Duration) -> Self {
+        self.timeout = timeout;
+        self
+    }
+
+    pub fn with_proxy(mut self, proxy: impl Into<String>) -> Self {
+        self.proxy = Some(proxy.into());
+        self
+    }
+
+    pub fn with_user_agent(mut self, user_agent: impl Into<String>) -> Self {
+        self.user_agent = user_agent.into();
+        self
+    }
+
+    pub fn with_accept(mut self, accept: impl Into<String>) -> Self {
+        self.accept = accept.into();
+        self
+    }
+
+    /// Headers sent with every request besides the user-agent.
+    fn default_headers(&self) -> Result<HeaderMap> {
+        let accept = HeaderValue::from_str(self.accept.trim())
+            .with_context(|| format!("invalid Accept header `{}`", self.accept))?;
+        Ok(HeaderMap::from_iter([(ACCEPT, accept)]))
+    }
+
+    fn user_agent(&self) -> Result<HeaderValue> {
+        HeaderValue::from_str(self.user_agent.trim())
+            .with_context(|| format!("invalid User-Agent `{}`", self.user_agent))
+    }
+
+    fn proxy(&self) -> Result<Option<Proxy>> {
+        let Some(url) = self.proxy.as_deref() else {
+            return Ok(None);
+        };
+        let proxy = Proxy::all(validate_proxy_url(url)?)
+            .with_context(|| format!("invalid proxy `{url}`"))?;
+        Ok(Some(proxy.no_proxy(NoProxy::from_env())))
+    }
+}
+
+/// Async client configured from `opts`.
+pub fn build_client(opts: &ClientOptions) -> Result<reqwest::Client> {
+    let mut builder = reqwest::Client::builder()
+        .user_agent(opts.user_agent()?)
+        .default_headers(opts.default_headers()?)
+        .timeout(opts.timeout)
+        .connect_timeout(opts.connect_timeout)
+        .pool_idle_timeout(POOL_IDLE_TIMEOUT)
+        .pool_max_idle_per_host(POOL_MAX_IDLE_PER_HOST);
+    if let Some(proxy) = opts.proxy()? {
+        builder = builder.proxy(proxy);
+    }
+    if opts.accept_invalid_certs {
+        warn_insecure();
+        builder = builder.danger_accept_invalid_certs(true);
+    }
+    builder.build().context("building HTTP client")
+}
+
+/// Blocking counterpart of [`build_client`].
+pub fn build_blocking_client(opts: &ClientOptions) -> Result<reqwest::blocking::Client> {
+    let mut builder = reqwest::blocking::Client::builder()
+        .user_agent(opts.user_agent()?)
+        .default_headers(opts.default_headers()?)
+        .timeout(opts.timeout)
+        .connect_timeout(opts.connect_timeout)
+        .pool_idle_timeout(POOL_IDLE_TIMEOUT)
+        .pool_max_idle_per_host(POOL_MAX_IDLE_PER_HOST);
+    if let Some(proxy) = opts.proxy()? {
+        builder = builder.proxy(proxy);
+    }
+    if opts.accept_invalid_certs {
+        warn_insecure();
+        builder = builder.danger_accept_invalid_certs(true);
+    }
+    builder.build().context("building HTTP client")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use shuttle_axum::axum::{http::HeaderMap, routing::get, Router};
+
+    /// Serve `GET /` answering with the request's `User-Agent` and
+    /// `Accept`, one per line.
+    async fn echo_server() -> String {
+        let app = Router::new().route(
+            "/",
+            get(|headers: HeaderMap| async move {
+                let header = |name| {
+                    headers
+                        .get(name)
+                        .and_then(|v| v.to_str().ok())
+                        .unwrap_or_default()
+                        .to_owned()
+                };
+                format!("{}\n{}", header("user-agent"), header("accept"))
+            }),
+        );
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        tokio::spawn(async move { shuttle_axum::axum::serve(listener, app).await.unwrap() });
+        format!("http://{addr}/")
+    }
+
+    #[tokio::test]
+    async fn client_sends_versioned_user_agent() {
+        let url = echo_server().await;
+        let client = build_client(&ClientOptions::default()).unwrap();
+
+        let echoed = client.get(&url).send().await.unwrap().text().await.unwrap();
+        let (ua, accept) = echoed.split_once('\n').unwrap();
+        assert_eq!(ua, USER_AGENT);
+        assert!(ua.ends_with(env!("CARGO_PKG_VERSION")));
+        assert_eq!(accept, DEFAULT_ACCEPT);
+    }
+
+    #[tokio::test]
+    async fn both_clients_send_the_configured_headers() {
+        let url = echo_server().await;
+        let opts = ClientOptions::default()
+            .with_user_agent("scanner-test/1.0")
+            .with_accept("application/json, text/plain");
+
+        let client = build_client(&opts).unwrap();
+        let echoed = client.get(&url).send().await.unwrap().text().await.unwrap();
+        assert_eq!(echoed, "scanner-test/1.0\napplication/json, text/plain");
+
+        // A request's own Accept wins over the default.
+        let echoed = client
+            .get(&url)
+            .header(ACCEPT, "text/csv")
+            .send()
+            .await
+            .unwrap()
+            .text()
+            .await
+            .unwrap();
+        assert_eq!(echoed, "scanner-test/1.0\ntext/csv");
+
+        let blocking = tokio::task::spawn_blocking(move || {
+            let client = build_blocking_client(&ClientOptions::default()).unwrap();
+            client.get(&url).send().unwrap().text().unwrap()
+        })
+        .await
+        .unwrap();
+        assert_eq!(blocking, format!("{USER_AGENT}\n{DEFAULT_ACCEPT}"));
+    }
+
+    #[test]
+    fn invalid_header_values_are_rejected() {
+        let err =
+            build_client(&ClientOptions::default().with_accept("application/\njson")).unwrap_err();
+        assert!(err.to_string().contains("invalid Accept header"), "{err}");
+        let opts = ClientOptions::default().with_user_agent("bad\u{7f}agent");
+        assert!(build_blocking_client(&opts).is_err());
+    }
+
+    #[test]
+    fn invalid_proxy_is_rejected() {
+        let opts = ClientOptions::default().with_proxy("not a url");
+        assert!(build_client(&opts).is_err());
+
+        assert!(validate_proxy_url("ftp://proxy.local:21").is_err());
+        assert!(validate_proxy_url("socks5h://127.0.0.1:9050").is_ok());
+    }
+
+    #[test]
+    fn configured_proxy_is_attached_to_the_client() {
+        let opts = ClientOptions::default()
+            .with_proxy_override(Some("http://proxy.local:3128"))
+            .with_proxy_override(None);
+        assert_eq!(opts.proxy.as_deref(), Some("http://proxy.local:3128"));
+
+        let client = format!("{:?}", build_client(&opts).unwrap());
+        assert!(client.contains("proxy.local:3128"), "{client}");
+        // The blocking client's Debug output is opaque; just check it builds.
+        assert!(build_blocking_client(&opts).is_ok());
+    }
+
+    #[test]
+    fn insecure_flag_disables_certificate_checks() {
+        let secure = ClientOptions::default().with_insecure_override(false);
+        assert!(!secure.accept_invalid_certs);
+
+        let insecure = secure.with_insecure_override(true);
+        assert!(insecure.accept_invalid_certs);
+        // A flag can't turn the environment's opt-in back off.
+        assert!(
+            insecure
+                .clone()
+                .with_insecure_override(false)
+                .accept_invalid_certs
+        );
+        // Neither client's Debug output shows the setting; check they build.
+        assert!(build_client(&insecure).is_ok());
+        assert!(build_blocking_client(&insecure).is_ok());
+    }
+
+    #[test]
+    fn env_proxy_prefers_https_and_is_validated() {
+        let vars = |pairs: &'static [(&'static str, &'static str)]| {
+            move |key: &str| {
+                pairs
+                    .iter()
+                    .find(|(k, _)| *k == key)
+                    .map(|(_, v)| v.to_string())
+            }
+        };
+
+        let proxy = proxy_from_vars(vars(&[
+            ("ALL_PROXY", "socks5h://127.0.0.1:9050"),
+            ("HTTPS_PROXY", "http://corp:8080"),
+        ]));
+        assert_eq!(proxy.unwrap().as_deref(), Some("http://corp:8080"));
+
+        let proxy = proxy_from_vars(vars(&[
+            ("HTTPS_PROXY", " "),
+            ("all_proxy", "socks5://tor:9050"),
+        ]));
+        assert_eq!(proxy.unwrap().as_deref(), Some("socks5://tor:9050"));
+
+        assert_eq!(proxy_from_vars(vars(&[])).unwrap(), None);
+        let err = proxy_from_vars(vars(&[("HTTP_PROXY", "gopher://x")])).unwrap_err();
+        assert!(format!("{err:#}").contains("HTTP_PROXY"));
+    }
+}
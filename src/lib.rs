@@ -1,4 +1,8 @@
 pub mod util {
+    use std::{fmt, future::Future, time::Duration};
+
+    use futures::{stream, StreamExt};
+
     /// Returns the number of logical CPU cores available on the system.
     pub fn cpu_core_count() -> usize {
         num_cpus::get()
@@ -10,33 +14,183 @@ pub mod util {
             .map(|n| n.get())
             .unwrap_or(1)
     }
+
+    /// Runs `f` over `items` with at most `concurrency` futures in flight.
+    ///
+    /// With `ordered` the results line up with `items`; otherwise they come
+    /// back in completion order, which lets a fast result overtake a slow one
+    /// without waiting for it. A `concurrency` of 0 is treated as 1.
+    pub async fn parallel_map<I, F, Fut, T>(
+        items: I,
+        concurrency: usize,
+        ordered: bool,
+        f: F,
+    ) -> Vec<T>
+    where
+        I: IntoIterator,
+        F: FnMut(I::Item) -> Fut,
+        Fut: Future<Output = T>,
+    {
+        let concurrency = concurrency.max(1);
+        let tasks = stream::iter(items).map(f);
+        if ordered {
+            tasks.buffered(concurrency).collect().await
+        } else {
+            tasks.buffer_unordered(concurrency).collect().await
+        }
+    }
+
+    /// A [`parallel_map_with_timeout`] task that did not finish in time.
+    #[derive(Debug, Clone, Copy, PartialEq, Eq, thiserror::Error)]
+    #[error("task timed out after {0:?}")]
+    pub struct TimedOut(pub Duration);
+
+    /// [`parallel_map`] where each task gets at most `timeout`, so a stuck
+    /// future gives its concurrency slot back instead of holding it forever.
+    /// Tasks that run out of time yield [`TimedOut`].
+    pub async fn parallel_map_with_timeout<I, F, Fut, T>(
+        items: I,
+        concurrency: usize,
+        ordered: bool,
+        timeout: Duration,
+        mut f: F,
+    ) -> Vec<Result<T, TimedOut>>
+    where
+        I: IntoIterator,
+        F: FnMut(I::Item) -> Fut,
+        Fut: Future<Output = T>,
+    {
+        parallel_map(items, concurrency, ordered, |item| {
+            let task = f(item);
+            async move {
+                tokio::time::timeout(timeout, task)
+                    .await
+                    .map_err(|_| TimedOut(timeout))
+            }
+        })
+        .await
+    }
+
+    /// Totals for a batch run, printed once at the end.
+    #[derive(Debug, Clone, Copy, Default, PartialEq)]
+    pub struct BatchSummary {
+        pub items: usize,
+        pub errors: usize,
+        pub elapsed: Duration,
+    }
+
+    impl BatchSummary {
+        /// Count one processed item, and an error if it failed.
+        pub fn record<T, E>(&mut self, result: &Result<T, E>) {
+            self.items += 1;
+            if result.is_err() {
+                self.errors += 1;
+            }
+        }
+
+        /// Items processed per second of wall-clock time; 0 before any time
+        /// has been recorded.
+        pub fn items_per_sec(&self) -> f64 {
+            let secs = self.elapsed.as_secs_f64();
+            if secs > 0.0 {
+                self.items as f64 / secs
+            } else {
+                0.0
+            }
+        }
+    }
+
+    impl fmt::Display for BatchSummary {
+        fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+            let plural = |n: usize| if n == 1 { "" } else { "s" };
+            write!(
+                f,
+                "{} item{} in {:.2?} ({:.1} items/s), {} error{}",
+                self.items,
+                plural(self.items),
+                self.elapsed,
+                self.items_per_sec(),
+                self.errors,
+                plural(self.errors)
+            )
+        }
+    }
 }
 
 /// The version of the `crypto-scanner-agent` library. This is populated at
 /// compile time using the `CARGO_PKG_VERSION` environment variable.
 pub const VERSION: &str = env!("CARGO_PKG_VERSION");
 
+pub mod admin;
+pub mod bench;
+pub mod binance;
+pub mod client;
+pub mod events;
+pub mod filter;
+pub mod first_seen;
+pub mod http;
+pub mod logging;
+pub mod market;
+pub mod notify;
+pub mod numbers;
+pub mod output;
+pub mod pools;
+pub mod preflight;
+pub mod price;
+pub mod raydium;
+pub mod scanner;
+pub mod shutdown;
+pub mod snippet;
 pub mod solana;
+pub mod supervisor;
+#[cfg(any(test, feature = "test-util"))]
+pub mod testing;
+pub mod tls;
 
+mod protocol;
 mod stream;
+mod symbols;
+mod volume;
 mod ws;
 
-use std::sync::Arc;
-use tracing_subscriber::{fmt, layer::SubscriberExt, util::SubscriberInitExt};
 use serde::Serialize;
+use shuttle_axum::axum::{
+    http::{header, HeaderValue, Response},
+    response::IntoResponse,
+    routing::{get, post, MethodRouter},
+    Extension, Json, Router,
+};
+use std::{net::SocketAddr, path::PathBuf, sync::Arc};
 use tokio::sync::{watch, Mutex};
-use tower_http::services::ServeDir;
-use shuttle_axum::{
-    axum::{
-        extract::ws::Message,
-        routing::get,
-        Extension, Json, Router, response::IntoResponse,
-    },
-    ShuttleAxum,
+use tower_http::{
+    services::ServeDir,
+    set_header::{MakeHeaderValue, SetResponseHeader},
+};
+use tracing_subscriber::{fmt, layer::SubscriberExt, util::SubscriberInitExt};
+
+use admin::{shutdown_handler, snapshot_handler, thresholds_handler, AdminToken};
+use binance::UsedWeight;
+use events::{
+    buffer_size_from_env, events_handler, signals_csv_handler, snapshot_dir_from_env, SignalBuffer,
+};
+use first_seen::FirstSeen;
+use market::{symbols_handler, LatestTickers};
+use notify::Notifiers;
+use pools::{refresh_top_pools, top_pools_handler, TopPoolsCache, TopPoolsConfig};
+use protocol::protocol_handler;
+use shutdown::{Shutdown, ShutdownReason};
+use stream::{
+    max_concurrent_connects_from_env, max_connection_from_env, spawn_ws_feed, ConnectLimit,
+    FeedSelection, FeedSinks,
 };
+use supervisor::{supervise, RestartCounter, RestartPolicy};
+use ws::{broadcast_heartbeats, heartbeat_interval_from_env, websocket_handler};
+
+pub use stream::{extract_signals_from_text, Feed, Signal, SignalConfig, Tier};
+pub use ws::{Heartbeat, State};
 
-use stream::spawn_raydium_feed;
-use ws::{websocket_handler, State};
+/// State shared by every request handler.
+pub type SharedState = Arc<Mutex<State>>;
 
 #[derive(Serialize)]
 struct VersionResponse<'a> {
@@ -47,8 +201,315 @@ async fn version_handler() -> impl IntoResponse {
     Json(VersionResponse { version: VERSION })
 }
 
-#[shuttle_runtime::main]
-pub async fn main() -> ShuttleAxum {
+#[derive(Serialize)]
+struct StatsResponse {
+    clients: usize,
+    buffered_signals: usize,
+    buffer_capacity: usize,
+    /// `seq` of the most recent signal, `0` before the first. It is also
+    /// the SSE event id, so a client can resume from it with
+    /// `Last-Event-ID` or `/websocket?since=`.
+    seq: u64,
+    /// Times a feed was restarted after exiting or going stale.
+    feed_restarts: u64,
+    /// When `/pools/top` was last refreshed, `null` before the first time.
+    top_pools_refreshed_at: Option<chrono::DateTime<chrono::Utc>>,
+    /// Request weight Binance last reported as used this minute, `null`
+    /// until the Binance feed has connected.
+    binance_used_weight: Option<u64>,
+}
+
+/// `GET /stats` — live resource usage of the server.
+async fn stats_handler(Extension(state): Extension<SharedState>) -> impl IntoResponse {
+    let state = state.lock().await;
+    let buffer = state.buffer.lock().unwrap();
+    Json(StatsResponse {
+        clients: state.clients_count,
+        buffered_signals: buffer.len(),
+        buffer_capacity: buffer.capacity(),
+        seq: buffer.last_id(),
+        feed_restarts: state
+            .feed_restarts
+            .load(std::sync::atomic::Ordering::Relaxed),
+        top_pools_refreshed_at: state.top_pools.refreshed_at(),
+        binance_used_weight: state.binance_weight.get(),
+    })
+}
+
+/// Deployment-specific knobs for [`build_router`].
+#[derive(Debug, Clone)]
+pub struct RouterConfig {
+    /// Directory the dashboard is served from.
+    pub static_dir: PathBuf,
+    /// `Cache-Control: max-age` for static assets. HTML pages are always
+    /// served with `no-cache` so a redeploy is picked up immediately.
+    pub static_max_age_secs: u64,
+    /// Guards the `/admin` routes; they answer 404 while unset.
+    pub admin_token: AdminToken,
+}
+
+impl Default for RouterConfig {
+    fn default() -> Self {
+        Self {
+            static_dir: PathBuf::from("static"),
+            static_max_age_secs: 3600,
+            admin_token: AdminToken::default(),
+        }
+    }
+}
+
+impl RouterConfig {
+    /// Defaults overridden by `STATIC_DIR`, `STATIC_MAX_AGE_SECS` and
+    /// `ADMIN_TOKEN` when set.
+    pub fn from_env() -> anyhow::Result<Self> {
+        let mut config = Self {
+            admin_token: AdminToken::from_env(),
+            ..Self::default()
+        };
+        if let Ok(dir) = std::env::var("STATIC_DIR") {
+            config.static_dir = dir.into();
+        }
+        if let Ok(secs) = std::env::var("STATIC_MAX_AGE_SECS") {
+            config.static_max_age_secs = secs
+                .trim()
+                .parse()
+                .map_err(|e| anyhow::anyhow!("invalid STATIC_MAX_AGE_SECS `{secs}`: {e}"))?;
+        }
+        Ok(config)
+    }
+}
+
+/// Picks the `Cache-Control` value for a static response: `no-cache` for HTML
+/// pages, `max-age` for everything else. Error responses are left untouched.
+#[derive(Clone)]
+struct StaticCacheControl {
+    max_age: HeaderValue,
+}
+
+impl<B> MakeHeaderValue<Response<B>> for StaticCacheControl {
+    fn make_header_value(&mut self, res: &Response<B>) -> Option<HeaderValue> {
+        if !res.status().is_success() {
+            return None;
+        }
+        let is_html = res
+            .headers()
+            .get(header::CONTENT_TYPE)
+            .and_then(|v| v.to_str().ok())
+            .is_some_and(|ct| ct.starts_with("text/html"));
+        Some(if is_html {
+            HeaderValue::from_static("no-cache")
+        } else {
+            self.max_age.clone()
+        })
+    }
+}
+
+/// The dashboard file service. Only it carries caching headers; API and
+/// WebSocket routes are not affected.
+fn static_service(config: &RouterConfig) -> SetResponseHeader<ServeDir, StaticCacheControl> {
+    let max_age = HeaderValue::from_str(&format!("public, max-age={}", config.static_max_age_secs))
+        .expect("numeric header value");
+
+    SetResponseHeader::overriding(
+        ServeDir::new(&config.static_dir),
+        header::CACHE_CONTROL,
+        StaticCacheControl { max_age },
+    )
+}
+
+/// A route served by [`build_router`], as listed by `GET /routes`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+pub struct RouteInfo {
+    pub method: &'static str,
+    pub path: &'static str,
+}
+
+/// Every API route as data: `build_router` registers exactly these and
+/// `/routes` reports them, so the two can't drift apart.
+fn api_routes() -> Vec<(&'static str, &'static str, MethodRouter)> {
+    vec![
+        ("GET", "/version", get(version_handler)),
+        ("GET", "/protocol", get(protocol_handler)),
+        ("GET", "/stats", get(stats_handler)),
+        ("GET", "/routes", get(routes_handler)),
+        ("GET", "/websocket", get(websocket_handler)),
+        ("GET", "/events", get(events_handler)),
+        ("GET", "/signals.csv", get(signals_csv_handler)),
+        ("GET", "/pools/top", get(top_pools_handler)),
+        ("GET", "/symbols", get(symbols_handler)),
+        ("POST", "/admin/thresholds", post(thresholds_handler)),
+        ("POST", "/admin/snapshot", post(snapshot_handler)),
+        ("POST", "/admin/shutdown", post(shutdown_handler)),
+    ]
+}
+
+/// Everything [`build_router`] serves: the API routes plus the dashboard
+/// mounted at `/`.
+pub fn routes() -> Vec<RouteInfo> {
+    api_routes()
+        .into_iter()
+        .map(|(method, path, _)| RouteInfo { method, path })
+        .chain([RouteInfo {
+            method: "GET",
+            path: "/",
+        }])
+        .collect()
+}
+
+/// `GET /routes` — the registered routes, for ops documentation.
+async fn routes_handler() -> impl IntoResponse {
+    Json(routes())
+}
+
+/// Assemble the HTTP router: API routes, the `/websocket` feed and the static
+/// dashboard. This is the single source of truth for route wiring, shared by
+/// the Shuttle entry point and the local `server` binary.
+pub fn build_router(state: SharedState, config: &RouterConfig) -> Router {
+    api_routes()
+        .into_iter()
+        .fold(Router::new(), |router, (_, path, handler)| {
+            router.route(path, handler)
+        })
+        .nest_service("/", static_service(config))
+        .layer(Extension(config.admin_token.clone()))
+        .layer(Extension(state))
+}
+
+/// Start the feeds selected by `FEED` (plus any configured notifiers) in
+/// the background, each under a [`supervise`]d task, along with the
+/// top-pools refresh and, on Unix, a `SIGUSR1` snapshot trigger, and return
+/// the state the router serves them from. Triggering the state's `shutdown`
+/// stops the feeds and the refresh; a feed supervisor that dies triggers it
+/// with [`ShutdownReason::FeedFailure`].
+pub fn spawn_feed() -> anyhow::Result<SharedState> {
+    let (tx, rx) = watch::channel(None);
+    let buffer = SignalBuffer::shared(buffer_size_from_env().map_err(anyhow::Error::msg)?);
+    let notifiers = Notifiers::from_env().map_err(anyhow::Error::msg)?;
+    let config = Arc::new(std::sync::RwLock::new(
+        SignalConfig::from_env().map_err(anyhow::Error::msg)?,
+    ));
+    let selection = FeedSelection::from_env().map_err(anyhow::Error::msg)?;
+    let max_connection = max_connection_from_env().map_err(anyhow::Error::msg)?;
+    let connect_limit =
+        ConnectLimit::new(max_concurrent_connects_from_env().map_err(anyhow::Error::msg)?);
+    let accept_invalid_certs = tls::allow_invalid_certs_from_env().map_err(anyhow::Error::msg)?;
+    let policy = RestartPolicy::from_env().map_err(anyhow::Error::msg)?;
+    let top_pools_config = TopPoolsConfig::from_env().map_err(anyhow::Error::msg)?;
+    let heartbeat_interval = heartbeat_interval_from_env().map_err(anyhow::Error::msg)?;
+    let restarts = RestartCounter::default();
+    let snapshot_dir = snapshot_dir_from_env();
+    let shutdown = Shutdown::default();
+
+    let state_path = notifiers.state_path().map(ToOwned::to_owned);
+    let notifiers = Arc::new(std::sync::Mutex::new(notifiers));
+    if let Some(path) = state_path {
+        tokio::spawn(notify::flush_alert_state(notifiers.clone(), path));
+    }
+    let max_symbols = config.read().unwrap().max_symbols;
+    let tickers = LatestTickers::new(max_symbols);
+    let first_seen = match first_seen::first_seen_path_from_env() {
+        Some(path) => {
+            let store = FirstSeen::load(&path, max_symbols).map_err(anyhow::Error::msg)?;
+            tracing::info!(
+                path = %path.display(),
+                symbols = store.times().len(),
+                "First-seen times restored"
+            );
+            tokio::spawn(first_seen::flush_first_seen(
+                store.clone(),
+                path,
+                shutdown.token(),
+            ));
+            store
+        }
+        None => FirstSeen::new(max_symbols),
+    };
+    let sinks = FeedSinks {
+        tx,
+        buffer: buffer.clone(),
+        notifiers,
+        tickers: tickers.clone(),
+        first_seen,
+        connect_limit,
+        used_weight: UsedWeight::default(),
+    };
+    for feed in selection.feeds() {
+        let (sinks, config) = (sinks.clone(), config.clone());
+        let supervisor = tokio::spawn(supervise(
+            feed.to_string(),
+            restarts.clone(),
+            policy,
+            shutdown.clone(),
+            move |liveness| {
+                spawn_ws_feed(
+                    feed,
+                    sinks.clone(),
+                    config.clone(),
+                    accept_invalid_certs,
+                    max_connection,
+                    liveness,
+                )
+            },
+        ));
+        let shutdown = shutdown.clone();
+        tokio::spawn(async move {
+            let detail = match supervisor.await {
+                Err(e) if e.is_panic() => format!("{feed} supervisor panicked"),
+                _ if shutdown.token().is_cancelled() => return,
+                _ => format!("{feed} supervisor stopped"),
+            };
+            shutdown.trigger(ShutdownReason::FeedFailure(detail));
+        });
+    }
+
+    #[cfg(unix)]
+    {
+        let (buffer, dir) = (buffer.clone(), snapshot_dir.clone());
+        tokio::spawn(async move {
+            if let Err(e) = events::snapshot_on_sigusr1(buffer, dir).await {
+                tracing::warn!("SIGUSR1 snapshots unavailable: {e:#}");
+            }
+        });
+    }
+
+    let top_pools = TopPoolsCache::default();
+    if let Some(pools_config) = top_pools_config {
+        let client = http::build_client(&http::ClientOptions::from_env()?)?;
+        tokio::spawn(refresh_top_pools(
+            top_pools.clone(),
+            client,
+            pools_config,
+            shutdown.token(),
+        ));
+    }
+
+    let heartbeat_shutdown = shutdown.token();
+    let (heartbeat_tx, heartbeat_rx) = watch::channel(None);
+    let signals = rx.clone();
+    let state = State::new(rx, buffer)
+        .with_heartbeats(heartbeat_rx)
+        .with_config(config)
+        .with_feed_restarts(restarts)
+        .with_top_pools(top_pools)
+        .with_tickers(tickers)
+        .with_binance_weight(sinks.used_weight.clone())
+        .with_shutdown(shutdown)
+        .with_snapshot_dir(snapshot_dir);
+    let state = Arc::new(Mutex::new(state));
+    if let Some(interval) = heartbeat_interval {
+        tokio::spawn(broadcast_heartbeats(
+            state.clone(),
+            signals,
+            heartbeat_tx,
+            interval,
+            heartbeat_shutdown,
+        ));
+    }
+    Ok(state)
+}
+
+/// Log to stdout and to a daily-rotated `logs/server.log`.
+pub fn init_server_tracing() {
     let file_appender = tracing_appender::rolling::daily("logs", "server.log");
     let (file_writer, guard) = tracing_appender::non_blocking(file_appender);
 
@@ -59,21 +520,45 @@ pub async fn main() -> ShuttleAxum {
         .with(fmt::layer().with_target(false).with_writer(file_writer));
 
     let _ = registry.try_init();
+}
 
-    let (tx, rx) = watch::channel(Message::Text("{}".into()));
-    tokio::spawn(spawn_raydium_feed(tx));
+/// The router as a Shuttle service, served until its [`Shutdown`] is
+/// triggered. A feed failure ends the service with an error, so the
+/// platform sees the deployment fail instead of stop cleanly.
+pub struct ScannerService {
+    router: Router,
+    shutdown: Shutdown,
+}
 
-    let state = Arc::new(Mutex::new(State {
-        clients_count: 0,
-        rx,
-    }));
+#[shuttle_runtime::async_trait]
+impl shuttle_runtime::Service for ScannerService {
+    async fn bind(mut self, addr: SocketAddr) -> Result<(), shuttle_runtime::Error> {
+        let listener = tokio::net::TcpListener::bind(addr)
+            .await
+            .map_err(shuttle_runtime::CustomError::new)?;
+        shuttle_axum::axum::serve(
+            listener,
+            self.router
+                .into_make_service_with_connect_info::<SocketAddr>(),
+        )
+        .with_graceful_shutdown(shutdown::on_signals(self.shutdown.clone()))
+        .await
+        .map_err(shuttle_runtime::CustomError::new)?;
+        Ok(self.shutdown.result()?)
+    }
+}
 
-    let router = Router::new()
-        .route("/version", get(version_handler))
-        .route("/websocket", get(websocket_handler))
-        .nest_service("/", ServeDir::new("static"))
-        .layer(Extension(state));
+#[shuttle_runtime::main]
+pub async fn main() -> Result<ScannerService, shuttle_runtime::Error> {
+    init_server_tracing();
 
-    Ok(router.into())
-}
+    let state = spawn_feed()?;
+    let shutdown = state.lock().await.shutdown.clone();
 
+    let config = RouterConfig::from_env()?;
+
+    Ok(ScannerService {
+        router: build_router(state, &config),
+        shutdown,
+    })
+}
@@ -0,0 +1,230 @@
+//! Log setup shared by the command-line binaries, and throttling for
+//! warnings that keep recurring.
+//!
+//! Logs go to stderr so that results printed on stdout stay pipeable, and
+//! their level is picked with the common `-q/--quiet` and `-v/--verbose`
+//! flags.
+
+use std::{
+    collections::HashMap,
+    fmt,
+    time::{Duration, Instant},
+};
+
+use tracing::level_filters::LevelFilter;
+
+/// How chatty a binary's logs are.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum Verbosity {
+    /// No logs at all, only results.
+    Quiet,
+    /// Progress and warnings.
+    #[default]
+    Normal,
+    /// Everything down to debug, e.g. raw requests and responses.
+    Verbose,
+}
+
+impl Verbosity {
+    pub fn level(self) -> LevelFilter {
+        match self {
+            Verbosity::Quiet => LevelFilter::OFF,
+            Verbosity::Normal => LevelFilter::INFO,
+            Verbosity::Verbose => LevelFilter::DEBUG,
+        }
+    }
+}
+
+/// The `-q/--quiet` and `-v/--verbose` flags, to `#[command(flatten)]` into
+/// a binary's arguments.
+#[derive(clap::Args, Debug, Clone, Copy, Default)]
+pub struct VerbosityArgs {
+    /// Suppress logs, only print results
+    #[arg(short, long, global = true, conflicts_with = "verbose")]
+    pub quiet: bool,
+    /// Show debug logs
+    #[arg(short, long, global = true)]
+    pub verbose: bool,
+}
+
+impl VerbosityArgs {
+    pub fn verbosity(self) -> Verbosity {
+        if self.quiet {
+            Verbosity::Quiet
+        } else if self.verbose {
+            Verbosity::Verbose
+        } else {
+            Verbosity::Normal
+        }
+    }
+}
+
+/// Install the global subscriber for a command-line binary.
+pub fn init_tracing(verbosity: Verbosity) {
+    tracing_subscriber::fmt()
+        .with_target(false)
+        .with_max_level(verbosity.level())
+        .with_writer(std::io::stderr)
+        .init();
+}
+
+/// Lets a recurring warning through the first time, then at most once per
+/// interval with how often it recurred meanwhile, so e.g. a reconnect loop
+/// against an upstream that is down doesn't flood the logs. Warnings are
+/// told apart by a key, typically their message without the details that
+/// change between attempts.
+#[derive(Debug)]
+pub struct LogThrottle {
+    interval: Duration,
+    seen: HashMap<String, Window>,
+}
+
+/// Occurrences of one key since it was last logged.
+#[derive(Debug)]
+struct Window {
+    logged: Instant,
+    count: u64,
+}
+
+/// What a warning let through by [`LogThrottle::admit`] stands for. Shown
+/// after the message: nothing for a single occurrence, else e.g.
+/// ` (40 times in the last 60s)`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Repeats {
+    /// Occurrences since the key was last logged, this one included.
+    pub count: u64,
+    /// Time since the key was last logged.
+    pub over: Duration,
+}
+
+impl fmt::Display for Repeats {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if self.count <= 1 {
+            return Ok(());
+        }
+        write!(
+            f,
+            " ({} times in the last {}s)",
+            self.count,
+            self.over.as_secs()
+        )
+    }
+}
+
+impl LogThrottle {
+    pub fn new(interval: Duration) -> Self {
+        Self {
+            interval,
+            seen: HashMap::new(),
+        }
+    }
+
+    /// Whether to log the warning `key` now: the first time it comes up,
+    /// and then the first time again once `interval` has passed since it
+    /// was logged. `None` means it was only counted.
+    pub fn admit(&mut self, key: &str) -> Option<Repeats> {
+        self.admit_at(key, Instant::now())
+    }
+
+    fn admit_at(&mut self, key: &str, now: Instant) -> Option<Repeats> {
+        let Some(window) = self.seen.get_mut(key) else {
+            let window = Window {
+                logged: now,
+                count: 0,
+            };
+            self.seen.insert(key.to_owned(), window);
+            return Some(Repeats {
+                count: 1,
+                over: Duration::ZERO,
+            });
+        };
+        window.count += 1;
+        let over = now.saturating_duration_since(window.logged);
+        if over < self.interval {
+            return None;
+        }
+        let repeats = Repeats {
+            count: window.count,
+            over,
+        };
+        *window = Window {
+            logged: now,
+            count: 0,
+        };
+        Some(repeats)
+    }
+
+    /// Warn `message`, keyed by itself, if [`admit`](Self::admit) lets it
+    /// through.
+    pub fn warn(&mut self, message: &str) {
+        if let Some(repeats) = self.admit(message) {
+            tracing::warn!("{message}{repeats}");
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::CapturedLogs;
+
+    #[test]
+    fn verbosity_maps_to_level() {
+        assert_eq!(Verbosity::Quiet.level(), LevelFilter::OFF);
+        assert_eq!(Verbosity::Normal.level(), LevelFilter::INFO);
+        assert_eq!(Verbosity::Verbose.level(), LevelFilter::DEBUG);
+    }
+
+    #[test]
+    fn flags_map_to_verbosity() {
+        let args = |quiet, verbose| VerbosityArgs { quiet, verbose }.verbosity();
+        assert_eq!(args(false, false), Verbosity::Normal);
+        assert_eq!(args(true, false), Verbosity::Quiet);
+        assert_eq!(args(false, true), Verbosity::Verbose);
+    }
+
+    #[test]
+    fn rapid_repeats_log_a_bounded_number_of_lines() {
+        let (logs, _guard) = CapturedLogs::capture();
+
+        let mut throttle = LogThrottle::new(Duration::from_secs(60));
+        for _ in 0..1000 {
+            throttle.warn("Binance WS connect failed");
+            throttle.warn("Bybit WS connect failed");
+        }
+
+        let logs = logs.contents();
+        assert_eq!(logs.lines().count(), 2, "{logs}");
+        assert!(logs.contains("Binance WS connect failed\n"), "{logs}");
+        assert!(logs.contains("Bybit WS connect failed\n"), "{logs}");
+    }
+
+    #[test]
+    fn repeats_are_summarised_once_the_interval_passes() {
+        let mut throttle = LogThrottle::new(Duration::from_secs(60));
+        let start = Instant::now();
+        let at = |secs| start + Duration::from_secs(secs);
+
+        let first = throttle.admit_at("connect failed", at(0)).unwrap();
+        assert_eq!(first.to_string(), "");
+        for secs in 1..40 {
+            assert_eq!(throttle.admit_at("connect failed", at(secs)), None);
+        }
+        let summary = throttle.admit_at("connect failed", at(60)).unwrap();
+        assert_eq!(
+            summary,
+            Repeats {
+                count: 40,
+                over: Duration::from_secs(60)
+            }
+        );
+        assert_eq!(summary.to_string(), " (40 times in the last 60s)");
+
+        // The count starts over, and a lone recurrence reads as a first.
+        assert_eq!(throttle.admit_at("connect failed", at(90)), None);
+        let again = throttle.admit_at("connect failed", at(200)).unwrap();
+        assert_eq!(again.to_string(), " (2 times in the last 140s)");
+        let lone = throttle.admit_at("connect failed", at(300)).unwrap();
+        assert_eq!(lone.to_string(), "");
+    }
+}
@@ -0,0 +1,80 @@
+//! The last values seen for every symbol the feeds report, whether or not
+//! it passes the thresholds, so `GET /symbols` can show the whole market
+//! rather than only the signals.
+
+use std::sync::{Arc, Mutex};
+
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use shuttle_axum::axum::{extract::Query, response::IntoResponse, Extension, Json};
+
+use crate::{symbols::SymbolState, SharedState};
+
+/// A symbol's most recent ticker.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct LatestTicker {
+    pub symbol: String,
+    pub pct_gain_24h: f64,
+    /// Reported 24-hour quote volume, or the rolling sum when rolling
+    /// volume is on.
+    pub quote_vol_usdt: f64,
+    /// `null` when the ticker had no parseable price.
+    pub last_price: Option<f64>,
+    /// Whether this ticker was emitted as a signal.
+    pub signalling: bool,
+    /// Exchange event time when the ticker carries one, else when it was
+    /// received.
+    pub ts: DateTime<Utc>,
+}
+
+/// Shared between the feeds and `/symbols`; bounded like the other
+/// per-symbol state, forgetting the least recently updated symbol.
+#[derive(Debug, Clone, Default)]
+pub struct LatestTickers(Arc<Mutex<SymbolState<LatestTicker>>>);
+
+impl LatestTickers {
+    /// A store of at most `max` symbols.
+    pub fn new(max: usize) -> Self {
+        Self(Arc::new(Mutex::new(SymbolState::new(max))))
+    }
+
+    /// Replace the stored values of the symbols in `tickers`.
+    pub fn update(&self, tickers: impl IntoIterator<Item = LatestTicker>) {
+        let mut latest = self.0.lock().unwrap();
+        for ticker in tickers {
+            let symbol = ticker.symbol.clone();
+            latest.insert(&symbol, ticker);
+        }
+    }
+
+    /// Every stored ticker by symbol, or only the signalling ones with
+    /// `above_only`.
+    pub fn snapshot(&self, above_only: bool) -> Vec<LatestTicker> {
+        let latest = self.0.lock().unwrap();
+        let mut tickers: Vec<_> = latest
+            .iter()
+            .map(|(_, ticker)| ticker)
+            .filter(|ticker| !above_only || ticker.signalling)
+            .cloned()
+            .collect();
+        tickers.sort_by(|a, b| a.symbol.cmp(&b.symbol));
+        tickers
+    }
+}
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(default)]
+pub struct SymbolsQuery {
+    /// Only symbols whose latest ticker was a signal.
+    pub above_only: bool,
+}
+
+/// `GET /symbols[?above_only=true]` — the latest ticker of every symbol
+/// seen since startup, sorted by symbol.
+pub async fn symbols_handler(
+    Extension(state): Extension<SharedState>,
+    Query(query): Query<SymbolsQuery>,
+) -> impl IntoResponse {
+    let tickers = state.lock().await.tickers.clone();
+    Json(tickers.snapshot(query.above_only))
+}
@@ -0,0 +1,852 @@
+use std::{
+    collections::HashMap,
+    path::{Path, PathBuf},
+    str::FromStr,
+    sync::{Arc, Mutex},
+    time::{Duration, Instant as StdInstant, SystemTime, UNIX_EPOCH},
+};
+
+use reqwest::Client;
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+use tokio::{sync::mpsc, time::Instant};
+
+use crate::{
+    http::{build_client, ClientOptions},
+    stream::{Signal, Tier},
+    symbols::{max_symbols_from_env, SymbolState},
+};
+
+/// Signals waiting to be delivered before new ones are dropped.
+const QUEUE_CAPACITY: usize = 64;
+
+/// Delays between delivery attempts of a single notification.
+const RETRY_DELAYS_MS: [u64; 3] = [500, 1_000, 2_000];
+
+/// Minimum gap between two Telegram messages (Telegram allows ~20 msgs/min
+/// per chat). Signals arriving in the meantime are batched into one message.
+const TELEGRAM_MIN_INTERVAL: Duration = Duration::from_secs(3);
+
+const TELEGRAM_API_URL: &str = "https://api.telegram.org";
+
+/// Default per-symbol quiet period between two alerts.
+const DEFAULT_COOLDOWN: Duration = Duration::from_secs(5 * 60);
+
+/// How often alert state is written to `ALERT_STATE_PATH`.
+const STATE_FLUSH_INTERVAL: Duration = Duration::from_secs(30);
+
+/// Out-of-band alerting for signals, configured from the environment.
+///
+/// Every notifier runs on its own task behind a bounded queue, so a slow or
+/// unreachable endpoint never stalls the feed.
+#[derive(Default)]
+pub struct Notifiers {
+    min_tier: Tier,
+    cooldown: Cooldown,
+    /// When set, only tier transitions alert, still subject to `cooldown`.
+    tier_changes: Option<TierTracker>,
+    /// Where [`AlertState`] is persisted, if anywhere.
+    state_path: Option<PathBuf>,
+    webhook: Option<WebhookNotifier>,
+    telegram: Option<TelegramNotifier>,
+}
+
+impl Notifiers {
+    /// Enable the webhook notifier when `ALERT_WEBHOOK_URL` is set (payload
+    /// shape from `WEBHOOK_FORMAT`, default `raw`) and the
+    /// Telegram notifier when both `TELEGRAM_BOT_TOKEN` and `TELEGRAM_CHAT_ID`
+    /// are set. The minimum tier is read from `ALERT_MIN_TIER` (default
+    /// `strong`) and the per-symbol cooldown from `ALERT_COOLDOWN_SECS`
+    /// (default 300). `ALERT_ON_TIER_CHANGE=up|any` limits alerts to tier
+    /// transitions, which are still rate-limited by the cooldown.
+    ///
+    /// With `ALERT_STATE_PATH` set, cooldowns and tiers saved there by a
+    /// previous run are restored, so a restart doesn't re-alert. Both
+    /// remember at most `SYMBOL_STATE_MAX` symbols (default 5000).
+    pub fn from_env() -> Result<Self, String> {
+        let min_tier = match std::env::var("ALERT_MIN_TIER") {
+            Ok(t) => t.parse()?,
+            Err(_) => Tier::Strong,
+        };
+        let cooldown = match std::env::var("ALERT_COOLDOWN_SECS") {
+            Ok(secs) => Duration::from_secs(
+                secs.trim()
+                    .parse()
+                    .map_err(|e| format!("invalid ALERT_COOLDOWN_SECS `{secs}`: {e}"))?,
+            ),
+            Err(_) => DEFAULT_COOLDOWN,
+        };
+        let max_symbols = max_symbols_from_env()?;
+        let tier_changes = non_empty_env("ALERT_ON_TIER_CHANGE")
+            .map(|v| {
+                v.parse()
+                    .map_err(|e| format!("invalid ALERT_ON_TIER_CHANGE `{v}`: {e}"))
+            })
+            .transpose()?
+            .map(|changes| TierTracker::new(changes).with_max_symbols(max_symbols));
+        let format = match non_empty_env("WEBHOOK_FORMAT") {
+            Some(v) => v
+                .parse()
+                .map_err(|e| format!("invalid WEBHOOK_FORMAT `{v}`: {e}"))?,
+            None => WebhookFormat::Raw,
+        };
+        let webhook = non_empty_env("ALERT_WEBHOOK_URL").map(|url| {
+            tracing::info!(%url, ?min_tier, ?format, "Webhook alerts enabled");
+            WebhookNotifier::spawn(url, min_tier, format)
+        });
+        let telegram = match (
+            non_empty_env("TELEGRAM_BOT_TOKEN"),
+            non_empty_env("TELEGRAM_CHAT_ID"),
+        ) {
+            (Some(token), Some(chat_id)) => {
+                tracing::info!(%chat_id, ?min_tier, "Telegram alerts enabled");
+                Some(TelegramNotifier::spawn(
+                    TELEGRAM_API_URL,
+                    &token,
+                    chat_id,
+                    min_tier,
+                ))
+            }
+            _ => None,
+        };
+
+        let mut notifiers = Self {
+            min_tier,
+            cooldown: Cooldown::new(cooldown).with_max_symbols(max_symbols),
+            tier_changes,
+            state_path: non_empty_env("ALERT_STATE_PATH").map(PathBuf::from),
+            webhook,
+            telegram,
+        };
+        if let Some(path) = &notifiers.state_path {
+            let state = AlertState::load(path, cooldown)?;
+            tracing::info!(
+                path = %path.display(),
+                symbols = state.cooldowns.len().max(state.tiers.len()),
+                "Alert state restored"
+            );
+            notifiers.restore(&state);
+        }
+        Ok(notifiers)
+    }
+
+    /// Where alert state is persisted, from `ALERT_STATE_PATH`.
+    pub fn state_path(&self) -> Option<&Path> {
+        self.state_path.as_deref()
+    }
+
+    /// Cooldowns and tiers as they stand, for persisting.
+    pub fn state(&self) -> AlertState {
+        let clock = Clock::now();
+        AlertState {
+            cooldowns: self
+                .cooldown
+                .last_alert
+                .iter()
+                .map(|(symbol, at)| (symbol.to_owned(), clock.unix_secs(*at)))
+                .collect(),
+            tiers: self
+                .tier_changes
+                .iter()
+                .flat_map(|tracker| tracker.last_tier.iter())
+                .map(|(symbol, (tier, at))| {
+                    let seen = SeenTier {
+                        tier: *tier,
+                        at: clock.unix_secs(*at),
+                    };
+                    (symbol.to_owned(), seen)
+                })
+                .collect(),
+        }
+    }
+
+    /// Pick up where a previous run left off. Tiers are ignored unless tier
+    /// changes are tracked.
+    pub fn restore(&mut self, state: &AlertState) {
+        let clock = Clock::now();
+        for (symbol, at) in &state.cooldowns {
+            self.cooldown.last_alert.insert(symbol, clock.instant(*at));
+        }
+        if let Some(tracker) = &mut self.tier_changes {
+            for (symbol, seen) in &state.tiers {
+                tracker
+                    .last_tier
+                    .insert(symbol, (seen.tier, clock.instant(seen.at)));
+            }
+        }
+    }
+
+    /// Dispatch `sig` to every enabled notifier, unless, when tracking tier
+    /// changes, its tier didn't change the right way or its symbol already
+    /// alerted within the cooldown period.
+    pub fn notify(&mut self, sig: &Signal) {
+        if self.webhook.is_none() && self.telegram.is_none() {
+            return;
+        }
+        // Track tiers below `min_tier` too, so falling under it and coming
+        // back counts as an escalation.
+        let allowed = match &mut self.tier_changes {
+            Some(tracker) => tracker.observe(&sig.symbol, sig.tier),
+            None => true,
+        };
+        if !allowed || sig.tier < self.min_tier {
+            return;
+        }
+        if !self.cooldown.allow(&sig.symbol, StdInstant::now()) {
+            return;
+        }
+        if let Some(webhook) = &self.webhook {
+            webhook.notify(sig);
+        }
+        if let Some(telegram) = &self.telegram {
+            telegram.notify(sig);
+        }
+    }
+}
+
+/// Per-symbol alert rate limit: each symbol passes at most once per `period`.
+///
+/// This is notifier state only; it does not affect what is broadcast to
+/// WebSocket clients.
+pub struct Cooldown {
+    period: Duration,
+    last_alert: SymbolState<StdInstant>,
+}
+
+impl Default for Cooldown {
+    fn default() -> Self {
+        Self::new(DEFAULT_COOLDOWN)
+    }
+}
+
+impl Cooldown {
+    pub fn new(period: Duration) -> Self {
+        Self {
+            period,
+            last_alert: SymbolState::default(),
+        }
+    }
+
+    /// Remember at most `max` symbols; a forgotten one may alert again.
+    pub fn with_max_symbols(mut self, max: usize) -> Self {
+        self.last_alert = SymbolState::new(max);
+        self
+    }
+
+    /// Returns `true` (and restarts the cooldown) when `symbol` may alert at
+    /// `now`.
+    pub fn allow(&mut self, symbol: &str, now: StdInstant) -> bool {
+        match self.last_alert.get(symbol) {
+            Some(at) if now.saturating_duration_since(*at) < self.period => false,
+            _ => {
+                self.last_alert.insert(symbol, now);
+                true
+            }
+        }
+    }
+}
+
+/// Which tier transitions alert, from `ALERT_ON_TIER_CHANGE`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TierChanges {
+    /// Escalations only, e.g. watch → strong.
+    Up,
+    /// Escalations and downgrades.
+    Any,
+}
+
+impl FromStr for TierChanges {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.trim().to_ascii_lowercase().as_str() {
+            "up" => Ok(TierChanges::Up),
+            "any" => Ok(TierChanges::Any),
+            other => Err(format!("unknown tier change `{other}` (expected up|any)")),
+        }
+    }
+}
+
+/// Last tier seen per symbol, so a symbol alerts when it first appears and
+/// then only when its tier changes, not on every repeat at the same level.
+pub struct TierTracker {
+    changes: TierChanges,
+    /// Last tier per symbol and when it was seen.
+    last_tier: SymbolState<(Tier, StdInstant)>,
+}
+
+impl TierTracker {
+    pub fn new(changes: TierChanges) -> Self {
+        Self {
+            changes,
+            last_tier: SymbolState::default(),
+        }
+    }
+
+    /// Remember at most `max` symbols; a forgotten one alerts again as if
+    /// new.
+    pub fn with_max_symbols(mut self, max: usize) -> Self {
+        self.last_tier = SymbolState::new(max);
+        self
+    }
+
+    /// Record `tier` for `symbol` and return whether it warrants an alert.
+    pub fn observe(&mut self, symbol: &str, tier: Tier) -> bool {
+        let prev = self
+            .last_tier
+            .insert(symbol, (tier, StdInstant::now()))
+            .map(|(prev, _)| prev);
+        match prev {
+            None => true,
+            Some(prev) if tier > prev => true,
+            Some(prev) if tier < prev => self.changes == TierChanges::Any,
+            Some(_) => false,
+        }
+    }
+}
+
+/// Alert state persisted to `ALERT_STATE_PATH`, keyed by symbol. Times are
+/// Unix seconds, since `Instant`s don't survive a restart.
+#[derive(Debug, Default, Clone, PartialEq, Serialize, Deserialize)]
+pub struct AlertState {
+    /// When each symbol last alerted.
+    #[serde(default)]
+    pub cooldowns: HashMap<String, u64>,
+    /// Last tier seen per symbol.
+    #[serde(default)]
+    pub tiers: HashMap<String, SeenTier>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct SeenTier {
+    pub tier: Tier,
+    pub at: u64,
+}
+
+impl AlertState {
+    /// Read `path`, dropping entries older than `max_age`. A missing file is
+    /// an empty state, as on the very first run.
+    pub fn load(path: &Path, max_age: Duration) -> Result<Self, String> {
+        let mut state: Self = match std::fs::read(path) {
+            Ok(bytes) => serde_json::from_slice(&bytes)
+                .map_err(|e| format!("invalid alert state in {}: {e}", path.display()))?,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => Self::default(),
+            Err(e) => return Err(format!("reading {}: {e}", path.display())),
+        };
+        let cutoff = Clock::now().unix.saturating_sub(max_age.as_secs());
+        state.cooldowns.retain(|_, at| *at >= cutoff);
+        state.tiers.retain(|_, seen| seen.at >= cutoff);
+        Ok(state)
+    }
+
+    /// Write to `path` through a temporary file, so a crash mid-write never
+    /// leaves a truncated state behind.
+    pub fn save(&self, path: &Path) -> std::io::Result<()> {
+        let tmp = path.with_extension("tmp");
+        std::fs::write(&tmp, serde_json::to_vec(self)?)?;
+        std::fs::rename(tmp, path)
+    }
+}
+
+/// Write the alert state to `path` every [`STATE_FLUSH_INTERVAL`] whenever
+/// it changed. Runs until the process exits.
+pub async fn flush_alert_state(notifiers: Arc<Mutex<Notifiers>>, path: PathBuf) {
+    let mut saved = AlertState::default();
+    let mut interval = tokio::time::interval(STATE_FLUSH_INTERVAL);
+    loop {
+        interval.tick().await;
+        let state = notifiers.lock().unwrap().state();
+        if state == saved {
+            continue;
+        }
+        match state.save(&path) {
+            Ok(()) => saved = state,
+            Err(e) => {
+                tracing::warn!(path = %path.display(), error = %e, "Saving alert state failed")
+            }
+        }
+    }
+}
+
+/// A monotonic and a wall-clock reading taken together, to convert between
+/// the two.
+struct Clock {
+    now: StdInstant,
+    unix: u64,
+}
+
+impl Clock {
+    fn now() -> Self {
+        Self {
+            now: StdInstant::now(),
+            unix: SystemTime::now()
+                .duration_since(UNIX_EPOCH)
+                .unwrap_or_default()
+                .as_secs(),
+        }
+    }
+
+    fn unix_secs(&self, at: StdInstant) -> u64 {
+        let age = self.now.saturating_duration_since(at);
+        self.unix.saturating_sub(age.as_secs())
+    }
+
+    fn instant(&self, unix: u64) -> StdInstant {
+        let age = Duration::from_secs(self.unix.saturating_sub(unix));
+        self.now.checked_sub(age).unwrap_or(self.now)
+    }
+}
+
+fn non_empty_env(key: &str) -> Option<String> {
+    std::env::var(key).ok().filter(|v| !v.trim().is_empty())
+}
+
+/// Tier filter in front of a notifier's delivery task.
+struct AlertQueue {
+    name: &'static str,
+    min_tier: Tier,
+    tx: mpsc::Sender<Signal>,
+}
+
+impl AlertQueue {
+    fn new(name: &'static str, min_tier: Tier) -> (Self, mpsc::Receiver<Signal>) {
+        let (tx, rx) = mpsc::channel(QUEUE_CAPACITY);
+        (Self { name, min_tier, tx }, rx)
+    }
+
+    /// Queue `sig` for delivery if it is severe enough. Never blocks: when
+    /// the queue is full the signal is dropped with a warning.
+    fn push(&self, sig: &Signal) {
+        if sig.tier < self.min_tier {
+            return;
+        }
+        if self.tx.try_send(sig.clone()).is_err() {
+            tracing::warn!(notifier = self.name, symbol = %sig.symbol, "Alert queue full, dropping alert");
+        }
+    }
+}
+
+fn http_client() -> Client {
+    let opts = ClientOptions::from_env().unwrap_or_else(|e| {
+        tracing::warn!("{e:#}; sending alerts without a proxy");
+        ClientOptions::default()
+    });
+    build_client(&opts.with_timeout(Duration::from_secs(10))).unwrap_or_default()
+}
+
+/// POST `body` as JSON, retrying with backoff. Gives up after the last retry.
+async fn post_with_retry<T: Serialize>(client: &Client, url: &str, body: &T, what: &str) {
+    for (attempt, delay) in RETRY_DELAYS_MS.iter().enumerate() {
+        let res = client
+            .post(url)
+            .json(body)
+            .send()
+            .await
+            .and_then(|r| r.error_for_status());
+        match res {
+            Ok(_) => return,
+            Err(e) => {
+                tracing::warn!(attempt, "{what} delivery failed: {e}");
+                tokio::time::sleep(Duration::from_millis(*delay)).await;
+            }
+        }
+    }
+    tracing::error!(
+        "{what} delivery abandoned after {} attempts",
+        RETRY_DELAYS_MS.len()
+    );
+}
+
+/// Shape of the webhook body, from `WEBHOOK_FORMAT`.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum WebhookFormat {
+    /// The [`Signal`] as sent to WebSocket clients.
+    #[default]
+    Raw,
+    /// A TradingView-style alert: `{"ticker","action","price",…}`.
+    TradingView,
+}
+
+impl FromStr for WebhookFormat {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.trim().to_ascii_lowercase().as_str() {
+            "raw" => Ok(WebhookFormat::Raw),
+            "tradingview" => Ok(WebhookFormat::TradingView),
+            other => Err(format!(
+                "unknown webhook format `{other}` (expected raw|tradingview)"
+            )),
+        }
+    }
+}
+
+impl WebhookFormat {
+    /// The body to POST for `sig`.
+    pub fn payload(self, sig: &Signal) -> serde_json::Value {
+        match self {
+            WebhookFormat::Raw => json!(sig),
+            // Signals are 24h gainers, so every alert is a long entry.
+            WebhookFormat::TradingView => json!({
+                "ticker": sig.symbol,
+                "action": "buy",
+                "price": sig.last_price,
+                "time": sig.ts,
+                "tier": sig.tier,
+                "message": format!("{} +{:.2}% 24h", sig.display_symbol, sig.pct_gain_24h),
+            }),
+        }
+    }
+}
+
+/// POSTs every signal at or above `min_tier` to a webhook URL, in `format`.
+pub struct WebhookNotifier(AlertQueue);
+
+impl WebhookNotifier {
+    pub fn spawn(url: String, min_tier: Tier, format: WebhookFormat) -> Self {
+        let (queue, rx) = AlertQueue::new("webhook", min_tier);
+        tokio::spawn(run_webhook(url, format, rx));
+        Self(queue)
+    }
+
+    pub fn notify(&self, sig: &Signal) {
+        self.0.push(sig);
+    }
+}
+
+async fn run_webhook(url: String, format: WebhookFormat, mut rx: mpsc::Receiver<Signal>) {
+    let client = http_client();
+    while let Some(sig) = rx.recv().await {
+        post_with_retry(&client, &url, &format.payload(&sig), "Webhook").await;
+    }
+}
+
+/// Sends a readable summary of every signal at or above `min_tier` to a
+/// Telegram chat through the Bot API.
+pub struct TelegramNotifier(AlertQueue);
+
+impl TelegramNotifier {
+    /// `api_url` is the Bot API base, normally `https://api.telegram.org`.
+    pub fn spawn(api_url: &str, token: &str, chat_id: String, min_tier: Tier) -> Self {
+        let (queue, rx) = AlertQueue::new("telegram", min_tier);
+        let url = format!("{}/bot{token}/sendMessage", api_url.trim_end_matches('/'));
+        tokio::spawn(run_telegram(url, chat_id, rx));
+        Self(queue)
+    }
+
+    pub fn notify(&self, sig: &Signal) {
+        self.0.push(sig);
+    }
+}
+
+/// One line per signal, e.g. `🚀 BTCUSDT +12.50% | vol $2.0M | price 30000`.
+fn format_telegram_line(sig: &Signal) -> String {
+    format!(
+        "🚀 {} +{:.2}% | vol ${:.1}M | price {}",
+        sig.symbol,
+        sig.pct_gain_24h,
+        sig.quote_vol_usdt / 1_000_000.0,
+        sig.last_price
+    )
+}
+
+async fn run_telegram(url: String, chat_id: String, mut rx: mpsc::Receiver<Signal>) {
+    let client = http_client();
+    let mut last_sent: Option<Instant> = None;
+
+    while let Some(first) = rx.recv().await {
+        // Respect the rate limit; whatever queues up meanwhile joins the batch.
+        if let Some(at) = last_sent {
+            tokio::time::sleep_until(at + TELEGRAM_MIN_INTERVAL).await;
+        }
+        let mut batch = vec![first];
+        while let Ok(sig) = rx.try_recv() {
+            batch.push(sig);
+        }
+
+        let text = batch
+            .iter()
+            .map(format_telegram_line)
+            .collect::<Vec<_>>()
+            .join("\n");
+        let body = json!({ "chat_id": chat_id, "text": text });
+        post_with_retry(&client, &url, &body, "Telegram").await;
+        last_sent = Some(Instant::now());
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::Utc;
+    use serde_json::Value;
+    use shuttle_axum::axum::{extract::State, routing::post, Json, Router};
+
+    fn signal(symbol: &str, pct: f64) -> Signal {
+        Signal {
+            symbol: symbol.into(),
+            display_symbol: crate::stream::display_symbol(symbol),
+            pct_gain_24h: pct,
+            quote_vol_usdt: 2_000_000.0,
+            last_price: 1.5,
+            tier: Tier::from_gain(pct),
+            ts: Utc::now(),
+            received_at: None,
+            seq: 0,
+            tag: None,
+            age_secs: None,
+            new_token: false,
+        }
+    }
+
+    type Received = mpsc::UnboundedSender<Value>;
+
+    async fn receive(State(tx): State<Received>, Json(body): Json<Value>) {
+        let _ = tx.send(body);
+    }
+
+    /// Spawn a webhook receiver forwarding every payload to the returned channel.
+    async fn mock_webhook() -> (String, mpsc::UnboundedReceiver<Value>) {
+        let (tx, rx) = mpsc::unbounded_channel();
+        let app = Router::new()
+            .route("/hook", post(receive))
+            .route("/botTOKEN/sendMessage", post(receive))
+            .with_state(tx);
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        tokio::spawn(async move { shuttle_axum::axum::serve(listener, app).await });
+        (format!("http://{addr}"), rx)
+    }
+
+    #[test]
+    fn tradingview_payload_maps_the_signal() {
+        let sig = signal("BTCUSDT", 12.5);
+        let payload = WebhookFormat::TradingView.payload(&sig);
+
+        assert_eq!(payload["ticker"], "BTCUSDT");
+        assert_eq!(payload["action"], "buy");
+        assert_eq!(payload["price"], 1.5);
+        assert_eq!(payload["tier"], "strong");
+        assert_eq!(payload["message"], "BTC/USDT +12.50% 24h");
+        assert_eq!(payload["time"], json!(sig.ts));
+
+        assert_eq!(WebhookFormat::Raw.payload(&sig)["pct_gain_24h"], 12.5);
+        assert_eq!("TradingView".parse(), Ok(WebhookFormat::TradingView));
+        assert!("csv".parse::<WebhookFormat>().is_err());
+    }
+
+    #[tokio::test]
+    async fn webhook_receives_qualifying_signal() {
+        let (base, mut received) = mock_webhook().await;
+        let notifier =
+            WebhookNotifier::spawn(format!("{base}/hook"), Tier::Strong, WebhookFormat::Raw);
+
+        notifier.notify(&signal("LOWUSDT", 6.0));
+        notifier.notify(&signal("HIGHUSDT", 12.5));
+
+        let body = tokio::time::timeout(Duration::from_secs(5), received.recv())
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(body["symbol"], "HIGHUSDT");
+        assert_eq!(body["pct_gain_24h"], 12.5);
+        assert_eq!(body["tier"], "strong");
+        assert!(received.try_recv().is_err());
+    }
+
+    #[tokio::test]
+    async fn telegram_batches_bunched_signals_into_one_message() {
+        let (base, mut received) = mock_webhook().await;
+        let notifier = TelegramNotifier::spawn(&base, "TOKEN", "42".into(), Tier::Watch);
+
+        notifier.notify(&signal("BTCUSDT", 12.5));
+        notifier.notify(&signal("ETHUSDT", 5.0));
+
+        let body = tokio::time::timeout(Duration::from_secs(5), received.recv())
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(body["chat_id"], "42");
+        assert_eq!(
+            body["text"],
+            "🚀 BTCUSDT +12.50% | vol $2.0M | price 1.5\n🚀 ETHUSDT +5.00% | vol $2.0M | price 1.5"
+        );
+    }
+
+    #[test]
+    fn cooldown_allows_once_per_period() {
+        let mut cooldown = Cooldown::new(Duration::from_secs(60));
+        let t0 = StdInstant::now();
+
+        assert!(cooldown.allow("BTCUSDT", t0));
+        assert!(!cooldown.allow("BTCUSDT", t0 + Duration::from_secs(1)));
+        assert!(!cooldown.allow("BTCUSDT", t0 + Duration::from_secs(59)));
+        assert!(cooldown.allow("ETHUSDT", t0 + Duration::from_secs(1)));
+        assert!(cooldown.allow("BTCUSDT", t0 + Duration::from_secs(60)));
+    }
+
+    #[tokio::test]
+    async fn repeated_signals_notify_once_within_cooldown() {
+        let (base, mut received) = mock_webhook().await;
+        let mut notifiers = Notifiers {
+            webhook: Some(WebhookNotifier::spawn(
+                format!("{base}/hook"),
+                Tier::Watch,
+                WebhookFormat::Raw,
+            )),
+            ..Default::default()
+        };
+
+        for pct in [6.0, 7.0, 8.0] {
+            notifiers.notify(&signal("BTCUSDT", pct));
+        }
+        notifiers.notify(&signal("ETHUSDT", 6.0));
+
+        let mut symbols = Vec::new();
+        for _ in 0..2 {
+            let body = tokio::time::timeout(Duration::from_secs(5), received.recv())
+                .await
+                .unwrap()
+                .unwrap();
+            symbols.push(body["symbol"].as_str().unwrap().to_owned());
+        }
+        assert_eq!(symbols, ["BTCUSDT", "ETHUSDT"]);
+        tokio::time::sleep(Duration::from_millis(100)).await;
+        assert!(received.try_recv().is_err());
+    }
+
+    #[test]
+    fn tier_tracker_alerts_on_transitions() {
+        use Tier::*;
+        let path = [Watch, Strong, Strong, Extreme, Strong, Watch, Extreme];
+        let alerts = |changes| {
+            let mut tracker = TierTracker::new(changes);
+            path.iter()
+                .map(|tier| tracker.observe("BTCUSDT", *tier))
+                .collect::<Vec<_>>()
+        };
+
+        assert_eq!(
+            alerts(TierChanges::Up),
+            [true, true, false, true, false, false, true]
+        );
+        assert_eq!(
+            alerts(TierChanges::Any),
+            [true, true, false, true, true, true, true]
+        );
+        assert!("down".parse::<TierChanges>().is_err());
+    }
+
+    #[tokio::test]
+    async fn tier_changes_are_still_rate_limited_by_the_cooldown() {
+        let (base, mut received) = mock_webhook().await;
+        let mut notifiers = Notifiers {
+            min_tier: Tier::Watch,
+            tier_changes: Some(TierTracker::new(TierChanges::Up)),
+            webhook: Some(WebhookNotifier::spawn(
+                format!("{base}/hook"),
+                Tier::Watch,
+                WebhookFormat::Raw,
+            )),
+            ..Default::default()
+        };
+
+        // A symbol flapping between watch and strong escalates every other
+        // signal, but only its first signal falls outside the cooldown.
+        for pct in [6.0, 12.0, 6.0, 12.0, 6.0, 12.0] {
+            notifiers.notify(&signal("BTCUSDT", pct));
+        }
+        // Another symbol has its own cooldown.
+        notifiers.notify(&signal("ETHUSDT", 12.0));
+
+        let mut alerts = Vec::new();
+        for _ in 0..2 {
+            let body = tokio::time::timeout(Duration::from_secs(5), received.recv())
+                .await
+                .unwrap()
+                .unwrap();
+            alerts.push((
+                body["symbol"].as_str().unwrap().to_owned(),
+                body["pct_gain_24h"].as_f64().unwrap(),
+            ));
+        }
+        assert_eq!(
+            alerts,
+            [("BTCUSDT".to_owned(), 6.0), ("ETHUSDT".to_owned(), 12.0)]
+        );
+        tokio::time::sleep(Duration::from_millis(100)).await;
+        assert!(received.try_recv().is_err());
+    }
+
+    #[tokio::test]
+    async fn saved_state_suppresses_realerts_after_a_restart() {
+        let path = std::env::temp_dir().join(format!("alert-state-{}.json", std::process::id()));
+        let (base, mut received) = mock_webhook().await;
+        let notifiers = || Notifiers {
+            webhook: Some(WebhookNotifier::spawn(
+                format!("{base}/hook"),
+                Tier::Watch,
+                WebhookFormat::Raw,
+            )),
+            // No cooldown, so only the restored tiers hold back the repeat.
+            cooldown: Cooldown::new(Duration::ZERO),
+            tier_changes: Some(TierTracker::new(TierChanges::Up)),
+            ..Default::default()
+        };
+
+        let mut before = notifiers();
+        before.notify(&signal("BTCUSDT", 12.0));
+        tokio::time::timeout(Duration::from_secs(5), received.recv())
+            .await
+            .unwrap()
+            .unwrap();
+        before.state().save(&path).unwrap();
+
+        let mut after = notifiers();
+        after.restore(&AlertState::load(&path, DEFAULT_COOLDOWN).unwrap());
+        std::fs::remove_file(&path).unwrap();
+        after.notify(&signal("BTCUSDT", 13.0));
+        tokio::time::sleep(Duration::from_millis(100)).await;
+        assert!(received.try_recv().is_err());
+
+        // A new tier still gets through.
+        after.notify(&signal("BTCUSDT", 25.0));
+        let body = tokio::time::timeout(Duration::from_secs(5), received.recv())
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(body["tier"], "extreme");
+    }
+
+    #[test]
+    fn stale_alert_state_is_pruned_on_load() {
+        let path = std::env::temp_dir().join(format!("alert-prune-{}.json", std::process::id()));
+        let now = Clock::now().unix;
+        let state = AlertState {
+            cooldowns: HashMap::from([("OLDUSDT".into(), now - 600), ("NEWUSDT".into(), now - 60)]),
+            tiers: HashMap::from([(
+                "OLDUSDT".into(),
+                SeenTier {
+                    tier: Tier::Strong,
+                    at: now - 600,
+                },
+            )]),
+        };
+        state.save(&path).unwrap();
+
+        let loaded = AlertState::load(&path, DEFAULT_COOLDOWN).unwrap();
+        std::fs::remove_file(&path).unwrap();
+        assert_eq!(loaded.cooldowns.keys().collect::<Vec<_>>(), ["NEWUSDT"]);
+        assert!(loaded.tiers.is_empty());
+        assert_eq!(
+            AlertState::load(&path, DEFAULT_COOLDOWN).unwrap(),
+            AlertState::default()
+        );
+    }
+
+    #[test]
+    fn tier_parses_case_insensitively() {
+        assert_eq!("Extreme".parse::<Tier>().unwrap(), Tier::Extreme);
+        assert!("loud".parse::<Tier>().is_err());
+    }
+}
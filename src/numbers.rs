@@ -0,0 +1,108 @@
+//! Volumes for the command-line tables: raw, with thousands separators or
+//! shortened with an SI-style suffix.
+
+use clap::ValueEnum;
+
+/// How [`format_volume`] writes a number, picked with `--number-format`.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, ValueEnum)]
+pub enum NumberFormat {
+    /// Whole units, e.g. `2000000`.
+    #[default]
+    Plain,
+    /// Whole units with thousands separators, e.g. `2,000,000`.
+    Commas,
+    /// One decimal and a K, M, B or T suffix, e.g. `2.0M`.
+    Si,
+}
+
+/// Suffixes for [`NumberFormat::Si`], each a thousand times the previous.
+const SI_SUFFIXES: [&str; 4] = ["K", "M", "B", "T"];
+
+/// `value` in `style`. Values under a thousand are whole units in every
+/// style; non-finite ones are written as Rust prints them.
+pub fn format_volume(value: f64, style: NumberFormat) -> String {
+    if !value.is_finite() {
+        return value.to_string();
+    }
+    match style {
+        NumberFormat::Plain => format!("{value:.0}"),
+        NumberFormat::Commas => with_commas(value),
+        NumberFormat::Si => si(value),
+    }
+}
+
+fn with_commas(value: f64) -> String {
+    let digits = format!("{:.0}", value.abs());
+    let mut out = String::with_capacity(digits.len() + digits.len() / 3 + 1);
+    if value.is_sign_negative() && digits != "0" {
+        out.push('-');
+    }
+    for (i, digit) in digits.chars().enumerate() {
+        if i > 0 && (digits.len() - i) % 3 == 0 {
+            out.push(',');
+        }
+        out.push(digit);
+    }
+    out
+}
+
+fn si(value: f64) -> String {
+    let mut scaled = value.abs();
+    let mut suffix = None;
+    for next in SI_SUFFIXES {
+        // 999_950 rounds to 1000.0K, so it is already 1.0M.
+        let rounds_up = if suffix.is_none() { 999.5 } else { 999.95 };
+        if scaled < rounds_up {
+            break;
+        }
+        scaled /= 1000.0;
+        suffix = Some(next);
+    }
+    let sign = if value < 0.0 { "-" } else { "" };
+    match suffix {
+        Some(suffix) => format!("{sign}{scaled:.1}{suffix}"),
+        None => format!("{sign}{scaled:.0}"),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn formats_across_magnitudes() {
+        let cases = [
+            (0.0, "0", "0", "0"),
+            (7.4, "7", "7", "7"),
+            (999.0, "999", "999", "999"),
+            (999.6, "1000", "1,000", "1.0K"),
+            (1_500.0, "1500", "1,500", "1.5K"),
+            (2_000_000.0, "2000000", "2,000,000", "2.0M"),
+            (999_960.0, "999960", "999,960", "1.0M"),
+            (1_500_000_000.0, "1500000000", "1,500,000,000", "1.5B"),
+            (
+                3_260_000_000_000.0,
+                "3260000000000",
+                "3,260,000,000,000",
+                "3.3T",
+            ),
+            (
+                4_200_000_000_000_000.0,
+                "4200000000000000",
+                "4,200,000,000,000,000",
+                "4200.0T",
+            ),
+            (-12_345.0, "-12345", "-12,345", "-12.3K"),
+        ];
+        for (value, plain, commas, si) in cases {
+            assert_eq!(format_volume(value, NumberFormat::Plain), plain, "{value}");
+            assert_eq!(
+                format_volume(value, NumberFormat::Commas),
+                commas,
+                "{value}"
+            );
+            assert_eq!(format_volume(value, NumberFormat::Si), si, "{value}");
+        }
+        assert_eq!(format_volume(f64::NAN, NumberFormat::Si), "NaN");
+    }
+}
@@ -0,0 +1,76 @@
+//! Where the command-line binaries write their files.
+//!
+//! Relative output paths are placed under `--output-dir=PATH`, else
+//! `OUTPUT_DIR`, else the working directory, so a container can point every
+//! file at a mounted volume.
+
+use std::path::{Path, PathBuf};
+
+use anyhow::{Context, Result};
+
+/// The `--output-dir` flag, to `#[command(flatten)]` into a binary's
+/// arguments.
+#[derive(clap::Args, Debug, Clone, Default)]
+pub struct OutputArgs {
+    /// Write output files under this directory, creating it if missing
+    /// [default: OUTPUT_DIR, else the working directory]
+    #[arg(long, global = true, value_name = "PATH")]
+    pub output_dir: Option<PathBuf>,
+}
+
+impl OutputArgs {
+    /// The flag, else `OUTPUT_DIR`.
+    pub fn resolve(&self) -> OutputDir {
+        let env = std::env::var_os("OUTPUT_DIR").filter(|v| !v.is_empty());
+        OutputDir(self.output_dir.clone().or(env.map(PathBuf::from)))
+    }
+}
+
+/// Resolved output directory; `None` is the working directory.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct OutputDir(pub Option<PathBuf>);
+
+impl OutputDir {
+    /// `name` under the output directory, creating the directories it needs.
+    /// An absolute `name` is used as given.
+    pub fn resolve_output_path(&self, name: impl AsRef<Path>) -> Result<PathBuf> {
+        let path = match &self.0 {
+            Some(dir) => dir.join(name),
+            None => name.as_ref().to_owned(),
+        };
+        if let Some(parent) = path.parent().filter(|p| !p.as_os_str().is_empty()) {
+            std::fs::create_dir_all(parent)
+                .with_context(|| format!("creating {}", parent.display()))?;
+        }
+        Ok(path)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn output_paths_are_joined_under_the_directory() {
+        let dir = std::env::temp_dir().join(format!("output-dir-{}", std::process::id()));
+        let out = OutputDir(Some(dir.join("nested")));
+
+        let path = out.resolve_output_path("pools.json").unwrap();
+        assert_eq!(path, dir.join("nested/pools.json"));
+        assert!(dir.join("nested").is_dir());
+        let absolute = std::env::temp_dir().join("elsewhere.json");
+        assert_eq!(out.resolve_output_path(&absolute).unwrap(), absolute);
+        std::fs::remove_dir_all(&dir).unwrap();
+
+        let cwd = OutputDir::default();
+        assert_eq!(
+            cwd.resolve_output_path("pools.json").unwrap(),
+            Path::new("pools.json")
+        );
+
+        let flag = OutputArgs {
+            output_dir: Some("/data".into()),
+        };
+        assert_eq!(flag.resolve(), OutputDir(Some("/data".into())));
+    }
+}
@@ -0,0 +1,233 @@
+//! The highest-volume Raydium pools, kept fresh by a background task so
+//! `GET /pools/top` answers from memory instead of calling Raydium per
+//! request.
+
+use std::{
+    sync::{Arc, RwLock},
+    time::Duration,
+};
+
+use anyhow::{Context, Result};
+use chrono::{DateTime, Utc};
+use reqwest::{Client, Url};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use shuttle_axum::axum::{http::StatusCode, response::IntoResponse, Extension, Json};
+use tokio_util::sync::CancellationToken;
+
+use crate::{
+    raydium::{locate_pool_array, Envelope},
+    SharedState,
+};
+
+/// Raydium's pool list, overridable with `TOP_POOLS_URL`.
+pub const DEFAULT_TOP_POOLS_URL: &str = "https://api-v3.raydium.io/pools/info/list";
+
+const DEFAULT_REFRESH: Duration = Duration::from_secs(60);
+const DEFAULT_LIMIT: usize = 50;
+
+/// A pool as served by `/pools/top`.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct TopPool {
+    #[serde(default)]
+    pub id: String,
+    /// Pair name, e.g. `"SOL/USDC"`; some rows omit it.
+    #[serde(default)]
+    pub name: String,
+    pub price: Option<f64>,
+    #[serde(rename = "volume24h", alias = "volume_24h")]
+    pub volume_24h: Option<f64>,
+}
+
+/// The pools from the most recent successful refresh.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct TopPoolsSnapshot {
+    pub refreshed_at: DateTime<Utc>,
+    pub pools: Vec<TopPool>,
+}
+
+/// Shared between the refresh task and the handlers; empty until the first
+/// refresh succeeds.
+#[derive(Debug, Clone, Default)]
+pub struct TopPoolsCache(Arc<RwLock<Option<TopPoolsSnapshot>>>);
+
+impl TopPoolsCache {
+    pub fn get(&self) -> Option<TopPoolsSnapshot> {
+        self.0.read().unwrap().clone()
+    }
+
+    pub fn refreshed_at(&self) -> Option<DateTime<Utc>> {
+        self.0.read().unwrap().as_ref().map(|s| s.refreshed_at)
+    }
+
+    fn set(&self, pools: Vec<TopPool>) {
+        *self.0.write().unwrap() = Some(TopPoolsSnapshot {
+            refreshed_at: Utc::now(),
+            pools,
+        });
+    }
+}
+
+/// Where and how often to refresh.
+#[derive(Debug, Clone, PartialEq)]
+pub struct TopPoolsConfig {
+    pub url: String,
+    pub interval: Duration,
+    /// Pools kept, by 24h volume.
+    pub limit: usize,
+}
+
+impl Default for TopPoolsConfig {
+    fn default() -> Self {
+        Self {
+            url: DEFAULT_TOP_POOLS_URL.to_owned(),
+            interval: DEFAULT_REFRESH,
+            limit: DEFAULT_LIMIT,
+        }
+    }
+}
+
+impl TopPoolsConfig {
+    /// Defaults overridden by `TOP_POOLS_URL` and `TOP_POOLS_REFRESH_SECS`.
+    /// A refresh interval of `0` disables the task: `None`.
+    pub fn from_env() -> Result<Option<Self>, String> {
+        let mut config = Self::default();
+        if let Ok(url) = std::env::var("TOP_POOLS_URL") {
+            config.url = url;
+        }
+        if let Ok(secs) = std::env::var("TOP_POOLS_REFRESH_SECS") {
+            let secs: u64 = secs
+                .trim()
+                .parse()
+                .map_err(|e| format!("invalid TOP_POOLS_REFRESH_SECS `{secs}`: {e}"))?;
+            if secs == 0 {
+                return Ok(None);
+            }
+            config.interval = Duration::from_secs(secs);
+        }
+        Ok(Some(config))
+    }
+}
+
+/// One page of pools sorted by 24h volume.
+pub async fn fetch_top_pools(client: &Client, url: &str, limit: usize) -> Result<Vec<TopPool>> {
+    let url = Url::parse_with_params(
+        url,
+        &[
+            ("poolType", "all"),
+            ("poolSortField", "volume24h"),
+            ("sortType", "desc"),
+            ("pageSize", &limit.to_string()),
+            ("page", "1"),
+        ],
+    )?;
+    let body: Value = client
+        .get(url)
+        .send()
+        .await?
+        .error_for_status()?
+        .json()
+        .await?;
+    Envelope::<Value>::deserialize(&body)?.into_data("/pools/info/list")?;
+    let pools =
+        serde_json::from_value(Value::Array(locate_pool_array(&body)?)).context("parsing pools")?;
+    Ok(pools)
+}
+
+/// Refresh `cache` now and then every `config.interval` until `shutdown`
+/// is cancelled. A failed refresh keeps the previous pools.
+pub async fn refresh_top_pools(
+    cache: TopPoolsCache,
+    client: Client,
+    config: TopPoolsConfig,
+    shutdown: CancellationToken,
+) {
+    let mut interval = tokio::time::interval(config.interval);
+    interval.set_missed_tick_behavior(tokio::time::MissedTickBehavior::Delay);
+    loop {
+        tokio::select! {
+            _ = shutdown.cancelled() => break,
+            _ = interval.tick() => {}
+        }
+        match fetch_top_pools(&client, &config.url, config.limit).await {
+            Ok(pools) => {
+                tracing::debug!(pools = pools.len(), "Top pools refreshed");
+                cache.set(pools);
+            }
+            Err(e) => tracing::warn!("Top pools refresh failed: {e:#}"),
+        }
+    }
+    tracing::info!("Top pools refresh stopped");
+}
+
+/// `GET /pools/top` — the cached pools and when they were fetched; 503
+/// until the first refresh.
+pub async fn top_pools_handler(Extension(state): Extension<SharedState>) -> impl IntoResponse {
+    match state.lock().await.top_pools.get() {
+        Some(snapshot) => Ok(Json(snapshot)),
+        None => Err((StatusCode::SERVICE_UNAVAILABLE, "top pools not loaded yet")),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use shuttle_axum::axum::{routing::get, Router};
+    use std::sync::atomic::{AtomicU64, Ordering};
+
+    /// A pool list whose volume is the number of calls so far.
+    async fn raydium() -> String {
+        let calls = Arc::new(AtomicU64::new(0));
+        let app = Router::new().route(
+            "/pools/info/list",
+            get(move || {
+                let n = calls.fetch_add(1, Ordering::SeqCst) + 1;
+                async move {
+                    Json(serde_json::json!({
+                        "success": true,
+                        "data": { "count": 1, "data": [
+                            { "id": "p1", "name": "SOL/USDC", "price": 150.0, "volume24h": n }
+                        ] }
+                    }))
+                }
+            }),
+        );
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        tokio::spawn(async move { shuttle_axum::axum::serve(listener, app).await });
+        format!("http://{addr}/pools/info/list")
+    }
+
+    #[tokio::test]
+    async fn refresh_task_populates_the_cache_on_its_interval() {
+        let cache = TopPoolsCache::default();
+        let shutdown = CancellationToken::new();
+        let config = TopPoolsConfig {
+            url: raydium().await,
+            interval: Duration::from_millis(100),
+            limit: 10,
+        };
+        let task = tokio::spawn(refresh_top_pools(
+            cache.clone(),
+            Client::new(),
+            config,
+            shutdown.clone(),
+        ));
+
+        tokio::time::sleep(Duration::from_millis(50)).await;
+        let first = cache.get().expect("refreshed immediately");
+        assert_eq!(first.pools[0].name, "SOL/USDC");
+        assert_eq!(first.pools[0].volume_24h, Some(1.0));
+
+        tokio::time::sleep(Duration::from_millis(200)).await;
+        let later = cache.get().unwrap();
+        assert!(later.pools[0].volume_24h.unwrap() >= 2.0);
+        assert!(later.refreshed_at > first.refreshed_at);
+
+        shutdown.cancel();
+        tokio::time::timeout(Duration::from_secs(1), task)
+            .await
+            .expect("task stops on shutdown")
+            .unwrap();
+    }
+}
@@ -0,0 +1,263 @@
+//! The feed configuration the server would start with, resolved from the
+//! environment, and what its signal filter makes of a few bundled ticker
+//! frames. `raydium_cli validate-config` prints it, so a typo'd expression
+//! or thresholds that reject everything show up before deployment instead
+//! of as a feed that never signals.
+
+use std::{fmt, time::Duration};
+
+use crate::stream::{max_connection_from_env, FeedSelection, FeedState, SignalConfig};
+
+/// `tests/fixtures/<name>.json`, as bundled ticker frames.
+macro_rules! sample {
+    ($name:literal) => {
+        (
+            $name,
+            include_str!(concat!(
+                env!("CARGO_MANIFEST_DIR"),
+                "/tests/fixtures/",
+                $name,
+                ".json"
+            )),
+        )
+    };
+}
+
+/// Binance `!ticker@arr` frames: an ordinary mix and a broad rally.
+const SAMPLES: [(&str, &str); 2] = [sample!("binance_tickers"), sample!("binance_rally")];
+
+/// One sample frame run through the filter.
+#[derive(Debug, Clone, PartialEq)]
+pub struct SampleRun {
+    pub name: &'static str,
+    pub tickers: usize,
+    /// Symbols that would have been signalled.
+    pub signals: Vec<String>,
+    /// Rejected tickers by reason; reasons without any are left out.
+    pub rejected: Vec<(&'static str, u64)>,
+    /// Why the frame couldn't be evaluated, e.g. a passing ticker without
+    /// a symbol under the configured `TICKER_FIELDS`.
+    pub error: Option<String>,
+}
+
+impl SampleRun {
+    fn evaluate(name: &'static str, frame: &str, config: &SignalConfig) -> Self {
+        let tickers = serde_json::from_str::<Vec<serde_json::Value>>(frame)
+            .map_or(0, |tickers| tickers.len());
+        // Fresh per frame: rolling volume and confirmations would otherwise
+        // depend on which samples ran before.
+        let mut state = FeedState::new(config);
+        let (signals, error) = match state.evaluate_frame(frame, config) {
+            Ok(signals) => (signals.into_iter().map(|s| s.symbol).collect(), None),
+            Err(e) => (Vec::new(), Some(e.to_string())),
+        };
+        Self {
+            name,
+            tickers,
+            signals,
+            rejected: state
+                .rejections()
+                .into_iter()
+                .filter(|&(_, count)| count > 0)
+                .collect(),
+            error,
+        }
+    }
+}
+
+/// What `validate-config` reports.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ConfigReport {
+    /// Each setting and its resolved value, in print order.
+    pub settings: Vec<(&'static str, String)>,
+    pub samples: Vec<SampleRun>,
+}
+
+impl ConfigReport {
+    /// Resolve `FEED`, `WS_MAX_CONNECTION_SECS` and the signal settings as
+    /// the server does at startup; any invalid value is the error it would
+    /// fail with.
+    pub fn from_env() -> anyhow::Result<Self> {
+        let feeds = FeedSelection::from_env().map_err(anyhow::Error::msg)?;
+        let max_connection = max_connection_from_env().map_err(anyhow::Error::msg)?;
+        let config = SignalConfig::from_env().map_err(anyhow::Error::msg)?;
+        Ok(Self::new(feeds, max_connection, &config))
+    }
+
+    fn new(feeds: FeedSelection, max_connection: Option<Duration>, config: &SignalConfig) -> Self {
+        let off = || "off".to_owned();
+        let feeds: Vec<String> = feeds.feeds().iter().map(ToString::to_string).collect();
+        let settings = vec![
+            ("FEED", feeds.join(", ")),
+            ("min_pct_gain", config.min_pct_gain.to_string()),
+            ("min_quote_vol", config.min_quote_vol.to_string()),
+            (
+                "MIN_LAST_PRICE",
+                if config.min_last_price > 0.0 {
+                    config.min_last_price.to_string()
+                } else {
+                    off()
+                },
+            ),
+            (
+                "SIGNAL_FILTER_EXPR",
+                config.filter.as_ref().map_or_else(off, ToString::to_string),
+            ),
+            ("TICKER_FIELDS", config.fields.to_string()),
+            (
+                "ROLLING_VOLUME",
+                config
+                    .rolling_volume
+                    .map_or_else(off, |window| format!("{window:?} window")),
+            ),
+            ("SIGNAL_CONFIRMATIONS", config.confirmations.to_string()),
+            (
+                "FEED_SAMPLE_INTERVAL_MS",
+                config
+                    .sample_interval
+                    .map_or_else(off, |interval| interval.as_millis().to_string()),
+            ),
+            ("SYMBOL_STATE_MAX", config.max_symbols.to_string()),
+            (
+                "WS_MAX_CONNECTION_SECS",
+                max_connection.map_or_else(off, |max| max.as_secs().to_string()),
+            ),
+            (
+                "DEPLOYMENT_TAG",
+                config.tag.clone().unwrap_or_else(|| "none".to_owned()),
+            ),
+            (
+                "FEED_GZIP",
+                if config.gzip_frames { "on" } else { "off" }.to_owned(),
+            ),
+            (
+                "MIN_TOKEN_AGE_SECS",
+                config
+                    .min_age
+                    .map_or_else(off, |min_age| min_age.as_secs().to_string()),
+            ),
+            (
+                "NEW_TOKEN_AGE_SECS",
+                config.new_token_age.as_secs().to_string(),
+            ),
+        ];
+        let samples = SAMPLES
+            .iter()
+            .map(|&(name, frame)| SampleRun::evaluate(name, frame, config))
+            .collect();
+        Self { settings, samples }
+    }
+
+    /// Signals over all the sample frames.
+    pub fn signals(&self) -> usize {
+        self.samples.iter().map(|s| s.signals.len()).sum()
+    }
+}
+
+impl fmt::Display for ConfigReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, "Resolved configuration")?;
+        for (name, value) in &self.settings {
+            writeln!(f, "  {name:<24} {value}")?;
+        }
+        writeln!(f, "Sample frames")?;
+        for sample in &self.samples {
+            write!(
+                f,
+                "  {:<24} {} of {} tickers would signal",
+                sample.name,
+                sample.signals.len(),
+                sample.tickers
+            )?;
+            if !sample.signals.is_empty() {
+                write!(f, " ({})", sample.signals.join(", "))?;
+            }
+            if !sample.rejected.is_empty() {
+                let rejected: Vec<String> = sample
+                    .rejected
+                    .iter()
+                    .map(|(reason, count)| format!("{reason} {count}"))
+                    .collect();
+                write!(f, "; rejected: {}", rejected.join(", "))?;
+            }
+            if let Some(error) = &sample.error {
+                write!(f, "; error: {error}")?;
+            }
+            writeln!(f)?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn reports_resolved_values_and_sample_signals() {
+        let config = SignalConfig {
+            min_pct_gain: 10.0,
+            min_last_price: 0.01,
+            filter: Some("symbol != \"JUPUSDT\"".parse().unwrap()),
+            tag: Some("eu-1".to_owned()),
+            ..Default::default()
+        };
+        let report = ConfigReport::new(FeedSelection::Both, None, &config);
+
+        let setting = |name| {
+            report
+                .settings
+                .iter()
+                .find(|(n, _)| *n == name)
+                .map(|(_, v)| v.as_str())
+                .unwrap()
+        };
+        assert_eq!(setting("FEED"), "Binance, Raydium");
+        assert_eq!(setting("min_pct_gain"), "10");
+        assert_eq!(setting("MIN_LAST_PRICE"), "0.01");
+        assert_eq!(setting("SIGNAL_FILTER_EXPR"), "symbol != \"JUPUSDT\"");
+        assert_eq!(setting("ROLLING_VOLUME"), "off");
+        assert_eq!(setting("WS_MAX_CONNECTION_SECS"), "off");
+        assert_eq!(setting("DEPLOYMENT_TAG"), "eu-1");
+
+        let [mixed, rally] = &report.samples[..] else {
+            panic!("expected two samples");
+        };
+        assert_eq!(mixed.signals, ["WIFUSDT"]);
+        assert_eq!(mixed.rejected, [("below_gain", 3), ("below_volume", 1)]);
+        assert_eq!(rally.signals, ["SOLUSDT"]);
+        assert_eq!(rally.tickers, 8);
+        assert_eq!(
+            rally.rejected,
+            [
+                ("below_gain", 3),
+                ("below_volume", 1),
+                ("below_price", 2),
+                ("filtered", 1)
+            ]
+        );
+        assert_eq!(report.signals(), 2);
+
+        let printed = report.to_string();
+        assert!(
+            printed.contains("  SIGNAL_FILTER_EXPR       symbol != \"JUPUSDT\"\n"),
+            "{printed}"
+        );
+        assert!(
+            printed.contains("binance_tickers          1 of 5 tickers would signal (WIFUSDT); rejected: below_gain 3, below_volume 1\n"),
+            "{printed}"
+        );
+    }
+
+    #[test]
+    fn an_expression_that_rejects_everything_shows_in_the_tally() {
+        let config = SignalConfig {
+            filter: Some("symbol.ends_with(\"usdt\")".parse().unwrap()),
+            ..Default::default()
+        };
+        let report = ConfigReport::new(FeedSelection::default(), None, &config);
+        assert_eq!(report.signals(), 0);
+        assert_eq!(report.samples[0].rejected.last(), Some(&("filtered", 3)));
+        assert_eq!(report.samples[1].rejected.last(), Some(&("filtered", 5)));
+    }
+}
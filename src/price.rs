@@ -0,0 +1,682 @@
+//! USD prices by mint from interchangeable sources.
+//!
+//! `PRICE_PROVIDER` picks the source: `raydium` (the default), `jupiter`,
+//! whose aggregated pricing covers tokens that have no Raydium pool, or
+//! `coingecko` for majors such as BTC and ETH that live elsewhere.
+//! `PRICE_PROVIDERS=raydium,jupiter,coingecko` instead tries several in
+//! order, each only for the ids the previous ones couldn't price.
+
+use std::{collections::HashMap, fmt, str::FromStr, time::Duration};
+
+use anyhow::Result;
+use futures::future::BoxFuture;
+use reqwest::{header::RETRY_AFTER, Client, RequestBuilder, Response, StatusCode, Url};
+use serde::Deserialize;
+use tokio::sync::OnceCell;
+
+use crate::raydium::Envelope;
+
+/// Raydium V3 API base, used unless a caller configures its own.
+pub const DEFAULT_RAYDIUM_BASE: &str = "https://api-v3.raydium.io";
+
+/// Raydium's price endpoint below the API base.
+pub const RAYDIUM_PRICE_PATH: &str = "/mint/price";
+
+/// Jupiter price API, overridable with `JUPITER_PRICE_URL`.
+pub const DEFAULT_JUPITER_URL: &str = "https://price.jup.ag/v6/price";
+
+/// Token prices are quoted in, overridable with `JUPITER_VS_TOKEN`.
+pub const DEFAULT_VS_TOKEN: &str = "USDC";
+
+/// CoinGecko API, overridable with `COINGECKO_API_URL` (e.g. the pro API).
+pub const DEFAULT_COINGECKO_URL: &str = "https://api.coingecko.com/api/v3";
+
+/// Longest `Retry-After` waited out when CoinGecko rate-limits a request.
+const MAX_RETRY_AFTER: Duration = Duration::from_secs(60);
+
+/// CoinGecko ids of well-known symbols and Solana mints, so the majors need
+/// no `/coins/list` download. Symbols there are ambiguous anyway: plenty of
+/// coins call themselves `ETH`.
+const KNOWN_COINGECKO_IDS: [(&str, &str); 11] = [
+    ("BTC", "bitcoin"),
+    ("ETH", "ethereum"),
+    ("SOL", "solana"),
+    ("USDC", "usd-coin"),
+    ("USDT", "tether"),
+    ("BNB", "binancecoin"),
+    ("BONK", "bonk"),
+    ("JUP", "jupiter-exchange-solana"),
+    ("So11111111111111111111111111111111111111112", "solana"),
+    ("EPjFWdd5AufqSSqeM2qN1xzybapC8G4wEGGkZwyTDt1v", "usd-coin"),
+    ("Es9vMFrzaCERmJfrF4H2FYD4KCoNkY11McCe8BenwNYB", "tether"),
+];
+
+/// A source of prices. Ids the source doesn't know are left out of the
+/// result rather than failing the whole lookup.
+pub trait PriceProvider: Send + Sync {
+    /// Short name for logs and errors.
+    fn name(&self) -> &'static str;
+
+    /// Whether ids may be symbols (`BTC`) as well as mints, so callers
+    /// needn't resolve symbols through Raydium's mint list first.
+    fn accepts_symbols(&self) -> bool {
+        false
+    }
+
+    /// Price of each of `ids` (mint addresses) that the source knows.
+    fn prices<'a>(&'a self, ids: &'a [&'a str]) -> BoxFuture<'a, Result<HashMap<String, f64>>>;
+}
+
+/// Raydium's `/mint/price`.
+pub struct RaydiumPrices {
+    client: Client,
+    base: String,
+}
+
+impl RaydiumPrices {
+    pub fn new(client: Client, base: &str) -> Self {
+        Self {
+            client,
+            base: base.trim_end_matches('/').to_owned(),
+        }
+    }
+}
+
+impl PriceProvider for RaydiumPrices {
+    fn name(&self) -> &'static str {
+        "raydium"
+    }
+
+    fn prices<'a>(&'a self, ids: &'a [&'a str]) -> BoxFuture<'a, Result<HashMap<String, f64>>> {
+        Box::pin(async move {
+            let url = Url::parse_with_params(
+                &format!("{}{RAYDIUM_PRICE_PATH}", self.base),
+                &[("ids", ids.join(","))],
+            )?;
+            let outer: Envelope<HashMap<String, f64>> =
+                self.client.get(url).send().await?.json().await?;
+            outer.into_data(RAYDIUM_PRICE_PATH)
+        })
+    }
+}
+
+/// Jupiter's aggregated prices, quoted in `vs_token`.
+pub struct JupiterPrices {
+    client: Client,
+    url: String,
+    vs_token: String,
+}
+
+impl JupiterPrices {
+    pub fn new(client: Client, url: &str, vs_token: &str) -> Self {
+        Self {
+            client,
+            url: url.to_owned(),
+            vs_token: vs_token.to_owned(),
+        }
+    }
+
+    /// `JUPITER_PRICE_URL` and `JUPITER_VS_TOKEN`, else the defaults.
+    pub fn from_env(client: Client) -> Self {
+        let var = |key, default: &str| {
+            std::env::var(key)
+                .ok()
+                .filter(|v| !v.trim().is_empty())
+                .unwrap_or_else(|| default.to_owned())
+        };
+        Self::new(
+            client,
+            &var("JUPITER_PRICE_URL", DEFAULT_JUPITER_URL),
+            &var("JUPITER_VS_TOKEN", DEFAULT_VS_TOKEN),
+        )
+    }
+}
+
+/// `{"data": {"<id>": {"id": …, "price": 1.23, …}}, "timeTaken": …}`
+#[derive(Deserialize)]
+struct JupiterOuter {
+    data: HashMap<String, JupiterPrice>,
+}
+
+#[derive(Deserialize)]
+struct JupiterPrice {
+    price: f64,
+}
+
+impl PriceProvider for JupiterPrices {
+    fn name(&self) -> &'static str {
+        "jupiter"
+    }
+
+    fn prices<'a>(&'a self, ids: &'a [&'a str]) -> BoxFuture<'a, Result<HashMap<String, f64>>> {
+        Box::pin(async move {
+            let url = Url::parse_with_params(
+                &self.url,
+                &[("ids", ids.join(",")), ("vsToken", self.vs_token.clone())],
+            )?;
+            let outer: JupiterOuter = self
+                .client
+                .get(url)
+                .send()
+                .await?
+                .error_for_status()?
+                .json()
+                .await?;
+            Ok(outer
+                .data
+                .into_iter()
+                .map(|(id, p)| (id, p.price))
+                .collect())
+        })
+    }
+}
+
+/// CoinGecko's `/simple/price` in USD. Ids are symbols or Solana mints,
+/// mapped to CoinGecko ids through [`KNOWN_COINGECKO_IDS`] and, for the
+/// rest, `/coins/list`, downloaded once per provider.
+pub struct CoinGeckoPrices {
+    client: Client,
+    base: String,
+    api_key: Option<String>,
+    coin_ids: OnceCell<HashMap<String, String>>,
+}
+
+#[derive(Deserialize)]
+struct CoinListEntry {
+    id: String,
+    symbol: String,
+    #[serde(default)]
+    platforms: HashMap<String, Option<String>>,
+}
+
+impl CoinGeckoPrices {
+    pub fn new(client: Client, base: &str, api_key: Option<&str>) -> Self {
+        Self {
+            client,
+            base: base.trim_end_matches('/').to_owned(),
+            api_key: api_key.map(str::to_owned),
+            coin_ids: OnceCell::new(),
+        }
+    }
+
+    /// `COINGECKO_API_URL` and the optional `COINGECKO_API_KEY`.
+    pub fn from_env(client: Client) -> Self {
+        let var = |key| std::env::var(key).ok().filter(|v| !v.trim().is_empty());
+        Self::new(
+            client,
+            &var("COINGECKO_API_URL").unwrap_or_else(|| DEFAULT_COINGECKO_URL.to_owned()),
+            var("COINGECKO_API_KEY").as_deref(),
+        )
+    }
+
+    fn request(&self, path: &str, query: &[(&str, &str)]) -> RequestBuilder {
+        let req = self.client.get(format!("{}{path}", self.base)).query(query);
+        match &self.api_key {
+            // Pro keys only work against the pro host, demo keys elsewhere.
+            Some(key) if self.base.contains("pro-api") => req.header("x-cg-pro-api-key", key),
+            Some(key) => req.header("x-cg-demo-api-key", key),
+            None => req,
+        }
+    }
+
+    /// GET `path`, waiting out one `429 Too Many Requests` as long as
+    /// CoinGecko's `Retry-After` asks (up to [`MAX_RETRY_AFTER`]).
+    async fn get(&self, path: &str, query: &[(&str, &str)]) -> Result<Response> {
+        let res = self.request(path, query).send().await?;
+        if res.status() != StatusCode::TOO_MANY_REQUESTS {
+            return Ok(res.error_for_status()?);
+        }
+        let wait = res
+            .headers()
+            .get(RETRY_AFTER)
+            .and_then(|v| v.to_str().ok())
+            .and_then(|v| v.trim().parse().ok())
+            .map_or(MAX_RETRY_AFTER, Duration::from_secs)
+            .min(MAX_RETRY_AFTER);
+        tracing::warn!("CoinGecko rate limit hit, retrying {path} in {wait:?}");
+        tokio::time::sleep(wait).await;
+        Ok(self.request(path, query).send().await?.error_for_status()?)
+    }
+
+    /// Solana mint → id and, where only one coin uses it, symbol → id.
+    async fn coin_ids(&self) -> Result<&HashMap<String, String>> {
+        self.coin_ids
+            .get_or_try_init(|| async {
+                let coins: Vec<CoinListEntry> = self
+                    .get("/coins/list", &[("include_platform", "true")])
+                    .await?
+                    .json()
+                    .await?;
+                let mut by_symbol: HashMap<String, Option<String>> = HashMap::new();
+                let mut ids = HashMap::new();
+                for coin in coins {
+                    if let Some(Some(mint)) = coin.platforms.get("solana") {
+                        ids.insert(mint.clone(), coin.id.clone());
+                    }
+                    by_symbol
+                        .entry(coin.symbol.to_ascii_uppercase())
+                        .and_modify(|id| *id = None)
+                        .or_insert(Some(coin.id));
+                }
+                ids.extend(by_symbol.into_iter().filter_map(|(s, id)| Some((s, id?))));
+                Ok(ids)
+            })
+            .await
+    }
+}
+
+/// Symbols match case-insensitively, mints exactly.
+fn known_coingecko_id(id: &str) -> Option<&'static str> {
+    KNOWN_COINGECKO_IDS
+        .iter()
+        .find(|(key, _)| *key == id || (key.len() < 32 && key.eq_ignore_ascii_case(id)))
+        .map(|(_, cg)| *cg)
+}
+
+impl PriceProvider for CoinGeckoPrices {
+    fn name(&self) -> &'static str {
+        "coingecko"
+    }
+
+    fn accepts_symbols(&self) -> bool {
+        true
+    }
+
+    fn prices<'a>(&'a self, ids: &'a [&'a str]) -> BoxFuture<'a, Result<HashMap<String, f64>>> {
+        Box::pin(async move {
+            let mut wanted: Vec<(&str, String)> = Vec::new();
+            let mut unknown = Vec::new();
+            for &id in ids {
+                match known_coingecko_id(id) {
+                    Some(cg) => wanted.push((id, cg.to_owned())),
+                    None => unknown.push(id),
+                }
+            }
+            if !unknown.is_empty() {
+                let coin_ids = self.coin_ids().await?;
+                for id in unknown {
+                    let cg = coin_ids
+                        .get(id)
+                        .or_else(|| coin_ids.get(&id.to_ascii_uppercase()));
+                    if let Some(cg) = cg {
+                        wanted.push((id, cg.clone()));
+                    }
+                }
+            }
+            if wanted.is_empty() {
+                return Ok(HashMap::new());
+            }
+
+            let mut cg_ids: Vec<&str> = wanted.iter().map(|(_, cg)| cg.as_str()).collect();
+            cg_ids.sort_unstable();
+            cg_ids.dedup();
+            let quotes: HashMap<String, HashMap<String, f64>> = self
+                .get(
+                    "/simple/price",
+                    &[("ids", &cg_ids.join(",")), ("vs_currencies", "usd")],
+                )
+                .await?
+                .json()
+                .await?;
+            Ok(wanted
+                .into_iter()
+                .filter_map(|(id, cg)| Some((id.to_owned(), *quotes.get(&cg)?.get("usd")?)))
+                .collect())
+        })
+    }
+}
+
+/// Tries each provider in turn, asking each only for the ids still
+/// unpriced, so the first provider that knows an id wins. A failing
+/// provider is skipped with a warning; the lookup only fails if all do.
+pub struct ChainedPriceProvider {
+    providers: Vec<Box<dyn PriceProvider>>,
+}
+
+impl ChainedPriceProvider {
+    pub fn new(providers: Vec<Box<dyn PriceProvider>>) -> Self {
+        Self { providers }
+    }
+}
+
+impl PriceProvider for ChainedPriceProvider {
+    fn name(&self) -> &'static str {
+        "chain"
+    }
+
+    /// Only if every provider does; otherwise some would be handed symbols
+    /// they can't price.
+    fn accepts_symbols(&self) -> bool {
+        self.providers.iter().all(|p| p.accepts_symbols())
+    }
+
+    fn prices<'a>(&'a self, ids: &'a [&'a str]) -> BoxFuture<'a, Result<HashMap<String, f64>>> {
+        Box::pin(async move {
+            let mut prices = HashMap::new();
+            let mut last_err = None;
+            let mut answered = false;
+            for provider in &self.providers {
+                let missing: Vec<&str> = ids
+                    .iter()
+                    .copied()
+                    .filter(|id| !prices.contains_key(*id))
+                    .collect();
+                if missing.is_empty() {
+                    break;
+                }
+                match provider.prices(&missing).await {
+                    Ok(found) => {
+                        answered = true;
+                        prices.extend(
+                            found
+                                .into_iter()
+                                .filter(|(id, _)| missing.contains(&id.as_str())),
+                        );
+                    }
+                    Err(e) => {
+                        tracing::warn!(
+                            "{} prices failed, trying the next provider: {e:#}",
+                            provider.name()
+                        );
+                        last_err = Some(e);
+                    }
+                }
+            }
+            match last_err {
+                Some(e) if !answered => Err(e),
+                _ => Ok(prices),
+            }
+        })
+    }
+}
+
+/// The provider configured by `PRICE_PROVIDERS` / `PRICE_PROVIDER`: a
+/// single source as is, several as a [`ChainedPriceProvider`].
+pub fn provider_from_env(
+    client: Client,
+    raydium_base: &str,
+) -> Result<Box<dyn PriceProvider>, String> {
+    let mut providers: Vec<_> = PriceSource::list_from_env()?
+        .into_iter()
+        .map(|source| source.provider(client.clone(), raydium_base))
+        .collect();
+    Ok(if providers.len() == 1 {
+        providers.remove(0)
+    } else {
+        Box::new(ChainedPriceProvider::new(providers))
+    })
+}
+
+/// Which [`PriceProvider`] to use, from `PRICE_PROVIDER`.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum PriceSource {
+    #[default]
+    Raydium,
+    Jupiter,
+    CoinGecko,
+}
+
+impl PriceSource {
+    /// `PRICE_PROVIDERS` (comma-separated, in priority order), else the
+    /// single `PRICE_PROVIDER`, else Raydium alone.
+    pub fn list_from_env() -> Result<Vec<Self>, String> {
+        let var = |key| std::env::var(key).ok().filter(|v| !v.trim().is_empty());
+        match (var("PRICE_PROVIDERS"), var("PRICE_PROVIDER")) {
+            (Some(list), _) => Self::parse_list(&list),
+            (None, Some(one)) => Ok(vec![one.parse()?]),
+            (None, None) => Ok(vec![Self::default()]),
+        }
+    }
+
+    /// `raydium,jupiter`; a source listed twice is only asked once.
+    pub fn parse_list(s: &str) -> Result<Vec<Self>, String> {
+        let mut sources = Vec::new();
+        for source in s.split(',').filter(|p| !p.trim().is_empty()) {
+            let source: Self = source.parse()?;
+            if !sources.contains(&source) {
+                sources.push(source);
+            }
+        }
+        if sources.is_empty() {
+            return Err("PRICE_PROVIDERS lists no provider".to_owned());
+        }
+        Ok(sources)
+    }
+
+    /// The provider for this source; `raydium_base` is the Raydium API base.
+    pub fn provider(self, client: Client, raydium_base: &str) -> Box<dyn PriceProvider> {
+        match self {
+            PriceSource::Raydium => Box::new(RaydiumPrices::new(client, raydium_base)),
+            PriceSource::Jupiter => Box::new(JupiterPrices::from_env(client)),
+            PriceSource::CoinGecko => Box::new(CoinGeckoPrices::from_env(client)),
+        }
+    }
+}
+
+impl fmt::Display for PriceSource {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            PriceSource::Raydium => "raydium",
+            PriceSource::Jupiter => "jupiter",
+            PriceSource::CoinGecko => "coingecko",
+        })
+    }
+}
+
+impl FromStr for PriceSource {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.trim().to_ascii_lowercase().as_str() {
+            "raydium" => Ok(PriceSource::Raydium),
+            "jupiter" => Ok(PriceSource::Jupiter),
+            "coingecko" => Ok(PriceSource::CoinGecko),
+            other => Err(format!(
+                "unknown PRICE_PROVIDER `{other}` (expected raydium|jupiter|coingecko)"
+            )),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use shuttle_axum::axum::{
+        extract::Query,
+        http::{HeaderMap, StatusCode as AxumStatus},
+        response::IntoResponse,
+        routing::get,
+        Json, Router,
+    };
+    use std::sync::{
+        atomic::{AtomicUsize, Ordering},
+        Arc,
+    };
+
+    /// Serve `app` on an ephemeral port and return its base URL.
+    async fn serve(app: Router) -> String {
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        tokio::spawn(async move { shuttle_axum::axum::serve(listener, app).await.unwrap() });
+        format!("http://{addr}")
+    }
+
+    #[tokio::test]
+    async fn jupiter_response_is_mapped_to_prices() {
+        let app = Router::new().route(
+            "/v6/price",
+            get(|Query(q): Query<HashMap<String, String>>| async move {
+                assert_eq!(q["ids"], "SoMint,BonkMint,Unknown");
+                assert_eq!(q["vsToken"], "USDC");
+                Json(serde_json::json!({
+                    "data": {
+                        "SoMint": {
+                            "id": "SoMint", "mintSymbol": "SOL",
+                            "vsToken": "EPjF", "vsTokenSymbol": "USDC", "price": 151.25
+                        },
+                        "BonkMint": {
+                            "id": "BonkMint", "mintSymbol": "Bonk",
+                            "vsToken": "EPjF", "vsTokenSymbol": "USDC", "price": 0.0000231
+                        }
+                    },
+                    "timeTaken": 0.0012
+                }))
+            }),
+        );
+        let url = format!("{}/v6/price", serve(app).await);
+        let jupiter = JupiterPrices::new(Client::new(), &url, DEFAULT_VS_TOKEN);
+
+        let prices = jupiter
+            .prices(&["SoMint", "BonkMint", "Unknown"])
+            .await
+            .unwrap();
+        assert_eq!(prices.len(), 2);
+        assert_eq!(prices["SoMint"], 151.25);
+        assert_eq!(prices["BonkMint"], 0.0000231);
+    }
+
+    #[tokio::test]
+    async fn raydium_failure_reports_its_message() {
+        let app = Router::new().route(
+            RAYDIUM_PRICE_PATH,
+            get(|| async {
+                Json(serde_json::json!({ "id": "x", "success": false, "msg": "rate limited" }))
+            }),
+        );
+        let raydium = RaydiumPrices::new(Client::new(), &serve(app).await);
+
+        let err = raydium.prices(&["SoMint"]).await.unwrap_err();
+        assert_eq!(err.to_string(), "Raydium error: rate limited");
+    }
+
+    /// A CoinGecko stand-in: one Solana token in `/coins/list`, whose
+    /// download count is tracked, and a `/simple/price` that rate-limits
+    /// its first call.
+    async fn coingecko() -> (String, Arc<AtomicUsize>) {
+        let list_calls = Arc::new(AtomicUsize::new(0));
+        let price_calls = Arc::new(AtomicUsize::new(0));
+        let counter = list_calls.clone();
+        let app = Router::new()
+            .route(
+                "/coins/list",
+                get(move |headers: HeaderMap| async move {
+                    assert_eq!(headers["x-cg-demo-api-key"], "k3y");
+                    counter.fetch_add(1, Ordering::SeqCst);
+                    Json(serde_json::json!([
+                        { "id": "dogwifcoin", "symbol": "wif",
+                          "platforms": { "solana": "EKpQGSJtjMFqKZ9KQanSqYXRcF8fBopzLHYxdM65zcjm" } },
+                        { "id": "bitcoin", "symbol": "btc", "platforms": {} },
+                        { "id": "fake-one", "symbol": "dup", "platforms": { "ethereum": null } },
+                        { "id": "fake-two", "symbol": "dup", "platforms": {} }
+                    ]))
+                }),
+            )
+            .route(
+                "/simple/price",
+                get(move |Query(q): Query<HashMap<String, String>>| async move {
+                    if price_calls.fetch_add(1, Ordering::SeqCst) == 0 {
+                        return (AxumStatus::TOO_MANY_REQUESTS, [("retry-after", "0")])
+                            .into_response();
+                    }
+                    assert_eq!(q["vs_currencies"], "usd");
+                    let mut quotes = serde_json::Map::new();
+                    for id in q["ids"].split(',') {
+                        let usd = if id == "bitcoin" { 65000.0 } else { 2.5 };
+                        quotes.insert(id.to_owned(), serde_json::json!({ "usd": usd }));
+                    }
+                    Json(quotes).into_response()
+                }),
+            );
+        (serve(app).await, list_calls)
+    }
+
+    #[tokio::test]
+    async fn coingecko_maps_symbols_and_mints() {
+        let (base, list_calls) = coingecko().await;
+        let gecko = CoinGeckoPrices::new(Client::new(), &base, Some("k3y"));
+        let wif = "EKpQGSJtjMFqKZ9KQanSqYXRcF8fBopzLHYxdM65zcjm";
+
+        // Built-in ids need no coin list.
+        let prices = gecko.prices(&["btc"]).await.unwrap();
+        assert_eq!(prices["btc"], 65000.0);
+        assert_eq!(list_calls.load(Ordering::SeqCst), 0);
+
+        let prices = gecko.prices(&["BTC", wif, "DUP", "NOPE"]).await.unwrap();
+        assert_eq!(prices.len(), 2, "{prices:?}");
+        assert_eq!(prices[wif], 2.5);
+
+        gecko.prices(&["WIF"]).await.unwrap();
+        assert_eq!(list_calls.load(Ordering::SeqCst), 1, "coin list is cached");
+    }
+
+    /// Knows a fixed set of prices, or fails.
+    struct Fake(&'static [(&'static str, f64)], bool);
+
+    impl PriceProvider for Fake {
+        fn name(&self) -> &'static str {
+            "fake"
+        }
+
+        fn prices<'a>(&'a self, ids: &'a [&'a str]) -> BoxFuture<'a, Result<HashMap<String, f64>>> {
+            Box::pin(async move {
+                if self.1 {
+                    return Err(anyhow::anyhow!("down"));
+                }
+                Ok(self
+                    .0
+                    .iter()
+                    .filter(|(id, _)| ids.contains(id))
+                    .map(|(id, p)| (id.to_string(), *p))
+                    .collect())
+            })
+        }
+    }
+
+    #[tokio::test]
+    async fn chain_fills_in_what_earlier_providers_missed() {
+        let chain = ChainedPriceProvider::new(vec![
+            Box::new(Fake(&[("A", 1.0), ("B", 2.0)], false)),
+            Box::new(Fake(&[("B", 20.0), ("C", 30.0)], false)),
+        ]);
+        let prices = chain.prices(&["A", "B", "C", "D"]).await.unwrap();
+        let mut prices: Vec<_> = prices.into_iter().collect();
+        prices.sort_by(|a, b| a.0.cmp(&b.0));
+        // B comes from the first provider, C only from the second.
+        assert_eq!(
+            prices,
+            [("A".into(), 1.0), ("B".into(), 2.0), ("C".into(), 30.0)]
+        );
+
+        let flaky = ChainedPriceProvider::new(vec![
+            Box::new(Fake(&[], true)),
+            Box::new(Fake(&[("A", 1.5)], false)),
+        ]);
+        assert_eq!(flaky.prices(&["A"]).await.unwrap()["A"], 1.5);
+
+        let down = ChainedPriceProvider::new(vec![Box::new(Fake(&[], true))]);
+        assert!(down.prices(&["A"]).await.is_err());
+    }
+
+    #[test]
+    fn provider_list_parses() {
+        assert_eq!(
+            PriceSource::parse_list("raydium, jupiter,raydium,coingecko"),
+            Ok(vec![
+                PriceSource::Raydium,
+                PriceSource::Jupiter,
+                PriceSource::CoinGecko
+            ])
+        );
+        assert!(PriceSource::parse_list(" , ").is_err());
+        assert!(PriceSource::parse_list("raydium,binance").is_err());
+    }
+
+    #[test]
+    fn price_source_parses() {
+        assert_eq!("CoinGecko".parse(), Ok(PriceSource::CoinGecko));
+        assert_eq!("Jupiter".parse(), Ok(PriceSource::Jupiter));
+        assert_eq!(" raydium ".parse(), Ok(PriceSource::Raydium));
+        assert!("binance".parse::<PriceSource>().is_err());
+        assert_eq!(PriceSource::Jupiter.to_string(), "jupiter");
+    }
+}
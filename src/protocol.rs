@@ -0,0 +1,76 @@
+use schemars::schema_for;
+use serde_json::{json, Value};
+use shuttle_axum::axum::{response::IntoResponse, Json};
+
+use crate::{events::Gap, stream::Signal, ws::Heartbeat, VERSION};
+
+/// Machine-readable description of the `/websocket` wire contract.
+///
+/// Message schemas are generated from the Rust types, so the description
+/// cannot drift from what the server actually sends.
+pub fn protocol_description() -> Value {
+    json!({
+        "version": VERSION,
+        "endpoint": "/websocket",
+        "encoding": "json-text",
+        "messages": [
+            {
+                "type": "Signal",
+                "direction": "server-to-client",
+                "description": "A ticker that passed the gain and volume filters.",
+                "schema": schema_for!(Signal),
+            },
+            {
+                "type": "gap",
+                "direction": "server-to-client",
+                "description": "Sent on connect with `?since=SEQ` when some signals after SEQ are no longer buffered and can't be replayed.",
+                "schema": schema_for!(Gap),
+            },
+            {
+                "type": "heartbeat",
+                "direction": "server-to-client",
+                "description": "Sent every HEARTBEAT_INTERVAL_SECS (default 15) in which no signal went out, with the number of connected clients. Also sent on `/events`, without an event id.",
+                "schema": schema_for!(Heartbeat),
+            }
+        ],
+    })
+}
+
+pub async fn protocol_handler() -> impl IntoResponse {
+    Json(protocol_description())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use shuttle_axum::axum::body::to_bytes;
+
+    #[tokio::test]
+    async fn protocol_endpoint_returns_signal_schema() {
+        let response = protocol_handler().await.into_response();
+        assert_eq!(response.status(), 200);
+
+        let body = to_bytes(response.into_body(), usize::MAX).await.unwrap();
+        let doc: Value = serde_json::from_slice(&body).unwrap();
+
+        assert_eq!(doc["version"], VERSION);
+        let msg = &doc["messages"][0];
+        assert_eq!(msg["type"], "Signal");
+        assert_eq!(msg["schema"]["title"], "Signal");
+        assert!(msg["schema"]["definitions"]["Tier"].is_object());
+        for field in [
+            "symbol",
+            "pct_gain_24h",
+            "quote_vol_usdt",
+            "last_price",
+            "tier",
+            "ts",
+            "seq",
+        ] {
+            assert!(
+                msg["schema"]["properties"][field].is_object(),
+                "missing {field}"
+            );
+        }
+    }
+}
@@ -0,0 +1,219 @@
+//! Helpers for Raydium API v3 responses shared by the binaries.
+
+use anyhow::{anyhow, bail, Context, Result};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use tracing::instrument;
+
+use crate::snippet::json_parse_error;
+
+/// Status wrapper around every Raydium API v3 response. `data` is optional
+/// because failed calls often leave it out.
+#[derive(Debug, Deserialize)]
+pub struct Envelope<T> {
+    pub success: bool,
+    /// Raydium's reason when `success` is false.
+    #[serde(default)]
+    pub msg: Option<String>,
+    pub data: Option<T>,
+}
+
+impl<T> Envelope<T> {
+    /// The payload, or an error carrying Raydium's own message. `path`
+    /// names the endpoint when Raydium gives no reason.
+    pub fn into_data(self, path: &str) -> Result<T> {
+        if !self.success {
+            match self.msg.filter(|m| !m.trim().is_empty()) {
+                Some(msg) => bail!("Raydium error: {msg}"),
+                None => bail!("Raydium API returned success=false for {path}"),
+            }
+        }
+        self.data
+            .ok_or_else(|| anyhow!("Raydium response for {path} has no data"))
+    }
+}
+
+/// Keys under `data` that have held the pool list in some API version.
+const LIST_KEYS: [&str; 3] = ["data", "list", "lists"];
+
+/// Find the pool list in a response body, whichever of the known shapes it
+/// has:
+///
+/// * `[…]`
+/// * `{ "data": […] }`
+/// * `{ "data": { "data" | "list" | "lists": […] } }`
+/// * `{ "data": { "official": […], "unOfficial": […] } }` (concatenated)
+///
+/// Anything else is an error naming the keys that were present, which is
+/// usually enough to spot where a new API version moved the list.
+pub fn locate_pool_array(body: &Value) -> Result<Vec<Value>> {
+    if let Some(pools) = body.as_array() {
+        return Ok(pools.clone());
+    }
+    let data = body.get("data");
+    if let Some(pools) = data.and_then(Value::as_array) {
+        return Ok(pools.clone());
+    }
+    if let Some(data) = data.and_then(Value::as_object) {
+        if let Some(pools) = LIST_KEYS
+            .iter()
+            .find_map(|key| data.get(*key).and_then(Value::as_array))
+        {
+            return Ok(pools.clone());
+        }
+        let official = data.get("official").and_then(Value::as_array);
+        let unofficial = data.get("unOfficial").and_then(Value::as_array);
+        if official.is_some() || unofficial.is_some() {
+            return Ok(official
+                .into_iter()
+                .chain(unofficial)
+                .flatten()
+                .cloned()
+                .collect());
+        }
+    }
+
+    let mut found = format!("top-level keys: {}", keys(body));
+    if let Some(data) = data {
+        found.push_str(&format!("; `data` keys: {}", keys(data)));
+    }
+    Err(anyhow!(
+        "unrecognised payload shape, no pool list found ({found})"
+    ))
+}
+
+/// Pool row – keep only the bits we care about.
+#[derive(Debug, Clone, PartialEq, Deserialize, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct RaydiumPool {
+    /// Pair name, e.g. `"SOL/USDC"`.  
+    /// Some rows sadly omit it, so we supply an empty string instead of
+    /// aborting the whole deserialisation.
+    #[serde(default)]
+    pub name: String,
+
+    pub price: Option<f64>, // mid-price
+    pub volume24h: Option<f64>,
+
+    /// Base and quote token; left out of some payloads.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub mint_a: Option<PoolMint>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub mint_b: Option<PoolMint>,
+}
+
+/// One token of a [`RaydiumPool`].
+#[derive(Debug, Clone, PartialEq, Deserialize, Serialize)]
+pub struct PoolMint {
+    pub address: String,
+    #[serde(default)]
+    pub symbol: String,
+}
+
+/// The pool rows of a `/pools/info/list` body, whichever shape it has.
+/// Parse errors quote the part of `raw` they point at.
+#[instrument(level = "debug", skip(raw))]
+pub fn parse_json(raw: &str) -> Result<Vec<RaydiumPool>> {
+    let body: Value =
+        serde_json::from_str(raw).map_err(|e| json_parse_error(raw, &e, "invalid JSON"))?;
+    // `data` varies in shape and is found by `locate_pool_array`.
+    Envelope::<Value>::deserialize(&body)
+        .context("wrapper parse failed")?
+        .into_data("/pools/info/list")?;
+
+    let arr = locate_pool_array(&body)?;
+    serde_json::from_value::<Vec<RaydiumPool>>(Value::Array(arr))
+        .map_err(|e| json_parse_error(raw, &e, "pool array parse failed"))
+}
+
+/// Comma-separated keys of an object, or what the value is instead.
+fn keys(value: &Value) -> String {
+    match value {
+        Value::Object(map) if map.is_empty() => "none".to_owned(),
+        Value::Object(map) => map.keys().cloned().collect::<Vec<_>>().join(", "),
+        Value::Null => "none (null)".to_owned(),
+        Value::Bool(_) => "none (a boolean)".to_owned(),
+        Value::Number(_) => "none (a number)".to_owned(),
+        Value::String(_) => "none (a string)".to_owned(),
+        Value::Array(_) => "none (an array)".to_owned(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn ids(body: Value) -> Vec<Value> {
+        locate_pool_array(&body)
+            .unwrap()
+            .into_iter()
+            .map(|p| p["id"].clone())
+            .collect()
+    }
+
+    #[test]
+    fn every_known_shape_is_found() {
+        let pools = json!([{ "id": 1 }, { "id": 2 }]);
+        let expected = [json!(1), json!(2)];
+
+        assert_eq!(ids(pools.clone()), expected);
+        assert_eq!(ids(json!({ "data": pools })), expected);
+        for key in LIST_KEYS {
+            let body = json!({ "success": true, "data": { "count": 2, key: pools } });
+            assert_eq!(ids(body), expected, "{key}");
+        }
+        let split = json!({ "data": { "official": [{ "id": 1 }], "unOfficial": [{ "id": 2 }] } });
+        assert_eq!(ids(split), expected);
+        let official_only = json!({ "data": { "official": [{ "id": 1 }] } });
+        assert_eq!(ids(official_only), [json!(1)]);
+    }
+
+    #[test]
+    fn failure_surfaces_raydium_message() {
+        let envelope = |body| serde_json::from_value::<Envelope<Value>>(body).unwrap();
+
+        let failed = json!({ "id": "x", "success": false, "msg": "ids too long" });
+        let err = envelope(failed.clone())
+            .into_data("/mint/price")
+            .unwrap_err();
+        assert_eq!(err.to_string(), "Raydium error: ids too long");
+        // A pool list body fails the same way.
+        let err = parse_json(&failed.to_string()).unwrap_err();
+        assert_eq!(err.to_string(), "Raydium error: ids too long");
+
+        let err = envelope(json!({ "success": false }))
+            .into_data("/main/info")
+            .unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "Raydium API returned success=false for /main/info"
+        );
+
+        let data = envelope(json!({ "success": true, "data": { "tvl": 1 } }))
+            .into_data("/main/info")
+            .unwrap();
+        assert_eq!(data["tvl"], 1);
+        assert!(envelope(json!({ "success": true }))
+            .into_data("/main/info")
+            .is_err());
+    }
+
+    #[test]
+    fn unknown_shape_lists_the_keys_present() {
+        let body = json!({ "id": "x", "success": true, "data": { "count": 2, "items": [] } });
+        let err = locate_pool_array(&body).unwrap_err().to_string();
+        assert!(err.contains("top-level keys: data, id, success"), "{err}");
+        assert!(err.contains("`data` keys: count, items"), "{err}");
+
+        let err = locate_pool_array(&json!({ "result": [] }))
+            .unwrap_err()
+            .to_string();
+        assert!(err.ends_with("(top-level keys: result)"), "{err}");
+
+        let err = locate_pool_array(&json!({ "data": null }))
+            .unwrap_err()
+            .to_string();
+        assert!(err.contains("`data` keys: none (null)"), "{err}");
+    }
+}
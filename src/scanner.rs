@@ -0,0 +1,158 @@
+//! The feeds without the HTTP server, for embedding the scanner in another
+//! Rust application: [`run_scanner`] connects to the configured upstreams,
+//! filters their tickers and publishes every signal on a watch channel
+//! until it is cancelled.
+
+use std::{
+    path::PathBuf,
+    sync::{Arc, RwLock},
+    time::Duration,
+};
+
+use tokio::sync::watch;
+use tokio_util::sync::CancellationToken;
+
+use crate::{
+    events::SignalBuffer,
+    first_seen::{first_seen_path_from_env, flush_first_seen, FirstSeen},
+    market::LatestTickers,
+    shutdown::Shutdown,
+    stream::{
+        max_concurrent_connects_from_env, max_connection_from_env, run_ws_feed, ConnectLimit,
+        FeedSelection, FeedSinks,
+    },
+    supervisor::{supervise, RestartCounter, RestartPolicy},
+    tls, Feed, SignalConfig,
+};
+
+/// What signals are sent as on [`run_scanner`]'s channel.
+pub use shuttle_axum::axum::extract::ws::Message;
+
+/// What [`run_scanner`] connects to and how it filters.
+#[derive(Debug, Clone)]
+pub struct ScannerConfig {
+    /// Upstreams and the WebSocket URL of each.
+    pub feeds: Vec<(Feed, String)>,
+    pub signals: SignalConfig,
+    /// Replace a connection once it is this old, see `WS_MAX_CONNECTION_SECS`.
+    pub max_connection: Option<Duration>,
+    /// Accept self-signed certificates from the upstreams.
+    pub accept_invalid_certs: bool,
+    /// Feeds that may be connecting at once, see
+    /// `MAX_CONCURRENT_FEED_CONNECTS`; `None` is no limit.
+    pub max_concurrent_connects: Option<usize>,
+    pub restart_policy: RestartPolicy,
+    /// Where symbols' first-seen times are kept across runs, see
+    /// `FIRST_SEEN_PATH`; only in memory without one.
+    pub first_seen_path: Option<PathBuf>,
+}
+
+impl ScannerConfig {
+    /// The configuration the server starts its feeds with: `FEED` and the
+    /// upstream URLs, the signal settings, `WS_MAX_CONNECTION_SECS`,
+    /// `ALLOW_INVALID_CERTS`, `MAX_CONCURRENT_FEED_CONNECTS`,
+    /// `FEED_STALE_SECS` and `FIRST_SEEN_PATH`.
+    pub fn from_env() -> anyhow::Result<Self> {
+        let feeds = FeedSelection::from_env()
+            .map_err(anyhow::Error::msg)?
+            .feeds()
+            .into_iter()
+            .map(|feed| (feed, feed.url()))
+            .collect();
+        Ok(Self {
+            feeds,
+            signals: SignalConfig::from_env().map_err(anyhow::Error::msg)?,
+            max_connection: max_connection_from_env().map_err(anyhow::Error::msg)?,
+            accept_invalid_certs: tls::allow_invalid_certs_from_env()
+                .map_err(anyhow::Error::msg)?,
+            max_concurrent_connects: max_concurrent_connects_from_env()
+                .map_err(anyhow::Error::msg)?,
+            restart_policy: RestartPolicy::from_env().map_err(anyhow::Error::msg)?,
+            first_seen_path: first_seen_path_from_env(),
+        })
+    }
+}
+
+/// Run the feeds in `config`, each restarted under its `restart_policy`,
+/// until `shutdown` is cancelled; returns once they have stopped.
+///
+/// The channel contract:
+///
+/// * Every signal is sent as `Some(Message::Text(json))`, where `json` is a
+///   [`Signal`](crate::Signal) as `/websocket` clients receive it. Its
+///   `seq` counts up from 1 across all the feeds of this call.
+/// * `None` is never sent; it is only the channel's initial value.
+/// * A watch channel keeps just the latest value, so a receiver that falls
+///   behind skips to the most recent signal rather than queueing.
+/// * Nothing is sent for tickers that don't pass the thresholds, and
+///   nothing when a feed reconnects.
+/// * Sending doesn't fail the scanner: it keeps running with no receivers
+///   until cancelled.
+///
+/// First-seen times are restored from `config.first_seen_path` and saved
+/// back there periodically and on cancellation; a file that can't be read
+/// is logged and the scanner starts without it.
+pub async fn run_scanner(
+    config: ScannerConfig,
+    tx: watch::Sender<Option<Message>>,
+    shutdown: CancellationToken,
+) {
+    let max_symbols = config.signals.max_symbols;
+    let (first_seen, flush) = match &config.first_seen_path {
+        Some(path) => {
+            let store = FirstSeen::load(path, max_symbols).unwrap_or_else(|e| {
+                tracing::warn!("Starting without first-seen times: {e}");
+                FirstSeen::new(max_symbols)
+            });
+            let flush = flush_first_seen(store.clone(), path.clone(), shutdown.clone());
+            (store, Some(flush))
+        }
+        None => (FirstSeen::new(max_symbols), None),
+    };
+    let sinks = FeedSinks {
+        tx,
+        // Only numbers the signals; nothing replays from it.
+        buffer: SignalBuffer::shared(1),
+        notifiers: Default::default(),
+        tickers: LatestTickers::new(max_symbols),
+        first_seen,
+        connect_limit: ConnectLimit::new(config.max_concurrent_connects),
+        used_weight: Default::default(),
+    };
+    let signals = Arc::new(RwLock::new(config.signals));
+    let shutdown = Shutdown::from_token(shutdown);
+    let restarts = RestartCounter::default();
+
+    let feeds = config.feeds.into_iter().map(|(feed, url)| {
+        let (sinks, signals) = (sinks.clone(), signals.clone());
+        let (accept_invalid_certs, max_connection) =
+            (config.accept_invalid_certs, config.max_connection);
+        supervise(
+            feed.to_string(),
+            restarts.clone(),
+            config.restart_policy,
+            shutdown.clone(),
+            move |liveness| {
+                let (url, sinks, signals) = (url.clone(), sinks.clone(), signals.clone());
+                async move {
+                    run_ws_feed(
+                        feed,
+                        &url,
+                        sinks,
+                        signals,
+                        accept_invalid_certs,
+                        max_connection,
+                        liveness,
+                    )
+                    .await
+                }
+            },
+        )
+    });
+    let flush = async {
+        if let Some(flush) = flush {
+            flush.await;
+        }
+    };
+    tokio::join!(futures::future::join_all(feeds), flush);
+}
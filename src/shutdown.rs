@@ -0,0 +1,219 @@
+//! Why the server is stopping. Whatever asks for the shutdown first — a
+//! signal, a feed that can't be kept alive or an operator — is recorded,
+//! logged once as a structured event and turned into the process exit code.
+
+use std::{
+    fmt,
+    sync::{Arc, OnceLock},
+};
+
+use tokio_util::sync::CancellationToken;
+
+/// What triggered the shutdown.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ShutdownReason {
+    /// `SIGINT`, e.g. Ctrl-C.
+    Interrupt,
+    /// `SIGTERM`, e.g. from a container runtime.
+    Terminate,
+    /// A feed stopped in a way its supervisor can't recover from.
+    FeedFailure(String),
+    /// `POST /admin/shutdown`.
+    Admin,
+}
+
+impl ShutdownReason {
+    /// Short name logged as the `reason` field.
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            ShutdownReason::Interrupt => "sigint",
+            ShutdownReason::Terminate => "sigterm",
+            ShutdownReason::FeedFailure(_) => "feed_failure",
+            ShutdownReason::Admin => "admin",
+        }
+    }
+
+    /// Exit code for the process: `128 + signal` for signals, as a shell
+    /// would report them, `1` for failures and `0` for a requested stop.
+    pub fn exit_code(&self) -> i32 {
+        match self {
+            ShutdownReason::Interrupt => 130,
+            ShutdownReason::Terminate => 143,
+            ShutdownReason::FeedFailure(_) => 1,
+            ShutdownReason::Admin => 0,
+        }
+    }
+}
+
+impl fmt::Display for ShutdownReason {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ShutdownReason::FeedFailure(detail) => write!(f, "feed_failure: {detail}"),
+            other => f.write_str(other.as_str()),
+        }
+    }
+}
+
+/// Shared handle that starts the shutdown and tells background tasks about
+/// it. Cloning is cheap; every clone sees the same reason.
+#[derive(Debug, Clone, Default)]
+pub struct Shutdown {
+    token: CancellationToken,
+    reason: Arc<OnceLock<ShutdownReason>>,
+}
+
+impl Shutdown {
+    /// Start shutting down because of `reason`. Only the first call counts
+    /// and logs; it returns `false` for later ones.
+    pub fn trigger(&self, reason: ShutdownReason) -> bool {
+        let detail = reason.to_string();
+        if self.reason.set(reason).is_err() {
+            return false;
+        }
+        let reason = self.reason.get().expect("set above");
+        let exit_code = reason.exit_code();
+        if exit_code == 0 {
+            tracing::info!(
+                reason = reason.as_str(),
+                exit_code,
+                "Shutting down: {detail}"
+            );
+        } else {
+            tracing::warn!(
+                reason = reason.as_str(),
+                exit_code,
+                "Shutting down: {detail}"
+            );
+        }
+        self.token.cancel();
+        true
+    }
+
+    /// A shutdown that is also triggered, without a reason, by cancelling
+    /// `token`; for callers that bring their own.
+    pub fn from_token(token: CancellationToken) -> Self {
+        Self {
+            token,
+            reason: Arc::default(),
+        }
+    }
+
+    /// The recorded reason, `None` while running.
+    pub fn reason(&self) -> Option<&ShutdownReason> {
+        self.reason.get()
+    }
+
+    /// Exit code for the recorded reason; `0` if none was recorded.
+    pub fn exit_code(&self) -> i32 {
+        self.reason().map_or(0, ShutdownReason::exit_code)
+    }
+
+    /// An error if a failure triggered the shutdown, for runtimes such as
+    /// Shuttle that judge a service by its result rather than an exit code.
+    /// Signals and requested stops are clean.
+    pub fn result(&self) -> anyhow::Result<()> {
+        match self.reason() {
+            Some(reason @ ShutdownReason::FeedFailure(_)) => {
+                Err(anyhow::anyhow!("shut down by {reason}"))
+            }
+            _ => Ok(()),
+        }
+    }
+
+    /// Cancelled once the shutdown has been triggered, for tasks that take a
+    /// plain token.
+    pub fn token(&self) -> CancellationToken {
+        self.token.clone()
+    }
+
+    /// Resolves once the shutdown has been triggered.
+    pub async fn cancelled(&self) {
+        self.token.cancelled().await
+    }
+}
+
+/// Trigger `shutdown` on `SIGINT` or, on Unix, `SIGTERM`, and resolve once
+/// it has been triggered by anything.
+pub async fn on_signals(shutdown: Shutdown) {
+    #[cfg(unix)]
+    let terminate = async {
+        use tokio::signal::unix::{signal, SignalKind};
+        match signal(SignalKind::terminate()) {
+            Ok(mut term) => {
+                term.recv().await;
+            }
+            Err(e) => {
+                tracing::warn!("Not listening for SIGTERM: {e}");
+                std::future::pending::<()>().await
+            }
+        }
+    };
+    #[cfg(not(unix))]
+    let terminate = std::future::pending::<()>();
+
+    tokio::select! {
+        _ = tokio::signal::ctrl_c() => {
+            shutdown.trigger(ShutdownReason::Interrupt);
+        }
+        _ = terminate => {
+            shutdown.trigger(ShutdownReason::Terminate);
+        }
+        _ = shutdown.cancelled() => {}
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::CapturedLogs;
+
+    #[test]
+    fn first_trigger_is_logged_and_sets_the_exit_code() {
+        let (logs, _guard) = CapturedLogs::capture();
+
+        let shutdown = Shutdown::default();
+        assert_eq!(shutdown.exit_code(), 0);
+        assert!(shutdown.trigger(ShutdownReason::FeedFailure("binance gave up".into())));
+        assert!(!shutdown.trigger(ShutdownReason::Admin));
+        assert!(shutdown.token().is_cancelled());
+        assert_eq!(shutdown.exit_code(), 1);
+
+        let out = logs.contents();
+        let lines: Vec<_> = out
+            .lines()
+            .filter(|l| l.contains("Shutting down"))
+            .collect();
+        assert_eq!(lines.len(), 1, "{out}");
+        assert!(lines[0].contains("reason=\"feed_failure\""), "{}", lines[0]);
+        assert!(lines[0].contains("exit_code=1"), "{}", lines[0]);
+        assert!(lines[0].contains("binance gave up"), "{}", lines[0]);
+    }
+
+    #[test]
+    fn signals_exit_like_a_shell_reports_them() {
+        assert_eq!(ShutdownReason::Interrupt.exit_code(), 130);
+        assert_eq!(ShutdownReason::Terminate.exit_code(), 143);
+        assert_eq!(ShutdownReason::Admin.exit_code(), 0);
+    }
+
+    #[test]
+    fn only_failures_end_the_service_with_an_error() {
+        let shutdown = Shutdown::default();
+        assert!(shutdown.result().is_ok());
+        for reason in [
+            ShutdownReason::Interrupt,
+            ShutdownReason::Terminate,
+            ShutdownReason::Admin,
+        ] {
+            let clean = Shutdown::default();
+            clean.trigger(reason);
+            assert!(clean.result().is_ok());
+        }
+
+        shutdown.trigger(ShutdownReason::FeedFailure("binance gave up".into()));
+        assert_eq!(
+            shutdown.result().unwrap_err().to_string(),
+            "shut down by feed_failure: binance gave up"
+        );
+    }
+}
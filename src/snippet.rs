@@ -0,0 +1,85 @@
+//! Bounded excerpts of response bodies for error messages, so a parse
+//! failure shows what the server actually sent without dumping megabytes.
+
+use anyhow::anyhow;
+
+/// Bytes kept on each side of the point of interest.
+pub const SNIPPET_RADIUS: usize = 100;
+
+/// Up to [`SNIPPET_RADIUS`] bytes either side of byte `offset` in `raw`,
+/// widened to character boundaries and marked with `…` where cut.
+pub fn snippet(raw: &str, offset: usize) -> String {
+    let offset = offset.min(raw.len());
+    let mut start = offset.saturating_sub(SNIPPET_RADIUS);
+    while !raw.is_char_boundary(start) {
+        start -= 1;
+    }
+    let mut end = (offset + SNIPPET_RADIUS).min(raw.len());
+    while !raw.is_char_boundary(end) {
+        end += 1;
+    }
+
+    let mut out = String::new();
+    if start > 0 {
+        out.push('…');
+    }
+    out.push_str(&raw[start..end]);
+    if end < raw.len() {
+        out.push('…');
+    }
+    out
+}
+
+/// Byte offset in `raw` of the position serde_json reported in `err`.
+pub fn json_error_offset(raw: &str, err: &serde_json::Error) -> usize {
+    if err.line() == 0 {
+        return 0;
+    }
+    let line_start: usize = raw
+        .split_inclusive('\n')
+        .take(err.line() - 1)
+        .map(str::len)
+        .sum();
+    (line_start + err.column().saturating_sub(1)).min(raw.len())
+}
+
+/// `ctx: err` followed by the part of `raw` serde_json choked on.
+pub fn json_parse_error(raw: &str, err: &serde_json::Error, ctx: &str) -> anyhow::Error {
+    let near = snippet(raw, json_error_offset(raw, err));
+    anyhow!("{ctx}: {err}; near `{near}`")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn snippet_is_bounded_and_marks_cuts() {
+        let raw = format!("{}X{}", "a".repeat(500), "b".repeat(500));
+        let s = snippet(&raw, 500);
+        assert!(s.starts_with('…') && s.ends_with('…'));
+        assert!(s.contains("aXb"));
+        assert_eq!(s.chars().count(), 2 * SNIPPET_RADIUS + 2);
+
+        assert_eq!(snippet("short", 2), "short");
+    }
+
+    #[test]
+    fn snippet_respects_char_boundaries() {
+        let raw = "é".repeat(200);
+        // Odd offsets fall inside a two-byte character.
+        let s = snippet(&raw, 101);
+        assert!(s.trim_matches('…').chars().all(|c| c == 'é'));
+    }
+
+    #[test]
+    fn parse_error_points_at_the_offending_line() {
+        let raw = "{\n  \"ok\": 1,\n  \"bad\": nope\n}";
+        let err = serde_json::from_str::<serde_json::Value>(raw).unwrap_err();
+        assert_eq!(err.line(), 3);
+
+        let msg = json_parse_error(raw, &err, "decoding").to_string();
+        assert!(msg.starts_with("decoding: expected ident"), "{msg}");
+        assert!(msg.contains("nope"), "{msg}");
+    }
+}
@@ -1,102 +1,585 @@
+use std::{
+    collections::HashMap,
+    str::FromStr,
+    sync::{
+        atomic::{AtomicU64, Ordering},
+        Mutex,
+    },
+    time::{Duration, Instant},
+};
+
 use anyhow::{anyhow, Result};
+use futures::{stream, StreamExt};
 use reqwest::Client;
+use rust_decimal::{prelude::ToPrimitive, Decimal};
+use serde::{de::DeserializeOwned, Deserialize, Serialize};
 use serde_json::{json, Value};
-use tracing::{debug, error, info, instrument};
+use tracing::{debug, error, info, instrument, warn};
+
+use crate::{
+    http::{build_client, ClientOptions},
+    snippet::{json_parse_error, snippet},
+};
 
 const TOKEN_PROGRAM_ID: &str = "TokenkegQfeZyiNwAJbNbGKPFXCWuBvf9Ss623VQ5DA";
 
+/// Maximum number of endpoints probed at the same time by [`bench_rpcs`].
+const RPC_BENCH_CONCURRENCY: usize = 8;
+
+/// Number of decimals of native SOL (1 SOL = 10⁹ lamports).
+pub const SOL_DECIMALS: u8 = 9;
+
+/// Wrapped-SOL mint, used wherever native SOL needs a mint address
+/// (e.g. price lookups).
+pub const WSOL_MINT: &str = "So11111111111111111111111111111111111111112";
+
+/// A single balance held by an account, in raw base units.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct TokenBalance {
+    /// Mint address, or `"SOL"` for the native balance.
+    pub mint: String,
+    pub amount: u64,
+    pub decimals: u8,
+}
+
+impl TokenBalance {
+    /// Amount scaled by `decimals`, e.g. lamports → SOL.
+    pub fn ui_amount(&self) -> f64 {
+        self.amount as f64 / 10f64.powi(self.decimals as i32)
+    }
+
+    /// Mint to use for price lookups (`"SOL"` maps to wrapped SOL).
+    pub fn price_mint(&self) -> &str {
+        if self.mint == "SOL" {
+            WSOL_MINT
+        } else {
+            &self.mint
+        }
+    }
+}
+
+/* ------------------------------------------------------------ RPC client */
+
+/// How final the state read by a query must be.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Commitment {
+    Processed,
+    Confirmed,
+    Finalized,
+}
+
+impl Commitment {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Commitment::Processed => "processed",
+            Commitment::Confirmed => "confirmed",
+            Commitment::Finalized => "finalized",
+        }
+    }
+}
+
+impl FromStr for Commitment {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.trim().to_ascii_lowercase().as_str() {
+            "processed" => Ok(Commitment::Processed),
+            "confirmed" => Ok(Commitment::Confirmed),
+            "finalized" => Ok(Commitment::Finalized),
+            other => Err(format!(
+                "unknown commitment `{other}` (expected processed|confirmed|finalized)"
+            )),
+        }
+    }
+}
+
+/// Consistency options added to every account read. Unset fields are left
+/// to the node's defaults.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct ReadOptions {
+    pub commitment: Option<Commitment>,
+    /// Reject the read if the node has not yet reached this slot, so that
+    /// reads across endpoints never go back in time.
+    pub min_context_slot: Option<u64>,
+}
+
+impl ReadOptions {
+    /// The RPC config object for these options merged into `base`.
+    fn apply(&self, mut base: Value) -> Value {
+        if let Some(commitment) = self.commitment {
+            base["commitment"] = json!(commitment.as_str());
+        }
+        if let Some(slot) = self.min_context_slot {
+            base["minContextSlot"] = json!(slot);
+        }
+        base
+    }
+}
+
+/// Thin typed client for a Solana JSON-RPC endpoint. Every method goes
+/// through [`SolanaRpc::call`], which builds the request envelope and
+/// extracts `result` the same way for all of them.
+#[derive(Debug)]
+pub struct SolanaRpc {
+    client: Client,
+    url: String,
+    next_id: AtomicU64,
+    read: ReadOptions,
+    /// Decimals per mint, for token accounts that don't carry them.
+    decimals: Mutex<HashMap<String, u8>>,
+}
+
+/// `{ "context": …, "value": T }` wrapper used by most account queries.
+#[derive(Deserialize)]
+struct WithContext<T> {
+    value: T,
+}
+
+/// The part of a `getTokenSupply` result needed to read token amounts.
+#[derive(Deserialize)]
+struct TokenSupply {
+    decimals: u8,
+}
+
+impl SolanaRpc {
+    pub fn new(url: &str) -> Self {
+        let client = build_client(&ClientOptions::default()).expect("default HTTP client");
+        Self::with_client(client, url)
+    }
+
+    pub fn with_client(client: Client, url: &str) -> Self {
+        Self {
+            client,
+            url: url.to_owned(),
+            next_id: AtomicU64::new(1),
+            read: ReadOptions::default(),
+            decimals: Mutex::default(),
+        }
+    }
+
+    /// Apply `read` to every account query made by this client.
+    pub fn with_read_options(mut self, read: ReadOptions) -> Self {
+        self.read = read;
+        self
+    }
+
+    /// Take these mints' decimals as known, e.g. from a mint list, for
+    /// token accounts without them; other mints are looked up with
+    /// `getTokenSupply`.
+    pub fn with_known_decimals(self, decimals: impl IntoIterator<Item = (String, u8)>) -> Self {
+        self.decimals.lock().unwrap().extend(decimals);
+        self
+    }
+
+    pub fn url(&self) -> &str {
+        &self.url
+    }
+
+    /// Request object for `method`; `params` is omitted when `Value::Null`.
+    fn envelope(&self, method: &str, params: Value) -> (u64, Value) {
+        let id = self.next_id.fetch_add(1, Ordering::Relaxed);
+        let mut req = json!({
+            "jsonrpc": "2.0",
+            "id":      id,
+            "method":  method,
+        });
+        if !params.is_null() {
+            req["params"] = params;
+        }
+        (id, req)
+    }
+
+    /// POST `body` and parse the reply. A body that isn't JSON (an HTML
+    /// error page, a truncated reply, …) is quoted in the error.
+    async fn post(&self, body: &Value) -> Result<Value> {
+        let text = self
+            .client
+            .post(&self.url)
+            .json(body)
+            .send()
+            .await?
+            .text()
+            .await?;
+        serde_json::from_str(&text)
+            .map_err(|e| json_parse_error(&text, &e, &format!("invalid JSON from {}", self.url)))
+    }
+
+    /// Send one JSON-RPC request and deserialize its `result`. A JSON-RPC
+    /// `error` object (rate limit, unsupported method, …) is returned as the
+    /// error.
+    pub async fn call<T: DeserializeOwned>(&self, method: &str, params: Value) -> Result<T> {
+        let (_, req) = self.envelope(method, params);
+        debug!("{method} request  ➜  {req}");
+        let resp = self.post(&req).await?;
+        debug!("{method} response ➜  {resp}");
+
+        decode_result(method, extract_result(method, &resp)?)
+    }
+
+    /// Send several requests as one JSON-RPC batch and return each raw
+    /// `result` in the order of `calls`, matched up by id. The outer error
+    /// means the batch as a whole failed, e.g. because the endpoint does not
+    /// accept batches; the inner ones are per-call failures.
+    pub async fn call_batch(&self, calls: &[(&str, Value)]) -> Result<Vec<Result<Value>>> {
+        let (ids, reqs): (Vec<u64>, Vec<Value>) = calls
+            .iter()
+            .map(|(method, params)| self.envelope(method, params.clone()))
+            .unzip();
+        let body = Value::Array(reqs);
+        debug!("batch request  ➜  {body}");
+        let resp = self.post(&body).await?;
+        debug!("batch response ➜  {resp}");
+
+        let Some(items) = resp.as_array() else {
+            return Err(anyhow!(
+                "batch request rejected: {}",
+                snippet(&resp.to_string(), 0)
+            ));
+        };
+        let by_id: HashMap<u64, &Value> = items
+            .iter()
+            .filter_map(|item| Some((item.get("id")?.as_u64()?, item)))
+            .collect();
+
+        Ok(ids
+            .iter()
+            .zip(calls)
+            .map(|(id, (method, _))| match by_id.get(id) {
+                Some(item) => extract_result(method, item),
+                None => Err(anyhow!("no {method} response in batch")),
+            })
+            .collect())
+    }
+
+    /// `getHealth`; succeeds only if the node reports `"ok"`.
+    pub async fn get_health(&self) -> Result<()> {
+        let health: String = self.call("getHealth", Value::Null).await?;
+        if health == "ok" {
+            Ok(())
+        } else {
+            Err(anyhow!("unhealthy: {health}"))
+        }
+    }
+
+    /// `getBalance` in lamports.
+    pub async fn get_balance(&self, pubkey: &str) -> Result<u64> {
+        let resp: WithContext<u64> = self.call("getBalance", self.pubkey_params(pubkey)).await?;
+        Ok(resp.value)
+    }
+
+    /// `getTokenSupply`'s decimals of `mint`.
+    pub async fn get_token_decimals(&self, mint: &str) -> Result<u8> {
+        let resp: WithContext<TokenSupply> = self
+            .call("getTokenSupply", self.pubkey_params(mint))
+            .await?;
+        Ok(resp.value.decimals)
+    }
+
+    /// `getTokenAccountsByOwner` for one token program, as balances. Accounts
+    /// that are not in the expected `jsonParsed` shape are skipped, as are
+    /// those whose decimals are neither given nor found, see
+    /// [`token_balances`](Self::token_balances).
+    pub async fn get_token_accounts_by_owner(
+        &self,
+        owner: &str,
+        program_id: &str,
+    ) -> Result<Vec<TokenBalance>> {
+        let resp: WithContext<Vec<Value>> = self
+            .call(
+                "getTokenAccountsByOwner",
+                self.token_accounts_params(owner, program_id),
+            )
+            .await?;
+        Ok(self.token_balances(&resp.value).await)
+    }
+
+    /// Native balance and token accounts of `owner` in a single round trip.
+    /// Falls back to two sequential calls if the endpoint rejects batches.
+    pub async fn get_balance_and_token_accounts(
+        &self,
+        owner: &str,
+        program_id: &str,
+    ) -> Result<(u64, Vec<TokenBalance>)> {
+        let calls = [
+            ("getBalance", self.pubkey_params(owner)),
+            (
+                "getTokenAccountsByOwner",
+                self.token_accounts_params(owner, program_id),
+            ),
+        ];
+        let mut results = match self.call_batch(&calls).await {
+            Ok(results) => results.into_iter(),
+            Err(e) => {
+                warn!(error = %e, "Batch not supported, falling back to sequential calls");
+                return Ok((
+                    self.get_balance(owner).await?,
+                    self.get_token_accounts_by_owner(owner, program_id).await?,
+                ));
+            }
+        };
+
+        let lamports: WithContext<u64> =
+            decode_result("getBalance", results.next().expect("one result per call")?)?;
+        let accounts: WithContext<Vec<Value>> = decode_result(
+            "getTokenAccountsByOwner",
+            results.next().expect("one result per call")?,
+        )?;
+        Ok((lamports.value, self.token_balances(&accounts.value).await))
+    }
+
+    /// Balances of `accounts`. Some providers leave `decimals` out of
+    /// `tokenAmount`; those mints' decimals come from this client's cache,
+    /// else from one batch of `getTokenSupply` calls (sequential calls if
+    /// the endpoint rejects batches), and are cached for later queries. An
+    /// account whose decimals still aren't known is skipped with a warning.
+    async fn token_balances(&self, accounts: &[Value]) -> Vec<TokenBalance> {
+        let missing: Vec<&str> = {
+            let known = self.decimals.lock().unwrap();
+            mints_without_decimals(accounts)
+                .into_iter()
+                .filter(|mint| !known.contains_key(*mint))
+                .collect()
+        };
+        if !missing.is_empty() {
+            let found = self.fetch_decimals(&missing).await;
+            self.decimals.lock().unwrap().extend(found);
+        }
+        token_balances(accounts, &self.decimals.lock().unwrap())
+    }
+
+    /// Decimals of each of `mints` that `getTokenSupply` answers for.
+    async fn fetch_decimals(&self, mints: &[&str]) -> HashMap<String, u8> {
+        debug!(?mints, "Looking up decimals missing from token accounts");
+        let calls: Vec<_> = mints
+            .iter()
+            .map(|mint| ("getTokenSupply", self.pubkey_params(mint)))
+            .collect();
+        let results: Vec<Result<u8>> = match self.call_batch(&calls).await {
+            Ok(results) => results
+                .into_iter()
+                .map(|result| {
+                    let supply: WithContext<TokenSupply> =
+                        decode_result("getTokenSupply", result?)?;
+                    Ok(supply.value.decimals)
+                })
+                .collect(),
+            Err(e) => {
+                warn!(error = %e, "Batch not supported, falling back to sequential calls");
+                let mut results = Vec::with_capacity(mints.len());
+                for mint in mints {
+                    results.push(self.get_token_decimals(mint).await);
+                }
+                results
+            }
+        };
+        let mut found = HashMap::new();
+        for (mint, decimals) in mints.iter().zip(results) {
+            match decimals {
+                Ok(decimals) => {
+                    found.insert((*mint).to_owned(), decimals);
+                }
+                Err(e) => warn!(%mint, error = %e, "Looking up the mint's decimals failed"),
+            }
+        }
+        found
+    }
+
+    /// `[pubkey]`, plus the read options when there are any.
+    fn pubkey_params(&self, pubkey: &str) -> Value {
+        let config = self.read.apply(json!({}));
+        if config.as_object().is_some_and(|c| c.is_empty()) {
+            json!([pubkey])
+        } else {
+            json!([pubkey, config])
+        }
+    }
+
+    fn token_accounts_params(&self, owner: &str, program_id: &str) -> Value {
+        json!([
+            owner,
+            { "programId": program_id },
+            self.read.apply(json!({ "encoding": "jsonParsed" }))
+        ])
+    }
+}
+
+/// The `result` of a single JSON-RPC response, or its `error` as an error.
+fn extract_result(method: &str, resp: &Value) -> Result<Value> {
+    if let Some(err) = resp.get("error") {
+        let code = err.get("code").and_then(Value::as_i64).unwrap_or_default();
+        let message = err
+            .get("message")
+            .and_then(Value::as_str)
+            .unwrap_or("(no message)");
+        error!(code, message, "{method} failed");
+        return Err(anyhow!("RPC error {code}: {message}"));
+    }
+    resp.get("result").cloned().ok_or_else(|| {
+        error!("Invalid {method} response: {resp}");
+        anyhow!("invalid {method} response")
+    })
+}
+
+/// Deserialize a `result`, quoting the start of it when it has an
+/// unexpected shape.
+fn decode_result<T: DeserializeOwned>(method: &str, result: Value) -> Result<T> {
+    T::deserialize(&result).map_err(|e| {
+        anyhow!(
+            "invalid {method} result: {e}; got `{}`",
+            snippet(&result.to_string(), 0)
+        )
+    })
+}
+
+/// Balances of `accounts`, taking the decimals of mints whose accounts
+/// don't carry them from `known`.
+fn token_balances(accounts: &[Value], known: &HashMap<String, u8>) -> Vec<TokenBalance> {
+    accounts
+        .iter()
+        .filter_map(|acc| parse_token_account(acc, known))
+        .collect()
+}
+
+/// The `parsed.info` of a `jsonParsed` token account.
+fn account_info(acc: &Value) -> Option<&Value> {
+    acc.get("account")
+        .and_then(|a| a.get("data"))
+        .and_then(|d| d.get("parsed"))
+        .and_then(|p| p.get("info"))
+}
+
+/// `tokenAmount.decimals` of a token account's `info`, if it has them.
+fn account_decimals(info: &Value) -> Option<u8> {
+    info.get("tokenAmount")?
+        .get("decimals")?
+        .as_u64()?
+        .try_into()
+        .ok()
+}
+
+/// Each mint, once, whose token accounts in `accounts` lack decimals.
+fn mints_without_decimals(accounts: &[Value]) -> Vec<&str> {
+    let mut mints = Vec::new();
+    for info in accounts.iter().filter_map(account_info) {
+        let Some(mint) = info.get("mint").and_then(Value::as_str) else {
+            continue;
+        };
+        if account_decimals(info).is_none() && !mints.contains(&mint) {
+            mints.push(mint);
+        }
+    }
+    mints
+}
+
+/// Balance of a `jsonParsed` token account, if it has one, with decimals
+/// from `known` when the account has none. Accounts whose amount or
+/// decimals can't be read are skipped with a warning.
+fn parse_token_account(acc: &Value, known: &HashMap<String, u8>) -> Option<TokenBalance> {
+    let Some(info) = account_info(acc) else {
+        debug!("Skipping token account without jsonParsed data");
+        return None;
+    };
+    let token_amount = info.get("tokenAmount")?;
+
+    let mint = info.get("mint").and_then(Value::as_str)?;
+    let Some(decimals) = account_decimals(info).or_else(|| known.get(mint).copied()) else {
+        warn!(%mint, "Skipping token account whose mint's decimals are unknown");
+        return None;
+    };
+    let Some(amount) = raw_amount(token_amount, decimals) else {
+        warn!(%mint, %token_amount, "Skipping token account with an unreadable amount");
+        return None;
+    };
+    debug!(%mint, amount, decimals, "Parsed SPL-token balance");
+
+    Some(TokenBalance {
+        mint: mint.to_owned(),
+        amount,
+        decimals,
+    })
+}
+
+/// A `tokenAmount`'s raw base units: its integer `amount`, or, from
+/// providers that leave that out or send it as a float, `uiAmountString`
+/// scaled by `decimals`.
+fn raw_amount(token_amount: &Value, decimals: u8) -> Option<u64> {
+    let amount = token_amount.get("amount").and_then(Value::as_str);
+    if let Some(amount) = amount.and_then(|a| a.trim().parse().ok()) {
+        return Some(amount);
+    }
+    let ui = token_amount.get("uiAmountString").and_then(Value::as_str)?;
+    ui_to_raw(ui, decimals)
+}
+
+/// `ui` (e.g. `"12.5"`) in base units of a token with `decimals`, computed
+/// exactly; `None` for more fractional digits than the token has, negative
+/// amounts and amounts beyond `u64`.
+fn ui_to_raw(ui: &str, decimals: u8) -> Option<u64> {
+    let ui = Decimal::from_str(ui.trim()).ok()?;
+    let scale = Decimal::from(10u64.checked_pow(decimals.into())?);
+    let raw = ui.checked_mul(scale)?;
+    if raw.is_sign_negative() || !raw.fract().is_zero() {
+        return None;
+    }
+    raw.to_u64()
+}
+
+/// One balance per mint, in the order the mints first appear. A wallet can
+/// hold a mint in several token accounts (its associated account plus
+/// older ones); their amounts are summed.
+fn merge_by_mint(tokens: Vec<TokenBalance>) -> Vec<TokenBalance> {
+    let mut merged: Vec<TokenBalance> = Vec::with_capacity(tokens.len());
+    let mut index: HashMap<String, usize> = HashMap::new();
+    for token in tokens {
+        match index.get(&token.mint) {
+            Some(&i) => {
+                let total = &mut merged[i];
+                total.amount = total.amount.saturating_add(token.amount);
+            }
+            None => {
+                index.insert(token.mint.clone(), merged.len());
+                merged.push(token);
+            }
+        }
+    }
+    merged
+}
+
 /// Fetch balances for a Solana account.
 ///
 /// * Returns the SOL balance (lamports) **plus** every SPL-token balance > 0.
+/// * A mint held in several token accounts appears once, with the total.
 /// * Zero-balance tokens are filtered out (except SOL, which is always kept).
-#[instrument(name = "solana::fetch_balances", skip(rpc_url))]
 pub async fn fetch_balances(owner: &str, rpc_url: &str) -> Result<Vec<(String, u64)>> {
+    Ok(fetch_balances_ui(owner, &SolanaRpc::new(rpc_url))
+        .await?
+        .into_iter()
+        .map(|b| (b.mint, b.amount))
+        .collect())
+}
+
+/// Same as [`fetch_balances`] but keeps each token's decimals so callers can
+/// compute UI amounts. Queries go through `rpc`, so its HTTP client (and
+/// proxy) and read options apply.
+#[instrument(name = "solana::fetch_balances", skip(rpc))]
+pub async fn fetch_balances_ui(owner: &str, rpc: &SolanaRpc) -> Result<Vec<TokenBalance>> {
     info!(%owner, "Fetching Solana balances");
 
-    let client = Client::new();
-
-    /* ------------------------------------------------------------------ SOL */
-
-    let sol_req = json!({
-        "jsonrpc": "2.0",
-        "id":      1,
-        "method":  "getBalance",
-        "params":  [owner],
-    });
-    debug!("getBalance request  ➜  {sol_req}");
-    let sol_resp: Value = client
-        .post(rpc_url)
-        .json(&sol_req)
-        .send()
-        .await?
-        .json()
+    let (lamports, tokens) = rpc
+        .get_balance_and_token_accounts(owner, TOKEN_PROGRAM_ID)
         .await?;
-    debug!("getBalance response ➜  {sol_resp}");
-
-    let sol_lamports = sol_resp
-        .get("result")
-        .and_then(|r| r.get("value"))
-        .and_then(Value::as_u64)
-        .ok_or_else(|| {
-            error!("Invalid getBalance response: {sol_resp}");
-            anyhow!("invalid getBalance response")
-        })?;
-
-    let mut balances = vec![("SOL".to_owned(), sol_lamports)];
-
-    /* ------------------------------------------------------------- SPL tokens */
-
-    let tok_req = json!({
-        "jsonrpc": "2.0",
-        "id":      1,
-        "method":  "getTokenAccountsByOwner",
-        "params": [
-            owner,
-            { "programId": TOKEN_PROGRAM_ID },
-            { "encoding": "jsonParsed" }
-        ]
-    });
-    debug!("getTokenAccountsByOwner request  ➜  {tok_req}");
-    let tok_resp: Value = client
-        .post(rpc_url)
-        .json(&tok_req)
-        .send()
-        .await?
-        .json()
-        .await?;
-    debug!("getTokenAccountsByOwner response ➜  {tok_resp}");
-
-    if let Some(arr) = tok_resp
-        .get("result")
-        .and_then(|r| r.get("value"))
-        .and_then(Value::as_array)
-    {
-        for acc in arr {
-            if let Some(info) = acc
-                .get("account")
-                .and_then(|a| a.get("data"))
-                .and_then(|d| d.get("parsed"))
-                .and_then(|p| p.get("info"))
-            {
-                if let (Some(mint), Some(amount_str)) = (
-                    info.get("mint").and_then(Value::as_str),
-                    info.get("tokenAmount")
-                        .and_then(|ta| ta.get("amount"))
-                        .and_then(Value::as_str),
-                ) {
-                    if let Ok(amount) = amount_str.parse::<u64>() {
-                        debug!(%mint, amount, "Parsed SPL-token balance");
-                        balances.push((mint.to_owned(), amount));
-                    }
-                }
-            }
-        }
-    }
+
+    let mut balances = vec![TokenBalance {
+        mint: "SOL".to_owned(),
+        amount: lamports,
+        decimals: SOL_DECIMALS,
+    }];
+    balances.extend(merge_by_mint(tokens));
 
     /* ------------------------------------------------------- final filtering */
 
     let before = balances.len();
-    balances.retain(|(mint, amt)| *amt > 0 || mint == "SOL");
+    balances.retain(|b| b.amount > 0 || b.mint == "SOL");
     let after = balances.len();
 
     info!(
@@ -107,3 +590,516 @@ pub async fn fetch_balances(owner: &str, rpc_url: &str) -> Result<Vec<(String, u
     );
     Ok(balances)
 }
+
+/* ------------------------------------------------------------ RPC bench */
+
+/// Result of probing a single RPC endpoint with [`probe_rpc`].
+#[derive(Debug, Clone)]
+pub struct RpcProbe {
+    pub url: String,
+    /// Round-trip time for `getHealth` + `getBalance`, `None` on failure.
+    pub latency: Option<Duration>,
+    pub error: Option<String>,
+}
+
+impl RpcProbe {
+    pub fn is_healthy(&self) -> bool {
+        self.error.is_none()
+    }
+}
+
+/// Ping `rpc_url` with `getHealth` followed by a `getBalance` of a well-known
+/// account, measuring the combined latency. The whole probe is bounded by
+/// `timeout`, so a hung endpoint is reported as failed instead of stalling.
+#[instrument(name = "solana::probe_rpc", skip(client))]
+pub async fn probe_rpc(client: &Client, rpc_url: &str, timeout: Duration) -> RpcProbe {
+    let started = Instant::now();
+
+    let rpc = SolanaRpc::with_client(client.clone(), rpc_url);
+    let probe = async {
+        rpc.get_health().await?;
+        rpc.get_balance(TOKEN_PROGRAM_ID).await?;
+        Ok::<_, anyhow::Error>(())
+    };
+
+    let outcome = match tokio::time::timeout(timeout, probe).await {
+        Ok(Ok(())) => Ok(started.elapsed()),
+        Ok(Err(e)) => Err(e.to_string()),
+        Err(_) => Err(format!("timed out after {timeout:?}")),
+    };
+
+    match outcome {
+        Ok(latency) => {
+            debug!(?latency, "RPC probe succeeded");
+            RpcProbe {
+                url: rpc_url.to_owned(),
+                latency: Some(latency),
+                error: None,
+            }
+        }
+        Err(e) => {
+            warn!(error = %e, "RPC probe failed");
+            RpcProbe {
+                url: rpc_url.to_owned(),
+                latency: None,
+                error: Some(e),
+            }
+        }
+    }
+}
+
+/// Probe every endpoint concurrently and return them ranked: healthy
+/// endpoints first (fastest first), followed by the failed ones.
+pub async fn bench_rpcs(client: &Client, rpc_urls: &[String], timeout: Duration) -> Vec<RpcProbe> {
+    let mut probes = stream::iter(rpc_urls)
+        .map(|url| probe_rpc(client, url, timeout))
+        .buffer_unordered(RPC_BENCH_CONCURRENCY)
+        .collect::<Vec<_>>()
+        .await;
+
+    probes.sort_by_key(|p| (p.latency.is_none(), p.latency));
+    probes
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::CapturedLogs;
+    use shuttle_axum::axum::{routing::post, Json, Router};
+    use std::sync::{Arc, Mutex};
+
+    /// Answer a single JSON-RPC request object.
+    fn answer(req: &Value) -> Value {
+        let result = match req["method"].as_str() {
+            Some("getHealth") => json!("ok"),
+            Some("getTokenAccountsByOwner") if req["params"][0] == "TwoAccounts" => json!({
+                "context": { "slot": 1 },
+                "value": [
+                    token_account("MintA", "1500", 6),
+                    token_account("MintC", "7", 0),
+                    token_account("MintA", "250", 6),
+                ],
+            }),
+            // A provider that leaves the decimals out.
+            Some("getTokenAccountsByOwner") if req["params"][0] == "NoDecimals" => {
+                let account = |mint: &str, token_amount: Value| {
+                    json!({ "account": { "data": { "parsed": { "info": {
+                        "mint": mint,
+                        "tokenAmount": token_amount,
+                    }}}}})
+                };
+                json!({
+                    "context": { "slot": 1 },
+                    "value": [
+                        account("MintD", json!({ "amount": "2500000" })),
+                        account("MintD", json!({ "uiAmountString": "0.5" })),
+                        account("MintE", json!({ "amount": "30" })),
+                        account("MintX", json!({ "amount": "1" })),
+                    ],
+                })
+            }
+            Some("getTokenSupply") if req["params"][0] == "MintD" => json!({
+                "context": { "slot": 1 },
+                "value": { "amount": "1000000000", "decimals": 6, "uiAmountString": "1000" },
+            }),
+            Some("getTokenAccountsByOwner") => json!({
+                "context": { "slot": 1 },
+                "value": [
+                    token_account("MintA", "1500", 6),
+                    token_account("MintB", "0", 9),
+                    { "account": { "data": ["base64", "AAAA"] } },
+                ],
+            }),
+            Some("getBalance") if req["params"][0] == "RateLimited" => {
+                return json!({
+                    "jsonrpc": "2.0",
+                    "id": req["id"],
+                    "error": { "code": -32005, "message": "Too many requests" },
+                });
+            }
+            Some("getBalance") => json!({ "context": { "slot": 1 }, "value": 42 }),
+            _ => return json!({ "jsonrpc": "2.0", "id": req["id"] }),
+        };
+        json!({ "jsonrpc": "2.0", "id": req["id"], "result": result })
+    }
+
+    /// Spawn a fake RPC answering every call after `delay`. Batches are
+    /// answered in reverse order (to exercise matching by id), or rejected
+    /// like some public endpoints do when `batch` is false.
+    async fn mock_server(delay: Duration, batch: bool) -> String {
+        let app = Router::new().route(
+            "/",
+            post(move |Json(req): Json<Value>| async move {
+                tokio::time::sleep(delay).await;
+                Json(match req.as_array() {
+                    Some(reqs) if batch => reqs.iter().rev().map(answer).collect(),
+                    Some(_) => json!({
+                        "jsonrpc": "2.0",
+                        "id": null,
+                        "error": { "code": -32600, "message": "Batch requests are disabled" },
+                    }),
+                    None => answer(&req),
+                })
+            }),
+        );
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        tokio::spawn(async move { shuttle_axum::axum::serve(listener, app).await });
+        format!("http://{addr}")
+    }
+
+    fn client() -> Client {
+        build_client(&ClientOptions::default()).unwrap()
+    }
+
+    async fn mock_rpc(delay: Duration) -> String {
+        mock_server(delay, true).await
+    }
+
+    /// Like [`mock_rpc`], but also keeps every request body it receives.
+    async fn recording_rpc() -> (String, Arc<Mutex<Vec<Value>>>) {
+        let seen = Arc::new(Mutex::new(Vec::new()));
+        let log = seen.clone();
+        let app = Router::new().route(
+            "/",
+            post(move |Json(req): Json<Value>| async move {
+                log.lock().unwrap().push(req.clone());
+                Json(match req.as_array() {
+                    Some(reqs) => reqs.iter().map(answer).collect(),
+                    None => answer(&req),
+                })
+            }),
+        );
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        tokio::spawn(async move { shuttle_axum::axum::serve(listener, app).await });
+        (format!("http://{addr}"), seen)
+    }
+
+    fn token_account(mint: &str, amount: &str, decimals: u8) -> Value {
+        json!({
+            "account": { "data": { "parsed": { "info": {
+                "mint": mint,
+                "tokenAmount": { "amount": amount, "decimals": decimals },
+            }}}}
+        })
+    }
+
+    #[tokio::test]
+    async fn rpc_get_health() {
+        let rpc = SolanaRpc::new(&mock_rpc(Duration::ZERO).await);
+        rpc.get_health().await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn rpc_get_balance() {
+        let rpc = SolanaRpc::new(&mock_rpc(Duration::ZERO).await);
+        assert_eq!(rpc.get_balance("Owner111").await.unwrap(), 42);
+    }
+
+    #[tokio::test]
+    async fn rpc_get_token_accounts_by_owner() {
+        let rpc = SolanaRpc::new(&mock_rpc(Duration::ZERO).await);
+        let accounts = rpc
+            .get_token_accounts_by_owner("Owner111", TOKEN_PROGRAM_ID)
+            .await
+            .unwrap();
+
+        // The non-`jsonParsed` account is skipped.
+        assert_eq!(
+            accounts,
+            [
+                TokenBalance {
+                    mint: "MintA".into(),
+                    amount: 1500,
+                    decimals: 6
+                },
+                TokenBalance {
+                    mint: "MintB".into(),
+                    amount: 0,
+                    decimals: 9
+                },
+            ]
+        );
+    }
+
+    #[tokio::test]
+    async fn rpc_call_without_result_is_an_error() {
+        let rpc = SolanaRpc::new(&mock_rpc(Duration::ZERO).await);
+        let err = rpc.call::<Value>("getSlot", Value::Null).await.unwrap_err();
+        assert!(
+            err.to_string().contains("invalid getSlot response"),
+            "{err}"
+        );
+    }
+
+    #[tokio::test]
+    async fn rpc_error_object_is_surfaced() {
+        let url = mock_rpc(Duration::ZERO).await;
+        let err = fetch_balances("RateLimited", &url).await.unwrap_err();
+        assert_eq!(err.to_string(), "RPC error -32005: Too many requests");
+    }
+
+    #[tokio::test]
+    async fn malformed_response_is_quoted_in_the_error() {
+        let app = Router::new().route(
+            "/",
+            post(|| async { r#"{"jsonrpc":"2.0","id":1,"result":{"value": 4x2}}"# }),
+        );
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        tokio::spawn(async move { shuttle_axum::axum::serve(listener, app).await });
+
+        let err = fetch_balances("Owner111", &format!("http://{addr}"))
+            .await
+            .unwrap_err()
+            .to_string();
+        assert!(err.starts_with("invalid JSON from http://"), "{err}");
+        assert!(err.contains(r#""value": 4x2"#), "{err}");
+    }
+
+    #[test]
+    fn unexpected_result_shape_is_quoted() {
+        let err = decode_result::<u64>("getBalance", json!({ "value": "lots" }))
+            .unwrap_err()
+            .to_string();
+        assert!(err.contains(r#"got `{"value":"lots"}`"#), "{err}");
+    }
+
+    #[tokio::test]
+    async fn fetch_balances_keeps_sol_and_non_zero_tokens() {
+        let url = mock_rpc(Duration::ZERO).await;
+        let balances = fetch_balances("Owner111", &url).await.unwrap();
+        assert_eq!(
+            balances,
+            [("SOL".to_owned(), 42), ("MintA".to_owned(), 1500)]
+        );
+    }
+
+    #[test]
+    fn amounts_fall_back_to_the_ui_amount() {
+        let account = |token_amount: Value| {
+            json!({
+                "account": { "data": { "parsed": { "info": {
+                    "mint": "MintA",
+                    "tokenAmount": token_amount,
+                }}}}
+            })
+        };
+        let amount = |token_amount| {
+            parse_token_account(&account(token_amount), &HashMap::new()).map(|b| b.amount)
+        };
+
+        assert_eq!(
+            amount(json!({ "uiAmountString": "12.5", "decimals": 6 })),
+            Some(12_500_000)
+        );
+        assert_eq!(
+            amount(json!({ "amount": "1500.0", "uiAmountString": "0.0015", "decimals": 6 })),
+            Some(1500)
+        );
+        // A well-formed `amount` wins over the UI amount.
+        assert_eq!(
+            amount(json!({ "amount": "7", "uiAmountString": "9", "decimals": 0 })),
+            Some(7)
+        );
+        assert_eq!(ui_to_raw("18446744073.709551615", 9), Some(u64::MAX));
+        assert_eq!(ui_to_raw("18446744073.709551616", 9), None);
+        assert_eq!(ui_to_raw("-1", 0), None);
+    }
+
+    #[test]
+    fn unreadable_amounts_are_skipped_with_a_warning() {
+        let (logs, _guard) = CapturedLogs::capture();
+
+        let accounts = [
+            token_account("MintA", "1500", 6),
+            // More fractional digits than the token has.
+            json!({ "account": { "data": { "parsed": { "info": {
+                "mint": "MintB",
+                "tokenAmount": { "amount": "n/a", "uiAmountString": "1.1234567", "decimals": 6 },
+            }}}}}),
+            json!({ "account": { "data": { "parsed": { "info": {
+                "mint": "MintC",
+                "tokenAmount": { "decimals": 9 },
+            }}}}}),
+        ];
+        let balances = token_balances(&accounts, &HashMap::new());
+        assert_eq!(balances.len(), 1);
+        assert_eq!(balances[0].mint, "MintA");
+
+        let logs = logs.contents();
+        let skipped: Vec<&str> = logs
+            .lines()
+            .filter(|l| l.contains("Skipping token account with an unreadable amount"))
+            .collect();
+        assert_eq!(skipped.len(), 2, "{logs}");
+        assert!(
+            skipped[0].contains("WARN") && skipped[0].contains("mint=MintB"),
+            "{logs}"
+        );
+        assert!(skipped[1].contains("mint=MintC"), "{logs}");
+    }
+
+    #[tokio::test]
+    async fn missing_decimals_are_looked_up_once_per_mint() {
+        let (url, seen) = recording_rpc().await;
+        let rpc = SolanaRpc::new(&url).with_known_decimals([("MintE".to_owned(), 2)]);
+        let mint = |mint: &str, amount, decimals| TokenBalance {
+            mint: mint.into(),
+            amount,
+            decimals,
+        };
+        // MintX has no supply either, so its account is skipped.
+        let expected = [
+            mint("SOL", 42, SOL_DECIMALS),
+            mint("MintD", 3_000_000, 6),
+            mint("MintE", 30, 2),
+        ];
+        assert_eq!(
+            fetch_balances_ui("NoDecimals", &rpc).await.unwrap(),
+            expected
+        );
+
+        let lookups: Vec<Value> = seen.lock().unwrap()[1]
+            .as_array()
+            .unwrap()
+            .iter()
+            .map(|req| json!([req["method"], req["params"][0]]))
+            .collect();
+        assert_eq!(
+            lookups,
+            [
+                json!(["getTokenSupply", "MintD"]),
+                json!(["getTokenSupply", "MintX"])
+            ]
+        );
+
+        // MintD's decimals are cached; only the unknown MintX is asked again.
+        assert_eq!(
+            fetch_balances_ui("NoDecimals", &rpc).await.unwrap(),
+            expected
+        );
+        let seen = seen.lock().unwrap();
+        assert_eq!(seen.len(), 4);
+        assert_eq!(seen[3][0]["params"], json!(["MintX"]));
+    }
+
+    #[tokio::test]
+    async fn accounts_of_the_same_mint_are_summed() {
+        let rpc = SolanaRpc::new(&mock_rpc(Duration::ZERO).await);
+        let balances = fetch_balances_ui("TwoAccounts", &rpc).await.unwrap();
+        let mint = |mint: &str, amount, decimals| TokenBalance {
+            mint: mint.into(),
+            amount,
+            decimals,
+        };
+        assert_eq!(
+            balances,
+            [
+                mint("SOL", 42, SOL_DECIMALS),
+                mint("MintA", 1750, 6),
+                mint("MintC", 7, 0),
+            ]
+        );
+    }
+
+    #[tokio::test]
+    async fn batch_responses_are_matched_by_id() {
+        let rpc = SolanaRpc::new(&mock_rpc(Duration::ZERO).await);
+        let (lamports, tokens) = rpc
+            .get_balance_and_token_accounts("Owner111", TOKEN_PROGRAM_ID)
+            .await
+            .unwrap();
+        assert_eq!(lamports, 42);
+        assert_eq!(tokens.len(), 2);
+        assert_eq!(tokens[0].mint, "MintA");
+    }
+
+    #[tokio::test]
+    async fn rejected_batch_falls_back_to_sequential_calls() {
+        let url = mock_server(Duration::ZERO, false).await;
+        let rpc = SolanaRpc::new(&url);
+        assert!(rpc.call_batch(&[("getHealth", Value::Null)]).await.is_err());
+
+        let balances = fetch_balances("Owner111", &url).await.unwrap();
+        assert_eq!(
+            balances,
+            [("SOL".to_owned(), 42), ("MintA".to_owned(), 1500)]
+        );
+    }
+
+    #[tokio::test]
+    async fn read_options_are_sent_with_account_queries() {
+        let (url, seen) = recording_rpc().await;
+        let read = ReadOptions {
+            commitment: Some(Commitment::Finalized),
+            min_context_slot: Some(250_000_000),
+        };
+        let rpc = SolanaRpc::new(&url).with_read_options(read);
+        fetch_balances_ui("Owner111", &rpc).await.unwrap();
+
+        let batch = seen.lock().unwrap()[0].clone();
+        assert_eq!(
+            batch[0]["params"],
+            json!(["Owner111", { "commitment": "finalized", "minContextSlot": 250_000_000 }])
+        );
+        assert_eq!(
+            batch[1]["params"][2],
+            json!({
+                "encoding": "jsonParsed",
+                "commitment": "finalized",
+                "minContextSlot": 250_000_000,
+            })
+        );
+    }
+
+    #[tokio::test]
+    async fn default_read_options_add_no_params() {
+        let (url, seen) = recording_rpc().await;
+        SolanaRpc::new(&url).get_balance("Owner111").await.unwrap();
+        assert_eq!(seen.lock().unwrap()[0]["params"], json!(["Owner111"]));
+    }
+
+    #[test]
+    fn commitment_is_validated() {
+        assert_eq!("Finalized".parse(), Ok(Commitment::Finalized));
+        assert_eq!("confirmed".parse(), Ok(Commitment::Confirmed));
+        assert!("max".parse::<Commitment>().is_err());
+    }
+
+    #[tokio::test]
+    async fn bench_rpcs_ranks_fastest_first() {
+        let slow = mock_rpc(Duration::from_millis(150)).await;
+        let fast = mock_rpc(Duration::from_millis(0)).await;
+
+        let ranked = bench_rpcs(
+            &client(),
+            &[slow.clone(), fast.clone()],
+            Duration::from_secs(5),
+        )
+        .await;
+
+        assert_eq!(ranked.len(), 2);
+        assert_eq!(ranked[0].url, fast);
+        assert_eq!(ranked[1].url, slow);
+        assert!(ranked.iter().all(RpcProbe::is_healthy));
+    }
+
+    #[tokio::test]
+    async fn bench_rpcs_reports_timeouts_last() {
+        let hung = mock_rpc(Duration::from_secs(5)).await;
+        let fast = mock_rpc(Duration::from_millis(0)).await;
+
+        let ranked = bench_rpcs(
+            &client(),
+            &[hung.clone(), fast.clone()],
+            Duration::from_millis(200),
+        )
+        .await;
+
+        assert_eq!(ranked[0].url, fast);
+        assert_eq!(ranked[1].url, hung);
+        assert!(ranked[1].error.as_deref().unwrap().contains("timed out"));
+    }
+}
@@ -1,42 +1,961 @@
-use std::{error::Error, time::Duration};
+use std::{
+    borrow::Cow,
+    error::Error,
+    fmt,
+    io::Read,
+    str::FromStr,
+    sync::{Arc, Mutex, RwLock},
+    time::{Duration, Instant, SystemTime},
+};
 
-use chrono::{DateTime, Utc};
+use chrono::{DateTime, SecondsFormat, Utc};
 use futures::{SinkExt, StreamExt};
-use serde::Serialize;
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
 use shuttle_axum::axum::extract::ws::Message;
-use tokio::sync::watch;
-use tokio_tungstenite::{connect_async, tungstenite};
+use tokio::sync::{watch, OwnedSemaphorePermit, Semaphore};
+use tokio_tungstenite::tungstenite;
 
-#[derive(Serialize, Clone)]
+use crate::{
+    binance::{RateLimit, UsedWeight},
+    events::SharedBuffer,
+    filter::{FilterExpr, TickerFields},
+    first_seen::{age_secs, FirstSeen},
+    logging::LogThrottle,
+    market::{LatestTicker, LatestTickers},
+    notify::Notifiers,
+    supervisor::Liveness,
+    symbols::{max_symbols_from_env, SymbolState, DEFAULT_MAX_SYMBOLS},
+    tls::connect_ws,
+    volume::{RollingVolume, DEFAULT_WINDOW},
+};
+
+/// A ticker that passed the filters, as sent to `/websocket` clients.
+#[derive(Serialize, Deserialize, Clone, Debug, JsonSchema)]
 pub struct Signal {
+    /// Exchange symbol as received, e.g. `BTCUSDT`.
     pub symbol: String,
+    /// `symbol` split into base and quote, e.g. `BTC/USDT`; the raw symbol
+    /// when the quote asset isn't recognised.
+    #[serde(default)]
+    pub display_symbol: String,
     pub pct_gain_24h: f64,
     pub quote_vol_usdt: f64,
     pub last_price: f64,
+    pub tier: Tier,
+    /// Exchange event time when the ticker carries one, else when it was
+    /// received.
     pub ts: DateTime<Utc>,
+    /// When the server received the ticker, if `ts` is the exchange's time.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub received_at: Option<DateTime<Utc>>,
+    /// Position in the server's signal sequence, starting at 1 and
+    /// increasing by one per emitted signal across all feeds. A jump tells
+    /// a client it missed signals.
+    #[serde(default)]
+    pub seq: u64,
+    /// `DEPLOYMENT_TAG` of the scanner that emitted the signal, e.g. a
+    /// region or exchange, so an aggregator can tell instances apart.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub tag: Option<String>,
+    /// Seconds since the scanner first observed the symbol, across restarts
+    /// with `FIRST_SEEN_PATH`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub age_secs: Option<u64>,
+    /// Whether the symbol was first observed less than `NEW_TOKEN_AGE_SECS`
+    /// ago; left out when it wasn't.
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub new_token: bool,
+}
+
+impl Signal {
+    /// Columns of [`to_csv_row`](Self::to_csv_row), in order.
+    pub const CSV_HEADER: &'static str = "symbol,pct_gain_24h,quote_vol_usdt,last_price,ts";
+
+    /// The signal as one CSV line without a line break, for spreadsheets;
+    /// `ts` is RFC 3339 in UTC.
+    pub fn to_csv_row(&self) -> String {
+        format!(
+            "{},{},{},{},{}",
+            csv_field(&self.symbol),
+            self.pct_gain_24h,
+            self.quote_vol_usdt,
+            self.last_price,
+            self.ts.to_rfc3339_opts(SecondsFormat::AutoSi, true)
+        )
+    }
+}
+
+/// `value` quoted as CSV needs it to be: only when it holds a separator,
+/// quote or line break, with quotes doubled.
+fn csv_field(value: &str) -> Cow<'_, str> {
+    if value.contains([',', '"', '\n', '\r']) {
+        Cow::Owned(format!("\"{}\"", value.replace('"', "\"\"")))
+    } else {
+        Cow::Borrowed(value)
+    }
+}
+
+/// Quote assets recognised by [`display_symbol`], longest first so the
+/// first matching suffix is also the longest one.
+const QUOTE_ASSETS: [&str; 12] = [
+    "FDUSD", "USDT", "USDC", "BUSD", "TUSD", "EUR", "TRY", "BRL", "DAI", "BTC", "ETH", "BNB",
+];
+
+/// `BTCUSDT` → `BTC/USDT`. The quote is the longest known asset the symbol
+/// ends with, so `USDCUSDT` is `USDC/USDT`, never a guess based on the
+/// start of the symbol. Unknown quotes keep the raw symbol.
+pub fn display_symbol(symbol: &str) -> String {
+    QUOTE_ASSETS
+        .iter()
+        .find_map(|quote| {
+            let base = symbol.strip_suffix(quote)?;
+            (!base.is_empty()).then(|| format!("{base}/{quote}"))
+        })
+        .unwrap_or_else(|| symbol.to_owned())
+}
+
+/// Severity of a signal, derived from its 24-hour gain.
+#[derive(
+    Serialize, Deserialize, Clone, Copy, Debug, Default, PartialEq, Eq, PartialOrd, Ord, JsonSchema,
+)]
+#[serde(rename_all = "lowercase")]
+pub enum Tier {
+    /// Passed the filter (≥ 5 %).
+    #[default]
+    Watch,
+    /// ≥ 10 % gain.
+    Strong,
+    /// ≥ 20 % gain.
+    Extreme,
+}
+
+impl Tier {
+    pub fn from_gain(pct_gain_24h: f64) -> Self {
+        if pct_gain_24h >= 20.0 {
+            Tier::Extreme
+        } else if pct_gain_24h >= 10.0 {
+            Tier::Strong
+        } else {
+            Tier::Watch
+        }
+    }
+}
+
+impl FromStr for Tier {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.trim().to_ascii_lowercase().as_str() {
+            "watch" => Ok(Tier::Watch),
+            "strong" => Ok(Tier::Strong),
+            "extreme" => Ok(Tier::Extreme),
+            other => Err(format!(
+                "unknown tier `{other}` (expected watch|strong|extreme)"
+            )),
+        }
+    }
+}
+
+/// Age under which a symbol is flagged as a new token, unless overridden by
+/// `NEW_TOKEN_AGE_SECS`.
+const DEFAULT_NEW_TOKEN_AGE: Duration = Duration::from_secs(24 * 60 * 60);
+
+/// Thresholds a ticker has to meet to become a [`Signal`].
+#[derive(Debug, Clone, PartialEq)]
+pub struct SignalConfig {
+    /// Minimum 24-hour gain in percent.
+    pub min_pct_gain: f64,
+    /// Minimum 24-hour quote volume in USDT.
+    pub min_quote_vol: f64,
+    /// Minimum last price; filters out penny-fraction tokens whose huge
+    /// percentage moves are mostly noise. `0` disables the check.
+    pub min_last_price: f64,
+    /// Optional custom expression a ticker must additionally satisfy.
+    pub filter: Option<FilterExpr>,
+    /// Where each ticker value is found in an upstream object.
+    pub fields: FieldMap,
+    /// Treat the volume field as per-update trade volume and compare the
+    /// sum over this window against `min_quote_vol` instead.
+    pub rolling_volume: Option<Duration>,
+    /// Parse frames into borrowed [`RawTicker`]s instead of a
+    /// `serde_json::Value` tree. Only applies with Binance's field names.
+    pub borrowed_parsing: bool,
+    /// Symbols per-symbol state such as rolling volume remembers before
+    /// forgetting the least recently seen.
+    pub max_symbols: usize,
+    /// Process at most one ticker frame per interval, dropping the rest,
+    /// for deployments that don't need every update.
+    pub sample_interval: Option<Duration>,
+    /// Consecutive frames a symbol has to pass the thresholds in before it
+    /// is emitted; `1` emits on the first.
+    pub confirmations: u32,
+    /// Periodically log why tickers were rejected, for tuning thresholds.
+    pub diagnostics: bool,
+    /// Stamped on every signal as its `tag`.
+    pub tag: Option<String>,
+    /// Gunzip binary upstream frames that start with the gzip header
+    /// before parsing them; other binary frames are read as UTF-8 text.
+    pub gzip_frames: bool,
+    /// Hold back signals for symbols first observed less than this long ago.
+    pub min_age: Option<Duration>,
+    /// Symbols first observed less than this long ago are flagged as new.
+    pub new_token_age: Duration,
+}
+
+/// JSON keys of the ticker values, so an exchange that names them
+/// differently only needs a new mapping, not a new parser. Values are
+/// expected as decimal strings, as Binance sends them.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FieldMap {
+    pub symbol: String,
+    /// 24-hour gain in percent.
+    pub pct: String,
+    /// 24-hour quote volume.
+    pub volume: String,
+    /// Last price.
+    pub price: String,
+    /// Event time in milliseconds since the epoch, as a number or string;
+    /// optional.
+    pub time: String,
+}
+
+/// Binance's 24-hour ticker keys.
+impl Default for FieldMap {
+    fn default() -> Self {
+        Self {
+            symbol: "s".to_owned(),
+            pct: "P".to_owned(),
+            volume: "q".to_owned(),
+            price: "c".to_owned(),
+            time: "E".to_owned(),
+        }
+    }
+}
+
+impl FieldMap {
+    /// Whether these are Binance's keys, which [`RawTicker`] hard-codes.
+    fn is_binance(&self) -> bool {
+        self.symbol == "s"
+            && self.pct == "P"
+            && self.volume == "q"
+            && self.price == "c"
+            && self.time == "E"
+    }
+}
+
+impl fmt::Display for FieldMap {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "symbol={},pct={},volume={},price={},time={}",
+            self.symbol, self.pct, self.volume, self.price, self.time
+        )
+    }
+}
+
+/// `symbol=s,pct=P,…`; fields left out keep Binance's key.
+impl FromStr for FieldMap {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut map = Self::default();
+        for pair in s.split(',').map(str::trim).filter(|p| !p.is_empty()) {
+            let (field, key) = pair
+                .split_once('=')
+                .map(|(f, k)| (f.trim(), k.trim()))
+                .filter(|(_, k)| !k.is_empty())
+                .ok_or_else(|| format!("expected field=key, got `{pair}`"))?;
+            let slot = match field {
+                "symbol" => &mut map.symbol,
+                "pct" => &mut map.pct,
+                "volume" => &mut map.volume,
+                "price" => &mut map.price,
+                "time" => &mut map.time,
+                other => {
+                    return Err(format!(
+                        "unknown field `{other}` (expected symbol|pct|volume|price|time)"
+                    ))
+                }
+            };
+            *slot = key.to_owned();
+        }
+        Ok(map)
+    }
+}
+
+impl Default for SignalConfig {
+    fn default() -> Self {
+        Self {
+            min_pct_gain: 5.0,
+            min_quote_vol: 1_000_000.0,
+            min_last_price: 0.0,
+            filter: None,
+            fields: FieldMap::default(),
+            rolling_volume: None,
+            borrowed_parsing: false,
+            max_symbols: DEFAULT_MAX_SYMBOLS,
+            sample_interval: None,
+            confirmations: 1,
+            diagnostics: false,
+            tag: None,
+            gzip_frames: false,
+            min_age: None,
+            new_token_age: DEFAULT_NEW_TOKEN_AGE,
+        }
+    }
+}
+
+/// `ROLLING_VOLUME_WINDOW_SECS`; an empty window would never hold any volume.
+fn rolling_window(secs: &str) -> Result<Duration, String> {
+    match secs.trim().parse::<u64>() {
+        Ok(0) => Err(format!(
+            "invalid ROLLING_VOLUME_WINDOW_SECS `{secs}`: must be at least 1"
+        )),
+        Ok(n) => Ok(Duration::from_secs(n)),
+        Err(e) => Err(format!("invalid ROLLING_VOLUME_WINDOW_SECS `{secs}`: {e}")),
+    }
+}
+
+impl SignalConfig {
+    /// Defaults overridden by `MIN_LAST_PRICE`, `SIGNAL_FILTER_EXPR`,
+    /// `TICKER_FIELDS`, `ROLLING_VOLUME` (with `ROLLING_VOLUME_WINDOW_SECS`,
+    /// default a day), `BORROWED_PARSING`, `SYMBOL_STATE_MAX`,
+    /// `FEED_SAMPLE_INTERVAL_MS` (`0` processes every frame),
+    /// `SIGNAL_CONFIRMATIONS`, `FEED_DIAGNOSTICS`, `DEPLOYMENT_TAG`,
+    /// `FEED_GZIP`, `MIN_TOKEN_AGE_SECS` (`0` disables it) and
+    /// `NEW_TOKEN_AGE_SECS` when set. An invalid expression or mapping is an error so
+    /// it fails fast at startup.
+    pub fn from_env() -> Result<Self, String> {
+        let mut config = Self {
+            max_symbols: max_symbols_from_env()?,
+            ..Self::default()
+        };
+        if let Ok(v) = std::env::var("MIN_LAST_PRICE") {
+            config.min_last_price = v
+                .trim()
+                .parse()
+                .map_err(|e| format!("invalid MIN_LAST_PRICE `{v}`: {e}"))?;
+        }
+        if let Ok(expr) = std::env::var("SIGNAL_FILTER_EXPR") {
+            if !expr.trim().is_empty() {
+                let parsed: FilterExpr = expr
+                    .parse()
+                    .map_err(|e| format!("invalid SIGNAL_FILTER_EXPR `{expr}`: {e}"))?;
+                tracing::info!(filter = %parsed, "Custom signal filter enabled");
+                config.filter = Some(parsed);
+            }
+        }
+        if let Ok(v) = std::env::var("TICKER_FIELDS") {
+            config.fields = v
+                .parse()
+                .map_err(|e| format!("invalid TICKER_FIELDS `{v}`: {e}"))?;
+            tracing::info!(fields = %config.fields, "Custom ticker fields");
+        }
+        if let Ok(v) = std::env::var("ROLLING_VOLUME") {
+            match v.trim() {
+                "" | "0" | "false" => {}
+                "1" | "true" => {
+                    let window = match std::env::var("ROLLING_VOLUME_WINDOW_SECS") {
+                        Ok(secs) => rolling_window(&secs)?,
+                        Err(_) => DEFAULT_WINDOW,
+                    };
+                    tracing::info!(?window, "Rolling volume enabled");
+                    config.rolling_volume = Some(window);
+                }
+                other => {
+                    return Err(format!(
+                        "invalid ROLLING_VOLUME `{other}` (expected 1 or 0)"
+                    ))
+                }
+            }
+        }
+        if let Ok(v) = std::env::var("BORROWED_PARSING") {
+            config.borrowed_parsing = match v.trim() {
+                "" | "0" | "false" => false,
+                "1" | "true" => true,
+                other => {
+                    return Err(format!(
+                        "invalid BORROWED_PARSING `{other}` (expected 1 or 0)"
+                    ))
+                }
+            };
+            if config.borrowed_parsing && !config.fields.is_binance() {
+                tracing::warn!("BORROWED_PARSING needs Binance's ticker fields; ignored");
+            }
+        }
+        if let Ok(v) = std::env::var("FEED_SAMPLE_INTERVAL_MS") {
+            let ms: u64 = v
+                .trim()
+                .parse()
+                .map_err(|e| format!("invalid FEED_SAMPLE_INTERVAL_MS `{v}`: {e}"))?;
+            config.sample_interval = (ms > 0).then(|| Duration::from_millis(ms));
+            if let Some(interval) = config.sample_interval {
+                tracing::info!(?interval, "Feed sampling enabled");
+            }
+        }
+        if let Ok(v) = std::env::var("SIGNAL_CONFIRMATIONS") {
+            config.confirmations = match v.trim().parse() {
+                Ok(0) => return Err("SIGNAL_CONFIRMATIONS must be at least 1".to_owned()),
+                Ok(n) => n,
+                Err(e) => return Err(format!("invalid SIGNAL_CONFIRMATIONS `{v}`: {e}")),
+            };
+            if config.confirmations > 1 {
+                tracing::info!(
+                    confirmations = config.confirmations,
+                    "Signal confirmation enabled"
+                );
+            }
+        }
+        if let Ok(v) = std::env::var("FEED_DIAGNOSTICS") {
+            config.diagnostics = match v.trim() {
+                "" | "0" | "false" => false,
+                "1" | "true" => true,
+                other => {
+                    return Err(format!(
+                        "invalid FEED_DIAGNOSTICS `{other}` (expected 1 or 0)"
+                    ))
+                }
+            };
+        }
+        if let Ok(v) = std::env::var("DEPLOYMENT_TAG") {
+            config.tag = Some(v.trim().to_owned()).filter(|tag| !tag.is_empty());
+        }
+        if let Ok(v) = std::env::var("FEED_GZIP") {
+            config.gzip_frames = match v.trim() {
+                "" | "0" | "false" => false,
+                "1" | "true" => true,
+                other => return Err(format!("invalid FEED_GZIP `{other}` (expected 1 or 0)")),
+            };
+        }
+        if let Ok(v) = std::env::var("MIN_TOKEN_AGE_SECS") {
+            let secs: u64 = v
+                .trim()
+                .parse()
+                .map_err(|e| format!("invalid MIN_TOKEN_AGE_SECS `{v}`: {e}"))?;
+            config.min_age = (secs > 0).then(|| Duration::from_secs(secs));
+            if let Some(min_age) = config.min_age {
+                tracing::info!(?min_age, "Signals for new tokens held back");
+            }
+        }
+        if let Ok(v) = std::env::var("NEW_TOKEN_AGE_SECS") {
+            config.new_token_age = Duration::from_secs(
+                v.trim()
+                    .parse()
+                    .map_err(|e| format!("invalid NEW_TOKEN_AGE_SECS `{v}`: {e}"))?,
+            );
+        }
+        Ok(config)
+    }
+}
+
+/// An upstream ticker stream. Both speak Binance's 24-hour ticker array
+/// format, so the same parser handles either.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Feed {
+    Binance,
+    Raydium,
+}
+
+impl Feed {
+    /// WebSocket URL, overridable with `BINANCE_WS_URL` / `RAYDIUM_WS_URL`.
+    pub(crate) fn url(self) -> String {
+        let (var, default) = match self {
+            Feed::Binance => (
+                "BINANCE_WS_URL",
+                "wss://stream.binance.com:9443/ws/!ticker@arr",
+            ),
+            Feed::Raydium => ("RAYDIUM_WS_URL", "wss://api.raydium.io/ws"),
+        };
+        std::env::var(var).unwrap_or_else(|_| default.to_owned())
+    }
+}
+
+impl fmt::Display for Feed {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            Feed::Binance => "Binance",
+            Feed::Raydium => "Raydium",
+        })
+    }
+}
+
+/// Which feeds the server runs, from `FEED=binance|raydium|both`.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum FeedSelection {
+    Binance,
+    #[default]
+    Raydium,
+    Both,
+}
+
+impl FeedSelection {
+    /// `FEED`, defaulting to the Raydium feed alone.
+    pub fn from_env() -> Result<Self, String> {
+        match std::env::var("FEED") {
+            Ok(v) if !v.trim().is_empty() => v.parse(),
+            _ => Ok(Self::default()),
+        }
+    }
+
+    /// The feeds to spawn. With [`FeedSelection::Both`] they all write into
+    /// the same channel and buffer.
+    pub fn feeds(self) -> Vec<Feed> {
+        match self {
+            FeedSelection::Binance => vec![Feed::Binance],
+            FeedSelection::Raydium => vec![Feed::Raydium],
+            FeedSelection::Both => vec![Feed::Binance, Feed::Raydium],
+        }
+    }
+}
+
+impl FromStr for FeedSelection {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.trim().to_ascii_lowercase().as_str() {
+            "binance" => Ok(FeedSelection::Binance),
+            "raydium" => Ok(FeedSelection::Raydium),
+            "both" => Ok(FeedSelection::Both),
+            other => Err(format!(
+                "unknown FEED `{other}` (expected binance|raydium|both)"
+            )),
+        }
+    }
+}
+
+/// The values of one upstream ticker, however the frame was parsed.
+/// Values that are missing or not strings are `None`.
+struct TickerInput<'a> {
+    symbol: Option<&'a str>,
+    pct: Option<&'a str>,
+    volume: Option<&'a str>,
+    price: Option<&'a str>,
+    event_time: Option<DateTime<Utc>>,
+}
+
+impl<'a> TickerInput<'a> {
+    fn from_value(obj: &'a serde_json::Value, fields: &FieldMap) -> Self {
+        Self {
+            symbol: obj[&fields.symbol].as_str(),
+            pct: obj[&fields.pct].as_str(),
+            volume: obj[&fields.volume].as_str(),
+            price: obj[&fields.price].as_str(),
+            event_time: event_time(&obj[&fields.time]),
+        }
+    }
+}
+
+/// A Binance ticker borrowing its strings from the frame, so a frame of
+/// thousands of tickers costs one `Vec` instead of a map per ticker.
+/// Anything this can't represent exactly, such as a numeric `P` or a
+/// string `E`, fails to deserialize and the frame is re-parsed as a
+/// `serde_json::Value`.
+#[derive(Debug, Deserialize)]
+struct RawTicker<'a> {
+    #[serde(rename = "s", borrow)]
+    symbol: Option<Cow<'a, str>>,
+    #[serde(rename = "P", borrow)]
+    pct: Option<Cow<'a, str>>,
+    #[serde(rename = "q", borrow)]
+    volume: Option<Cow<'a, str>>,
+    #[serde(rename = "c", borrow)]
+    price: Option<Cow<'a, str>>,
+    #[serde(rename = "E")]
+    time: Option<i64>,
+}
+
+impl RawTicker<'_> {
+    fn input(&self) -> TickerInput<'_> {
+        TickerInput {
+            symbol: self.symbol.as_deref(),
+            pct: self.pct.as_deref(),
+            volume: self.volume.as_deref(),
+            price: self.price.as_deref(),
+            event_time: self.time.and_then(DateTime::from_timestamp_millis),
+        }
+    }
+}
+
+/// What a feed remembers about each symbol across frames and reconnects.
+#[derive(Debug)]
+pub(crate) struct FeedState {
+    rolling: RollingVolume,
+    /// Frames in a row each symbol has passed the thresholds in.
+    passed: SymbolState<u32>,
+    /// Tickers evaluated since the last [`LatestTickers::update`].
+    seen: Vec<LatestTicker>,
+    /// Outcomes since diagnostics were last logged.
+    tally: RejectionTally,
+}
+
+/// Why a ticker didn't become a signal.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Rejection {
+    BelowGain,
+    BelowVolume,
+    BelowPrice,
+    /// `SIGNAL_FILTER_EXPR` didn't match.
+    Filtered,
+    /// Passed, but not yet in `SIGNAL_CONFIRMATIONS` frames in a row.
+    Unconfirmed,
+    /// Passed, but first observed less than `MIN_TOKEN_AGE_SECS` ago.
+    TooNew,
+}
+
+/// How often `FEED_DIAGNOSTICS` logs its tally.
+const DIAGNOSTICS_INTERVAL: Duration = Duration::from_secs(60);
+
+/// Frames, signals and rejections by reason, for `FEED_DIAGNOSTICS`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct RejectionTally {
+    since: Instant,
+    frames: u64,
+    signals: u64,
+    below_gain: u64,
+    below_volume: u64,
+    below_price: u64,
+    filtered: u64,
+    unconfirmed: u64,
+    too_new: u64,
+}
+
+impl Default for RejectionTally {
+    fn default() -> Self {
+        Self {
+            since: Instant::now(),
+            frames: 0,
+            signals: 0,
+            below_gain: 0,
+            below_volume: 0,
+            below_price: 0,
+            filtered: 0,
+            unconfirmed: 0,
+            too_new: 0,
+        }
+    }
+}
+
+impl RejectionTally {
+    fn reject(&mut self, reason: Rejection) {
+        let count = match reason {
+            Rejection::BelowGain => &mut self.below_gain,
+            Rejection::BelowVolume => &mut self.below_volume,
+            Rejection::BelowPrice => &mut self.below_price,
+            Rejection::Filtered => &mut self.filtered,
+            Rejection::Unconfirmed => &mut self.unconfirmed,
+            Rejection::TooNew => &mut self.too_new,
+        };
+        *count += 1;
+    }
+
+    /// Rejections by reason, named as the diagnostics log them.
+    fn by_reason(&self) -> [(&'static str, u64); 6] {
+        [
+            ("below_gain", self.below_gain),
+            ("below_volume", self.below_volume),
+            ("below_price", self.below_price),
+            ("filtered", self.filtered),
+            ("unconfirmed", self.unconfirmed),
+            ("too_new", self.too_new),
+        ]
+    }
+
+    /// Log the tally and start a new one once [`DIAGNOSTICS_INTERVAL`] has
+    /// passed since it started.
+    fn log_if_due(&mut self, now: Instant) {
+        if now.saturating_duration_since(self.since) < DIAGNOSTICS_INTERVAL {
+            return;
+        }
+        tracing::info!(
+            frames = self.frames,
+            signals = self.signals,
+            below_gain = self.below_gain,
+            below_volume = self.below_volume,
+            below_price = self.below_price,
+            filtered = self.filtered,
+            unconfirmed = self.unconfirmed,
+            too_new = self.too_new,
+            "Feed diagnostics for the last {:?}",
+            now.saturating_duration_since(self.since)
+        );
+        *self = Self {
+            since: now,
+            ..Self::default()
+        };
+    }
+}
+
+impl FeedState {
+    pub(crate) fn new(config: &SignalConfig) -> Self {
+        let window = config.rolling_volume.unwrap_or(DEFAULT_WINDOW);
+        Self {
+            rolling: RollingVolume::new(window).with_max_symbols(config.max_symbols),
+            passed: SymbolState::new(config.max_symbols),
+            seen: Vec::new(),
+            tally: RejectionTally::default(),
+        }
+    }
+
+    /// Evaluate one ticker frame with the current `config` and deliver its
+    /// signals to `sinks`, returning how many there were. Every ticker in
+    /// the frame counts as observed in `sinks.first_seen`, and each signal
+    /// is stamped with its symbol's age.
+    pub(crate) fn process_frame(
+        &mut self,
+        txt: &str,
+        config: &RwLock<SignalConfig>,
+        sinks: &FeedSinks,
+    ) -> Result<usize, Box<dyn Error + Send + Sync>> {
+        let (signals, diagnostics, min_age, new_token_age) = {
+            let config = config.read().unwrap();
+            let signals = extract_signals(txt, &config, Some(&mut *self))?;
+            (
+                signals,
+                config.diagnostics,
+                config.min_age,
+                config.new_token_age,
+            )
+        };
+        let now = Utc::now().timestamp();
+        for ticker in &self.seen {
+            sinks.first_seen.observe(&ticker.symbol, now);
+        }
+        sinks.tickers.update(self.seen.drain(..));
+        let mut aged = Vec::with_capacity(signals.len());
+        for mut sig in signals {
+            let age = age_secs(sinks.first_seen.observe(&sig.symbol, now));
+            sig.age_secs = Some(age);
+            sig.new_token = age < new_token_age.as_secs();
+            if min_age.is_some_and(|min| age < min.as_secs()) {
+                self.tally.reject(Rejection::TooNew);
+                continue;
+            }
+            aged.push(sig);
+        }
+        let signals = aged;
+        self.tally.frames += 1;
+        self.tally.signals += signals.len() as u64;
+        if diagnostics {
+            self.tally.log_if_due(Instant::now());
+        }
+        let delivered = signals.len();
+        for sig in signals {
+            sinks.deliver(sig)?;
+        }
+        Ok(delivered)
+    }
+
+    /// The signals in `txt` under `config`, tallied but not delivered, as
+    /// `validate-config` previews them.
+    pub(crate) fn evaluate_frame(
+        &mut self,
+        txt: &str,
+        config: &SignalConfig,
+    ) -> Result<Vec<Signal>, Box<dyn Error + Send + Sync>> {
+        extract_signals(txt, config, Some(self))
+    }
+
+    /// Tickers rejected so far, by reason.
+    pub(crate) fn rejections(&self) -> [(&'static str, u64); 6] {
+        self.tally.by_reason()
+    }
+
+    /// Count a frame in which `symbol` did or didn't pass and return whether
+    /// it has now passed in `needed` frames in a row.
+    fn confirm(&mut self, symbol: &str, passed: bool, needed: u32) -> bool {
+        if !passed {
+            if let Some(count) = self.passed.get_mut(symbol) {
+                *count = 0;
+            }
+            return false;
+        }
+        let count = self.passed.get_or_insert_with(symbol, || 0);
+        *count = count.saturating_add(1);
+        *count >= needed
+    }
+}
+
+/// Turn a single ticker object into a [`Signal`] if it passes `config`.
+/// With `state`, rolling volume and confirmations apply as configured and
+/// the ticker is added to its `seen` ones and any rejection to its tally.
+fn evaluate_ticker(
+    obj: &serde_json::Value,
+    config: &SignalConfig,
+    state: Option<&mut FeedState>,
+) -> Result<Option<Signal>, Box<dyn Error + Send + Sync>> {
+    evaluate_input(TickerInput::from_value(obj, &config.fields), config, state)
+}
+
+fn evaluate_input(
+    ticker: TickerInput<'_>,
+    config: &SignalConfig,
+    state: Option<&mut FeedState>,
+) -> Result<Option<Signal>, Box<dyn Error + Send + Sync>> {
+    let Some(state) = state else {
+        return Ok(evaluate_thresholds(ticker, config, None, None)?.ok());
+    };
+    let (symbol, event_time) = (ticker.symbol, ticker.event_time);
+    let rolling = config
+        .rolling_volume
+        .is_some()
+        .then_some(&mut state.rolling);
+    let mut observed = None;
+    let mut verdict = evaluate_thresholds(ticker, config, rolling, Some(&mut observed))?;
+    if let (Some(symbol), true) = (symbol, config.confirmations > 1) {
+        let confirmed = state.confirm(symbol, verdict.is_ok(), config.confirmations);
+        if verdict.is_ok() && !confirmed {
+            verdict = Err(Rejection::Unconfirmed);
+        }
+    }
+    if let Err(reason) = verdict {
+        state.tally.reject(reason);
+    }
+    if let (Some(symbol), Some(observed)) = (symbol, observed) {
+        state.seen.push(LatestTicker {
+            symbol: symbol.to_owned(),
+            pct_gain_24h: observed.pct,
+            quote_vol_usdt: observed.vol,
+            last_price: observed.price,
+            signalling: verdict.is_ok(),
+            ts: event_time.unwrap_or_else(Utc::now),
+        });
+    }
+    Ok(verdict.ok())
+}
+
+/// A ticker's values as parsed, whether or not it passed.
+struct Observed {
+    pct: f64,
+    vol: f64,
+    price: Option<f64>,
+}
+
+/// The [`Signal`] for `ticker` if it passes the thresholds in `config`,
+/// else the first one it fails. With `rolling`, the ticker's volume is added
+/// to it first and the window sum is what has to pass. The parsed values go
+/// to `observed`, if given.
+fn evaluate_thresholds(
+    ticker: TickerInput<'_>,
+    config: &SignalConfig,
+    rolling: Option<&mut RollingVolume>,
+    observed: Option<&mut Option<Observed>>,
+) -> Result<Result<Signal, Rejection>, Box<dyn Error + Send + Sync>> {
+    let fields = &config.fields;
+    let symbol = || {
+        ticker
+            .symbol
+            .ok_or_else(|| format!("ticker without a `{}` symbol", fields.symbol))
+    };
+    let pct: f64 = ticker.pct.unwrap_or("0").parse()?;
+    let mut vol: f64 = ticker.volume.unwrap_or("0").parse()?;
+    if let Some(rolling) = rolling {
+        vol = rolling.record(symbol()?, vol, Instant::now());
+    }
+    if pct < config.min_pct_gain || vol < config.min_quote_vol {
+        if let Some(observed) = observed {
+            // Only a ticker that could pass has its price checked.
+            let price = ticker.price.and_then(|p| p.parse().ok());
+            *observed = Some(Observed { pct, vol, price });
+        }
+        return Ok(Err(if pct < config.min_pct_gain {
+            Rejection::BelowGain
+        } else {
+            Rejection::BelowVolume
+        }));
+    }
+
+    let last_price: f64 = ticker.price.unwrap_or("0").parse()?;
+    if let Some(observed) = observed {
+        *observed = Some(Observed {
+            pct,
+            vol,
+            price: ticker.price.map(|_| last_price),
+        });
+    }
+    if last_price < config.min_last_price {
+        return Ok(Err(Rejection::BelowPrice));
+    }
+
+    let symbol = symbol()?;
+    if let Some(filter) = &config.filter {
+        let fields = TickerFields {
+            symbol,
+            pct_gain_24h: pct,
+            quote_vol_usdt: vol,
+            last_price,
+        };
+        if !filter.matches(&fields) {
+            return Ok(Err(Rejection::Filtered));
+        }
+    }
+
+    let received_at = Utc::now();
+    let event_time = ticker.event_time;
+    Ok(Ok(Signal {
+        symbol: symbol.to_owned(),
+        display_symbol: display_symbol(symbol),
+        pct_gain_24h: pct,
+        quote_vol_usdt: vol,
+        last_price,
+        tier: Tier::from_gain(pct),
+        ts: event_time.unwrap_or(received_at),
+        received_at: event_time.map(|_| received_at),
+        seq: 0,
+        tag: config.tag.clone(),
+        age_secs: None,
+        new_token: false,
+    }))
+}
+
+/// An epoch-milliseconds timestamp, as Binance's `E`, given as a number or
+/// a string.
+fn event_time(value: &serde_json::Value) -> Option<DateTime<Utc>> {
+    let ms = match value {
+        serde_json::Value::Number(n) => n.as_i64()?,
+        serde_json::Value::String(s) => s.trim().parse().ok()?,
+        _ => return None,
+    };
+    DateTime::from_timestamp_millis(ms)
 }
 
 /// Parse incoming JSON text into a list of [`Signal`]s.
 ///
-/// The function filters entries where the 24-hour percentage gain is below 5 % or
-/// the quote volume is below $1 M. Any valid signals are returned for further
-/// processing or broadcasting.
-fn extract_signals_from_text(txt: &str) -> Result<Vec<Signal>, Box<dyn Error + Send + Sync>> {
+/// The function filters entries where the 24-hour percentage gain, the quote
+/// volume or the last price is below the thresholds in `config` (by default
+/// 5 %, $1 M and no price floor). Any valid signals are returned for further
+/// processing or broadcasting. With `state`, volume is accumulated there
+/// when rolling volume is on, and a symbol is only returned once it has
+/// passed in `config.confirmations` frames in a row.
+///
+/// With `config.borrowed_parsing` and Binance's field names, the frame is
+/// first parsed into [`RawTicker`]s; a frame that doesn't fit them falls
+/// back to the `Value` path, so both give the same signals.
+fn extract_signals(
+    txt: &str,
+    config: &SignalConfig,
+    mut state: Option<&mut FeedState>,
+) -> Result<Vec<Signal>, Box<dyn Error + Send + Sync>> {
+    if config.borrowed_parsing && config.fields.is_binance() {
+        // Parse the whole frame before evaluating any of it, so falling back
+        // never records rolling volume or confirmations twice.
+        if let Ok(tickers) = serde_json::from_str::<Vec<RawTicker<'_>>>(txt) {
+            let mut signals = Vec::new();
+            for ticker in &tickers {
+                if let Some(sig) = evaluate_input(ticker.input(), config, state.as_deref_mut())? {
+                    signals.push(sig);
+                }
+            }
+            return Ok(signals);
+        }
+    }
+
     let parsed: serde_json::Value = serde_json::from_str(txt)?;
     let mut signals = Vec::new();
 
     if let Some(arr) = parsed.as_array() {
         for obj in arr {
-            let pct: f64 = obj["P"].as_str().unwrap_or("0").parse()?;
-            let vol: f64 = obj["q"].as_str().unwrap_or("0").parse()?;
-            if pct >= 5.0 && vol >= 1_000_000.0 {
-                let sig = Signal {
-                    symbol: obj["s"].as_str().unwrap().to_owned(),
-                    pct_gain_24h: pct,
-                    quote_vol_usdt: vol,
-                    last_price: obj["c"].as_str().unwrap_or("0").parse()?,
-                    ts: Utc::now(),
-                };
+            if let Some(sig) = evaluate_ticker(obj, config, state.as_deref_mut())? {
                 signals.push(sig);
             }
         }
@@ -45,70 +964,453 @@ fn extract_signals_from_text(txt: &str) -> Result<Vec<Signal>, Box<dyn Error + S
     Ok(signals)
 }
 
-/// Connect to the Raydium WebSocket feed and forward any valid signals to
-/// connected WebSocket clients via the provided watch channel.
+/// The signals in one upstream ticker frame under `config`, without
+/// per-symbol state: no rolling volume and no confirmations.
+pub fn extract_signals_from_text(
+    txt: &str,
+    config: &SignalConfig,
+) -> Result<Vec<Signal>, Box<dyn Error + Send + Sync>> {
+    extract_signals(txt, config, None)
+}
+
+/// Back-off between reconnects while a feed is still expected to come back.
+const RETRY_DELAYS_SECS: [u64; 4] = [2, 4, 8, 16];
+
+/// Consecutive attempts without a single frame after which a feed is
+/// treated as down, e.g. because its URL is a placeholder.
+const DEGRADE_AFTER: u32 = 5;
+
+/// Reconnect interval once a feed is degraded.
+const DEGRADED_RETRY: Duration = Duration::from_secs(60);
+
+/// How often a feed repeats the same reconnect warning, with a count of
+/// how many it held back.
+const RECONNECT_LOG_INTERVAL: Duration = Duration::from_secs(60);
+
+/// Default for `WS_MAX_CONNECTION_SECS`: an hour short of the 24 hours
+/// after which Binance drops a connection.
+const DEFAULT_MAX_CONNECTION: Duration = Duration::from_secs(23 * 60 * 60);
+
+/// How long a feed keeps one connection before reconnecting on its own,
+/// from `WS_MAX_CONNECTION_SECS` (default 23 h); `0` keeps it until the
+/// upstream drops it.
+pub fn max_connection_from_env() -> Result<Option<Duration>, String> {
+    let Ok(v) = std::env::var("WS_MAX_CONNECTION_SECS") else {
+        return Ok(Some(DEFAULT_MAX_CONNECTION));
+    };
+    let secs: u64 = v
+        .trim()
+        .parse()
+        .map_err(|e| format!("invalid WS_MAX_CONNECTION_SECS `{v}`: {e}"))?;
+    Ok((secs > 0).then(|| Duration::from_secs(secs)))
+}
+
+/// How a connection ended without an error.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Ended {
+    /// The upstream closed it.
+    Closed,
+    /// It reached its maximum age and we closed it.
+    Expired,
+}
+
+/// How loudly to report a failed attempt.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum FailureLog {
+    /// Log it; the feed is still expected to recover soon.
+    Loud,
+    /// The feed just crossed [`DEGRADE_AFTER`]: say so, once.
+    Degraded,
+    /// Already reported as degraded; keep it out of the logs.
+    Quiet,
+}
+
+/// Tracks fruitless connection attempts so a feed that never delivers
+/// anything slows down and stops flooding the logs.
+#[derive(Debug, Default)]
+struct FeedHealth {
+    fruitless: u32,
+}
+
+impl FeedHealth {
+    /// Record one connection attempt, `productive` if it delivered at least
+    /// one frame, and return the delay before the next one and how loudly
+    /// to report it.
+    fn record(&mut self, productive: bool) -> (Duration, FailureLog) {
+        if productive {
+            self.fruitless = 0;
+            return (Duration::from_secs(RETRY_DELAYS_SECS[0]), FailureLog::Loud);
+        }
+        self.fruitless += 1;
+        match self.fruitless {
+            n if n < DEGRADE_AFTER => {
+                let delay = RETRY_DELAYS_SECS[(n as usize - 1).min(RETRY_DELAYS_SECS.len() - 1)];
+                (Duration::from_secs(delay), FailureLog::Loud)
+            }
+            DEGRADE_AFTER => (DEGRADED_RETRY, FailureLog::Degraded),
+            _ => (DEGRADED_RETRY, FailureLog::Quiet),
+        }
+    }
+}
+
+/// Notifiers shared by every running feed, so cooldowns apply across them.
+pub type SharedNotifiers = Arc<Mutex<Notifiers>>;
+
+/// How many feeds may be connecting at once, shared by all of them. Feeds
+/// (re)connecting together then take turns with the handshake instead of
+/// opening every connection in one burst; the default has no limit.
+#[derive(Debug, Clone, Default)]
+pub struct ConnectLimit(Option<Arc<Semaphore>>);
+
+impl ConnectLimit {
+    /// At most `max` handshakes at a time; `None` or `0` is no limit.
+    pub fn new(max: Option<usize>) -> Self {
+        Self(max.filter(|&n| n > 0).map(|n| Arc::new(Semaphore::new(n))))
+    }
+
+    /// Wait for a turn to connect, held until the permit is dropped.
+    async fn acquire(&self) -> Option<OwnedSemaphorePermit> {
+        let semaphore = self.0.clone()?;
+        semaphore.acquire_owned().await.ok()
+    }
+}
+
+/// `MAX_CONCURRENT_FEED_CONNECTS`; unset or `0` is no limit.
+pub fn max_concurrent_connects_from_env() -> Result<Option<usize>, String> {
+    let Ok(v) = std::env::var("MAX_CONCURRENT_FEED_CONNECTS") else {
+        return Ok(None);
+    };
+    let max: usize = v
+        .trim()
+        .parse()
+        .map_err(|e| format!("invalid MAX_CONCURRENT_FEED_CONNECTS `{v}`: {e}"))?;
+    Ok((max > 0).then_some(max))
+}
+
+/// Where the feeds deliver what they parse, shared by all of them.
+#[derive(Clone)]
+pub struct FeedSinks {
+    /// Latest signal frame, for `/websocket` clients.
+    pub tx: watch::Sender<Option<Message>>,
+    /// Recent signals, for `/events` clients that resume.
+    pub buffer: SharedBuffer,
+    /// Out-of-band alerting.
+    pub notifiers: SharedNotifiers,
+    /// Every symbol's latest ticker, for `/symbols`.
+    pub tickers: LatestTickers,
+    /// When each symbol was first observed, for signals' `age_secs`.
+    pub first_seen: FirstSeen,
+    /// Turns to connect upstream, see `MAX_CONCURRENT_FEED_CONNECTS`.
+    pub connect_limit: ConnectLimit,
+    /// Binance's last reported request weight, for `/stats`.
+    pub used_weight: UsedWeight,
+}
+
+impl FeedSinks {
+    /// Number `sig`, buffer it, hand it to the notifiers and broadcast it.
+    pub fn deliver(&self, mut sig: Signal) -> serde_json::Result<()> {
+        let json = self.buffer.lock().unwrap().push_signal(&mut sig)?;
+        self.notifiers.lock().unwrap().notify(&sig);
+        let _ = self.tx.send(Some(Message::Text(json)));
+        Ok(())
+    }
+}
+
+/// Thresholds shared by the feeds and `POST /admin/thresholds`; read once
+/// per frame, so an update applies from the next frame on.
+pub type SharedConfig = Arc<RwLock<SignalConfig>>;
+
+/// Connect to `feed` and forward any valid signals to connected WebSocket
+/// clients via the `sinks` watch channel. Each signal is recorded in the
+/// sinks' `buffer` first so `/events` clients can resume, and is also handed
+/// to their `notifiers` for out-of-band alerting; every ticker updates
+/// `tickers`, signal or not. Tickers are filtered with the
+/// current `config`. `accept_invalid_certs` is for self-hosted feeds with
+/// self-signed certificates. `liveness` beats on every connection attempt
+/// and frame, for the supervisor.
+///
+/// A connection older than `max_connection` is closed and replaced right
+/// away, so an upstream limit such as Binance's 24 hours never cuts it off
+/// mid-stream.
+///
+/// After [`DEGRADE_AFTER`] attempts in a row without a single frame the
+/// feed logs one warning and then retries every minute at debug level,
+/// until a connection delivers data again. Before that, the same failure
+/// is warned about at most once per [`RECONNECT_LOG_INTERVAL`], with how
+/// often it recurred.
 ///
 /// *Fix:* `url` is now borrowed (`&str`) on each call so it is **not moved**
-/// into `connect_async`, eliminating the `E0382` compile error.
-pub async fn spawn_raydium_feed(tx: watch::Sender<Message>) {
-    // Default Raydium public feed. Can be overridden by the RAYDIUM_WS_URL
-    // environment variable if needed.
-    let url =
-        std::env::var("RAYDIUM_WS_URL").unwrap_or_else(|_| "wss://api.raydium.io/ws".to_string());
+/// into `connect_ws`, eliminating the `E0382` compile error.
+pub async fn spawn_ws_feed(
+    feed: Feed,
+    sinks: FeedSinks,
+    config: SharedConfig,
+    accept_invalid_certs: bool,
+    max_connection: Option<Duration>,
+    liveness: Liveness,
+) {
+    run_ws_feed(
+        feed,
+        &feed.url(),
+        sinks,
+        config,
+        accept_invalid_certs,
+        max_connection,
+        liveness,
+    )
+    .await
+}
+
+/// [`spawn_ws_feed`] on an explicit `url`.
+pub(crate) async fn run_ws_feed(
+    feed: Feed,
+    url: &str,
+    sinks: FeedSinks,
+    config: SharedConfig,
+    accept_invalid_certs: bool,
+    max_connection: Option<Duration>,
+    liveness: Liveness,
+) {
+    let mut health = FeedHealth::default();
+    let mut throttle = LogThrottle::new(RECONNECT_LOG_INTERVAL);
+    // Kept across reconnects, so a dropped connection doesn't reset volume
+    // or confirmations.
+    let mut state = FeedState::new(&config.read().unwrap());
 
     loop {
-        match connect_async(url.as_str()).await {
-            Ok((ws, _)) => {
-                tracing::info!("\u{1f7e2} Connected to Raydium stream");
-                if let Err(e) = handle_socket(ws, &tx).await {
-                    tracing::warn!("Raydium WS error: {:?}", e);
+        liveness.beat();
+        let mut progress = Progress {
+            frames: 0,
+            liveness: &liveness,
+        };
+        // Binance's rate-limit headers, from the handshake response.
+        let mut rate_limit = None;
+        let turn = sinks.connect_limit.acquire().await;
+        let connected = connect_ws(url, accept_invalid_certs).await;
+        drop(turn);
+        let failure = match connected {
+            Ok((ws, response)) => {
+                if feed == Feed::Binance {
+                    let limit =
+                        RateLimit::from_headers(response.status().as_u16(), response.headers());
+                    sinks.used_weight.record(&limit);
+                    rate_limit = Some(limit);
                 }
+                tracing::info!("\u{1f7e2} Connected to {feed} stream");
+                let outcome = handle_socket(
+                    ws,
+                    &sinks,
+                    &config,
+                    &mut state,
+                    &mut progress,
+                    max_connection,
+                )
+                .await;
+                match outcome {
+                    Ok(Ended::Expired) => {
+                        health.record(progress.frames > 0);
+                        tracing::info!(
+                            "{feed} connection reached {max_connection:?}; reconnecting"
+                        );
+                        continue;
+                    }
+                    Ok(Ended::Closed) => format!("{feed} stream closed"),
+                    Err(e) => format!("{feed} WS error: {e:?}"),
+                }
+            }
+            Err(tungstenite::Error::Http(response)) if feed == Feed::Binance => {
+                let status = response.status();
+                let limit = RateLimit::from_headers(status.as_u16(), response.headers());
+                sinks.used_weight.record(&limit);
+                rate_limit = Some(limit);
+                format!("{feed} WS connect refused: HTTP {status}")
+            }
+            Err(e) => format!("{feed} WS connect failed: {e:?}"),
+        };
+
+        let (delay, log) = health.record(progress.frames > 0);
+        let backoff = rate_limit.and_then(|limit| limit.backoff(SystemTime::now()));
+        if let Some(delay) = backoff {
+            if let Some(repeats) = throttle.admit(&failure) {
+                tracing::warn!("{failure}; Binance rate limit, reconnect in {delay:?}{repeats}");
             }
-            Err(e) => tracing::error!("WS connect failed: {:?}", e),
+            wait_alive(delay, &liveness).await;
+            continue;
         }
+        match log {
+            FailureLog::Loud => {
+                if let Some(repeats) = throttle.admit(&failure) {
+                    tracing::warn!("{failure}; reconnect in {delay:?}{repeats}");
+                }
+            }
+            FailureLog::Degraded => tracing::warn!(
+                "{failure}; no data from {url} after {DEGRADE_AFTER} attempts, \
+                 retrying every {delay:?} without further logging"
+            ),
+            FailureLog::Quiet => tracing::debug!("{failure}; reconnect in {delay:?}"),
+        }
+        tokio::time::sleep(delay).await;
+    }
+}
 
-        for delay in [2u64, 4, 8, 16] {
-            tracing::info!("Reconnect in {} s", delay);
-            tokio::time::sleep(Duration::from_secs(delay)).await;
+/// Sleep for `delay`, beating `liveness` so a long but deliberate wait
+/// doesn't look stale to the supervisor.
+async fn wait_alive(delay: Duration, liveness: &Liveness) {
+    let deadline = tokio::time::Instant::now() + delay;
+    while tokio::time::Instant::now() < deadline {
+        liveness.beat();
+        tokio::time::sleep_until(
+            deadline.min(tokio::time::Instant::now() + Duration::from_secs(10)),
+        )
+        .await;
+    }
+}
 
-            // Try a lightweight probe before re-entering the outer loop
-            if connect_async(url.as_str()).await.is_ok() {
-                break;
+/// Frames received on one connection, also reported to the supervisor.
+struct Progress<'a> {
+    frames: u64,
+    liveness: &'a Liveness,
+}
+
+impl Progress<'_> {
+    fn frame(&mut self) {
+        self.frames += 1;
+        self.liveness.beat();
+    }
+}
+
+/// Picks which ticker frames get processed: with an interval, the first
+/// frame of each interval and none of the ones that follow it.
+#[derive(Debug, Default)]
+struct FrameSampler {
+    last: Option<Instant>,
+}
+
+impl FrameSampler {
+    /// Whether the frame arriving at `now` should be processed.
+    fn take(&mut self, interval: Option<Duration>, now: Instant) -> bool {
+        let Some(interval) = interval else {
+            return true;
+        };
+        match self.last {
+            Some(last) if now.saturating_duration_since(last) < interval => false,
+            _ => {
+                self.last = Some(now);
+                true
             }
         }
     }
 }
 
+/// First bytes of a gzip stream.
+const GZIP_MAGIC: [u8; 2] = [0x1f, 0x8b];
+
+/// The text of a binary upstream frame: gunzipped first when `gzip` is set
+/// and `payload` has the gzip header, then decoded as UTF-8.
+fn binary_frame_text(payload: Vec<u8>, gzip: bool) -> Result<String, String> {
+    let payload = if gzip && payload.starts_with(&GZIP_MAGIC) {
+        let mut text = Vec::new();
+        flate2::read::GzDecoder::new(&payload[..])
+            .read_to_end(&mut text)
+            .map_err(|e| format!("invalid gzip payload: {e}"))?;
+        text
+    } else {
+        payload
+    };
+    String::from_utf8(payload).map_err(|e| {
+        if e.as_bytes().starts_with(&GZIP_MAGIC) {
+            "gzip payload; set FEED_GZIP=1 to decompress it".to_owned()
+        } else {
+            format!("not UTF-8 text: {}", e.utf8_error())
+        }
+    })
+}
+
 async fn handle_socket<S>(
     ws: tokio_tungstenite::WebSocketStream<S>,
-    tx: &watch::Sender<Message>,
-) -> Result<(), Box<dyn Error + Send + Sync>>
+    sinks: &FeedSinks,
+    config: &RwLock<SignalConfig>,
+    state: &mut FeedState,
+    progress: &mut Progress<'_>,
+    max_age: Option<Duration>,
+) -> Result<Ended, Box<dyn Error + Send + Sync>>
 where
     S: tokio::io::AsyncRead + tokio::io::AsyncWrite + Unpin,
 {
     let (mut sink, mut stream) = ws.split();
+    let mut sampler = FrameSampler::default();
+    // Binary frames that couldn't be read; only the first is a warning.
+    let mut unreadable = 0u64;
+    let expired = async {
+        match max_age {
+            Some(age) => tokio::time::sleep(age).await,
+            None => std::future::pending().await,
+        }
+    };
+    let mut expired = std::pin::pin!(expired);
 
-    while let Some(Ok(frame)) = stream.next().await {
-        match frame {
-            tungstenite::Message::Text(txt) => {
-                for sig in extract_signals_from_text(&txt)? {
-                    let json = serde_json::to_string(&sig)?;
-                    let _ = tx.send(Message::Text(json));
+    loop {
+        let frame = tokio::select! {
+            frame = stream.next() => frame,
+            _ = &mut expired => {
+                let _ = sink.send(tungstenite::Message::Close(None)).await;
+                return Ok(Ended::Expired);
+            }
+        };
+        let Some(Ok(frame)) = frame else {
+            break;
+        };
+        progress.frame();
+        let txt = match frame {
+            tungstenite::Message::Text(txt) => txt,
+            tungstenite::Message::Binary(payload) => {
+                let gzip = config.read().unwrap().gzip_frames;
+                match binary_frame_text(payload, gzip) {
+                    Ok(txt) => txt,
+                    Err(e) => {
+                        unreadable += 1;
+                        if unreadable == 1 {
+                            tracing::warn!("Skipping unreadable binary frame: {e}");
+                        } else {
+                            tracing::debug!(unreadable, "Skipping unreadable binary frame: {e}");
+                        }
+                        continue;
+                    }
                 }
             }
             tungstenite::Message::Ping(payload) => {
                 // Echo the ping payload back as recommended by the Raydium docs
                 sink.send(tungstenite::Message::Pong(payload)).await?;
+                continue;
+            }
+            tungstenite::Message::Close(frame) => {
+                // An exchange closing on purpose (e.g. Binance's 24 h limit)
+                // says why; reconnect right away instead of waiting for the
+                // stream to end.
+                match frame {
+                    Some(frame) => tracing::info!(
+                        code = u16::from(frame.code),
+                        reason = %frame.reason,
+                        "Upstream closed the connection"
+                    ),
+                    None => tracing::info!("Upstream closed the connection without a reason"),
+                }
+                break;
             }
-            _ => {}
+            _ => continue,
+        };
+        let interval = config.read().unwrap().sample_interval;
+        if sampler.take(interval, Instant::now()) {
+            state.process_frame(&txt, config, sinks)?;
         }
     }
-    Ok(())
+    Ok(Ended::Closed)
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::testing::CapturedLogs;
 
     #[tokio::test]
     async fn test_extract_signals_basic_filtering() {
@@ -117,19 +1419,83 @@ mod tests {
             { "s": "ETHUSDT", "P": "2.0", "q": "900000",  "c": "2000"  }
         ]"#;
 
-        let signals = extract_signals_from_text(json).unwrap();
+        let signals = extract_signals_from_text(json, &SignalConfig::default()).unwrap();
         assert_eq!(signals.len(), 1);
         let sig = &signals[0];
         assert_eq!(sig.symbol, "BTCUSDT");
+        assert_eq!(sig.display_symbol, "BTC/USDT");
         assert!((sig.pct_gain_24h - 5.5).abs() < f64::EPSILON);
         assert!((sig.quote_vol_usdt - 1_500_000.0).abs() < f64::EPSILON);
         assert!((sig.last_price - 30000.0).abs() < f64::EPSILON);
     }
 
+    #[test]
+    fn rolling_window_must_be_a_positive_number_of_seconds() {
+        assert_eq!(rolling_window(" 3600 "), Ok(Duration::from_secs(3600)));
+        for bad in ["0", "soon"] {
+            let err = rolling_window(bad).unwrap_err();
+            assert!(
+                err.starts_with(&format!("invalid ROLLING_VOLUME_WINDOW_SECS `{bad}`")),
+                "{err}"
+            );
+        }
+    }
+
+    #[test]
+    fn display_symbol_splits_base_and_quote() {
+        assert_eq!(display_symbol("BTCUSDT"), "BTC/USDT");
+        assert_eq!(display_symbol("ETHBTC"), "ETH/BTC");
+        assert_eq!(display_symbol("SOLFDUSD"), "SOL/FDUSD");
+        assert_eq!(display_symbol("PEPEEUR"), "PEPE/EUR");
+        // Both USDC and USDT are quotes; only the suffix decides.
+        assert_eq!(display_symbol("USDCUSDT"), "USDC/USDT");
+        assert_eq!(display_symbol("USDTUSDC"), "USDT/USDC");
+        // A bare quote asset or an unknown quote stays as-is.
+        assert_eq!(display_symbol("USDT"), "USDT");
+        assert_eq!(display_symbol("ABCXYZ"), "ABCXYZ");
+    }
+
+    #[test]
+    fn feed_selection_picks_the_feeds_to_spawn() {
+        let feeds = |v: &str| v.parse::<FeedSelection>().map(FeedSelection::feeds);
+        assert_eq!(feeds("binance").unwrap(), [Feed::Binance]);
+        assert_eq!(feeds("Raydium").unwrap(), [Feed::Raydium]);
+        assert_eq!(feeds(" both ").unwrap(), [Feed::Binance, Feed::Raydium]);
+        assert_eq!(FeedSelection::default().feeds(), [Feed::Raydium]);
+        assert!(feeds("kraken")
+            .unwrap_err()
+            .contains("binance|raydium|both"));
+    }
+
+    #[test]
+    fn failing_feed_goes_quiet_after_threshold() {
+        let mut health = FeedHealth::default();
+        let logs: Vec<_> = (0..20).map(|_| health.record(false)).collect();
+
+        let loud = logs.iter().filter(|(_, l)| *l == FailureLog::Loud).count();
+        let degraded = logs
+            .iter()
+            .filter(|(_, l)| *l == FailureLog::Degraded)
+            .count();
+        assert_eq!(loud as u32, DEGRADE_AFTER - 1);
+        assert_eq!(degraded, 1);
+        assert!(logs[DEGRADE_AFTER as usize..]
+            .iter()
+            .all(|&(delay, l)| l == FailureLog::Quiet && delay == DEGRADED_RETRY));
+        assert_eq!(logs[0].0, Duration::from_secs(2));
+
+        // One productive session resets the back-off.
+        assert_eq!(
+            health.record(true),
+            (Duration::from_secs(2), FailureLog::Loud)
+        );
+        assert_eq!(health.record(false).1, FailureLog::Loud);
+    }
+
     #[test]
     fn test_extract_signals_invalid_json() {
         let json = "{ invalid json }";
-        assert!(extract_signals_from_text(json).is_err());
+        assert!(extract_signals_from_text(json, &SignalConfig::default()).is_err());
     }
 
     #[tokio::test]
@@ -139,7 +1505,7 @@ mod tests {
             { "s": "ETHUSDT", "P": "5.0", "q": "1500000", "c": "2000"  }
         ]"#;
 
-        let signals = extract_signals_from_text(json).unwrap();
+        let signals = extract_signals_from_text(json, &SignalConfig::default()).unwrap();
         assert_eq!(signals.len(), 2);
     }
 
@@ -149,7 +1515,7 @@ mod tests {
             { "s": "BTCUSDT", "P": "five", "q": "1500000", "c": "30000" }
         ]"#;
 
-        assert!(extract_signals_from_text(json).is_err());
+        assert!(extract_signals_from_text(json, &SignalConfig::default()).is_err());
     }
 
     #[test]
@@ -158,21 +1524,21 @@ mod tests {
             { "s": "BTCUSDT", "P": 10, "q": 2000000, "c": 30000 }
         ]"#;
 
-        let signals = extract_signals_from_text(json).unwrap();
+        let signals = extract_signals_from_text(json, &SignalConfig::default()).unwrap();
         assert!(signals.is_empty());
     }
 
     #[test]
     fn test_extract_signals_empty_array() {
         let json = "[]";
-        let signals = extract_signals_from_text(json).unwrap();
+        let signals = extract_signals_from_text(json, &SignalConfig::default()).unwrap();
         assert!(signals.is_empty());
     }
 
     #[test]
     fn test_extract_signals_non_array_json_returns_empty() {
         let json = "{}";
-        let signals = extract_signals_from_text(json).unwrap();
+        let signals = extract_signals_from_text(json, &SignalConfig::default()).unwrap();
         assert!(signals.is_empty());
     }
 
@@ -182,7 +1548,7 @@ mod tests {
             { "s": "BTCUSDT", "P": "5.0", "q": "1000000", "c": "100" }
         ]"#;
 
-        let signals = extract_signals_from_text(json).unwrap();
+        let signals = extract_signals_from_text(json, &SignalConfig::default()).unwrap();
         assert_eq!(signals.len(), 1);
         let sig = &signals[0];
         assert_eq!(sig.symbol, "BTCUSDT");
@@ -197,7 +1563,7 @@ mod tests {
             { "s": "BTCUSDT", "P": "-10", "q": "2000000", "c": "30000" }
         ]"#;
 
-        let signals = extract_signals_from_text(json).unwrap();
+        let signals = extract_signals_from_text(json, &SignalConfig::default()).unwrap();
         assert!(signals.is_empty());
     }
 
@@ -207,6 +1573,619 @@ mod tests {
             { "s": "BTCUSDT", "P": "5.0", "q": "1_000_000", "c": "30000" }
         ]"#;
 
-        assert!(extract_signals_from_text(json).is_err());
+        assert!(extract_signals_from_text(json, &SignalConfig::default()).is_err());
+    }
+
+    #[test]
+    fn test_extract_signals_min_last_price() {
+        let json = r#"[
+            { "s": "DUSTUSDT", "P": "50.0", "q": "5000000", "c": "0.00001" },
+            { "s": "EDGEUSDT", "P": "50.0", "q": "5000000", "c": "0.01" },
+            { "s": "BTCUSDT",  "P": "6.0",  "q": "5000000", "c": "30000" }
+        ]"#;
+        let config = SignalConfig {
+            min_last_price: 0.01,
+            ..Default::default()
+        };
+
+        let signals = extract_signals_from_text(json, &config).unwrap();
+        let symbols: Vec<_> = signals.iter().map(|s| s.symbol.as_str()).collect();
+        assert_eq!(symbols, ["EDGEUSDT", "BTCUSDT"]);
+    }
+
+    #[test]
+    fn remapped_fields_parse_another_exchange_format() {
+        let json = r#"[
+            { "pair": "SOLUSDC", "change": "7.5", "quoteVolume": "2500000", "last": "150" },
+            { "pair": "BONKUSDC", "change": "1.0", "quoteVolume": "9000000", "last": "0.00002" }
+        ]"#;
+        let config = SignalConfig {
+            fields: "symbol=pair, pct=change,volume=quoteVolume,price=last"
+                .parse()
+                .unwrap(),
+            ..Default::default()
+        };
+
+        let signals = extract_signals_from_text(json, &config).unwrap();
+        assert_eq!(signals.len(), 1);
+        assert_eq!(signals[0].display_symbol, "SOL/USDC");
+        assert_eq!(signals[0].last_price, 150.0);
+        // Binance's keys are absent, so the default mapping finds nothing.
+        assert!(extract_signals_from_text(json, &SignalConfig::default())
+            .unwrap()
+            .is_empty());
+    }
+
+    #[test]
+    fn rolling_volume_replaces_the_reported_volume() {
+        let json = r#"[{ "s": "BTCUSDT", "P": "8.0", "q": "400000", "c": "30000" }]"#;
+        let config = SignalConfig {
+            rolling_volume: Some(Duration::from_secs(60)),
+            ..Default::default()
+        };
+        let mut state = FeedState::new(&config);
+
+        // $400k per update reaches the $1M floor on the third one.
+        for expected in [0, 0, 1] {
+            let signals = extract_signals(json, &config, Some(&mut state)).unwrap();
+            assert_eq!(signals.len(), expected);
+        }
+        let signals = extract_signals(json, &config, Some(&mut state)).unwrap();
+        assert_eq!(signals[0].quote_vol_usdt, 1_600_000.0);
+    }
+
+    #[tokio::test]
+    async fn close_frame_ends_the_connection_with_its_reason_logged() {
+        use tokio_tungstenite::{
+            tungstenite::protocol::{frame::coding::CloseCode, CloseFrame, Role},
+            WebSocketStream,
+        };
+
+        let (logs, _guard) = CapturedLogs::capture();
+
+        let (client, server) = tokio::io::duplex(1024);
+        let mut upstream = WebSocketStream::from_raw_socket(server, Role::Server, None).await;
+        let ws = WebSocketStream::from_raw_socket(client, Role::Client, None).await;
+        upstream
+            .send(tungstenite::Message::Close(Some(CloseFrame {
+                code: CloseCode::Away,
+                reason: "24h connection limit".into(),
+            })))
+            .await
+            .unwrap();
+        // Keep the upstream open: only the close frame may end the loop.
+        let _upstream = upstream;
+
+        let (tx, _rx) = watch::channel(None);
+        let sinks = FeedSinks {
+            tx,
+            buffer: crate::events::SignalBuffer::shared(1),
+            notifiers: Default::default(),
+            tickers: LatestTickers::default(),
+            first_seen: Default::default(),
+            connect_limit: Default::default(),
+            used_weight: Default::default(),
+        };
+        let config = RwLock::new(SignalConfig::default());
+        let mut state = FeedState::new(&config.read().unwrap());
+        let liveness = Liveness::default();
+        let mut progress = Progress {
+            frames: 0,
+            liveness: &liveness,
+        };
+        let ended = tokio::time::timeout(
+            Duration::from_secs(5),
+            handle_socket(ws, &sinks, &config, &mut state, &mut progress, None),
+        )
+        .await
+        .expect("loop exits on the close frame")
+        .unwrap();
+        assert_eq!(ended, Ended::Closed);
+
+        let out = logs.contents();
+        let line = out
+            .lines()
+            .find(|l| l.contains("Upstream closed the connection"))
+            .unwrap_or_else(|| panic!("no close line in:\n{out}"));
+        assert!(line.contains("code=1001"), "{line}");
+        assert!(line.contains("reason=24h connection limit"), "{line}");
+    }
+
+    #[tokio::test]
+    async fn gzipped_binary_frames_are_decompressed_before_parsing() {
+        use flate2::{write::GzEncoder, Compression};
+        use std::io::Write;
+        use tokio_tungstenite::{tungstenite::protocol::Role, WebSocketStream};
+
+        let frame = |symbol: &str| {
+            format!(r#"[{{ "s": "{symbol}", "P": "12.0", "q": "2000000", "c": "1.5" }}]"#)
+        };
+        let mut gzipped = GzEncoder::new(Vec::new(), Compression::default());
+        gzipped.write_all(frame("GZIPUSDT").as_bytes()).unwrap();
+        let gzipped = gzipped.finish().unwrap();
+
+        let (client, server) = tokio::io::duplex(4096);
+        let mut upstream = WebSocketStream::from_raw_socket(server, Role::Server, None).await;
+        let ws = WebSocketStream::from_raw_socket(client, Role::Client, None).await;
+        for message in [
+            tungstenite::Message::Binary(gzipped),
+            tungstenite::Message::Binary(frame("PLAINUSDT").into_bytes()),
+            tungstenite::Message::Binary(vec![0xff, 0xfe]),
+            tungstenite::Message::Close(None),
+        ] {
+            upstream.send(message).await.unwrap();
+        }
+        let _upstream = upstream;
+
+        let (tx, _rx) = watch::channel(None);
+        let sinks = FeedSinks {
+            tx,
+            buffer: crate::events::SignalBuffer::shared(8),
+            notifiers: Default::default(),
+            tickers: LatestTickers::default(),
+            first_seen: Default::default(),
+            connect_limit: Default::default(),
+            used_weight: Default::default(),
+        };
+        let config = RwLock::new(SignalConfig {
+            gzip_frames: true,
+            ..Default::default()
+        });
+        let mut state = FeedState::new(&config.read().unwrap());
+        let liveness = Liveness::default();
+        let mut progress = Progress {
+            frames: 0,
+            liveness: &liveness,
+        };
+        let ended = tokio::time::timeout(
+            Duration::from_secs(5),
+            handle_socket(ws, &sinks, &config, &mut state, &mut progress, None),
+        )
+        .await
+        .expect("loop exits on the close frame")
+        .unwrap();
+        assert_eq!(ended, Ended::Closed);
+
+        let symbols: Vec<String> = sinks
+            .buffer
+            .lock()
+            .unwrap()
+            .since(0)
+            .iter()
+            .map(|s| serde_json::from_str::<Signal>(&s.json).unwrap().symbol)
+            .collect();
+        assert_eq!(symbols, ["GZIPUSDT", "PLAINUSDT"]);
+    }
+
+    #[test]
+    fn gzip_needs_the_flag() {
+        use flate2::{write::GzEncoder, Compression};
+        use std::io::Write;
+
+        let mut gzipped = GzEncoder::new(Vec::new(), Compression::default());
+        gzipped.write_all(b"[]").unwrap();
+        let gzipped = gzipped.finish().unwrap();
+
+        assert_eq!(binary_frame_text(gzipped.clone(), true).unwrap(), "[]");
+        assert_eq!(
+            binary_frame_text(gzipped, false).unwrap_err(),
+            "gzip payload; set FEED_GZIP=1 to decompress it"
+        );
+        assert!(binary_frame_text(GZIP_MAGIC.to_vec(), true)
+            .unwrap_err()
+            .starts_with("invalid gzip payload"));
+    }
+
+    #[tokio::test]
+    async fn feed_reconnects_once_a_connection_reaches_its_max_age() {
+        use shuttle_axum::axum::{extract::WebSocketUpgrade, routing::get, Router};
+        use std::sync::atomic::{AtomicUsize, Ordering};
+
+        // Accepts connections and keeps them open, never closing or failing.
+        let connections = Arc::new(AtomicUsize::new(0));
+        let counter = connections.clone();
+        let app = Router::new().route(
+            "/ws",
+            get(move |ws: WebSocketUpgrade| {
+                counter.fetch_add(1, Ordering::SeqCst);
+                async move {
+                    ws.on_upgrade(|mut socket| async move {
+                        while let Some(Ok(_)) = socket.recv().await {}
+                    })
+                }
+            }),
+        );
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        tokio::spawn(async move { shuttle_axum::axum::serve(listener, app).await });
+
+        let (tx, _rx) = watch::channel(None);
+        let sinks = FeedSinks {
+            tx,
+            buffer: crate::events::SignalBuffer::shared(1),
+            notifiers: Default::default(),
+            tickers: LatestTickers::default(),
+            first_seen: Default::default(),
+            connect_limit: Default::default(),
+            used_weight: Default::default(),
+        };
+        let feed = tokio::spawn(async move {
+            run_ws_feed(
+                Feed::Binance,
+                &format!("ws://{addr}/ws"),
+                sinks,
+                SharedConfig::default(),
+                false,
+                Some(Duration::from_millis(150)),
+                Liveness::default(),
+            )
+            .await
+        });
+
+        // Without the timer the first connection would last forever; a
+        // failed one would wait seconds before retrying.
+        tokio::time::sleep(Duration::from_millis(700)).await;
+        feed.abort();
+        let made = connections.load(Ordering::SeqCst);
+        assert!(made >= 3, "only {made} connections");
+    }
+
+    #[test]
+    fn rejections_are_tallied_by_reason() {
+        let config = SignalConfig {
+            min_last_price: 0.5,
+            filter: Some(r#"!symbol.starts_with("SCAM")"#.parse().unwrap()),
+            confirmations: 2,
+            ..Default::default()
+        };
+        let mut state = FeedState::new(&config);
+        let json = r#"[
+            { "s": "GAINUSDT", "P": "1.0", "q": "9000000", "c": "1" },
+            { "s": "FLATUSDT", "P": "2.0", "q": "9000000", "c": "1" },
+            { "s": "THINUSDT", "P": "8.0", "q": "1000", "c": "1" },
+            { "s": "PENNYUSDT", "P": "8.0", "q": "9000000", "c": "0.001" },
+            { "s": "SCAMUSDT", "P": "8.0", "q": "9000000", "c": "1" },
+            { "s": "BTCUSDT", "P": "8.0", "q": "9000000", "c": "30000" }
+        ]"#;
+        assert!(extract_signals(json, &config, Some(&mut state))
+            .unwrap()
+            .is_empty());
+        assert_eq!(
+            extract_signals(json, &config, Some(&mut state))
+                .unwrap()
+                .len(),
+            1
+        );
+
+        let tally = state.tally;
+        assert_eq!(tally.below_gain, 4);
+        assert_eq!(tally.below_volume, 2);
+        assert_eq!(tally.below_price, 2);
+        assert_eq!(tally.filtered, 2);
+        // BTCUSDT passed twice but only counted as a signal the second time.
+        assert_eq!(tally.unconfirmed, 1);
+    }
+
+    #[test]
+    fn deployment_tag_is_stamped_on_signals_only_when_set() {
+        let json = r#"[{ "s": "BTCUSDT", "P": "6.0", "q": "2000000", "c": "30000" }]"#;
+        let untagged = &extract_signals_from_text(json, &SignalConfig::default()).unwrap()[0];
+        let value = serde_json::to_value(untagged).unwrap();
+        assert!(value.get("tag").is_none(), "{value}");
+
+        let config = SignalConfig {
+            tag: Some("eu-west/binance".to_owned()),
+            ..Default::default()
+        };
+        let tagged = &extract_signals_from_text(json, &config).unwrap()[0];
+        let value = serde_json::to_value(tagged).unwrap();
+        assert_eq!(value["tag"], "eu-west/binance");
+    }
+
+    #[test]
+    fn signals_carry_their_age_and_new_tokens_can_be_held_back() {
+        let now = Utc::now().timestamp();
+        let first_seen = FirstSeen::with_times(
+            100,
+            std::collections::HashMap::from([
+                ("BTCUSDT".to_owned(), now - 10 * 24 * 60 * 60),
+                ("WIFUSDT".to_owned(), now - 2 * 60 * 60),
+                ("SCAMUSDT".to_owned(), now - 60),
+            ]),
+        );
+        let (tx, _rx) = watch::channel(None);
+        let sinks = FeedSinks {
+            tx,
+            buffer: crate::events::SignalBuffer::shared(10),
+            notifiers: Default::default(),
+            tickers: LatestTickers::default(),
+            first_seen: first_seen.clone(),
+            connect_limit: Default::default(),
+            used_weight: Default::default(),
+        };
+        let config = RwLock::new(SignalConfig {
+            min_age: Some(Duration::from_secs(60 * 60)),
+            ..Default::default()
+        });
+        let mut state = FeedState::new(&config.read().unwrap());
+        let frame = r#"[
+            { "s": "BTCUSDT", "P": "6.0", "q": "2000000", "c": "30000" },
+            { "s": "WIFUSDT", "P": "25.0", "q": "2000000", "c": "2.5" },
+            { "s": "SCAMUSDT", "P": "900.0", "q": "2000000", "c": "0.1" },
+            { "s": "PEPEUSDT", "P": "50.0", "q": "2000000", "c": "0.01" },
+            { "s": "FLATUSDT", "P": "0.1", "q": "2000000", "c": "1" }
+        ]"#;
+        assert_eq!(state.process_frame(frame, &config, &sinks).unwrap(), 2);
+
+        let delivered: Vec<Signal> = sinks
+            .buffer
+            .lock()
+            .unwrap()
+            .since(0)
+            .iter()
+            .map(|entry| serde_json::from_str(&entry.json).unwrap())
+            .collect();
+        let aged: Vec<_> = delivered
+            .iter()
+            .map(|s| (s.symbol.as_str(), s.age_secs.unwrap() / 60, s.new_token))
+            .collect();
+        // In minutes, so the second or so the test takes doesn't matter.
+        assert_eq!(
+            aged,
+            [("BTCUSDT", 10 * 24 * 60, false), ("WIFUSDT", 2 * 60, true)]
+        );
+        let value = serde_json::to_value(&delivered[0]).unwrap();
+        assert!(value.get("new_token").is_none(), "{value}");
+        // Too new, including PEPEUSDT seen for the first time just now.
+        assert_eq!(state.tally.too_new, 2);
+        // Every ticker counts as observed, signal or not.
+        let times = first_seen.times();
+        assert!(times["PEPEUSDT"] >= now && times.contains_key("FLATUSDT"));
+
+        // Without a minimum age nothing is held back.
+        config.write().unwrap().min_age = None;
+        assert_eq!(state.process_frame(frame, &config, &sinks).unwrap(), 4);
+    }
+
+    #[test]
+    fn signals_serialise_to_csv_rows_in_header_order() {
+        let json = r#"[{ "E": 1700000000123, "s": "BTCUSDT", "P": "6.25", "q": "2500000", "c": "30000.5" }]"#;
+        let sig = &extract_signals_from_text(json, &SignalConfig::default()).unwrap()[0];
+        assert_eq!(
+            Signal::CSV_HEADER.split(',').collect::<Vec<_>>(),
+            [
+                "symbol",
+                "pct_gain_24h",
+                "quote_vol_usdt",
+                "last_price",
+                "ts"
+            ]
+        );
+        assert_eq!(
+            sig.to_csv_row(),
+            "BTCUSDT,6.25,2500000,30000.5,2023-11-14T22:13:20.123Z"
+        );
+
+        assert_eq!(csv_field(r#"A,"B"#), r#""A,""B""#);
+    }
+
+    #[test]
+    fn seen_tickers_keep_each_symbols_latest_values() {
+        let config = SignalConfig::default();
+        let mut state = FeedState::new(&config);
+        let tickers = LatestTickers::default();
+        for json in [
+            r#"[{ "s": "BTCUSDT", "P": "6.0", "q": "2000000", "c": "30000" },
+                { "s": "ETHUSDT", "P": "1.0", "q": "5000", "c": "2000" }]"#,
+            r#"[{ "s": "BTCUSDT", "P": "2.5", "q": "2100000", "c": "29000" }]"#,
+        ] {
+            extract_signals(json, &config, Some(&mut state)).unwrap();
+            tickers.update(state.seen.drain(..));
+        }
+
+        let latest = tickers.snapshot(false);
+        assert_eq!(latest.len(), 2);
+        assert_eq!(latest[0].symbol, "BTCUSDT");
+        assert_eq!(latest[0].pct_gain_24h, 2.5);
+        assert_eq!(latest[0].quote_vol_usdt, 2_100_000.0);
+        assert_eq!(latest[0].last_price, Some(29000.0));
+        assert!(!latest[0].signalling);
+        assert_eq!(latest[1].symbol, "ETHUSDT");
+        assert_eq!(latest[1].last_price, Some(2000.0));
+        assert!(tickers.snapshot(true).is_empty());
+    }
+
+    #[test]
+    fn confirmations_need_consecutive_passing_frames() {
+        let frame = |flicker: &str, sustain: &str| {
+            format!(
+                r#"[
+                    {{ "s": "FLKUSDT", "P": "{flicker}", "q": "2000000", "c": "1" }},
+                    {{ "s": "SUSUSDT", "P": "{sustain}", "q": "2000000", "c": "1" }}
+                ]"#
+            )
+        };
+        let config = SignalConfig {
+            confirmations: 3,
+            ..Default::default()
+        };
+        let mut state = FeedState::new(&config);
+        let mut symbols = |json: String| -> Vec<String> {
+            extract_signals(&json, &config, Some(&mut state))
+                .unwrap()
+                .into_iter()
+                .map(|s| s.symbol)
+                .collect()
+        };
+
+        // FLKUSDT drops below 5 % every third frame, so never gets three in
+        // a row; SUSUSDT is emitted from its third frame on.
+        assert!(symbols(frame("8.0", "8.0")).is_empty());
+        assert!(symbols(frame("8.0", "8.0")).is_empty());
+        assert_eq!(symbols(frame("1.0", "8.0")), ["SUSUSDT"]);
+        assert_eq!(symbols(frame("8.0", "8.0")), ["SUSUSDT"]);
+        assert_eq!(symbols(frame("8.0", "8.0")), ["SUSUSDT"]);
+        assert_eq!(symbols(frame("1.0", "8.0")), ["SUSUSDT"]);
+
+        // A frame without the symbol doesn't break its run.
+        let absent = r#"[{ "s": "FLKUSDT", "P": "8.0", "q": "2000000", "c": "1" }]"#;
+        assert!(symbols(absent.to_owned()).is_empty());
+        assert_eq!(symbols(frame("1.0", "8.0")), ["SUSUSDT"]);
+    }
+
+    #[test]
+    fn exchange_event_time_becomes_ts() {
+        let json = r#"[
+            { "e": "24hrTicker", "E": 1700000000123, "s": "BTCUSDT", "P": "6.0", "q": "2000000", "c": "30000" },
+            { "s": "ETHUSDT", "P": "6.0", "q": "2000000", "c": "2000" }
+        ]"#;
+        let signals = extract_signals_from_text(json, &SignalConfig::default()).unwrap();
+
+        let expected = DateTime::from_timestamp_millis(1_700_000_000_123).unwrap();
+        assert_eq!(signals[0].ts, expected);
+        let received = signals[0].received_at.expect("local time kept");
+        assert!(received > expected);
+        // Without `E` the receive time is all there is.
+        assert_eq!(signals[1].received_at, None);
+        assert!(signals[1].ts >= received);
+
+        let json = serde_json::to_value(&signals[1]).unwrap();
+        assert!(json.get("received_at").is_none());
+        assert_eq!(
+            event_time(&serde_json::json!("1700000000123")),
+            Some(expected)
+        );
+        assert_eq!(event_time(&serde_json::json!("soon")), None);
+    }
+
+    #[test]
+    fn field_map_overrides_only_what_is_given() {
+        let map: FieldMap = "price=last".parse().unwrap();
+        assert_eq!(map.price, "last");
+        assert_eq!(map.symbol, "s");
+        assert_eq!(map.to_string(), "symbol=s,pct=P,volume=q,price=last,time=E");
+        assert_eq!("".parse::<FieldMap>().unwrap(), FieldMap::default());
+
+        assert!("colour=red".parse::<FieldMap>().is_err());
+        assert!("price".parse::<FieldMap>().is_err());
+        assert!("price=".parse::<FieldMap>().is_err());
+
+        let config = SignalConfig {
+            fields: "symbol=pair".parse().unwrap(),
+            ..Default::default()
+        };
+        let json = r#"[{ "s": "BTCUSDT", "P": "9", "q": "2000000", "c": "1" }]"#;
+        assert!(extract_signals_from_text(json, &config).is_err());
+    }
+
+    #[test]
+    fn test_extract_signals_default_has_no_price_floor() {
+        let json = r#"[
+            { "s": "DUSTUSDT", "P": "50.0", "q": "5000000", "c": "0.00001" }
+        ]"#;
+
+        let signals = extract_signals_from_text(json, &SignalConfig::default()).unwrap();
+        assert_eq!(signals.len(), 1);
+    }
+
+    #[test]
+    fn test_extract_signals_custom_filter_augments_thresholds() {
+        let json = r#"[
+            { "s": "BTCUSDT", "P": "12.0", "q": "3000000", "c": "30000" },
+            { "s": "ETHBTC",  "P": "12.0", "q": "3000000", "c": "0.05"  },
+            { "s": "SOLUSDT", "P": "4.0",  "q": "3000000", "c": "150"   }
+        ]"#;
+        let config = SignalConfig {
+            filter: Some(r#"symbol.ends_with("USDT") || last_price < 1"#.parse().unwrap()),
+            ..Default::default()
+        };
+
+        let signals = extract_signals_from_text(json, &config).unwrap();
+        let symbols: Vec<_> = signals.iter().map(|s| s.symbol.as_str()).collect();
+        // SOLUSDT matches the expression but is still below the 5 % threshold.
+        assert_eq!(symbols, ["BTCUSDT", "ETHBTC"]);
+    }
+
+    /// Signals as JSON, minus the parts that depend on the wall clock.
+    fn comparable(signals: &[Signal]) -> Vec<serde_json::Value> {
+        signals
+            .iter()
+            .map(|sig| {
+                let mut json = serde_json::to_value(sig).unwrap();
+                let obj = json.as_object_mut().unwrap();
+                if obj.remove("received_at").is_none() {
+                    obj.remove("ts");
+                }
+                json
+            })
+            .collect()
+    }
+
+    #[test]
+    fn borrowed_parsing_matches_the_value_path() {
+        let frames = [
+            // Plain Binance frame, with and without event times.
+            r#"[
+                { "e": "24hrTicker", "E": 1700000000123, "s": "BTCUSDT", "P": "6.0", "q": "2000000", "c": "30000" },
+                { "s": "ETHUSDT", "P": "12.5", "q": "5000000", "c": "2000", "x": [1, {"y": null}] },
+                { "s": "DOGEUSDT", "P": "2.0", "q": "9000000", "c": "0.1" }
+            ]"#,
+            // Escaped symbols, null and missing values.
+            r#"[
+                { "s": "AB\u0043USDT", "P": "25", "q": "1000000", "c": "1" },
+                { "s": "NULLUSDT", "P": "25", "q": "1000000", "c": null },
+                { "s": "XUSDT", "P": "7", "q": "3000000" }
+            ]"#,
+            // Shapes `RawTicker` rejects, handled by the fallback.
+            r#"[
+                { "E": "1700000000123", "s": "BTCUSDT", "P": "6.0", "q": "2000000", "c": "1" },
+                { "s": "ETHUSDT", "P": 12.5, "q": "5000000", "c": "2000" }
+            ]"#,
+            r#"[{ "s": "BTCUSDT", "P": "6.0", "q": "2000000", "c": "1" }, 42]"#,
+            r#"{ "result": null, "id": 1 }"#,
+            "[]",
+        ];
+        let value = SignalConfig {
+            min_last_price: 0.5,
+            ..SignalConfig::default()
+        };
+        let borrowed = SignalConfig {
+            borrowed_parsing: true,
+            ..value.clone()
+        };
+
+        for frame in frames {
+            let expected = extract_signals_from_text(frame, &value).unwrap();
+            let actual = extract_signals_from_text(frame, &borrowed).unwrap();
+            assert_eq!(comparable(&actual), comparable(&expected), "{frame}");
+        }
+        let first = extract_signals_from_text(frames[0], &borrowed).unwrap();
+        assert_eq!(first.len(), 2);
+        assert!(first[0].received_at.is_some());
+        assert_eq!(
+            extract_signals_from_text(frames[1], &borrowed).unwrap()[0].symbol,
+            "ABCUSDT"
+        );
+
+        // Errors are the same too.
+        let bad = r#"[{ "s": "BTCUSDT", "P": "lots", "q": "1", "c": "1" }]"#;
+        assert!(extract_signals_from_text(bad, &value).is_err());
+        assert!(extract_signals_from_text(bad, &borrowed).is_err());
+    }
+
+    #[test]
+    fn sampling_keeps_the_first_frame_per_interval() {
+        let start = Instant::now();
+        let every = Some(Duration::from_secs(1));
+        let mut sampler = FrameSampler::default();
+        // A frame every 200 ms for 2.4 s.
+        let kept: Vec<u64> = (0..13)
+            .map(|i| i * 200)
+            .filter(|&ms| sampler.take(every, start + Duration::from_millis(ms)))
+            .collect();
+        assert_eq!(kept, [0, 1000, 2000]);
+
+        let mut unsampled = FrameSampler::default();
+        assert!((0..5).all(|_| unsampled.take(None, start)));
     }
 }
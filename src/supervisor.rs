@@ -0,0 +1,248 @@
+//! Keeps the feed tasks running: a feed that exits, panics or stops
+//! reporting frames is restarted, with a growing delay so a feed that keeps
+//! crashing can't spin.
+
+use std::{
+    future::Future,
+    sync::{
+        atomic::{AtomicU64, Ordering},
+        Arc,
+    },
+    time::{Duration, Instant},
+};
+
+use crate::shutdown::Shutdown;
+
+/// Restarts across every supervised feed, reported by `/stats`.
+pub type RestartCounter = Arc<AtomicU64>;
+
+/// How long a feed may go without a frame before it is restarted.
+const DEFAULT_STALE_AFTER: Duration = Duration::from_secs(120);
+
+/// When to restart a feed and how quickly.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct RestartPolicy {
+    /// Restart a feed that hasn't reported liveness for this long.
+    pub stale_after: Duration,
+    /// Delay before the first restart; doubled for every restart that
+    /// follows a run shorter than `max_delay`.
+    pub min_delay: Duration,
+    pub max_delay: Duration,
+}
+
+impl Default for RestartPolicy {
+    fn default() -> Self {
+        Self {
+            stale_after: DEFAULT_STALE_AFTER,
+            min_delay: Duration::from_secs(1),
+            max_delay: Duration::from_secs(60),
+        }
+    }
+}
+
+impl RestartPolicy {
+    /// Defaults, with the staleness threshold from `FEED_STALE_SECS`.
+    pub fn from_env() -> Result<Self, String> {
+        let mut policy = Self::default();
+        if let Ok(secs) = std::env::var("FEED_STALE_SECS") {
+            let secs: u64 = secs
+                .trim()
+                .parse()
+                .map_err(|e| format!("invalid FEED_STALE_SECS `{secs}`: {e}"))?;
+            if secs == 0 {
+                return Err("FEED_STALE_SECS must be at least 1".to_owned());
+            }
+            policy.stale_after = Duration::from_secs(secs);
+        }
+        Ok(policy)
+    }
+}
+
+/// Handed to a supervised feed, which calls [`Liveness::beat`] whenever it
+/// makes progress.
+#[derive(Debug, Clone)]
+pub struct Liveness {
+    started: Instant,
+    /// Milliseconds after `started` of the last beat.
+    last_beat_ms: Arc<AtomicU64>,
+}
+
+impl Default for Liveness {
+    fn default() -> Self {
+        Self {
+            started: Instant::now(),
+            last_beat_ms: Arc::default(),
+        }
+    }
+}
+
+impl Liveness {
+    pub fn beat(&self) {
+        let ms = self.started.elapsed().as_millis() as u64;
+        self.last_beat_ms.store(ms, Ordering::Relaxed);
+    }
+
+    /// Time since the last beat, or since the feed started.
+    pub fn idle(&self) -> Duration {
+        let last = Duration::from_millis(self.last_beat_ms.load(Ordering::Relaxed));
+        self.started.elapsed().saturating_sub(last)
+    }
+}
+
+/// Run `feed` until `shutdown` is triggered, restarting it whenever it
+/// returns, panics or goes without a beat for `policy.stale_after`. Every
+/// restart is logged and counted in `restarts`.
+pub async fn supervise<F, Fut>(
+    name: String,
+    restarts: RestartCounter,
+    policy: RestartPolicy,
+    shutdown: Shutdown,
+    mut feed: F,
+) where
+    F: FnMut(Liveness) -> Fut,
+    Fut: Future<Output = ()> + Send + 'static,
+{
+    let check_every = (policy.stale_after / 4).max(Duration::from_millis(10));
+    let mut delay = policy.min_delay;
+    loop {
+        let liveness = Liveness::default();
+        let started = Instant::now();
+        let mut task = tokio::spawn(feed(liveness.clone()));
+        let mut check = tokio::time::interval(check_every);
+
+        let reason = loop {
+            tokio::select! {
+                _ = shutdown.cancelled() => {
+                    task.abort();
+                    return;
+                }
+                res = &mut task => break match res {
+                    Ok(()) => "exited".to_owned(),
+                    Err(e) if e.is_panic() => "panicked".to_owned(),
+                    Err(e) => e.to_string(),
+                },
+                _ = check.tick() => {
+                    let idle = liveness.idle();
+                    if idle >= policy.stale_after {
+                        task.abort();
+                        break format!("stale, nothing for {idle:.0?}");
+                    }
+                }
+            }
+        };
+
+        // A run that lasted a while was healthy; start backing off afresh.
+        if started.elapsed() >= policy.max_delay {
+            delay = policy.min_delay;
+        }
+        let count = restarts.fetch_add(1, Ordering::Relaxed) + 1;
+        tracing::warn!(
+            feed = %name,
+            restarts = count,
+            "\u{1f501} Feed {reason}; restarting in {delay:?}"
+        );
+        tokio::select! {
+            _ = shutdown.cancelled() => return,
+            _ = tokio::time::sleep(delay) => {}
+        }
+        delay = (delay * 2).min(policy.max_delay);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::atomic::AtomicUsize;
+
+    fn fast_policy() -> RestartPolicy {
+        RestartPolicy {
+            stale_after: Duration::from_millis(100),
+            min_delay: Duration::from_millis(5),
+            max_delay: Duration::from_millis(20),
+        }
+    }
+
+    #[tokio::test]
+    async fn returning_feed_is_respawned() {
+        let restarts = RestartCounter::default();
+        let runs = Arc::new(AtomicUsize::new(0));
+        let counted = runs.clone();
+        let supervisor = tokio::spawn(supervise(
+            "test".into(),
+            restarts.clone(),
+            fast_policy(),
+            Shutdown::default(),
+            move |_| {
+                counted.fetch_add(1, Ordering::SeqCst);
+                async {}
+            },
+        ));
+
+        tokio::time::sleep(Duration::from_millis(200)).await;
+        supervisor.abort();
+        let restarts = restarts.load(Ordering::SeqCst);
+        assert!(restarts >= 2, "{restarts}");
+        // Every restart spawned the feed again.
+        assert!(runs.load(Ordering::SeqCst) as u64 >= restarts);
+    }
+
+    #[tokio::test]
+    async fn stale_feed_is_restarted_but_a_beating_one_is_not() {
+        let restarts = RestartCounter::default();
+        let supervisor = tokio::spawn(supervise(
+            "stuck".into(),
+            restarts.clone(),
+            fast_policy(),
+            Shutdown::default(),
+            |_| std::future::pending(),
+        ));
+        let beating = RestartCounter::default();
+        let healthy = tokio::spawn(supervise(
+            "healthy".into(),
+            beating.clone(),
+            fast_policy(),
+            Shutdown::default(),
+            |liveness: Liveness| async move {
+                loop {
+                    liveness.beat();
+                    tokio::time::sleep(Duration::from_millis(10)).await;
+                }
+            },
+        ));
+
+        tokio::time::sleep(Duration::from_millis(350)).await;
+        supervisor.abort();
+        healthy.abort();
+        assert!(restarts.load(Ordering::SeqCst) >= 1);
+        assert_eq!(beating.load(Ordering::SeqCst), 0);
+    }
+
+    #[tokio::test]
+    async fn shutdown_stops_the_supervisor_and_its_feed() {
+        let shutdown = Shutdown::default();
+        let (tx, mut rx) = tokio::sync::mpsc::channel::<()>(1);
+        let supervisor = tokio::spawn(supervise(
+            "stopping".into(),
+            RestartCounter::default(),
+            fast_policy(),
+            shutdown.clone(),
+            move |_| {
+                let tx = tx.clone();
+                // Holds a sender until aborted.
+                async move {
+                    std::future::pending::<()>().await;
+                    drop(tx)
+                }
+            },
+        ));
+
+        tokio::time::sleep(Duration::from_millis(20)).await;
+        shutdown.trigger(crate::shutdown::ShutdownReason::Admin);
+        tokio::time::timeout(Duration::from_secs(1), supervisor)
+            .await
+            .expect("supervisor returns")
+            .unwrap();
+        // Every sender is gone once the feed task was aborted.
+        assert_eq!(rx.recv().await, None);
+    }
+}
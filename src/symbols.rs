@@ -0,0 +1,141 @@
+//! Bounded per-symbol state. Exchanges list thousands of pairs and new ones
+//! keep appearing, so maps keyed by symbol forget the least recently used
+//! symbol once full instead of growing forever.
+
+use std::collections::{BTreeMap, HashMap};
+
+/// Symbols each store remembers, unless overridden by `SYMBOL_STATE_MAX`.
+pub const DEFAULT_MAX_SYMBOLS: usize = 5000;
+
+/// Store capacity from `SYMBOL_STATE_MAX`, or [`DEFAULT_MAX_SYMBOLS`].
+pub fn max_symbols_from_env() -> Result<usize, String> {
+    let Ok(v) = std::env::var("SYMBOL_STATE_MAX") else {
+        return Ok(DEFAULT_MAX_SYMBOLS);
+    };
+    match v.trim().parse::<usize>() {
+        Ok(0) => Err("SYMBOL_STATE_MAX must be at least 1".to_owned()),
+        Ok(n) => Ok(n),
+        Err(e) => Err(format!("invalid SYMBOL_STATE_MAX `{v}`: {e}")),
+    }
+}
+
+/// A map from symbol to `T` holding at most `max_entries` symbols. Reading
+/// or writing a symbol marks it as used; inserting a new one into a full
+/// store evicts the least recently used, which simply forgets its history.
+#[derive(Debug)]
+pub struct SymbolState<T> {
+    max_entries: usize,
+    /// Incremented on every use; an entry's tick is its last use.
+    tick: u64,
+    entries: HashMap<String, (T, u64)>,
+    /// Symbols by last use, oldest first.
+    recency: BTreeMap<u64, String>,
+}
+
+impl<T> Default for SymbolState<T> {
+    fn default() -> Self {
+        Self::new(DEFAULT_MAX_SYMBOLS)
+    }
+}
+
+impl<T> SymbolState<T> {
+    /// A store of at most `max_entries` symbols; `0` is treated as 1.
+    pub fn new(max_entries: usize) -> Self {
+        Self {
+            max_entries: max_entries.max(1),
+            tick: 0,
+            entries: HashMap::new(),
+            recency: BTreeMap::new(),
+        }
+    }
+
+    /// The state of `symbol`, marking it as used.
+    pub fn get(&mut self, symbol: &str) -> Option<&T> {
+        self.get_mut(symbol).map(|v| &*v)
+    }
+
+    /// The state of `symbol`, marking it as used.
+    pub fn get_mut(&mut self, symbol: &str) -> Option<&mut T> {
+        let tick = self.next_tick();
+        let (value, used) = self.entries.get_mut(symbol)?;
+        let name = self.recency.remove(used).expect("indexed on insert");
+        self.recency.insert(tick, name);
+        *used = tick;
+        Some(value)
+    }
+
+    /// Store `value` for `symbol` and return what it replaces.
+    pub fn insert(&mut self, symbol: &str, value: T) -> Option<T> {
+        if let Some(slot) = self.get_mut(symbol) {
+            return Some(std::mem::replace(slot, value));
+        }
+        self.insert_new(symbol, value);
+        None
+    }
+
+    /// The state of `symbol`, created with `default` if it has none.
+    pub fn get_or_insert_with(&mut self, symbol: &str, default: impl FnOnce() -> T) -> &mut T {
+        if !self.entries.contains_key(symbol) {
+            self.insert_new(symbol, default());
+        }
+        self.get_mut(symbol).expect("inserted above")
+    }
+
+    /// Every symbol and its state, without marking any as used.
+    pub fn iter(&self) -> impl Iterator<Item = (&str, &T)> {
+        self.entries
+            .iter()
+            .map(|(symbol, (value, _))| (symbol.as_str(), value))
+    }
+
+    fn insert_new(&mut self, symbol: &str, value: T) {
+        while self.entries.len() >= self.max_entries {
+            let Some((_, oldest)) = self.recency.pop_first() else {
+                break;
+            };
+            self.entries.remove(&oldest);
+        }
+        let tick = self.next_tick();
+        self.entries.insert(symbol.to_owned(), (value, tick));
+        self.recency.insert(tick, symbol.to_owned());
+    }
+
+    fn next_tick(&mut self) -> u64 {
+        self.tick += 1;
+        self.tick
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn least_recently_used_symbols_are_evicted() {
+        let mut state = SymbolState::new(3);
+        for (i, symbol) in ["AUSDT", "BUSDT", "CUSDT"].into_iter().enumerate() {
+            assert_eq!(state.insert(symbol, i), None);
+        }
+        // Using AUSDT makes BUSDT the oldest.
+        assert_eq!(state.get("AUSDT"), Some(&0));
+        state.insert("DUSDT", 3);
+        assert_eq!(state.iter().count(), 3);
+        assert_eq!(state.get("BUSDT"), None);
+
+        // Updating counts as a use too.
+        assert_eq!(state.insert("CUSDT", 20), Some(2));
+        *state.get_or_insert_with("EUSDT", || 4) += 1;
+        assert_eq!(state.get("AUSDT"), None);
+
+        let mut kept: Vec<_> = state.iter().map(|(s, v)| (s.to_owned(), *v)).collect();
+        kept.sort();
+        assert_eq!(
+            kept,
+            [
+                ("CUSDT".to_owned(), 20),
+                ("DUSDT".to_owned(), 3),
+                ("EUSDT".to_owned(), 5)
+            ]
+        );
+    }
+}
@@ -0,0 +1,151 @@
+//! An in-memory feed for end-to-end tests, behind the `test-util` feature.
+//! Signals and raw ticker frames go through the same delivery path as a
+//! connected upstream, so `/websocket`, `/events`, `/stats`, `/symbols` and
+//! the threshold filtering can be tested without network. Unit tests also
+//! get [`CapturedLogs`], to assert on what was logged.
+
+use std::sync::{Arc, Mutex};
+
+use chrono::Utc;
+use shuttle_axum::axum::Router;
+use tokio::sync::watch;
+
+use crate::{
+    build_router,
+    events::{SignalBuffer, DEFAULT_BUFFER_SIZE},
+    first_seen::FirstSeen,
+    market::LatestTickers,
+    stream::{FeedSinks, FeedState, SharedConfig, SignalConfig},
+    RouterConfig, SharedState, Signal, State, Tier,
+};
+
+/// A feed the test controls, wired into a [`State`] the router serves.
+pub struct TestFeed {
+    sinks: FeedSinks,
+    config: SharedConfig,
+    feed_state: Mutex<FeedState>,
+    state: SharedState,
+}
+
+impl TestFeed {
+    /// A feed filtering with `config`, buffering [`DEFAULT_BUFFER_SIZE`]
+    /// signals and without notifiers.
+    pub fn new(config: SignalConfig) -> Self {
+        let (tx, rx) = watch::channel(None);
+        let buffer = SignalBuffer::shared(DEFAULT_BUFFER_SIZE);
+        let tickers = LatestTickers::new(config.max_symbols);
+        let first_seen = FirstSeen::new(config.max_symbols);
+        let feed_state = Mutex::new(FeedState::new(&config));
+        let config = Arc::new(std::sync::RwLock::new(config));
+        let state = State::new(rx, buffer.clone())
+            .with_config(config.clone())
+            .with_tickers(tickers.clone());
+        Self {
+            sinks: FeedSinks {
+                tx,
+                buffer,
+                notifiers: Default::default(),
+                tickers,
+                first_seen,
+                connect_limit: Default::default(),
+                used_weight: Default::default(),
+            },
+            config,
+            feed_state,
+            state: Arc::new(tokio::sync::Mutex::new(state)),
+        }
+    }
+
+    /// The state the router serves, e.g. to wait for clients.
+    pub fn state(&self) -> SharedState {
+        self.state.clone()
+    }
+
+    /// The full router over this feed's state.
+    pub fn router(&self, config: &RouterConfig) -> Router {
+        build_router(self.state.clone(), config)
+    }
+
+    /// Deliver `signal` as if a feed had emitted it: it gets the next
+    /// `seq`, is buffered and is broadcast to WebSocket clients.
+    pub fn inject_signal(&self, signal: Signal) {
+        self.sinks.deliver(signal).expect("signal serialises");
+    }
+
+    /// Run a raw upstream ticker frame through the current thresholds and
+    /// deliver what passes, returning how many signals that was.
+    pub fn inject_frame(&self, frame: &str) -> usize {
+        self.feed_state
+            .lock()
+            .unwrap()
+            .process_frame(frame, &self.config, &self.sinks)
+            .unwrap_or_else(|e| panic!("invalid ticker frame: {e}"))
+    }
+}
+
+impl Default for TestFeed {
+    fn default() -> Self {
+        Self::new(SignalConfig::default())
+    }
+}
+
+/// A signal for `symbol` with `pct_gain_24h`, $2M volume and a price of 1.
+pub fn signal(symbol: &str, pct_gain_24h: f64) -> Signal {
+    Signal {
+        symbol: symbol.to_owned(),
+        display_symbol: crate::stream::display_symbol(symbol),
+        pct_gain_24h,
+        quote_vol_usdt: 2_000_000.0,
+        last_price: 1.0,
+        tier: Tier::from_gain(pct_gain_24h),
+        ts: Utc::now(),
+        received_at: None,
+        seq: 0,
+        tag: None,
+        age_secs: None,
+        new_token: false,
+    }
+}
+
+/// Everything logged on the current thread, captured by [`capture`](Self::capture).
+#[cfg(test)]
+#[derive(Clone, Default)]
+pub(crate) struct CapturedLogs(Arc<Mutex<Vec<u8>>>);
+
+#[cfg(test)]
+impl CapturedLogs {
+    /// Capture this thread's logs, without colours, until the guard drops.
+    pub(crate) fn capture() -> (Self, tracing::subscriber::DefaultGuard) {
+        let logs = Self::default();
+        let subscriber = tracing_subscriber::fmt()
+            .with_writer(logs.clone())
+            .with_ansi(false)
+            .finish();
+        (logs, tracing::subscriber::set_default(subscriber))
+    }
+
+    pub(crate) fn contents(&self) -> String {
+        String::from_utf8(self.0.lock().unwrap().clone()).unwrap()
+    }
+}
+
+#[cfg(test)]
+impl std::io::Write for CapturedLogs {
+    fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+        self.0.lock().unwrap().extend_from_slice(buf);
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> std::io::Result<()> {
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+impl<'a> tracing_subscriber::fmt::MakeWriter<'a> for CapturedLogs {
+    type Writer = Self;
+
+    fn make_writer(&'a self) -> Self::Writer {
+        self.clone()
+    }
+}
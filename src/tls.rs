@@ -0,0 +1,131 @@
+//! Opt-in acceptance of invalid TLS certificates, for self-hosted RPC and
+//! WebSocket endpoints behind self-signed certificates.
+//!
+//! Verification stays on unless `ALLOW_INVALID_CERTS=1` is set or a binary's
+//! `--insecure` flag is passed. Handshake signatures are still checked; only
+//! the certificate chain and host name are no longer validated, which lets
+//! anyone on the path impersonate the server.
+
+use std::sync::{Arc, Once};
+
+use rustls::{
+    client::danger::{HandshakeSignatureValid, ServerCertVerified, ServerCertVerifier},
+    crypto::{ring, verify_tls12_signature, verify_tls13_signature, WebPkiSupportedAlgorithms},
+    pki_types::{CertificateDer, ServerName, UnixTime},
+    ClientConfig, DigitallySignedStruct, SignatureScheme,
+};
+use tokio::net::TcpStream;
+use tokio_tungstenite::{
+    connect_async_tls_with_config,
+    tungstenite::{self, handshake::client::Response},
+    Connector, MaybeTlsStream, WebSocketStream,
+};
+
+/// `ALLOW_INVALID_CERTS`: `1`/`true`/`yes` to enable, unset, empty or
+/// `0`/`false`/`no` to keep verifying.
+pub fn allow_invalid_certs_from_env() -> Result<bool, String> {
+    parse_flag(std::env::var("ALLOW_INVALID_CERTS").ok().as_deref())
+}
+
+fn parse_flag(value: Option<&str>) -> Result<bool, String> {
+    match value.map(|v| v.trim().to_ascii_lowercase()).as_deref() {
+        None | Some("" | "0" | "false" | "no") => Ok(false),
+        Some("1" | "true" | "yes") => Ok(true),
+        Some(other) => Err(format!(
+            "invalid ALLOW_INVALID_CERTS `{other}` (expected 1 or 0)"
+        )),
+    }
+}
+
+/// Log, once per process, that certificates are not being verified.
+pub fn warn_insecure() {
+    static WARNED: Once = Once::new();
+    WARNED.call_once(|| {
+        tracing::warn!(
+            "\u{26a0}\u{fe0f} TLS certificate verification is DISABLED \
+             (ALLOW_INVALID_CERTS / --insecure); connections can be intercepted"
+        )
+    });
+}
+
+/// Accepts any certificate for any host, but still checks that the server
+/// holds the key of the certificate it presented.
+#[derive(Debug)]
+struct AcceptAnyCert(WebPkiSupportedAlgorithms);
+
+impl ServerCertVerifier for AcceptAnyCert {
+    fn verify_server_cert(
+        &self,
+        _end_entity: &CertificateDer<'_>,
+        _intermediates: &[CertificateDer<'_>],
+        _server_name: &ServerName<'_>,
+        _ocsp_response: &[u8],
+        _now: UnixTime,
+    ) -> Result<ServerCertVerified, rustls::Error> {
+        Ok(ServerCertVerified::assertion())
+    }
+
+    fn verify_tls12_signature(
+        &self,
+        message: &[u8],
+        cert: &CertificateDer<'_>,
+        dss: &DigitallySignedStruct,
+    ) -> Result<HandshakeSignatureValid, rustls::Error> {
+        verify_tls12_signature(message, cert, dss, &self.0)
+    }
+
+    fn verify_tls13_signature(
+        &self,
+        message: &[u8],
+        cert: &CertificateDer<'_>,
+        dss: &DigitallySignedStruct,
+    ) -> Result<HandshakeSignatureValid, rustls::Error> {
+        verify_tls13_signature(message, cert, dss, &self.0)
+    }
+
+    fn supported_verify_schemes(&self) -> Vec<SignatureScheme> {
+        self.0.supported_schemes()
+    }
+}
+
+/// Connector for [`connect_ws`]: `None` keeps tungstenite's verifying
+/// default.
+pub fn ws_connector(accept_invalid_certs: bool) -> Option<Connector> {
+    if !accept_invalid_certs {
+        return None;
+    }
+    warn_insecure();
+    let verifier = AcceptAnyCert(ring::default_provider().signature_verification_algorithms);
+    let config = ClientConfig::builder()
+        .dangerous()
+        .with_custom_certificate_verifier(Arc::new(verifier))
+        .with_no_client_auth();
+    Some(Connector::Rustls(Arc::new(config)))
+}
+
+/// `connect_async`, optionally accepting invalid certificates.
+pub async fn connect_ws(
+    url: &str,
+    accept_invalid_certs: bool,
+) -> tungstenite::Result<(WebSocketStream<MaybeTlsStream<TcpStream>>, Response)> {
+    connect_async_tls_with_config(url, None, false, ws_connector(accept_invalid_certs)).await
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn flag_is_off_unless_explicitly_enabled() {
+        for off in [None, Some(""), Some("0"), Some("false"), Some(" No ")] {
+            assert_eq!(parse_flag(off), Ok(false), "{off:?}");
+        }
+        for on in ["1", "true", "YES"] {
+            assert_eq!(parse_flag(Some(on)), Ok(true), "{on}");
+        }
+        assert!(parse_flag(Some("maybe")).is_err());
+
+        assert!(ws_connector(false).is_none());
+        assert!(matches!(ws_connector(true), Some(Connector::Rustls(_))));
+    }
+}
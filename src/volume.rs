@@ -0,0 +1,85 @@
+//! Rolling per-symbol volume, for sources that report per-update trade
+//! volume instead of an exchange-computed 24-hour total.
+
+use std::{
+    collections::VecDeque,
+    time::{Duration, Instant},
+};
+
+use crate::symbols::SymbolState;
+
+/// Default window, matching the 24 hours exchanges report.
+pub const DEFAULT_WINDOW: Duration = Duration::from_secs(24 * 60 * 60);
+
+/// Volume samples per symbol over the last `window`, with a running sum so
+/// each update costs only the evictions it causes.
+#[derive(Debug)]
+pub struct RollingVolume {
+    window: Duration,
+    symbols: SymbolState<Samples>,
+}
+
+#[derive(Debug, Default)]
+struct Samples {
+    samples: VecDeque<(Instant, f64)>,
+    sum: f64,
+}
+
+impl RollingVolume {
+    pub fn new(window: Duration) -> Self {
+        Self {
+            window,
+            symbols: SymbolState::default(),
+        }
+    }
+
+    /// Remember at most `max` symbols, forgetting the least recently
+    /// traded.
+    pub fn with_max_symbols(mut self, max: usize) -> Self {
+        self.symbols = SymbolState::new(max);
+        self
+    }
+
+    /// Add `volume` traded in `symbol` at `now`, drop samples that fell out
+    /// of the window and return the volume still inside it.
+    pub fn record(&mut self, symbol: &str, volume: f64, now: Instant) -> f64 {
+        let entry = self.symbols.get_or_insert_with(symbol, Samples::default);
+        entry.samples.push_back((now, volume));
+        entry.sum += volume;
+        while let Some((at, old)) = entry.samples.front().copied() {
+            if now.saturating_duration_since(at) < self.window {
+                break;
+            }
+            entry.samples.pop_front();
+            entry.sum -= old;
+        }
+        // Recompute rather than let rounding error pile up once empty.
+        if entry.samples.len() == 1 {
+            entry.sum = volume;
+        }
+        entry.sum.max(0.0)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn window_sum_drops_evicted_samples() {
+        let start = Instant::now();
+        let at = |secs| start + Duration::from_secs(secs);
+        let mut volume = RollingVolume::new(Duration::from_secs(60));
+
+        assert_eq!(volume.record("BTCUSDT", 100.0, at(0)), 100.0);
+        assert_eq!(volume.record("BTCUSDT", 50.0, at(30)), 150.0);
+        assert_eq!(volume.record("ETHUSDT", 7.0, at(30)), 7.0);
+        // The first sample is exactly a window old and falls out.
+        assert_eq!(volume.record("BTCUSDT", 25.0, at(60)), 75.0);
+        assert_eq!(volume.record("BTCUSDT", 5.0, at(89)), 80.0);
+        assert_eq!(volume.record("BTCUSDT", 5.0, at(90)), 35.0);
+        // Everything before this one is gone.
+        assert_eq!(volume.record("BTCUSDT", 1.0, at(200)), 1.0);
+        assert_eq!(volume.record("ETHUSDT", 3.0, at(200)), 3.0);
+    }
+}
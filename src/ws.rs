@@ -1,54 +1,413 @@
-use std::sync::Arc;
+use std::{
+    net::SocketAddr,
+    sync::{
+        atomic::{AtomicU64, Ordering},
+        Arc,
+    },
+    time::{Duration, Instant},
+};
 
+use chrono::{DateTime, Utc};
+use futures::{stream::SplitSink, SinkExt, StreamExt};
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
 use shuttle_axum::axum::{
     extract::{
         ws::{Message, WebSocket},
-        WebSocketUpgrade,
+        ConnectInfo, Query, WebSocketUpgrade,
     },
+    http::{header, HeaderMap},
     response::IntoResponse,
     Extension,
 };
-use futures::{SinkExt, StreamExt};
 use tokio::sync::{watch, Mutex};
+use tokio_util::sync::CancellationToken;
+
+use crate::{
+    binance::UsedWeight,
+    events::{replay_then_live, Gap, Outgoing, SharedBuffer},
+    market::LatestTickers,
+    output::OutputDir,
+    pools::TopPoolsCache,
+    shutdown::Shutdown,
+    stream::SharedConfig,
+    supervisor::RestartCounter,
+};
 
 pub struct State {
     pub clients_count: usize,
-    pub rx: watch::Receiver<Message>,
+    /// Latest frame from the feed; `None` until the first real signal, so
+    /// clients never receive a placeholder.
+    pub rx: watch::Receiver<Option<Message>>,
+    /// Heartbeats sent to streaming clients during quiet intervals.
+    pub heartbeats: watch::Receiver<Option<Heartbeat>>,
+    /// Recent signals, replayed to `/events` clients that resume.
+    pub buffer: SharedBuffer,
+    /// Thresholds the feeds filter with, adjustable at runtime.
+    pub config: SharedConfig,
+    /// Feed restarts by the supervisor since startup.
+    pub feed_restarts: RestartCounter,
+    /// Highest-volume pools, refreshed in the background.
+    pub top_pools: TopPoolsCache,
+    /// Triggered when the server shuts down, stopping background tasks.
+    pub shutdown: Shutdown,
+    /// Where `POST /admin/snapshot` writes the buffered signals.
+    pub snapshot_dir: OutputDir,
+    /// Every symbol's latest ticker, served by `/symbols`.
+    pub tickers: LatestTickers,
+    /// Binance's last reported request weight, served by `/stats`.
+    pub binance_weight: UsedWeight,
+}
+
+impl State {
+    pub fn new(rx: watch::Receiver<Option<Message>>, buffer: SharedBuffer) -> Self {
+        Self {
+            clients_count: 0,
+            rx,
+            heartbeats: watch::channel(None).1,
+            buffer,
+            config: SharedConfig::default(),
+            feed_restarts: RestartCounter::default(),
+            top_pools: TopPoolsCache::default(),
+            shutdown: Shutdown::default(),
+            snapshot_dir: OutputDir::default(),
+            tickers: LatestTickers::default(),
+            binance_weight: UsedWeight::default(),
+        }
+    }
+
+    /// Serve (and let the admin API update) the thresholds in `config`.
+    pub fn with_config(mut self, config: SharedConfig) -> Self {
+        self.config = config;
+        self
+    }
+
+    /// Send the heartbeats broadcast on `heartbeats` to streaming clients.
+    pub fn with_heartbeats(mut self, heartbeats: watch::Receiver<Option<Heartbeat>>) -> Self {
+        self.heartbeats = heartbeats;
+        self
+    }
+
+    /// Report the restarts counted in `restarts` through `/stats`.
+    pub fn with_feed_restarts(mut self, restarts: RestartCounter) -> Self {
+        self.feed_restarts = restarts;
+        self
+    }
+
+    /// Serve `/pools/top` from `cache`.
+    pub fn with_top_pools(mut self, cache: TopPoolsCache) -> Self {
+        self.top_pools = cache;
+        self
+    }
+
+    /// Background tasks stop once `shutdown` is triggered.
+    pub fn with_shutdown(mut self, shutdown: Shutdown) -> Self {
+        self.shutdown = shutdown;
+        self
+    }
+
+    /// Write snapshots under `dir`.
+    pub fn with_snapshot_dir(mut self, dir: OutputDir) -> Self {
+        self.snapshot_dir = dir;
+        self
+    }
+
+    /// Serve `/symbols` from `tickers`.
+    pub fn with_tickers(mut self, tickers: LatestTickers) -> Self {
+        self.tickers = tickers;
+        self
+    }
+
+    /// Report the weight recorded in `weight` through `/stats`.
+    pub fn with_binance_weight(mut self, weight: UsedWeight) -> Self {
+        self.binance_weight = weight;
+        self
+    }
+}
+
+/// Default for `HEARTBEAT_INTERVAL_SECS`.
+const DEFAULT_HEARTBEAT_INTERVAL: Duration = Duration::from_secs(15);
+
+/// Broadcast to `/websocket` and `/events` clients when no signal has gone
+/// out for a while, so dashboards can show how fresh the feed is.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, JsonSchema)]
+#[serde(tag = "type", rename = "heartbeat")]
+pub struct Heartbeat {
+    pub ts: DateTime<Utc>,
+    /// `/websocket` clients connected when it was sent.
+    pub clients: usize,
+}
+
+/// `HEARTBEAT_INTERVAL_SECS`, default 15; `0` turns heartbeats off.
+pub fn heartbeat_interval_from_env() -> Result<Option<Duration>, String> {
+    let Ok(v) = std::env::var("HEARTBEAT_INTERVAL_SECS") else {
+        return Ok(Some(DEFAULT_HEARTBEAT_INTERVAL));
+    };
+    let secs: u64 = v
+        .trim()
+        .parse()
+        .map_err(|e| format!("invalid HEARTBEAT_INTERVAL_SECS `{v}`: {e}"))?;
+    Ok((secs > 0).then(|| Duration::from_secs(secs)))
+}
+
+/// Every `interval`, broadcast a [`Heartbeat`] on `heartbeats` unless a
+/// signal went out on `signals` since the previous tick, until `shutdown`.
+/// Heartbeats have their own channel, so one never replaces a signal.
+pub(crate) async fn broadcast_heartbeats(
+    state: Arc<Mutex<State>>,
+    mut signals: watch::Receiver<Option<Message>>,
+    heartbeats: watch::Sender<Option<Heartbeat>>,
+    interval: Duration,
+    shutdown: CancellationToken,
+) {
+    let mut ticks = tokio::time::interval_at(tokio::time::Instant::now() + interval, interval);
+    loop {
+        tokio::select! {
+            _ = shutdown.cancelled() => return,
+            _ = ticks.tick() => {}
+        }
+        let heartbeat = Heartbeat {
+            ts: Utc::now(),
+            clients: state.lock().await.clients_count,
+        };
+        // Checked after the await above, so a signal sent meanwhile counts.
+        if signals.has_changed().unwrap_or(false) {
+            signals.borrow_and_update();
+            continue;
+        }
+        heartbeats.send_replace(Some(heartbeat));
+    }
+}
+
+/// The next heartbeat broadcast on `rx`, serialized. Never resolves once
+/// heartbeats are off and the sender is gone.
+pub(crate) async fn next_heartbeat(rx: &mut watch::Receiver<Option<Heartbeat>>) -> String {
+    loop {
+        if rx.changed().await.is_err() {
+            return std::future::pending().await;
+        }
+        if let Some(heartbeat) = rx.borrow_and_update().clone() {
+            return serde_json::to_string(&heartbeat).expect("heartbeat serializes");
+        }
+    }
+}
+
+/// Source of connection ids, so connect and disconnect log lines of the same
+/// client can be correlated.
+static NEXT_CONN_ID: AtomicU64 = AtomicU64::new(1);
+
+/// Best-effort client address: the peer address when the server was started
+/// with connect info, otherwise the first `X-Forwarded-For` hop (Shuttle
+/// sits behind a proxy).
+fn client_ip(peer: Option<SocketAddr>, headers: &HeaderMap) -> String {
+    peer.map(|addr| addr.ip().to_string())
+        .or_else(|| {
+            headers
+                .get("x-forwarded-for")
+                .and_then(|v| v.to_str().ok())
+                .and_then(|v| v.split(',').next())
+                .map(|ip| ip.trim().to_owned())
+        })
+        .unwrap_or_else(|| "unknown".to_owned())
+}
+
+#[derive(Debug, Default, Deserialize)]
+pub struct WebSocketParams {
+    /// `seq` of the last signal the client saw; everything after it that
+    /// is still buffered is replayed before live streaming starts.
+    since: Option<u64>,
 }
 
 pub async fn websocket_handler(
     ws: WebSocketUpgrade,
+    connect_info: Option<ConnectInfo<SocketAddr>>,
+    headers: HeaderMap,
+    Query(params): Query<WebSocketParams>,
     Extension(state): Extension<Arc<Mutex<State>>>,
 ) -> impl IntoResponse {
-    ws.on_upgrade(|socket| websocket(socket, state))
+    let conn_id = NEXT_CONN_ID.fetch_add(1, Ordering::Relaxed);
+    let header_str = |name| headers.get(name).and_then(|v| v.to_str().ok());
+    tracing::info!(
+        conn_id,
+        ip = %client_ip(connect_info.map(|ConnectInfo(addr)| addr), &headers),
+        user_agent = header_str(header::USER_AGENT).unwrap_or("-"),
+        subprotocol = header_str(header::SEC_WEBSOCKET_PROTOCOL).unwrap_or("-"),
+        since = params.since,
+        "WebSocket client connected"
+    );
+
+    ws.on_upgrade(move |socket| websocket(socket, state, conn_id, params.since))
+}
+
+type Sender = SplitSink<WebSocket, Message>;
+
+/// Forward the latest signal whenever it changes, and every heartbeat.
+/// Signals published in quick succession may be coalesced; clients spot
+/// that by `seq`.
+async fn send_latest(
+    sender: &mut Sender,
+    mut rx: watch::Receiver<Option<Message>>,
+    mut heartbeats: watch::Receiver<Option<Heartbeat>>,
+) -> String {
+    loop {
+        let msg = tokio::select! {
+            changed = rx.changed() => {
+                if changed.is_err() {
+                    return "feed stopped".to_owned();
+                }
+                let Some(msg) = rx.borrow_and_update().clone() else {
+                    continue;
+                };
+                msg
+            }
+            json = next_heartbeat(&mut heartbeats) => Message::Text(json),
+        };
+
+        if let Err(e) = sender.send(msg).await {
+            return format!("send failed: {e}");
+        }
+    }
+}
+
+/// Replay what the client missed after `since`, announcing a [`Gap`] first
+/// if part of it was already evicted, then stream every new signal from the
+/// buffer so none is coalesced away, along with every heartbeat.
+async fn send_since(
+    sender: &mut Sender,
+    rx: watch::Receiver<Option<Message>>,
+    heartbeats: watch::Receiver<Option<Heartbeat>>,
+    buffer: SharedBuffer,
+    since: u64,
+) -> String {
+    let replay = buffer.lock().unwrap().replay(since);
+    if replay.missed > 0 {
+        let gap = serde_json::to_string(&Gap {
+            missed: replay.missed,
+        })
+        .expect("gap notice serializes");
+        if let Err(e) = sender.send(Message::Text(gap)).await {
+            return format!("send failed: {e}");
+        }
+    }
+
+    let mut outgoing = std::pin::pin!(replay_then_live(replay, rx, heartbeats, buffer));
+    while let Some(out) = outgoing.next().await {
+        let json = match out {
+            Outgoing::Signal(signal) => signal.json,
+            Outgoing::Heartbeat(json) => json,
+        };
+        if let Err(e) = sender.send(Message::Text(json)).await {
+            return format!("send failed: {e}");
+        }
+    }
+    "feed stopped".to_owned()
 }
 
-async fn websocket(stream: WebSocket, state: Arc<Mutex<State>>) {
+async fn websocket(stream: WebSocket, state: Arc<Mutex<State>>, conn_id: u64, since: Option<u64>) {
+    let connected_at = Instant::now();
     let (mut sender, mut receiver) = stream.split();
 
-    let mut rx = {
+    let (rx, heartbeats, buffer) = {
         let mut state = state.lock().await;
         state.clients_count += 1;
-        state.rx.clone()
+        (
+            state.rx.clone(),
+            state.heartbeats.clone(),
+            state.buffer.clone(),
+        )
     };
 
     let mut send_task = tokio::spawn(async move {
-        while let Ok(()) = rx.changed().await {
-            let msg = rx.borrow().clone();
+        match since {
+            Some(since) => send_since(&mut sender, rx, heartbeats, buffer, since).await,
+            None => send_latest(&mut sender, rx, heartbeats).await,
+        }
+    });
 
-            if sender.send(msg).await.is_err() {
-                break;
+    let mut recv_task = tokio::spawn(async move {
+        loop {
+            match receiver.next().await {
+                Some(Ok(Message::Close(_))) => return "client closed".to_owned(),
+                Some(Ok(_)) => {}
+                Some(Err(e)) => return format!("client error: {e}"),
+                None => return "connection dropped".to_owned(),
             }
         }
     });
 
-    let mut recv_task =
-        tokio::spawn(async move { while let Some(Ok(_)) = receiver.next().await {} });
-
-    tokio::select! {
-        _ = (&mut send_task) => recv_task.abort(),
-        _ = (&mut recv_task) => send_task.abort(),
-    };
+    let reason = tokio::select! {
+        r = (&mut send_task) => { recv_task.abort(); r }
+        r = (&mut recv_task) => { send_task.abort(); r }
+    }
+    .unwrap_or_else(|e| format!("task failed: {e}"));
 
     state.lock().await.clients_count -= 1;
+    tracing::info!(
+        conn_id,
+        duration_ms = connected_at.elapsed().as_millis() as u64,
+        reason,
+        "WebSocket client disconnected"
+    );
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::events::SignalBuffer;
+
+    /// The next heartbeat broadcast on `rx`.
+    async fn next(rx: &mut watch::Receiver<Option<Heartbeat>>) -> Heartbeat {
+        let json = tokio::time::timeout(Duration::from_secs(5), next_heartbeat(rx))
+            .await
+            .expect("timed out waiting for a heartbeat");
+        serde_json::from_str(&json).unwrap()
+    }
+
+    #[tokio::test]
+    async fn heartbeats_fill_quiet_intervals_only() {
+        const INTERVAL: Duration = Duration::from_millis(40);
+
+        let (tx, rx) = watch::channel(None);
+        let (heartbeat_tx, mut heartbeats) = watch::channel(None);
+        let state = Arc::new(Mutex::new(State::new(rx.clone(), SignalBuffer::shared(1))));
+        state.lock().await.clients_count = 2;
+        let shutdown = CancellationToken::new();
+        let task = tokio::spawn(broadcast_heartbeats(
+            state,
+            rx.clone(),
+            heartbeat_tx,
+            INTERVAL,
+            shutdown.clone(),
+        ));
+
+        let started = Instant::now();
+        for beat in 1..=3u32 {
+            assert_eq!(next(&mut heartbeats).await.clients, 2);
+            assert!(started.elapsed() >= INTERVAL * beat, "beat {beat} early");
+        }
+        assert!(
+            started.elapsed() < INTERVAL * 3 + Duration::from_secs(1),
+            "heartbeats too slow"
+        );
+
+        // A signal resets the cadence and is left untouched: the next
+        // heartbeat waits for a full interval without one.
+        let signal = Message::Text(r#"{"symbol":"WIFUSDT"}"#.to_owned());
+        tx.send_replace(Some(signal.clone()));
+        let sent = Instant::now();
+        next(&mut heartbeats).await;
+        assert!(sent.elapsed() >= INTERVAL);
+        assert_eq!(*rx.borrow(), Some(signal));
+
+        shutdown.cancel();
+        task.await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn next_heartbeat_waits_forever_when_heartbeats_are_off() {
+        let mut heartbeats = watch::channel(None).1;
+        let next =
+            tokio::time::timeout(Duration::from_millis(50), next_heartbeat(&mut heartbeats)).await;
+        assert!(next.is_err(), "unexpected heartbeat: {next:?}");
+    }
 }
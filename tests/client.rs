@@ -0,0 +1,137 @@
+use std::{
+    sync::{Arc, Mutex},
+    time::{Duration, Instant},
+};
+
+use crypto_scanner_agent::{client::ScannerClient, testing::signal, Signal};
+use futures::StreamExt;
+use shuttle_axum::axum::{
+    self,
+    extract::{ws::Message, RawQuery, WebSocketUpgrade},
+    routing::get,
+    Router,
+};
+
+fn frame(symbol: &str, seq: u64) -> Message {
+    let signal = Signal {
+        seq,
+        ..signal(symbol, 12.0)
+    };
+    Message::Text(serde_json::to_string(&signal).unwrap())
+}
+
+/// A fake scanner whose first connection sends two signals and a heartbeat
+/// and then drops; later ones send a gap notice and one more signal and
+/// stay open. Returns its URL and the query string of every connection.
+async fn flaky_scanner() -> (String, Arc<Mutex<Vec<Option<String>>>>) {
+    let queries = Arc::new(Mutex::new(Vec::new()));
+    let seen = queries.clone();
+    let app = Router::new().route(
+        "/websocket",
+        get(move |ws: WebSocketUpgrade, RawQuery(query): RawQuery| {
+            let first = {
+                let mut seen = seen.lock().unwrap();
+                seen.push(query);
+                seen.len() == 1
+            };
+            async move {
+                ws.on_upgrade(move |mut socket| async move {
+                    let frames = if first {
+                        vec![
+                            frame("BTCUSDT", 1),
+                            Message::Text(r#"{"type":"heartbeat"}"#.to_owned()),
+                            frame("WIFUSDT", 2),
+                        ]
+                    } else {
+                        vec![
+                            Message::Text(r#"{"type":"gap","missed":1}"#.to_owned()),
+                            frame("SOLUSDT", 4),
+                        ]
+                    };
+                    for frame in frames {
+                        let _ = socket.send(frame).await;
+                    }
+                    if !first {
+                        while let Some(Ok(_)) = socket.recv().await {}
+                    }
+                })
+            }
+        }),
+    );
+    let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let addr = listener.local_addr().unwrap();
+    tokio::spawn(async move { axum::serve(listener, app).await });
+    (format!("ws://{addr}/websocket"), queries)
+}
+
+#[tokio::test]
+async fn signals_are_decoded_across_a_dropped_connection() {
+    let (url, queries) = flaky_scanner().await;
+    let signals = ScannerClient::new(url)
+        .with_retry_delays(vec![Duration::from_millis(10)])
+        .signals()
+        .take(3)
+        .collect::<Vec<_>>();
+    let signals = tokio::time::timeout(Duration::from_secs(5), signals)
+        .await
+        .expect("timed out waiting for signals");
+
+    let received: Vec<(&str, u64)> = signals.iter().map(|s| (s.symbol.as_str(), s.seq)).collect();
+    assert_eq!(received, [("BTCUSDT", 1), ("WIFUSDT", 2), ("SOLUSDT", 4)]);
+    assert_eq!(signals[1].display_symbol, "WIF/USDT");
+    // The reconnect resumes after the last signal seen.
+    assert_eq!(*queries.lock().unwrap(), [None, Some("since=2".to_owned())]);
+}
+
+#[tokio::test]
+async fn resuming_can_be_turned_off() {
+    let (url, queries) = flaky_scanner().await;
+    let signals = ScannerClient::new(url)
+        .with_resume(false)
+        .with_retry_delays(Vec::new())
+        .signals()
+        .take(3)
+        .collect::<Vec<_>>();
+    tokio::time::timeout(Duration::from_secs(5), signals)
+        .await
+        .expect("timed out waiting for signals");
+    assert_eq!(*queries.lock().unwrap(), [None, None]);
+}
+
+#[tokio::test]
+async fn failed_connects_back_off_through_every_delay_in_order() {
+    // Accepts and drops every connection, so each handshake fails.
+    let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let url = format!("ws://{}/websocket", listener.local_addr().unwrap());
+    let delays = [
+        Duration::from_millis(40),
+        Duration::from_millis(250),
+        Duration::from_millis(500),
+    ];
+    let client = tokio::spawn(async move {
+        let mut signals = std::pin::pin!(ScannerClient::new(url)
+            .with_retry_delays(delays.to_vec())
+            .signals());
+        signals.next().await
+    });
+
+    let mut attempts = Vec::new();
+    while attempts.len() < 5 {
+        let accepted = tokio::time::timeout(Duration::from_secs(5), listener.accept()).await;
+        drop(accepted.expect("timed out waiting for a connect").unwrap());
+        attempts.push(Instant::now());
+    }
+    client.abort();
+
+    let gaps: Vec<_> = attempts.windows(2).map(|w| w[1] - w[0]).collect();
+    // The first retry waits the first delay, and the last one repeats.
+    let expected = [delays[0], delays[1], delays[2], delays[2]];
+    for (gap, delay) in gaps.iter().zip(expected) {
+        assert!(
+            *gap >= delay,
+            "waited {gap:?}, expected {delay:?}: {gaps:?}"
+        );
+    }
+    assert!(gaps[0] < delays[1], "first retry skipped a step: {gaps:?}");
+    assert!(gaps[1] < delays[2], "second retry skipped a step: {gaps:?}");
+}
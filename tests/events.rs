@@ -0,0 +1,118 @@
+use std::{sync::Arc, time::Duration};
+
+use crypto_scanner_agent::{
+    build_router,
+    events::{SharedBuffer, SignalBuffer},
+    Heartbeat, RouterConfig, State,
+};
+use shuttle_axum::axum::{self, extract::ws::Message};
+use tokio::sync::{watch, Mutex};
+
+struct Feed {
+    tx: watch::Sender<Option<Message>>,
+    buffer: SharedBuffer,
+    heartbeats: watch::Sender<Option<Heartbeat>>,
+}
+
+impl Feed {
+    /// Publish a signal the same way the Raydium feed does.
+    fn push(&self, json: &str) {
+        self.buffer.lock().unwrap().push(json.to_owned());
+        self.tx.send(Some(Message::Text(json.to_owned()))).unwrap();
+    }
+}
+
+async fn serve() -> (String, Feed) {
+    let (tx, rx) = watch::channel(None);
+    let (heartbeats, heartbeat_rx) = watch::channel(None);
+    let buffer = SignalBuffer::shared(10);
+    let state = State::new(rx, buffer.clone()).with_heartbeats(heartbeat_rx);
+    let state = Arc::new(Mutex::new(state));
+    let router = build_router(state, &RouterConfig::default());
+
+    let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let addr = listener.local_addr().unwrap();
+    tokio::spawn(async move { axum::serve(listener, router).await });
+    let feed = Feed {
+        tx,
+        buffer,
+        heartbeats,
+    };
+    (format!("http://{addr}/events"), feed)
+}
+
+/// Read the SSE body until `n` `data:` lines have arrived.
+async fn read_events(res: &mut reqwest::Response, n: usize) -> String {
+    let mut body = String::new();
+    while body.matches("data:").count() < n {
+        let chunk = tokio::time::timeout(Duration::from_secs(5), res.chunk())
+            .await
+            .expect("timed out waiting for an event")
+            .unwrap()
+            .expect("stream ended early");
+        body.push_str(std::str::from_utf8(&chunk).unwrap());
+    }
+    body
+}
+
+#[tokio::test]
+async fn subscriber_receives_pushed_signal() {
+    let (url, feed) = serve().await;
+    let mut res = reqwest::get(&url).await.unwrap();
+    assert_eq!(
+        res.headers()["content-type"].to_str().unwrap(),
+        "text/event-stream"
+    );
+
+    feed.push(r#"{"symbol":"BTCUSDT"}"#);
+
+    let body = read_events(&mut res, 1).await;
+    assert!(body.contains("id: 1\n"), "{body}");
+    assert!(body.contains(r#"data: {"symbol":"BTCUSDT"}"#), "{body}");
+}
+
+#[tokio::test]
+async fn last_event_id_resumes_from_buffer() {
+    let (url, feed) = serve().await;
+    for sym in ["A", "B", "C"] {
+        feed.push(&format!(r#"{{"symbol":"{sym}"}}"#));
+    }
+
+    let mut res = reqwest::Client::new()
+        .get(&url)
+        .header("Last-Event-ID", "1")
+        .send()
+        .await
+        .unwrap();
+
+    let body = read_events(&mut res, 2).await;
+    assert!(!body.contains(r#""A""#), "{body}");
+    assert!(
+        body.contains("id: 2\n") && body.contains("id: 3\n"),
+        "{body}"
+    );
+}
+
+#[tokio::test]
+async fn heartbeats_are_sent_without_an_event_id() {
+    let (url, feed) = serve().await;
+    let mut res = reqwest::Client::new()
+        .get(&url)
+        .header("Last-Event-ID", "0")
+        .send()
+        .await
+        .unwrap();
+
+    feed.push(r#"{"symbol":"BTCUSDT"}"#);
+    let body = read_events(&mut res, 1).await;
+    feed.heartbeats
+        .send(Some(Heartbeat {
+            ts: chrono::Utc::now(),
+            clients: 0,
+        }))
+        .unwrap();
+    let body = body + &read_events(&mut res, 1).await;
+
+    assert!(body.contains(r#"data: {"type":"heartbeat","#), "{body}");
+    assert_eq!(body.matches("id:").count(), 1, "{body}");
+}
@@ -0,0 +1,115 @@
+use std::{net::SocketAddr, time::Duration};
+
+use crypto_scanner_agent::{
+    testing::{signal, TestFeed},
+    RouterConfig, SharedState, Signal, SignalConfig,
+};
+use futures::StreamExt;
+use shuttle_axum::axum;
+use tokio_tungstenite::connect_async;
+
+/// Serve `feed`'s router on an ephemeral port and return its address.
+async fn serve(feed: &TestFeed) -> SocketAddr {
+    let app = feed
+        .router(&RouterConfig::default())
+        .into_make_service_with_connect_info::<SocketAddr>();
+    let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let addr = listener.local_addr().unwrap();
+    tokio::spawn(async move { axum::serve(listener, app).await });
+    addr
+}
+
+async fn wait_for_clients(state: &SharedState, n: usize) {
+    for _ in 0..100 {
+        if state.lock().await.clients_count == n {
+            return;
+        }
+        tokio::time::sleep(Duration::from_millis(10)).await;
+    }
+    panic!("expected {n} connected clients");
+}
+
+async fn next_signal(
+    client: &mut (impl futures::Stream<
+        Item = tokio_tungstenite::tungstenite::Result<tokio_tungstenite::tungstenite::Message>,
+    > + Unpin),
+) -> Signal {
+    let frame = tokio::time::timeout(Duration::from_secs(5), client.next())
+        .await
+        .expect("timed out waiting for a signal")
+        .unwrap()
+        .unwrap();
+    serde_json::from_str(&frame.into_text().unwrap()).unwrap()
+}
+
+#[tokio::test]
+async fn websocket_client_receives_injected_signals() {
+    let feed = TestFeed::default();
+    let addr = serve(&feed).await;
+    let (mut client, _) = connect_async(format!("ws://{addr}/websocket"))
+        .await
+        .unwrap();
+    wait_for_clients(&feed.state(), 1).await;
+
+    feed.inject_signal(signal("BTCUSDT", 6.0));
+    let first = next_signal(&mut client).await;
+    feed.inject_signal(signal("WIFUSDT", 25.0));
+    let second = next_signal(&mut client).await;
+
+    assert_eq!((first.symbol.as_str(), first.seq), ("BTCUSDT", 1));
+    assert_eq!((second.symbol.as_str(), second.seq), ("WIFUSDT", 2));
+    assert_eq!(second.display_symbol, "WIF/USDT");
+}
+
+#[tokio::test]
+async fn injected_frames_are_filtered_by_the_thresholds() {
+    let feed = TestFeed::new(SignalConfig {
+        min_pct_gain: 10.0,
+        ..Default::default()
+    });
+    let delivered = feed.inject_frame(
+        r#"[
+            { "s": "BTCUSDT", "P": "6.0", "q": "2000000", "c": "30000" },
+            { "s": "WIFUSDT", "P": "25.0", "q": "2000000", "c": "2.5" }
+        ]"#,
+    );
+    assert_eq!(delivered, 1);
+
+    let addr = serve(&feed).await;
+    let stats: serde_json::Value = reqwest::get(format!("http://{addr}/stats"))
+        .await
+        .unwrap()
+        .json()
+        .await
+        .unwrap();
+    assert_eq!(stats["seq"], 1);
+    let symbols: serde_json::Value = reqwest::get(format!("http://{addr}/symbols"))
+        .await
+        .unwrap()
+        .json()
+        .await
+        .unwrap();
+    assert_eq!(symbols.as_array().unwrap().len(), 2);
+}
+
+#[tokio::test]
+async fn buffered_signals_download_as_csv() {
+    let feed = TestFeed::default();
+    feed.inject_signal(signal("BTCUSDT", 6.0));
+    feed.inject_signal(signal("WIFUSDT", 25.0));
+    let addr = serve(&feed).await;
+
+    let res = reqwest::get(format!("http://{addr}/signals.csv"))
+        .await
+        .unwrap();
+    assert_eq!(
+        res.headers()["content-type"].to_str().unwrap(),
+        "text/csv; charset=utf-8"
+    );
+    let csv = res.text().await.unwrap();
+    let lines: Vec<_> = csv.lines().collect();
+    assert_eq!(lines[0], Signal::CSV_HEADER);
+    assert_eq!(lines.len(), 3);
+    assert!(lines[1].starts_with("BTCUSDT,6,2000000,1,"), "{}", lines[1]);
+    assert!(lines[2].starts_with("WIFUSDT,25,"), "{}", lines[2]);
+}
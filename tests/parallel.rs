@@ -1,22 +1,22 @@
-use futures::{stream, StreamExt};
-use std::time::{Duration, Instant};
+use crypto_scanner_agent::util::{parallel_map, parallel_map_with_timeout, TimedOut};
+use std::{
+    sync::atomic::{AtomicUsize, Ordering},
+    time::{Duration, Instant},
+};
 
-async fn run_tasks(delays: Vec<u64>, concurrency: usize) -> Vec<u64> {
-    stream::iter(delays)
-        .map(|d| async move {
-            tokio::time::sleep(Duration::from_millis(d)).await;
-            d
-        })
-        .buffer_unordered(concurrency)
-        .collect::<Vec<_>>()
-        .await
+async fn run_tasks(delays: Vec<u64>, concurrency: usize, ordered: bool) -> Vec<u64> {
+    parallel_map(delays, concurrency, ordered, |d| async move {
+        tokio::time::sleep(Duration::from_millis(d)).await;
+        d
+    })
+    .await
 }
 
 #[tokio::test]
 async fn parallel_unordered_faster_than_sequential() {
     let delays = vec![100, 100, 100];
     let start = Instant::now();
-    let result = run_tasks(delays.clone(), delays.len()).await;
+    let result = run_tasks(delays.clone(), delays.len(), false).await;
     let elapsed = start.elapsed();
     assert_eq!(result.len(), delays.len());
     // With full parallelism, execution should take roughly the longest delay.
@@ -27,9 +27,58 @@ async fn parallel_unordered_faster_than_sequential() {
 async fn parallel_respects_concurrency_limit() {
     let delays = vec![100, 100, 100];
     let start = Instant::now();
-    let result = run_tasks(delays.clone(), 1).await;
+    let result = run_tasks(delays.clone(), 1, false).await;
     let elapsed = start.elapsed();
     assert_eq!(result.len(), delays.len());
     // With concurrency of 1, execution should be close to sequential time.
     assert!(elapsed >= Duration::from_millis(300));
 }
+
+#[tokio::test]
+async fn ordered_mode_keeps_input_order() {
+    let result = run_tasks(vec![150, 10, 80], 3, true).await;
+    assert_eq!(result, [150, 10, 80]);
+}
+
+#[tokio::test]
+async fn unordered_mode_yields_in_completion_order() {
+    let result = run_tasks(vec![150, 10, 80], 3, false).await;
+    assert_eq!(result, [10, 80, 150]);
+}
+
+#[tokio::test]
+async fn never_exceeds_concurrency_cap() {
+    let in_flight = AtomicUsize::new(0);
+    let peak = AtomicUsize::new(0);
+    parallel_map(0..10, 3, false, |_| async {
+        let now = in_flight.fetch_add(1, Ordering::SeqCst) + 1;
+        peak.fetch_max(now, Ordering::SeqCst);
+        tokio::time::sleep(Duration::from_millis(20)).await;
+        in_flight.fetch_sub(1, Ordering::SeqCst);
+    })
+    .await;
+    assert_eq!(peak.load(Ordering::SeqCst), 3);
+}
+
+#[tokio::test]
+async fn slow_task_times_out_while_others_complete() {
+    let start = Instant::now();
+    let result = parallel_map_with_timeout(
+        vec![10, 5_000, 20],
+        2,
+        true,
+        Duration::from_millis(100),
+        |d| async move {
+            tokio::time::sleep(Duration::from_millis(d)).await;
+            d
+        },
+    )
+    .await;
+
+    assert_eq!(
+        result,
+        [Ok(10), Err(TimedOut(Duration::from_millis(100))), Ok(20)]
+    );
+    // The stuck task released its slot instead of holding up the batch.
+    assert!(start.elapsed() < Duration::from_secs(1));
+}
@@ -1,22 +1,16 @@
-use futures::{stream, StreamExt};
-use std::time::{Duration, Instant};
+use crypto_scanner_agent::util::parallel_map;
 use rstest::rstest;
+use std::time::{Duration, Instant};
 
 async fn run_tasks(delays: Vec<u64>, concurrency: usize) -> Vec<u64> {
-    stream::iter(delays)
-        .map(|d| async move {
-            tokio::time::sleep(Duration::from_millis(d)).await;
-            d
-        })
-        .buffer_unordered(concurrency)
-        .collect::<Vec<_>>()
-        .await
+    parallel_map(delays, concurrency, false, |d| async move {
+        tokio::time::sleep(Duration::from_millis(d)).await;
+        d
+    })
+    .await
 }
 
-#[rstest(concurrency, is_parallel,
-    case(3, true),
-    case(1, false)
-)]
+#[rstest(concurrency, is_parallel, case(3, true), case(1, false))]
 #[tokio::test]
 async fn parallelism_behavior(concurrency: usize, is_parallel: bool) {
     let delays = vec![100, 100, 100];
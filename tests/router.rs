@@ -0,0 +1,357 @@
+use crypto_scanner_agent::{
+    admin::AdminToken,
+    binance::{RateLimit, UsedWeight},
+    build_router,
+    events::SignalBuffer,
+    market::{LatestTicker, LatestTickers},
+    output::OutputDir,
+    routes,
+    shutdown::ShutdownReason,
+    RouterConfig, State,
+};
+use shuttle_axum::axum::{
+    body::{to_bytes, Body},
+    http::{header, Request, Response, StatusCode},
+};
+use std::sync::Arc;
+use tokio::sync::{watch, Mutex};
+use tower::ServiceExt;
+
+fn router(config: &RouterConfig) -> shuttle_axum::axum::Router {
+    let (_tx, rx) = watch::channel(None);
+    let state = State::new(rx, SignalBuffer::shared(10));
+    build_router(Arc::new(Mutex::new(state)), config)
+}
+
+async fn get(config: &RouterConfig, uri: &str) -> Response<Body> {
+    router(config)
+        .oneshot(Request::get(uri).body(Body::empty()).unwrap())
+        .await
+        .unwrap()
+}
+
+async fn status_in(config: &RouterConfig, uri: &str) -> StatusCode {
+    get(config, uri).await.status()
+}
+
+fn cache_control(res: &Response<Body>) -> Option<&str> {
+    res.headers()
+        .get(header::CACHE_CONTROL)
+        .map(|v| v.to_str().unwrap())
+}
+
+/// A throw-away static directory with the given files.
+fn static_dir(name: &str, files: &[(&str, &str)]) -> std::path::PathBuf {
+    let dir = std::env::temp_dir().join(format!("scanner-{name}-{}", std::process::id()));
+    std::fs::create_dir_all(&dir).unwrap();
+    for (file, contents) in files {
+        std::fs::write(dir.join(file), contents).unwrap();
+    }
+    dir
+}
+
+async fn status_of(uri: &str) -> StatusCode {
+    status_in(&RouterConfig::default(), uri).await
+}
+
+#[tokio::test]
+async fn api_routes_are_wired() {
+    assert_eq!(status_of("/version").await, StatusCode::OK);
+    assert_eq!(status_of("/protocol").await, StatusCode::OK);
+    assert_eq!(status_of("/stats").await, StatusCode::OK);
+}
+
+#[tokio::test]
+async fn every_reported_route_is_registered() {
+    let config = RouterConfig {
+        admin_token: AdminToken(Some("s3cret".into())),
+        ..Default::default()
+    };
+    for route in routes() {
+        let req = Request::builder()
+            .method(route.method)
+            .uri(route.path)
+            .body(Body::empty())
+            .unwrap();
+        let status = router(&config).oneshot(req).await.unwrap().status();
+        assert_ne!(status, StatusCode::NOT_FOUND, "{route:?}");
+        assert_ne!(status, StatusCode::METHOD_NOT_ALLOWED, "{route:?}");
+    }
+}
+
+#[tokio::test]
+async fn routes_endpoint_lists_the_routes() {
+    let res = get(&RouterConfig::default(), "/routes").await;
+    assert_eq!(res.status(), StatusCode::OK);
+    let body = to_bytes(res.into_body(), usize::MAX).await.unwrap();
+    let listed: Vec<serde_json::Value> = serde_json::from_slice(&body).unwrap();
+    assert_eq!(listed.len(), routes().len());
+    assert!(listed
+        .iter()
+        .any(|r| r["method"] == "POST" && r["path"] == "/admin/thresholds"));
+}
+
+#[tokio::test]
+async fn websocket_route_is_wired() {
+    // A plain GET is not an upgrade, but the route must exist.
+    assert_ne!(status_of("/websocket").await, StatusCode::NOT_FOUND);
+}
+
+#[tokio::test]
+async fn dashboard_is_served_from_static() {
+    assert_eq!(status_of("/").await, StatusCode::OK);
+    assert_eq!(status_of("/missing.js").await, StatusCode::NOT_FOUND);
+}
+
+#[tokio::test]
+async fn static_dir_is_configurable() {
+    let dir = static_dir("custom", &[("custom.html", "<p>hi</p>")]);
+    let config = RouterConfig {
+        static_dir: dir.clone(),
+        ..Default::default()
+    };
+
+    assert_eq!(status_in(&config, "/custom.html").await, StatusCode::OK);
+    assert_eq!(
+        status_in(&config, "/index.html").await,
+        StatusCode::NOT_FOUND
+    );
+    // API routes do not depend on the static directory.
+    assert_eq!(status_in(&config, "/version").await, StatusCode::OK);
+
+    std::fs::remove_dir_all(dir).unwrap();
+}
+
+#[tokio::test]
+async fn static_assets_carry_cache_headers() {
+    let dir = static_dir(
+        "cache",
+        &[("app.js", "console.log(1)"), ("index.html", "<p/>")],
+    );
+    let config = RouterConfig {
+        static_dir: dir.clone(),
+        static_max_age_secs: 600,
+        ..Default::default()
+    };
+
+    let asset = get(&config, "/app.js").await;
+    assert_eq!(cache_control(&asset), Some("public, max-age=600"));
+
+    let index = get(&config, "/").await;
+    assert_eq!(cache_control(&index), Some("no-cache"));
+
+    let version = get(&config, "/version").await;
+    assert_eq!(cache_control(&version), None);
+
+    let missing = get(&config, "/missing.js").await;
+    assert_eq!(cache_control(&missing), None);
+
+    std::fs::remove_dir_all(dir).unwrap();
+}
+
+#[tokio::test]
+async fn stats_report_buffer_fill_level() {
+    let (_tx, rx) = watch::channel(None);
+    let buffer = SignalBuffer::shared(3);
+    for i in 0..5 {
+        buffer.lock().unwrap().push(format!(r#"{{"n":{i}}}"#));
+    }
+    assert_eq!(buffer.lock().unwrap().since(0)[0].json, r#"{"n":2}"#);
+
+    let state = Arc::new(Mutex::new(State::new(rx, buffer)));
+    let res = build_router(state, &RouterConfig::default())
+        .oneshot(Request::get("/stats").body(Body::empty()).unwrap())
+        .await
+        .unwrap();
+    let body = to_bytes(res.into_body(), usize::MAX).await.unwrap();
+    let stats: serde_json::Value = serde_json::from_slice(&body).unwrap();
+
+    assert_eq!(stats["buffered_signals"], 3);
+    assert_eq!(stats["buffer_capacity"], 3);
+    assert!(stats.get("last_event_id").is_none());
+    assert_eq!(stats["seq"], 5);
+    assert_eq!(stats["clients"], 0);
+    assert_eq!(stats["feed_restarts"], 0);
+    assert!(stats["top_pools_refreshed_at"].is_null());
+    assert!(stats["binance_used_weight"].is_null());
+}
+
+#[tokio::test]
+async fn stats_report_each_apps_own_binance_weight() {
+    let stats = |weight: UsedWeight| async move {
+        let (_tx, rx) = watch::channel(None);
+        let state = State::new(rx, SignalBuffer::shared(1)).with_binance_weight(weight);
+        let res = build_router(Arc::new(Mutex::new(state)), &RouterConfig::default())
+            .oneshot(Request::get("/stats").body(Body::empty()).unwrap())
+            .await
+            .unwrap();
+        let body = to_bytes(res.into_body(), usize::MAX).await.unwrap();
+        serde_json::from_slice::<serde_json::Value>(&body).unwrap()["binance_used_weight"].clone()
+    };
+
+    let weight = UsedWeight::default();
+    let mut headers = reqwest::header::HeaderMap::new();
+    headers.insert("x-mbx-used-weight-1m", "1234".parse().unwrap());
+    weight.record(&RateLimit::from_headers(200, &headers));
+
+    // Two apps in one process don't see each other's weight.
+    assert_eq!(stats(weight).await, 1234);
+    assert!(stats(UsedWeight::default()).await.is_null());
+}
+
+async fn post_thresholds(config: &RouterConfig, token: Option<&str>, body: &str) -> Response<Body> {
+    let mut req = Request::post("/admin/thresholds");
+    if let Some(token) = token {
+        req = req.header(header::AUTHORIZATION, format!("Bearer {token}"));
+    }
+    router(config)
+        .oneshot(req.body(Body::from(body.to_owned())).unwrap())
+        .await
+        .unwrap()
+}
+
+#[tokio::test]
+async fn admin_thresholds_require_the_token() {
+    let body = r#"{"min_pct_gain":3,"min_quote_vol":500000}"#;
+    let disabled = RouterConfig::default();
+    assert_eq!(
+        post_thresholds(&disabled, Some("s3cret"), body)
+            .await
+            .status(),
+        StatusCode::NOT_FOUND
+    );
+
+    let config = RouterConfig {
+        admin_token: AdminToken(Some("s3cret".into())),
+        ..Default::default()
+    };
+    let status = |res: Response<Body>| res.status();
+    assert_eq!(
+        status(post_thresholds(&config, None, body).await),
+        StatusCode::UNAUTHORIZED
+    );
+    assert_eq!(
+        status(post_thresholds(&config, Some("s3cret"), r#"{"min_pct_gain":"x"}"#).await),
+        StatusCode::BAD_REQUEST
+    );
+
+    let res = post_thresholds(&config, Some("s3cret"), body).await;
+    assert_eq!(res.status(), StatusCode::OK);
+    let body = to_bytes(res.into_body(), usize::MAX).await.unwrap();
+    let json: serde_json::Value = serde_json::from_slice(&body).unwrap();
+    assert_eq!(json["min_pct_gain"], 3.0);
+    assert_eq!(json["min_quote_vol"], 500_000.0);
+    assert_eq!(json["min_last_price"], 0.0);
+}
+
+#[tokio::test]
+async fn admin_snapshot_writes_the_buffered_signals() {
+    let dir = std::env::temp_dir().join(format!("scanner-snapshots-{}", std::process::id()));
+    let (_tx, rx) = watch::channel(None);
+    let buffer = SignalBuffer::shared(2);
+    for symbol in ["AUSDT", "BUSDT", "CUSDT"] {
+        buffer
+            .lock()
+            .unwrap()
+            .push(format!(r#"{{"symbol":"{symbol}"}}"#));
+    }
+    let state = State::new(rx, buffer).with_snapshot_dir(OutputDir(Some(dir.clone())));
+    let config = RouterConfig {
+        admin_token: AdminToken(Some("s3cret".into())),
+        ..Default::default()
+    };
+    let router = build_router(Arc::new(Mutex::new(state)), &config);
+
+    let unauthorized = router
+        .clone()
+        .oneshot(
+            Request::post("/admin/snapshot")
+                .body(Body::empty())
+                .unwrap(),
+        )
+        .await
+        .unwrap();
+    assert_eq!(unauthorized.status(), StatusCode::UNAUTHORIZED);
+    assert!(!dir.exists());
+
+    let req = Request::post("/admin/snapshot")
+        .header(header::AUTHORIZATION, "Bearer s3cret")
+        .body(Body::empty())
+        .unwrap();
+    let res = router.oneshot(req).await.unwrap();
+    assert_eq!(res.status(), StatusCode::OK);
+    let body = to_bytes(res.into_body(), usize::MAX).await.unwrap();
+    let json: serde_json::Value = serde_json::from_slice(&body).unwrap();
+    assert_eq!(json["signals"], 2);
+
+    let path = std::path::PathBuf::from(json["path"].as_str().unwrap());
+    assert!(path.starts_with(&dir), "{path:?}");
+    let file: serde_json::Value = serde_json::from_slice(&std::fs::read(&path).unwrap()).unwrap();
+    let symbols: Vec<_> = file["signals"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .map(|s| s["symbol"].as_str().unwrap())
+        .collect();
+    assert_eq!(symbols, ["BUSDT", "CUSDT"]);
+    assert!(file["taken_at"].is_string());
+    std::fs::remove_dir_all(dir).unwrap();
+}
+
+#[tokio::test]
+async fn admin_shutdown_records_the_reason() {
+    let (_tx, rx) = watch::channel(None);
+    let state = State::new(rx, SignalBuffer::shared(1));
+    let shutdown = state.shutdown.clone();
+    let config = RouterConfig {
+        admin_token: AdminToken(Some("s3cret".into())),
+        ..Default::default()
+    };
+    let req = Request::post("/admin/shutdown")
+        .header(header::AUTHORIZATION, "Bearer s3cret")
+        .body(Body::empty())
+        .unwrap();
+    let res = build_router(Arc::new(Mutex::new(state)), &config)
+        .oneshot(req)
+        .await
+        .unwrap();
+
+    assert_eq!(res.status(), StatusCode::ACCEPTED);
+    assert_eq!(shutdown.reason(), Some(&ShutdownReason::Admin));
+    assert!(shutdown.token().is_cancelled());
+    assert_eq!(shutdown.exit_code(), 0);
+}
+
+#[tokio::test]
+async fn symbols_lists_every_latest_ticker() {
+    let (_tx, rx) = watch::channel(None);
+    let tickers = LatestTickers::default();
+    let ticker = |symbol: &str, pct_gain_24h: f64, signalling: bool| LatestTicker {
+        symbol: symbol.to_owned(),
+        pct_gain_24h,
+        quote_vol_usdt: 2_000_000.0,
+        last_price: Some(1.0),
+        signalling,
+        ts: chrono::Utc::now(),
+    };
+    tickers.update([ticker("ETHUSDT", 1.5, false), ticker("BTCUSDT", 7.0, true)]);
+    let state = Arc::new(Mutex::new(
+        State::new(rx, SignalBuffer::shared(1)).with_tickers(tickers),
+    ));
+
+    let symbols = |uri: &'static str| {
+        let state = state.clone();
+        async move {
+            let res = build_router(state, &RouterConfig::default())
+                .oneshot(Request::get(uri).body(Body::empty()).unwrap())
+                .await
+                .unwrap();
+            assert_eq!(res.status(), StatusCode::OK);
+            let body = to_bytes(res.into_body(), usize::MAX).await.unwrap();
+            let listed: Vec<LatestTicker> = serde_json::from_slice(&body).unwrap();
+            listed.into_iter().map(|t| t.symbol).collect::<Vec<_>>()
+        }
+    };
+    assert_eq!(symbols("/symbols").await, ["BTCUSDT", "ETHUSDT"]);
+    assert_eq!(symbols("/symbols?above_only=true").await, ["BTCUSDT"]);
+}
@@ -0,0 +1,139 @@
+use std::{
+    sync::{Arc, Mutex},
+    time::{Duration, Instant},
+};
+
+use crypto_scanner_agent::{
+    scanner::{run_scanner, Message, ScannerConfig},
+    Feed, Signal, SignalConfig,
+};
+use shuttle_axum::axum::{self, extract::WebSocketUpgrade, routing::get, Router};
+use tokio::sync::watch;
+use tokio_util::sync::CancellationToken;
+
+/// A fake Binance ticker stream that sends `frame` on every connection and
+/// keeps it open.
+async fn mock_upstream(frame: &'static str) -> String {
+    let app = Router::new().route(
+        "/ws",
+        get(move |ws: WebSocketUpgrade| async move {
+            ws.on_upgrade(move |mut socket| async move {
+                let _ = socket.send(Message::Text(frame.to_owned())).await;
+                while let Some(Ok(_)) = socket.recv().await {}
+            })
+        }),
+    );
+    let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let addr = listener.local_addr().unwrap();
+    tokio::spawn(async move { axum::serve(listener, app).await });
+    format!("ws://{addr}/ws")
+}
+
+/// A fake upstream taking `handshake` to accept each connection, which it
+/// then keeps open. Returns its URL and when each handshake started, plus
+/// the most handshakes it had in progress at once.
+async fn slow_upstream(handshake: Duration) -> (String, Arc<Mutex<(Vec<Instant>, usize)>>) {
+    let stats = Arc::new(Mutex::new((Vec::new(), 0)));
+    let in_progress = Arc::new(Mutex::new(0));
+    let seen = stats.clone();
+    let app = Router::new().route(
+        "/ws",
+        get(move |ws: WebSocketUpgrade| {
+            let (seen, in_progress) = (seen.clone(), in_progress.clone());
+            async move {
+                {
+                    let mut now = in_progress.lock().unwrap();
+                    *now += 1;
+                    let mut stats = seen.lock().unwrap();
+                    stats.0.push(Instant::now());
+                    stats.1 = stats.1.max(*now);
+                }
+                tokio::time::sleep(handshake).await;
+                *in_progress.lock().unwrap() -= 1;
+                ws.on_upgrade(
+                    |mut socket| async move { while let Some(Ok(_)) = socket.recv().await {} },
+                )
+            }
+        }),
+    );
+    let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let addr = listener.local_addr().unwrap();
+    tokio::spawn(async move { axum::serve(listener, app).await });
+    (format!("ws://{addr}/ws"), stats)
+}
+
+#[tokio::test]
+async fn feeds_take_turns_connecting_under_a_limit() {
+    let handshake = Duration::from_millis(200);
+    let (url, stats) = slow_upstream(handshake).await;
+    let config = ScannerConfig {
+        feeds: vec![
+            (Feed::Binance, url.clone()),
+            (Feed::Raydium, url.clone()),
+            (Feed::Binance, url),
+        ],
+        signals: SignalConfig::default(),
+        max_connection: None,
+        accept_invalid_certs: false,
+        max_concurrent_connects: Some(2),
+        restart_policy: Default::default(),
+        first_seen_path: None,
+    };
+    let (tx, _rx) = watch::channel(None);
+    let shutdown = CancellationToken::new();
+    let scanner = tokio::spawn(run_scanner(config, tx, shutdown.clone()));
+
+    tokio::time::timeout(Duration::from_secs(5), async {
+        while stats.lock().unwrap().0.len() < 3 {
+            tokio::time::sleep(Duration::from_millis(10)).await;
+        }
+    })
+    .await
+    .expect("timed out waiting for the feeds to connect");
+    shutdown.cancel();
+    scanner.await.unwrap();
+
+    let (started, most) = stats.lock().unwrap().clone();
+    assert_eq!(most, 2, "two handshakes at a time");
+    // The third feed waited for one of the first two to connect.
+    assert!(started[2] - started[0] >= handshake, "{started:?}");
+}
+
+#[tokio::test]
+async fn embedded_scanner_publishes_signals_until_cancelled() {
+    let url = mock_upstream(
+        r#"[
+            { "s": "BTCUSDT", "P": "1.0", "q": "2000000", "c": "30000" },
+            { "s": "WIFUSDT", "P": "25.0", "q": "2000000", "c": "2.5" }
+        ]"#,
+    )
+    .await;
+    let config = ScannerConfig {
+        feeds: vec![(Feed::Binance, url)],
+        signals: SignalConfig::default(),
+        max_connection: None,
+        accept_invalid_certs: false,
+        max_concurrent_connects: None,
+        restart_policy: Default::default(),
+        first_seen_path: None,
+    };
+    let (tx, mut rx) = watch::channel(None);
+    let shutdown = CancellationToken::new();
+    let scanner = tokio::spawn(run_scanner(config, tx, shutdown.clone()));
+
+    tokio::time::timeout(Duration::from_secs(5), rx.changed())
+        .await
+        .expect("timed out waiting for a signal")
+        .unwrap();
+    let Some(Message::Text(json)) = rx.borrow_and_update().clone() else {
+        panic!("expected a text message");
+    };
+    let signal: Signal = serde_json::from_str(&json).unwrap();
+    assert_eq!((signal.symbol.as_str(), signal.seq), ("WIFUSDT", 1));
+
+    shutdown.cancel();
+    tokio::time::timeout(Duration::from_secs(5), scanner)
+        .await
+        .expect("scanner stops once cancelled")
+        .unwrap();
+}
@@ -1,4 +1,5 @@
-use crypto_scanner_agent::util::{cpu_core_count, max_parallel_threads};
+use crypto_scanner_agent::util::{cpu_core_count, max_parallel_threads, BatchSummary};
+use std::time::Duration;
 
 #[test]
 fn core_count_nonzero() {
@@ -9,3 +10,29 @@ fn core_count_nonzero() {
 fn parallel_threads_nonzero() {
     assert!(max_parallel_threads() >= 1);
 }
+
+#[test]
+fn batch_summary_throughput() {
+    let mut summary = BatchSummary::default();
+    assert_eq!(summary.items_per_sec(), 0.0);
+
+    for result in [Ok(()), Err("boom"), Ok(()), Ok(())] {
+        summary.record(&result);
+    }
+    summary.elapsed = Duration::from_millis(500);
+
+    assert_eq!(summary.items, 4);
+    assert_eq!(summary.errors, 1);
+    assert_eq!(summary.items_per_sec(), 8.0);
+    assert_eq!(
+        summary.to_string(),
+        "4 items in 500.00ms (8.0 items/s), 1 error"
+    );
+
+    let one = BatchSummary {
+        items: 1,
+        errors: 0,
+        elapsed: Duration::from_secs(1),
+    };
+    assert_eq!(one.to_string(), "1 item in 1.00s (1.0 items/s), 0 errors");
+}
@@ -0,0 +1,230 @@
+use std::{
+    net::SocketAddr,
+    sync::{Arc, Mutex as StdMutex},
+    time::Duration,
+};
+
+use crypto_scanner_agent::{
+    build_router, events::SignalBuffer, Heartbeat, RouterConfig, SharedState, State,
+};
+use futures::StreamExt;
+use shuttle_axum::axum::{self, extract::ws::Message};
+use tokio::sync::{watch, Mutex};
+use tokio_tungstenite::{
+    connect_async,
+    tungstenite::{self, client::IntoClientRequest},
+};
+use tracing_subscriber::fmt::MakeWriter;
+
+/// Serve the full router on an ephemeral port and return its address with
+/// the feed's sending side.
+async fn serve() -> (String, watch::Sender<Option<Message>>, SharedState) {
+    let (tx, rx) = watch::channel(None);
+    let state = Arc::new(Mutex::new(State::new(rx, SignalBuffer::shared(10))));
+    let router = build_router(state.clone(), &RouterConfig::default());
+
+    let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let addr = listener.local_addr().unwrap();
+    let app = router.into_make_service_with_connect_info::<SocketAddr>();
+    tokio::spawn(async move { axum::serve(listener, app).await });
+    (addr.to_string(), tx, state)
+}
+
+async fn wait_for_clients(state: &SharedState, n: usize) {
+    for _ in 0..100 {
+        if state.lock().await.clients_count == n {
+            return;
+        }
+        tokio::time::sleep(Duration::from_millis(10)).await;
+    }
+    panic!("expected {n} connected clients");
+}
+
+#[tokio::test]
+async fn placeholder_is_not_forwarded_to_new_clients() {
+    let (addr, tx, state) = serve().await;
+    let (mut client, _) = connect_async(format!("ws://{addr}/websocket"))
+        .await
+        .unwrap();
+    wait_for_clients(&state, 1).await;
+
+    // An empty update must be skipped; the first frame is the real signal.
+    tx.send(None).unwrap();
+    tx.send(Some(Message::Text(r#"{"symbol":"BTCUSDT"}"#.into())))
+        .unwrap();
+
+    let frame = tokio::time::timeout(Duration::from_secs(5), client.next())
+        .await
+        .unwrap()
+        .unwrap()
+        .unwrap();
+    assert_eq!(
+        frame,
+        tungstenite::Message::Text(r#"{"symbol":"BTCUSDT"}"#.into())
+    );
+}
+
+#[tokio::test]
+async fn nothing_is_sent_before_the_first_signal() {
+    let (addr, _tx, state) = serve().await;
+    let (mut client, _) = connect_async(format!("ws://{addr}/websocket"))
+        .await
+        .unwrap();
+    wait_for_clients(&state, 1).await;
+
+    let frame = tokio::time::timeout(Duration::from_millis(200), client.next()).await;
+    assert!(frame.is_err(), "unexpected frame: {frame:?}");
+}
+
+/// Log sink shared between the test and the subscriber.
+#[derive(Clone, Default)]
+struct CapturedLogs(Arc<StdMutex<Vec<u8>>>);
+
+impl CapturedLogs {
+    fn contents(&self) -> String {
+        String::from_utf8(self.0.lock().unwrap().clone()).unwrap()
+    }
+}
+
+impl std::io::Write for CapturedLogs {
+    fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+        self.0.lock().unwrap().extend_from_slice(buf);
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> std::io::Result<()> {
+        Ok(())
+    }
+}
+
+impl<'a> MakeWriter<'a> for CapturedLogs {
+    type Writer = Self;
+
+    fn make_writer(&'a self) -> Self::Writer {
+        self.clone()
+    }
+}
+
+#[tokio::test]
+async fn connect_is_logged_with_client_metadata() {
+    let logs = CapturedLogs::default();
+    let subscriber = tracing_subscriber::fmt()
+        .with_writer(logs.clone())
+        .with_ansi(false)
+        .finish();
+    // The current-thread test runtime runs the server on this thread too.
+    let _guard = tracing::subscriber::set_default(subscriber);
+
+    let (addr, _tx, state) = serve().await;
+    let mut req = format!("ws://{addr}/websocket")
+        .into_client_request()
+        .unwrap();
+    req.headers_mut()
+        .insert("user-agent", "scanner-test/1.0".parse().unwrap());
+    let (client, _) = connect_async(req).await.unwrap();
+    wait_for_clients(&state, 1).await;
+    drop(client);
+    wait_for_clients(&state, 0).await;
+
+    let out = logs.contents();
+    let connect = out
+        .lines()
+        .find(|l| l.contains("WebSocket client connected"))
+        .unwrap_or_else(|| panic!("no connect line in:\n{out}"));
+    assert!(connect.contains("ip=127.0.0.1"), "{connect}");
+    assert!(
+        connect.contains(r#"user_agent="scanner-test/1.0""#),
+        "{connect}"
+    );
+    assert!(connect.contains(r#"subprotocol="-""#), "{connect}");
+
+    let conn_id = connect
+        .split_whitespace()
+        .find(|f| f.starts_with("conn_id="))
+        .expect("conn_id field");
+    let disconnect = out
+        .lines()
+        .find(|l| l.contains("WebSocket client disconnected"))
+        .unwrap_or_else(|| panic!("no disconnect line in:\n{out}"));
+    assert!(disconnect.contains(conn_id), "{disconnect}");
+    assert!(disconnect.contains("duration_ms="), "{disconnect}");
+}
+
+/// Publish `n` signals the way the feed does, numbered by the buffer.
+async fn publish(state: &SharedState, tx: &watch::Sender<Option<Message>>, n: usize) {
+    let buffer = state.lock().await.buffer.clone();
+    for _ in 0..n {
+        let mut buffer = buffer.lock().unwrap();
+        let seq = buffer.last_id() + 1;
+        let json = format!(r#"{{"seq":{seq}}}"#);
+        buffer.push(json.clone());
+        tx.send(Some(Message::Text(json))).unwrap();
+    }
+}
+
+async fn next_text(
+    client: &mut (impl futures::Stream<Item = tungstenite::Result<tungstenite::Message>> + Unpin),
+) -> String {
+    let frame = tokio::time::timeout(Duration::from_secs(5), client.next())
+        .await
+        .expect("timed out waiting for a frame")
+        .unwrap()
+        .unwrap();
+    frame.into_text().unwrap()
+}
+
+#[tokio::test]
+async fn since_replays_buffered_signals_then_streams_live() {
+    let (addr, tx, state) = serve().await;
+    publish(&state, &tx, 12).await;
+
+    let (mut client, _) = connect_async(format!("ws://{addr}/websocket?since=10"))
+        .await
+        .unwrap();
+    assert_eq!(next_text(&mut client).await, r#"{"seq":11}"#);
+    assert_eq!(next_text(&mut client).await, r#"{"seq":12}"#);
+
+    // A burst after the replay arrives in full, not coalesced.
+    publish(&state, &tx, 2).await;
+    assert_eq!(next_text(&mut client).await, r#"{"seq":13}"#);
+    assert_eq!(next_text(&mut client).await, r#"{"seq":14}"#);
+}
+
+#[tokio::test]
+async fn since_older_than_the_buffer_announces_a_gap() {
+    let (addr, tx, state) = serve().await;
+    // The buffer holds 10, so seqs 1 and 2 are gone.
+    publish(&state, &tx, 12).await;
+
+    let (mut client, _) = connect_async(format!("ws://{addr}/websocket?since=0"))
+        .await
+        .unwrap();
+    assert_eq!(next_text(&mut client).await, r#"{"type":"gap","missed":2}"#);
+    assert_eq!(next_text(&mut client).await, r#"{"seq":3}"#);
+}
+
+#[tokio::test]
+async fn heartbeats_reach_clients_resuming_with_since() {
+    let (addr, tx, state) = serve().await;
+    let (heartbeats, heartbeat_rx) = watch::channel(None);
+    state.lock().await.heartbeats = heartbeat_rx;
+    publish(&state, &tx, 2).await;
+
+    let (mut client, _) = connect_async(format!("ws://{addr}/websocket?since=1"))
+        .await
+        .unwrap();
+    assert_eq!(next_text(&mut client).await, r#"{"seq":2}"#);
+
+    heartbeats
+        .send(Some(Heartbeat {
+            ts: chrono::Utc::now(),
+            clients: 1,
+        }))
+        .unwrap();
+    let heartbeat: Heartbeat = serde_json::from_str(&next_text(&mut client).await).unwrap();
+    assert_eq!(heartbeat.clients, 1);
+
+    // Live signals keep flowing after it.
+    publish(&state, &tx, 1).await;
+    assert_eq!(next_text(&mut client).await, r#"{"seq":3}"#);
+}